@@ -0,0 +1,72 @@
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::time::Duration;
+
+use crate::notation::{Language, Notation};
+use crate::session_log;
+use tess_rs::theme::Background;
+
+// ----------------------------------------------
+// Lichess account preferences, read with the `lichess_token` from
+// the config file and mapped to the closest tess settings. Settings
+// given on the command line or in the config file win.
+// ----------------------------------------------
+
+const PREFERENCES_URL: &str = "https://lichess.org/api/account/preferences";
+
+#[derive(Deserialize)]
+struct Response {
+    prefs: Prefs,
+    language: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Prefs {
+    // 100 light, 200 dark, 300 transparent, 400 follows the system
+    bg: Option<u16>,
+    // 0 piece symbols, 1 letters
+    piece_notation: Option<u8>,
+}
+
+/// The tess counterparts of the account's preferences, None where there is none
+#[derive(Debug, Default)]
+pub struct Preferences {
+    pub background: Option<Background>,
+    pub notation: Option<Notation>,
+    pub input_language: Option<Language>,
+}
+
+/// Fetch the preferences of the account the token belongs to (scope `preference:read`)
+pub fn preferences(token: &str) -> anyhow::Result<Preferences> {
+    let text = session_log::fetch(PREFERENCES_URL, || {
+        Ok(reqwest::blocking::Client::builder()
+            .timeout(Duration::from_secs(3))
+            .build()?
+            .get(PREFERENCES_URL)
+            .bearer_auth(token)
+            .send()?
+            .error_for_status()?
+            .text()?)
+    })?;
+    let response: Response = serde_json::from_str(&text)?;
+    Ok(Preferences {
+        background: match response.prefs.bg {
+            Some(100) => Some(Background::Light),
+            Some(200 | 300) => Some(Background::Dark),
+            Some(400) => Some(Background::Auto),
+            _ => None,
+        },
+        notation: match response.prefs.piece_notation {
+            Some(0) => Some(Notation::Figurine),
+            Some(1) => Some(Notation::Letters),
+            _ => None,
+        },
+        // "de-DE" -> de, for the piece letters accepted in SAN input
+        input_language: response
+            .language
+            .as_deref()
+            .and_then(|lang| lang.split(['-', '_']).next())
+            .and_then(|code| Language::from_str(code, true).ok()),
+    })
+}
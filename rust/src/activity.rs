@@ -0,0 +1,321 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::{session, session_log, stats, App, AppMode};
+
+// ----------------------------------------------
+// Training time per day and kind of activity, kept in
+// $XDG_STATE_HOME/tess/activity.json. Days are UTC days, and time
+// without a key press for a few minutes is not counted.
+// ----------------------------------------------
+
+const IDLE_AFTER: Duration = Duration::from_secs(120);
+const SAVE_EVERY: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Day {
+    /// seconds spent on puzzles
+    pub puzzles: f64,
+    /// seconds spent playing games
+    pub games: f64,
+    /// seconds spent in analysis and on the analysis board
+    pub review: f64,
+}
+
+impl Day {
+    fn total(&self) -> f64 {
+        self.puzzles + self.games + self.review
+    }
+
+    fn add(&mut self, other: &Day) {
+        self.puzzles += other.puzzles;
+        self.games += other.games;
+        self.review += other.review;
+    }
+}
+
+fn activity_path() -> Option<PathBuf> {
+    session::state_dir().map(|dir| dir.join("activity.json"))
+}
+
+/// Every recorded day, by "YYYY-MM-DD"
+pub fn load() -> BTreeMap<String, Day> {
+    activity_path()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+/// Counts the time of the tab on screen, saving it every minute and when dropped
+pub struct Tracker {
+    last_tick: Instant,
+    last_input: Instant,
+    last_save: Instant,
+    // not saved yet, by day
+    pending: BTreeMap<String, Day>,
+    reminder: Reminder,
+}
+
+impl Tracker {
+    pub fn new() -> Self {
+        let now = session_log::now();
+        Self {
+            last_tick: now,
+            last_input: now,
+            last_save: now,
+            pending: BTreeMap::new(),
+            reminder: Reminder::default(),
+        }
+    }
+
+    /// Nudge at `time` of day (seconds, UTC) when nothing was trained yet
+    pub fn with_reminder(mut self, time: Option<u64>) -> Self {
+        self.reminder = Reminder::new(time);
+        self
+    }
+
+    /// The reminder's nudge, once a day when it is due, counting the time not saved yet
+    pub fn nudge(&mut self) -> Option<String> {
+        let today = self.pending.get(&date(stats::now())).copied();
+        self.reminder.due(today.unwrap_or_default())
+    }
+
+    /// A key was pressed, the user is still there
+    pub fn input(&mut self) {
+        self.last_input = session_log::now();
+    }
+
+    pub fn tick(&mut self, app: &App) {
+        let now = session_log::now();
+        let spent = now - self.last_tick;
+        self.last_tick = now;
+        if now - self.last_input < IDLE_AFTER {
+            let day = self.pending.entry(date(stats::now())).or_default();
+            let seconds = match &app.mode {
+                AppMode::Puzzle { .. } if !app.analysing => &mut day.puzzles,
+                AppMode::StandardGame if !app.analysing => &mut day.games,
+                _ => &mut day.review,
+            };
+            *seconds += spent.as_secs_f64();
+        }
+        if now - self.last_save >= SAVE_EVERY {
+            self.last_save = now;
+            // best effort, the time is kept for the next try
+            let _ = self.save();
+        }
+    }
+
+    fn save(&mut self) -> anyhow::Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let Some(path) = activity_path() else {
+            return Ok(());
+        };
+        let mut days = load();
+        for (date, day) in &self.pending {
+            days.entry(date.clone()).or_default().add(day);
+        }
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_vec(&days)?)?;
+        self.pending.clear();
+        Ok(())
+    }
+}
+
+impl Drop for Tracker {
+    fn drop(&mut self) {
+        let _ = self.save();
+    }
+}
+
+// ----------------------------------------------
+// Streak of days in a row with some training, and a reminder at a set
+// time of day for the days without any yet
+// ----------------------------------------------
+
+// Minutes a day takes to count for the streak
+const STREAK_MINUTES: f64 = 5.0;
+
+pub struct Streak {
+    /// days in a row up to today, or yesterday if today is not done yet
+    pub days: usize,
+    pub today: bool,
+}
+
+/// The streak, with `extra` time of today not saved yet
+pub fn streak(days: &BTreeMap<String, Day>, extra: Day) -> Streak {
+    let today = stats::now() / 86400;
+    let trained = |day: u64| {
+        let mut total = days.get(&date(day * 86400)).copied().unwrap_or_default();
+        if day == today {
+            total.add(&extra);
+        }
+        total.total() >= STREAK_MINUTES * 60.0
+    };
+    let done = trained(today);
+    let last = if done { today } else { today.saturating_sub(1) };
+    let count = (0..=last).rev().take_while(|&day| trained(day)).count();
+    Streak {
+        days: count,
+        today: done,
+    }
+}
+
+impl Streak {
+    /// "Streak: 4 days, today included.", with a warning while today is missing
+    pub fn describe(&self) -> String {
+        let days = match self.days {
+            1 => "1 day".to_string(),
+            n => format!("{n} days"),
+        };
+        match (self.days, self.today) {
+            (0, _) => {
+                format!("No streak yet, {STREAK_MINUTES} minutes of training a day start one.")
+            }
+            (_, true) => format!("Streak: {days}, today included."),
+            (_, false) => format!(
+                "Streak: {days}. Nothing trained today yet, the streak ends at midnight UTC."
+            ),
+        }
+    }
+}
+
+/// "18:30" as seconds into the day
+pub fn parse_time_of_day(text: &str) -> Option<u64> {
+    let (hours, minutes) = text.trim().split_once(':')?;
+    let (hours, minutes): (u64, u64) = (hours.parse().ok()?, minutes.parse().ok()?);
+    (hours < 24 && minutes < 60).then_some(hours * 3600 + minutes * 60)
+}
+
+/// Time of day to be reminded at, and the day it last reminded
+#[derive(Default)]
+pub struct Reminder {
+    time: Option<u64>,
+    reminded: Option<String>,
+}
+
+impl Reminder {
+    pub fn new(time: Option<u64>) -> Self {
+        Self {
+            time,
+            reminded: None,
+        }
+    }
+
+    /// Once the time has come: the nudge if today has no training yet, with
+    /// `unsaved` time of today counted. Asks only once a day.
+    pub fn due(&mut self, unsaved: Day) -> Option<String> {
+        let time = self.time?;
+        let now = stats::now();
+        let today = date(now);
+        if now % 86400 < time || self.reminded.as_ref() == Some(&today) {
+            return None;
+        }
+        self.reminded = Some(today);
+        let streak = streak(&load(), unsaved);
+        (!streak.today).then(|| match streak.days {
+            0 => "Time for today's training.".to_string(),
+            n => format!(
+                "Time for today's training, or the streak of {n} days ends at midnight UTC."
+            ),
+        })
+    }
+}
+
+// ----------------------------------------------
+// Calendar of the last weeks, one column per week and one row per
+// weekday, shaded by the minutes trained that day
+// ----------------------------------------------
+
+const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+/// The calendar and the totals of the last `weeks` weeks, up to today
+pub fn calendar(days: &BTreeMap<String, Day>, weeks: usize) -> String {
+    let today = stats::now() / 86400;
+    // the calendar starts on the Monday `weeks - 1` weeks back; day 0 was a Thursday
+    let monday = today - (today + 3) % 7;
+    let first = monday.saturating_sub(7 * (weeks as u64 - 1));
+    let mut out = String::new();
+    let mut total = Day::default();
+    for (weekday, name) in WEEKDAYS.iter().enumerate() {
+        out.push_str(name);
+        for week in 0..weeks as u64 {
+            let day = first + 7 * week + weekday as u64;
+            if day > today {
+                break;
+            }
+            let minutes = days
+                .get(&date(day * 86400))
+                .map_or(0.0, |d| d.total() / 60.0);
+            out.push(' ');
+            out.push(shade(minutes));
+        }
+        out.push('\n');
+    }
+    for day in first..=today {
+        if let Some(d) = days.get(&date(day * 86400)) {
+            total.add(d);
+        }
+    }
+    out.push_str("\n    · none  ░ under 15 minutes  ▒ under 30  ▓ under 60  █ an hour or more\n\n");
+    out.push_str(&format!(
+        "Last {weeks} weeks: puzzles {}, games {}, review {}\n",
+        hours(total.puzzles),
+        hours(total.games),
+        hours(total.review)
+    ));
+    out.push_str(&streak(days, Day::default()).describe());
+    out.push('\n');
+    out
+}
+
+fn shade(minutes: f64) -> char {
+    match minutes {
+        m if m <= 0.0 => '·',
+        m if m < 15.0 => '░',
+        m if m < 30.0 => '▒',
+        m if m < 60.0 => '▓',
+        _ => '█',
+    }
+}
+
+fn hours(seconds: f64) -> String {
+    let minutes = (seconds / 60.0).round() as u64;
+    format!("{}h {:02}m", minutes / 60, minutes % 60)
+}
+
+/// Write every recorded day as CSV, the time in minutes
+pub fn export_csv(days: &BTreeMap<String, Day>, path: &Path) -> anyhow::Result<()> {
+    let mut out = String::from("date,puzzle_minutes,game_minutes,review_minutes\n");
+    for (date, day) in days {
+        out.push_str(&format!(
+            "{date},{:.1},{:.1},{:.1}\n",
+            day.puzzles / 60.0,
+            day.games / 60.0,
+            day.review / 60.0
+        ));
+    }
+    std::fs::write(path, out)?;
+    Ok(())
+}
+
+/// "YYYY-MM-DD" of a Unix time, in UTC
+pub fn date(unix: u64) -> String {
+    // days to the civil calendar, after Howard Hinnant's algorithm
+    let z = (unix / 86400) as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
@@ -0,0 +1,127 @@
+use serde::{Deserialize, Serialize};
+use shakmaty::{Color as ChessColor, Position};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::session::{self, SavedSession};
+use crate::{clock, engine, App};
+use tess_rs::board::DisplayMode;
+
+// ----------------------------------------------
+// Adjourned games: `:adjourn` writes the game against the engines as it
+// stands, the clock times and every engine with the options it was set
+// to, and `tess resume` starts the engines again and plays on
+// ----------------------------------------------
+
+#[derive(Serialize, Deserialize)]
+struct SavedClock {
+    control: clock::TimeControl,
+    kind: clock::IncrementKind,
+    white: Duration,
+    black: Duration,
+    /// the time the side to move had used, a delay not given twice
+    #[serde(default)]
+    used: Duration,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedEngine {
+    path: PathBuf,
+    /// the options set, in order, the strength among them
+    settings: Vec<(String, String)>,
+    /// 'w' or 'b'
+    color: char,
+    movetime: Option<Duration>,
+    nodes: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Adjourned {
+    game: SavedSession,
+    clock: Option<SavedClock>,
+    engines: Vec<SavedEngine>,
+    flipped: bool,
+}
+
+/// Where `:adjourn` and `tess resume` keep the game unless told otherwise
+pub fn default_path() -> Option<PathBuf> {
+    session::state_dir().map(|dir| dir.join("adjourned.json"))
+}
+
+/// Write the game of `app`, whose clock is stopped, to `path`
+pub fn save(app: &App, path: &Path) -> anyhow::Result<()> {
+    let clock = app.clock.as_ref().map(|clock| SavedClock {
+        control: clock.control,
+        kind: clock.kind,
+        white: clock.remaining(ChessColor::White),
+        black: clock.remaining(ChessColor::Black),
+        used: clock.turn_used(),
+    });
+    let engines = app
+        .engines
+        .iter()
+        .map(|engine| {
+            let strength = engine.strength();
+            SavedEngine {
+                path: engine.path.clone(),
+                settings: engine.settings.clone(),
+                color: engine.color.char(),
+                movetime: strength.movetime,
+                nodes: strength.nodes,
+            }
+        })
+        .collect();
+    let adjourned = Adjourned {
+        game: SavedSession::from_app(app),
+        clock,
+        engines,
+        flipped: app.flipped,
+    };
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, serde_json::to_vec_pretty(&adjourned)?)?;
+    Ok(())
+}
+
+/// The game adjourned to `path`, its engines started again and its clock
+/// running for the side to move
+pub fn resume(path: &Path, display: DisplayMode) -> anyhow::Result<App> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|err| anyhow::anyhow!("no adjourned game in {}: {err}", path.display()))?;
+    let adjourned: Adjourned = serde_json::from_str(&text)
+        .map_err(|err| anyhow::anyhow!("{} is not an adjourned game: {err}", path.display()))?;
+    let (start, history) = adjourned.game.replay()?;
+    let mut app = App::new_standard(start, display);
+    for mv in &history {
+        app.play(mv)?;
+    }
+    for saved in &adjourned.engines {
+        let Some(color) = ChessColor::from_char(saved.color) else {
+            anyhow::bail!(
+                "{}: no side '{}' for an engine",
+                path.display(),
+                saved.color
+            );
+        };
+        let strength = engine::Strength {
+            movetime: saved.movetime,
+            nodes: saved.nodes,
+            ..engine::Strength::default()
+        };
+        app.engines.push(
+            engine::Engine::start(&saved.path)?
+                .with_options(&saved.settings)?
+                .playing(color, strength)?,
+        );
+    }
+    if let Some(saved) = &adjourned.clock {
+        let mut clock = clock::Clock::new(saved.control, saved.kind)
+            .with_remaining(saved.white, saved.black)
+            .with_turn_used(saved.used);
+        clock.resume(app.board.turn());
+        app.clock = Some(clock);
+    }
+    app.flipped = adjourned.flipped;
+    Ok(app)
+}
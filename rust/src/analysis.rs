@@ -0,0 +1,278 @@
+use shakmaty::san::San;
+use shakmaty::{
+    attacks, Bitboard, Board, Chess, Color as ChessColor, File, Piece, Position, Role, Square,
+};
+
+// ----------------------------------------------
+// Static, engine-free analysis of a position
+// ----------------------------------------------
+
+/// Number of pieces of each side attacking (or defending) a square.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Control {
+    pub white: usize,
+    pub black: usize,
+}
+
+impl Control {
+    /// Positive if white controls the square, negative for black.
+    pub fn balance(&self) -> i32 {
+        self.white as i32 - self.black as i32
+    }
+
+    pub fn is_contested(&self) -> bool {
+        self.white > 0 || self.black > 0
+    }
+}
+
+pub fn square_control(board: &Board, sq: Square) -> Control {
+    let occupied = board.occupied();
+    Control {
+        white: board.attacks_to(sq, ChessColor::White, occupied).count(),
+        black: board.attacks_to(sq, ChessColor::Black, occupied).count(),
+    }
+}
+
+/// The control balance of every square averaged over `positions`, indexed by
+/// square: positive where white has more attackers, negative for black
+pub fn control_heat<'a>(positions: impl IntoIterator<Item = &'a Chess>) -> [f64; 64] {
+    let mut heat = [0.0; 64];
+    let mut count = 0;
+    for pos in positions {
+        for sq in Square::ALL {
+            heat[sq as usize] += f64::from(square_control(pos.board(), sq).balance());
+        }
+        count += 1;
+    }
+    if count > 0 {
+        heat.iter_mut().for_each(|value| *value /= f64::from(count));
+    }
+    heat
+}
+
+/// All pieces of `color` that attack `sq`, including pieces defending it.
+pub fn attackers_of(board: &Board, sq: Square, color: ChessColor) -> Vec<(Square, Piece)> {
+    board
+        .attacks_to(sq, color, board.occupied())
+        .into_iter()
+        .filter_map(|from| board.piece_at(from).map(|piece| (from, piece)))
+        .collect()
+}
+
+// ----------------------------------------------
+// Material and pawn structure summary
+// ----------------------------------------------
+
+/// Conventional piece values in pawns.
+pub fn piece_value(role: Role) -> u32 {
+    match role {
+        Role::Pawn => 1,
+        Role::Knight | Role::Bishop => 3,
+        Role::Rook => 5,
+        Role::Queen => 9,
+        Role::King => 0,
+    }
+}
+
+/// Static assessment of one side's material and pawn structure.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SideSummary {
+    pub material: u32,
+    pub bishop_pair: bool,
+    pub doubled_pawns: usize,
+    pub isolated_pawns: Vec<Square>,
+    pub passed_pawns: Vec<Square>,
+}
+
+pub fn side_summary(board: &Board, color: ChessColor) -> SideSummary {
+    let ours = board.by_color(color);
+    let our_pawns = board.pawns() & ours;
+    let their_pawns = board.pawns() & !ours;
+
+    let material = ours
+        .into_iter()
+        .filter_map(|sq| board.role_at(sq))
+        .map(piece_value)
+        .sum();
+
+    let bishops = board.bishops() & ours;
+    let bishop_pair =
+        bishops.into_iter().any(|sq| sq.is_light()) && bishops.into_iter().any(|sq| sq.is_dark());
+
+    let doubled_pawns = File::ALL
+        .into_iter()
+        .map(|file| {
+            (our_pawns & Bitboard::from_file(file))
+                .count()
+                .saturating_sub(1)
+        })
+        .sum();
+
+    let isolated_pawns = our_pawns
+        .into_iter()
+        .filter(|sq| (our_pawns & adjacent_files(sq.file())).is_empty())
+        .collect();
+
+    let passed_pawns = our_pawns
+        .into_iter()
+        .filter(|sq| {
+            let files = adjacent_files(sq.file()) | Bitboard::from_file(sq.file());
+            let ahead = (their_pawns & files).into_iter().any(|theirs| {
+                if color == ChessColor::White {
+                    theirs.rank() > sq.rank()
+                } else {
+                    theirs.rank() < sq.rank()
+                }
+            });
+            !ahead
+        })
+        .collect();
+
+    SideSummary {
+        material,
+        bishop_pair,
+        doubled_pawns,
+        isolated_pawns,
+        passed_pawns,
+    }
+}
+
+fn adjacent_files(file: File) -> Bitboard {
+    [file.offset(-1), file.offset(1)]
+        .into_iter()
+        .flatten()
+        .map(Bitboard::from_file)
+        .fold(Bitboard::EMPTY, |acc, bb| acc | bb)
+}
+
+// ----------------------------------------------
+// Hanging pieces and simple one-move tactics
+// ----------------------------------------------
+
+// Like `piece_value`, but the king counts as the most valuable target
+fn target_value(role: Role) -> u32 {
+    match role {
+        Role::King => 100,
+        role => piece_value(role),
+    }
+}
+
+/// A piece is hanging if the opponent can take it for free or for less
+/// than it is worth: attacked and undefended, or attacked by a lower
+/// valued piece.
+pub fn is_hanging(board: &Board, sq: Square) -> bool {
+    let Some(piece) = board.piece_at(sq) else {
+        return false;
+    };
+    if piece.role == Role::King {
+        return false;
+    }
+    let attackers = attackers_of(board, sq, piece.color.other());
+    if attackers.is_empty() {
+        return false;
+    }
+    let defended = !attackers_of(board, sq, piece.color).is_empty();
+    let cheapest = attackers
+        .iter()
+        .map(|(_, attacker)| piece_value(attacker.role))
+        .min()
+        .unwrap_or(0);
+    !defended || cheapest < piece_value(piece.role)
+}
+
+pub fn hanging_pieces(board: &Board) -> Vec<(Square, Piece)> {
+    board
+        .occupied()
+        .into_iter()
+        .filter(|sq| is_hanging(board, *sq))
+        .filter_map(|sq| board.piece_at(sq).map(|piece| (sq, piece)))
+        .collect()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TacticKind {
+    Fork,
+    Skewer,
+}
+
+/// A move for the side to move that creates a fork or a skewer.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tactic {
+    pub kind: TacticKind,
+    pub san: String,
+    pub to: Square,
+    pub targets: Vec<Square>,
+}
+
+/// Pattern check over all legal moves of the side to move. A fork hits two
+/// or more pieces that are either worth more than the attacker or
+/// undefended; a skewer attacks a valuable piece with a weaker piece
+/// behind it on the same line. The moving piece must not be hanging
+/// itself after the move.
+pub fn find_tactics(pos: &Chess) -> Vec<Tactic> {
+    let mut tactics = Vec::new();
+    for mv in pos.legal_moves() {
+        let mut after = pos.clone();
+        after.play_unchecked(&mv);
+        let board = after.board();
+        let to = mv.to();
+        let Some(piece) = board.piece_at(to) else {
+            continue;
+        };
+        if is_hanging(board, to) {
+            continue;
+        }
+        let san = San::from_move(pos, &mv).to_string();
+        let enemies = board.attacks_from(to) & board.by_color(piece.color.other());
+
+        let forked: Vec<Square> = enemies
+            .into_iter()
+            .filter(|sq| {
+                let role = board.role_at(*sq).unwrap_or(Role::Pawn);
+                target_value(role) > piece_value(piece.role)
+                    || attackers_of(board, *sq, piece.color.other()).is_empty()
+            })
+            .collect();
+        if forked.len() >= 2 {
+            tactics.push(Tactic {
+                kind: TacticKind::Fork,
+                san: san.clone(),
+                to,
+                targets: forked,
+            });
+        }
+
+        if matches!(piece.role, Role::Bishop | Role::Rook | Role::Queen) {
+            for front in enemies {
+                if let Some(back) = skewered_behind(board, to, front, piece) {
+                    tactics.push(Tactic {
+                        kind: TacticKind::Skewer,
+                        san: san.clone(),
+                        to,
+                        targets: vec![front, back],
+                    });
+                }
+            }
+        }
+    }
+    tactics
+}
+
+// The piece behind `front` on the line from the moved slider, if it forms a skewer
+fn skewered_behind(board: &Board, from: Square, front: Square, slider: Piece) -> Option<Square> {
+    let front_role = board.role_at(front)?;
+    if front_role != Role::King && target_value(front_role) <= piece_value(slider.role) {
+        return None;
+    }
+    let back = (attacks::ray(from, front) & board.occupied())
+        .into_iter()
+        .filter(|sq| attacks::between(from, *sq).contains(front))
+        .min_by_key(|sq| sq.distance(front))?;
+    let back_piece = board.piece_at(back)?;
+    let worth_taking = piece_value(back_piece.role) >= piece_value(slider.role)
+        || attackers_of(board, back, back_piece.color).is_empty();
+    (back_piece.color != slider.color
+        && target_value(front_role) > target_value(back_piece.role)
+        && worth_taking)
+        .then_some(back)
+}
@@ -0,0 +1,66 @@
+use std::time::Duration;
+
+use crate::review::CP_CEILING;
+
+// ----------------------------------------------
+// Commands embedded in PGN comments, as lichess and chess.com write
+// them after a move: { [%clk 0:04:58] }
+// ----------------------------------------------
+
+/// Cut `[%name ...]` out of the comment and return its argument
+pub fn take(comment: &mut String, name: &str) -> Option<String> {
+    let open = format!("[%{name} ");
+    let start = comment.find(&open)?;
+    let end = start + comment[start..].find(']')?;
+    let argument = comment[start + open.len()..end].trim().to_string();
+    comment.replace_range(start..=end, "");
+    Some(argument)
+}
+
+/// A clock reading, "0:04:58" or "4:58.3"
+pub fn parse_clock(text: &str) -> Option<Duration> {
+    let mut seconds = 0.0;
+    for part in text.split(':') {
+        seconds = seconds * 60.0 + part.trim().parse::<f64>().ok()?;
+    }
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+/// An evaluation, "0.17", "-1.50" or "#-3", in centipawns from white's side; a
+/// mate counts as the ceiling, a depth after a comma ("0.17,23") is ignored
+pub fn parse_eval(text: &str) -> Option<i32> {
+    let value = text.split(',').next()?.trim();
+    if let Some(moves) = value.strip_prefix('#') {
+        let moves: i32 = moves.parse().ok()?;
+        return Some(if moves < 0 { -CP_CEILING } else { CP_CEILING });
+    }
+    let pawns: f64 = value.parse().ok()?;
+    Some(((pawns * 100.0).round() as i32).clamp(-CP_CEILING, CP_CEILING))
+}
+
+/// The TimeControl tag in seconds, "300+3", as base time and increment
+pub fn parse_time_control(text: &str) -> Option<(Duration, Duration)> {
+    let (base, increment) = text.split_once('+').unwrap_or((text, "0"));
+    let base = Duration::try_from_secs_f64(base.trim().parse().ok()?).ok()?;
+    let increment = Duration::try_from_secs_f64(increment.trim().parse().ok()?).ok()?;
+    Some((base, increment))
+}
+
+/// The time each move took, from the clock left after every move. The first
+/// move of each side needs the time control to compare against.
+pub fn think_times(
+    clocks: &[Option<Duration>],
+    control: Option<(Duration, Duration)>,
+) -> Vec<Option<Duration>> {
+    let increment = control.map_or(Duration::ZERO, |(_, increment)| increment);
+    (0..clocks.len())
+        .map(|ply| {
+            let after = clocks[ply]?;
+            let before = match ply.checked_sub(2) {
+                Some(earlier) => clocks[earlier]?,
+                None => control?.0,
+            };
+            Some((before + increment).saturating_sub(after))
+        })
+        .collect()
+}
@@ -0,0 +1,294 @@
+use ratatui::style::{Color, Style};
+use ratatui::text::Line;
+use shakmaty::san::SanPlus;
+use shakmaty::{Chess, Move, Piece, Position, Role, Square};
+
+use tess_rs::board::{DisplayMode, PieceSet};
+
+use crate::analysis::piece_value;
+use crate::{App, AppMode};
+
+// ----------------------------------------------
+// Beginner assist: the name of the piece pointed at or typed and the
+// squares it can go to, a legend of the pieces, and after a move of mine
+// that loses material or lets me be mated, a tip on what went wrong. The
+// blunders are found by a shallow search of its own, no engine needed
+// ----------------------------------------------
+
+// Plies searched after my move; the position before it gets one more
+const DEPTH: u32 = 2;
+// Centipawns a move has to lose to get a tip
+const BLUNDER: i32 = 200;
+const MATE: i32 = 100_000;
+
+#[derive(Default)]
+pub struct Assist {
+    /// the square under the mouse pointer, on the board
+    pub pointed: Option<Square>,
+    // false until the game was first looked at, its moves then get no tips
+    known: bool,
+    // the moves of the game when last looked at
+    seen: Vec<Move>,
+    // what went wrong with my last move
+    tip: Option<String>,
+}
+
+fn name(role: Role) -> &'static str {
+    match role {
+        Role::Pawn => "pawn",
+        Role::Knight => "knight",
+        Role::Bishop => "bishop",
+        Role::Rook => "rook",
+        Role::Queen => "queen",
+        Role::King => "king",
+    }
+}
+
+fn moves_like(role: Role) -> &'static str {
+    match role {
+        Role::Pawn => "moves one square forward, two from its starting square, and takes one square diagonally forward",
+        Role::Knight => "jumps in an L, two squares one way and one to the side, over anything in between",
+        Role::Bishop => "moves any number of squares diagonally, so it stays on squares of one color",
+        Role::Rook => "moves any number of squares along its rank or file",
+        Role::Queen => "moves any number of squares along its rank, file or diagonals",
+        Role::King => "moves one square in any direction, never onto a square the opponent attacks",
+    }
+}
+
+/// The square the assist is about: the one pointed at, else the one typed first
+pub fn focus(app: &App) -> Option<Square> {
+    let assist = app.beginner.as_ref()?;
+    assist.pointed.or_else(|| {
+        app.input_buffer
+            .get(..2)
+            .and_then(|text| text.parse::<Square>().ok())
+    })
+}
+
+/// Where the piece on `sq` can go: its legal moves for the side to move, the
+/// squares it attacks for the other one. The captures come second.
+pub fn pattern(pos: &Chess, sq: Square) -> Option<(Vec<Square>, Vec<Square>)> {
+    let piece = pos.board().piece_at(sq)?;
+    let targets: Vec<Square> = if piece.color == pos.turn() {
+        let mut targets: Vec<Square> = pos
+            .legal_moves()
+            .iter()
+            .filter(|mv| mv.from() == Some(sq))
+            .map(Move::to)
+            .collect();
+        targets.dedup();
+        targets
+    } else {
+        (pos.board().attacks_from(sq) & !pos.board().by_color(piece.color))
+            .into_iter()
+            .collect()
+    };
+    let (captures, quiet) = targets
+        .into_iter()
+        .partition(|target| pos.board().piece_at(*target).is_some());
+    Some((quiet, captures))
+}
+
+/// The assist's pane, next to the info pane
+pub fn lines(app: &App) -> Vec<Line<'static>> {
+    let Some(assist) = &app.beginner else {
+        return Vec::new();
+    };
+    // the big pieces are drawings, their names only come with pointing
+    let letters = "K king, Q queen, R rook, B bishop, N knight, P pawn; white in capitals";
+    let legend = match app.display {
+        DisplayMode::Big => None,
+        _ if app.low_bandwidth || app.pieces == PieceSet::Letters => Some(letters),
+        DisplayMode::Simple => Some("♔ king ♕ queen ♖ rook ♗ bishop ♘ knight ♙ pawn"),
+    };
+    let mut lines: Vec<Line> = legend.into_iter().map(Line::raw).collect();
+    let pos = app.shown_board();
+    let about = focus(app).and_then(|sq| Some((sq, pos.board().piece_at(sq)?)));
+    match about {
+        None => lines.push(Line::raw(
+            "Point at a piece or type its square to see how it moves.",
+        )),
+        Some((sq, piece)) => {
+            let color = piece.color.fold_wb("White", "Black");
+            lines.push(Line::raw(format!(
+                "{color} {} on {sq}: {}.",
+                name(piece.role),
+                moves_like(piece.role)
+            )));
+            let (quiet, captures) = pattern(pos, sq).unwrap_or_default();
+            let squares = |list: &[Square]| {
+                list.iter()
+                    .map(Square::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            let own = piece.color == pos.turn();
+            lines.push(Line::raw(match (quiet.is_empty(), captures.is_empty()) {
+                (true, true) if own => "It cannot move now.".to_string(),
+                (true, true) => "It attacks nothing now.".to_string(),
+                (_, true) if own => format!("It can go to {}.", squares(&quiet)),
+                (true, _) if own => format!("It can take on {}.", squares(&captures)),
+                _ if own => format!(
+                    "It can go to {} and take on {}.",
+                    squares(&quiet),
+                    squares(&captures)
+                ),
+                _ => format!("It attacks {}.", squares(&[quiet, captures].concat())),
+            }));
+        }
+    }
+    if let Some(tip) = &assist.tip {
+        lines.push(Line::styled(
+            format!("Tip: {tip}"),
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+    lines
+}
+
+/// Look at the moves played since the last call and give a tip for a
+/// blunder among mine, true if the tip changed
+pub fn update(app: &mut App) -> bool {
+    let Some(assist) = app.beginner.as_mut() else {
+        return false;
+    };
+    let checked = matches!(app.mode, AppMode::StandardGame | AppMode::Board);
+    let before = assist.tip.clone();
+    if !assist.known || !app.history.starts_with(&assist.seen) {
+        // a new game, or moves taken back
+        assist.tip = None;
+    } else if checked && app.history.len() > assist.seen.len() {
+        let mut pos = app.start.clone();
+        for (ply, mv) in app.history.iter().enumerate() {
+            let mover = pos.turn();
+            if ply >= assist.seen.len() && app.engines.iter().all(|e| e.color != mover) {
+                assist.tip = tip(&pos, mv);
+            }
+            pos.play_unchecked(mv);
+        }
+    }
+    assist.known = true;
+    assist.seen = app.history.clone();
+    assist.tip != before
+}
+
+// What went wrong with `mv` in `pos`, None if it loses nothing
+fn tip(pos: &Chess, mv: &Move) -> Option<String> {
+    let mut after = pos.clone();
+    after.play_unchecked(mv);
+    if after.is_game_over() {
+        return None;
+    }
+    let opponent = pos.turn().other().fold_wb("White", "Black");
+    let san = |pos: &Chess, mv: &Move| SanPlus::from_move(pos.clone(), mv).to_string();
+    let mate = after.legal_moves().into_iter().find(|reply| {
+        let mut mated = after.clone();
+        mated.play_unchecked(reply);
+        mated.is_checkmate()
+    });
+    if let Some(reply) = mate {
+        return Some(format!(
+            "{opponent} can mate with {} now. Before each move, look at the checks the opponent could give.",
+            san(&after, &reply)
+        ));
+    }
+    let (best, best_score) = best_move(pos, DEPTH + 1)?;
+    let (reply, reply_score) = best_move(&after, DEPTH)?;
+    if best_score + reply_score < BLUNDER {
+        return None;
+    }
+    let reply_san = san(&after, &reply);
+    Some(match after.board().piece_at(reply.to()) {
+        Some(Piece { role, .. }) if reply.to() == mv.to() => format!(
+            "{opponent} can take the {} you moved with {reply_san}. Before moving a piece, check that its new square is defended.",
+            name(role)
+        ),
+        Some(Piece { role, .. }) => format!(
+            "{opponent} can take your {} on {} with {reply_san}. Before each move, check which of your pieces are attacked.",
+            name(role),
+            reply.to()
+        ),
+        None if best.is_capture() && !mv.is_capture() => format!(
+            "{} would have won material. Look at every capture before you move.",
+            san(pos, &best)
+        ),
+        None => format!(
+            "{opponent} wins material with {reply_san}. Look for moves that attack two of your pieces at once."
+        ),
+    })
+}
+
+// Material in centipawns, for the side to move
+fn material(pos: &Chess) -> i32 {
+    let board = pos.board();
+    board
+        .occupied()
+        .into_iter()
+        .filter_map(|sq| board.piece_at(sq))
+        .map(|piece| {
+            let value = 100 * piece_value(piece.role) as i32;
+            if piece.color == pos.turn() {
+                value
+            } else {
+                -value
+            }
+        })
+        .sum()
+}
+
+// The moves of `pos`, the captures of the most valuable pieces first
+fn ordered(pos: &Chess, captures_only: bool) -> Vec<Move> {
+    let mut moves: Vec<Move> = pos
+        .legal_moves()
+        .into_iter()
+        .filter(|mv| !captures_only || mv.is_capture())
+        .collect();
+    moves.sort_by_key(|mv| std::cmp::Reverse(mv.capture().map_or(0, |role| piece_value(role) + 1)));
+    moves
+}
+
+// The best move of the side to move and its score, searched `depth` plies
+fn best_move(pos: &Chess, depth: u32) -> Option<(Move, i32)> {
+    let mut best: Option<(Move, i32)> = None;
+    for mv in ordered(pos, false) {
+        let mut after = pos.clone();
+        after.play_unchecked(&mv);
+        let alpha = best.as_ref().map_or(-MATE - 1, |(_, score)| *score);
+        let score = -search(&after, depth - 1, -MATE - 1, -alpha);
+        if best.as_ref().is_none_or(|(_, best)| score > *best) {
+            best = Some((mv, score));
+        }
+    }
+    best
+}
+
+// Alpha-beta over every move `depth` plies deep, then over the captures
+fn search(pos: &Chess, depth: u32, mut alpha: i32, beta: i32) -> i32 {
+    if pos.is_checkmate() {
+        return -MATE;
+    }
+    if pos.is_stalemate() || pos.is_insufficient_material() {
+        return 0;
+    }
+    let moves = if depth == 0 {
+        // standing pat: the side to move need not take
+        let stand = material(pos);
+        if stand >= beta {
+            return stand;
+        }
+        alpha = alpha.max(stand);
+        ordered(pos, true)
+    } else {
+        ordered(pos, false)
+    };
+    for mv in moves {
+        let mut after = pos.clone();
+        after.play_unchecked(&mv);
+        let score = -search(&after, depth.saturating_sub(1), -beta, -alpha);
+        if score >= beta {
+            return score;
+        }
+        alpha = alpha.max(score);
+    }
+    alpha
+}
@@ -0,0 +1,166 @@
+use shakmaty::fen::Fen;
+use shakmaty::san::SanPlus;
+use shakmaty::{Color, EnPassantMode, Position};
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::engine::Engine;
+use crate::generate::{self, Limits};
+use crate::phase::Phase;
+use crate::review::{self, Judgement};
+use crate::{session, uci, Game, LastPosition, LichessNextPuzzle, Puzzle};
+
+// ----------------------------------------------
+// Puzzles from my blunders: my games reviewed by an engine, and where a
+// move of mine threw the game away, the refutation to find, kept in
+// $XDG_STATE_HOME/tess/blunder_puzzles.jsonl for `tess puzzle blunders`
+// ----------------------------------------------
+
+/// A blunder of mine with a refutation clear enough for a puzzle
+pub struct Found {
+    /// "games.pgn game 3"
+    pub source: String,
+    /// the blunder as numbered SAN, "14... Qxd4"
+    pub blunder: String,
+    /// of the position the blunder left
+    pub phase: Phase,
+    pub puzzle: LichessNextPuzzle,
+}
+
+/// How the games are searched
+pub struct Scan<'a> {
+    pub engine: &'a Path,
+    pub options: &'a [(String, String)],
+    /// the review finding the blunders, and the refutations
+    pub limits: Limits,
+    /// engines reviewing a game at the same time
+    pub jobs: usize,
+}
+
+fn puzzles_path() -> Option<PathBuf> {
+    session::state_dir().map(|dir| dir.join("blunder_puzzles.jsonl"))
+}
+
+/// My blunders in the games of PGN file `path` that `player` played, white or
+/// black. `progress` is told of every game's review.
+pub fn scan(
+    scan: &Scan,
+    path: &Path,
+    player: &str,
+    progress: &(dyn Fn(usize, usize) + Sync),
+) -> anyhow::Result<Vec<Found>> {
+    let text = std::fs::read_to_string(path)?;
+    let name = path.file_name().map_or_else(
+        || path.display().to_string(),
+        |name| name.to_string_lossy().to_string(),
+    );
+    let mut engine: Option<Engine> = None;
+    let mut reader = pgn_reader::BufferedReader::new(text.as_bytes());
+    let mut found = Vec::new();
+    let mut number = 0;
+    while let Some(game) = reader.read_game(&mut LastPosition::new(None))? {
+        number += 1;
+        let color = if game.white.eq_ignore_ascii_case(player) {
+            Color::White
+        } else if game.black.eq_ignore_ascii_case(player) {
+            Color::Black
+        } else {
+            continue;
+        };
+        let review = review::analyse(
+            &game.start,
+            &game.moves,
+            scan.engine,
+            scan.options,
+            scan.limits.movetime,
+            scan.jobs,
+            progress,
+        )?;
+        for (ply, judgement) in review.judgements().into_iter().enumerate() {
+            let (before, after) = (&review.positions[ply], &review.positions[ply + 1]);
+            if judgement != Some(Judgement::Blunder)
+                || before.turn() != color
+                || after.outcome().is_some()
+            {
+                continue;
+            }
+            // one engine for the refutations, started with the first game of mine
+            let engine = match &mut engine {
+                Some(engine) => engine,
+                None => engine.insert(Engine::start(scan.engine)?.with_options(scan.options)?),
+            };
+            let Some((_, solution)) = generate::solve(engine, after, &scan.limits)? else {
+                continue;
+            };
+            let mv = &review.moves[ply];
+            let san = SanPlus::from_move(before.clone(), mv);
+            let blunder = match before.turn() {
+                Color::White => format!("{}. {san}", before.fullmoves()),
+                Color::Black => format!("{}... {san}", before.fullmoves()),
+            };
+            let fen = Fen::from_position(before.clone(), EnPassantMode::Legal);
+            let source = format!("{name} game {number}");
+            found.push(Found {
+                puzzle: LichessNextPuzzle {
+                    puzzle: Puzzle {
+                        id: format!("blunder {source}, {blunder}"),
+                        rating: 0,
+                        solution: solution.iter().map(uci::format).collect(),
+                        // the blunder is played on the board before the puzzle starts
+                        initial_ply: 0,
+                    },
+                    game: Game {
+                        pgn: format!("[FEN \"{fen}\"]\n\n{blunder} *"),
+                    },
+                },
+                source,
+                blunder,
+                phase: Phase::of(after),
+            });
+        }
+    }
+    Ok(found)
+}
+
+/// Every blunder puzzle kept so far, oldest first
+pub fn load() -> anyhow::Result<Vec<LichessNextPuzzle>> {
+    let path = puzzles_path().ok_or_else(|| anyhow::anyhow!("no state directory (HOME unset)"))?;
+    if !path.exists() {
+        anyhow::bail!("no blunder puzzles yet, find some with `tess blunders`");
+    }
+    generate::load(&path)
+}
+
+/// Keep the puzzles of `found` not kept already, returning how many are new
+pub fn save(found: &[Found]) -> anyhow::Result<usize> {
+    let path = puzzles_path().ok_or_else(|| anyhow::anyhow!("no state directory (HOME unset)"))?;
+    // the same blunder from a file scanned twice, by its position and move
+    let mut seen: HashSet<String> = if path.exists() {
+        generate::load(&path)?
+            .into_iter()
+            .map(|puzzle| puzzle.game.pgn)
+            .collect()
+    } else {
+        HashSet::new()
+    };
+    let new: Vec<&LichessNextPuzzle> = found
+        .iter()
+        .map(|found| &found.puzzle)
+        .filter(|puzzle| seen.insert(puzzle.game.pgn.clone()))
+        .collect();
+    if new.is_empty() {
+        return Ok(0);
+    }
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    for puzzle in &new {
+        writeln!(file, "{}", serde_json::to_string(puzzle)?)?;
+    }
+    Ok(new.len())
+}
@@ -0,0 +1,657 @@
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Paragraph, Widget};
+use serde::{Deserialize, Serialize};
+use shakmaty::{Board, Color as ChessColor, File, Piece, Position, Rank, Role, Square};
+use std::collections::HashMap;
+use unicode_width::UnicodeWidthChar;
+
+use crate::theme::{Background, Palette};
+
+// ----------------------------------------------
+// The board as a ratatui widget: squares, pieces, rank and file labels
+// and the side to move, with optional square highlights and an arrow
+// ----------------------------------------------
+
+// ----------------------------------------------
+// Piece ASCII definitions
+// ----------------------------------------------
+static ASCII_PIECES: &[(&str, &[&str])] = &[
+    ("P", &[" ^ ", "(P)", "/_\\"]),
+    ("N", &[" __", "/ N", "\\_/"]),
+    ("B", &["  ^", " /B\\", " \\_/"]),
+    ("R", &["[R]", "[R]", "[R]"]),
+    ("Q", &[" Q ", "( )", " \\|"]),
+    ("K", &[" K ", "(. )", " | "]),
+    ("p", &[" ^ ", "(p)", "/_\\"]),
+    ("n", &[" __", "/ n", "\\_/"]),
+    ("b", &["  ^", " /b\\", " \\_/"]),
+    ("r", &["[r]", "[r]", "[r]"]),
+    ("q", &[" q ", "( )", " \\|"]),
+    ("k", &[" k ", "(. )", " | "]),
+];
+
+fn piece_ascii_map() -> HashMap<char, Vec<String>> {
+    let mut map = HashMap::new();
+    for (symbol, lines) in ASCII_PIECES {
+        map.insert(
+            symbol.chars().next().unwrap(),
+            lines.iter().map(|s| s.to_string()).collect(),
+        );
+    }
+    map
+}
+
+// Convert a shakmaty piece into a single ASCII letter for ASCII_PIECES map
+fn piece_char(piece: Piece) -> char {
+    let ch = match piece.role {
+        Role::Pawn => 'P',
+        Role::Knight => 'N',
+        Role::Bishop => 'B',
+        Role::Rook => 'R',
+        Role::Queen => 'Q',
+        Role::King => 'K',
+    };
+    if piece.color == ChessColor::Black {
+        ch.to_ascii_lowercase()
+    } else {
+        ch
+    }
+}
+
+// Convert a shakmaty piece into a Unicode character
+fn piece_unicode(piece: Piece) -> char {
+    match piece.role {
+        Role::Pawn => '♙',
+        Role::Knight => '♘',
+        Role::Bishop => '♗',
+        Role::Rook => '♖',
+        Role::Queen => '♕',
+        Role::King => '♔',
+    }
+}
+
+/// How pieces are drawn
+#[derive(Debug, clap::ValueEnum, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DisplayMode {
+    /// three-line ASCII art in squares of 5 by 3
+    Big,
+    /// one character per piece in squares of 2 by 1
+    Simple,
+}
+
+impl DisplayMode {
+    /// Width and height of a square at the smallest scale
+    pub fn default_cell_dimensions(&self) -> (usize, usize) {
+        match self {
+            DisplayMode::Big => (5, 3),
+            DisplayMode::Simple => (2, 1),
+        }
+    }
+}
+
+/// The characters of the pieces in the simple display
+#[derive(Debug, Default, clap::ValueEnum, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PieceSet {
+    /// chess symbols, ♔ ♛
+    #[default]
+    Symbols,
+    /// letters, K q, for fonts without the symbols
+    Letters,
+}
+
+// Square colors of an arrow
+const ARROW_FROM: Color = Color::Indexed(28);
+const ARROW_TO: Color = Color::Indexed(34);
+
+/// A position drawn as tess draws it, e.g.
+/// `BoardWidget::new(&pos).flipped(true).theme(&palette)`
+pub struct BoardWidget<'a> {
+    board: &'a Board,
+    turn: ChessColor,
+    flipped: bool,
+    palette: Palette,
+    display: DisplayMode,
+    cell_width: usize,
+    cell_height: usize,
+    pieces: PieceSet,
+    plain: bool,
+    highlights: Vec<(Square, Color)>,
+    arrow: Option<(Square, Square)>,
+    wide_symbols: bool,
+}
+
+impl<'a> BoardWidget<'a> {
+    /// The board of `pos` from white's side, simple pieces, dark background colors
+    pub fn new<P: Position>(pos: &'a P) -> Self {
+        let display = DisplayMode::Simple;
+        let (cell_width, cell_height) = display.default_cell_dimensions();
+        Self {
+            board: pos.board(),
+            turn: pos.turn(),
+            flipped: false,
+            palette: Palette::for_background(Background::Dark),
+            display,
+            cell_width,
+            cell_height,
+            pieces: PieceSet::Symbols,
+            plain: false,
+            highlights: Vec::new(),
+            arrow: None,
+            wide_symbols: false,
+        }
+    }
+
+    /// Draw the board from black's side
+    pub fn flipped(mut self, flipped: bool) -> Self {
+        self.flipped = flipped;
+        self
+    }
+
+    pub fn theme(mut self, palette: &Palette) -> Self {
+        self.palette = *palette;
+        self
+    }
+
+    /// The piece style, with squares of its default size
+    pub fn display(mut self, display: DisplayMode) -> Self {
+        self.display = display;
+        (self.cell_width, self.cell_height) = display.default_cell_dimensions();
+        self
+    }
+
+    /// Characters of a square, at least those of the display's default size
+    pub fn cell_size(mut self, width: usize, height: usize) -> Self {
+        let (min_width, min_height) = self.display.default_cell_dimensions();
+        self.cell_width = width.max(min_width);
+        self.cell_height = height.max(min_height);
+        self
+    }
+
+    /// The characters of the pieces in the simple display
+    pub fn pieces(mut self, pieces: PieceSet) -> Self {
+        self.pieces = pieces;
+        self
+    }
+
+    /// No colors, dark squares marked with '.', pieces as letters, for terminals
+    /// and links that do not take more
+    pub fn plain(mut self, plain: bool) -> Self {
+        self.plain = plain;
+        self
+    }
+
+    /// The terminal draws the chess symbols and the East Asian ambiguous
+    /// characters (•, ●, arrows) two columns wide, as CJK fonts do
+    pub fn wide_symbols(mut self, wide_symbols: bool) -> Self {
+        self.wide_symbols = wide_symbols;
+        self
+    }
+
+    /// A background for `square`; the first one given for a square counts
+    pub fn highlight(mut self, square: Square, color: Color) -> Self {
+        self.highlights.push((square, color));
+        self
+    }
+
+    /// An arrow from one square to another, under the highlights; none in plain mode
+    pub fn arrow(mut self, from: Square, to: Square) -> Self {
+        self.arrow = Some((from, to));
+        self
+    }
+
+    /// Columns and rows the board takes, labels included
+    pub fn size(&self) -> (u16, u16) {
+        (
+            (8 * self.cell_width + 2) as u16,
+            (8 * self.cell_height + 2) as u16,
+        )
+    }
+
+    /// The square drawn at column `x` and row `y` of the widget, None on the
+    /// labels and the border
+    pub fn square_at(&self, x: u16, y: u16) -> Option<Square> {
+        let col = usize::from(x).checked_sub(1)? / self.cell_width;
+        let row = usize::from(y).checked_sub(1)? / self.cell_height;
+        if col >= 8 || row >= 8 {
+            return None;
+        }
+        let (file, rank) = if self.flipped {
+            (7 - col, row)
+        } else {
+            (col, 7 - row)
+        };
+        Some(Square::from_coords(
+            File::new(file as u32),
+            Rank::new(rank as u32),
+        ))
+    }
+
+    /// The board as styled lines, one per row of the terminal, for ratatui: a
+    /// space stands in for a cell that the wide character before it covers
+    pub fn lines(&self) -> Vec<Line<'static>> {
+        self.grid().lines(true)
+    }
+
+    /// The board as styled lines to write to the terminal directly, which moves
+    /// past the cells a wide character covers by itself
+    pub fn printed_lines(&self) -> Vec<Line<'static>> {
+        self.grid().lines(false)
+    }
+
+    // Every cell of the board
+    fn grid(&self) -> Grid {
+        let (cell_width, cell_height) = (self.cell_width, self.cell_height);
+        let board_width = 8 * cell_width;
+        let board_height = 8 * cell_height;
+
+        let palette = &self.palette;
+        let mut pink_style = Style::default()
+            .fg(palette.white_piece)
+            .bg(palette.dark_square);
+        let mut yellow_style = Style::default()
+            .fg(palette.white_piece)
+            .bg(palette.light_square);
+        let mut label_style = Style::default().fg(palette.label).bg(Color::Reset);
+        let mut white_piece_style = Style::default().fg(palette.white_piece);
+        let mut black_piece_style = Style::default().fg(palette.black_piece);
+
+        // Plain text, dark squares marked with '.', pieces as letters
+        if self.plain {
+            pink_style = Style::default();
+            yellow_style = Style::default();
+            label_style = Style::default();
+            white_piece_style = Style::default();
+            black_piece_style = Style::default();
+        }
+
+        // Prepare piece ASCII map
+        let ascii_map = piece_ascii_map();
+
+        let mut buffer = Grid::new(board_width + 2, board_height + 2, self.wide_symbols);
+
+        let arrow = self.arrow.filter(|_| !self.plain);
+        // top-left corner of a square's cell in the buffer
+        let cell_of = |sq: Square| {
+            let (file, rank) = (usize::from(sq.file()), usize::from(sq.rank()));
+            let (col, row) = if self.flipped {
+                (7 - file, rank)
+            } else {
+                (file, 7 - rank)
+            };
+            (col * cell_width + 1, row * cell_height + 1)
+        };
+
+        // Fill squares
+        for row in 0..8 {
+            for col in 0..8 {
+                // top-left corner of this cell in the buffer
+                let cell_x = col * cell_width + 1;
+                let cell_y = row * cell_height + 1;
+
+                let (file, rank) = if self.flipped {
+                    (7 - col, row)
+                } else {
+                    (col, 7 - row)
+                };
+                let sq = Square::from_coords(File::new(file as u32), Rank::new(rank as u32));
+
+                // color
+                let mut style = if (row + col) % 2 == 0 {
+                    // "light" square => yellow
+                    yellow_style
+                } else {
+                    pink_style
+                };
+
+                if let Some((_, bg)) = self.highlights.iter().find(|(s, _)| *s == sq) {
+                    style = style.bg(*bg);
+                } else if let Some((from, _)) = arrow.filter(|(from, to)| sq == *from || sq == *to)
+                {
+                    style = style.bg(if sq == from { ARROW_FROM } else { ARROW_TO });
+                }
+
+                // fill with spaces
+                for dy in 0..cell_height {
+                    for dx in 0..cell_width {
+                        buffer.put(cell_x + dx, cell_y + dy, ' ', style);
+                    }
+                }
+                if self.plain && (row + col) % 2 == 1 {
+                    buffer.put(
+                        cell_x + cell_width / 2,
+                        cell_y + cell_height / 2,
+                        '.',
+                        style,
+                    );
+                }
+
+                // place piece ASCII if any
+                if let Some(piece) = self.board.piece_at(sq) {
+                    let piece_style = if piece.color == ChessColor::White {
+                        white_piece_style
+                    } else {
+                        black_piece_style
+                    }
+                    .bg(style.bg.unwrap_or(Color::Reset)); // Use the same background color as the square
+
+                    if self.display == DisplayMode::Simple {
+                        let symbol_char = if self.plain || self.pieces == PieceSet::Letters {
+                            piece_char(piece)
+                        } else {
+                            piece_unicode(piece)
+                        };
+                        let (x, y) = (cell_x + (cell_width - 1) / 2, cell_y + cell_height / 2);
+                        if !buffer.put(x, y, symbol_char, piece_style) {
+                            buffer.put(x, y, piece_char(piece), piece_style);
+                        }
+                    } else {
+                        let symbol_char = piece_char(piece);
+                        if let Some(shape_lines) = ascii_map.get(&symbol_char) {
+                            let shape_height = shape_lines.len();
+                            let shape_width =
+                                shape_lines.iter().map(|l| l.len()).max().unwrap_or(0);
+
+                            let offset_y = (cell_height.saturating_sub(shape_height)) / 2;
+                            let offset_x = (cell_width.saturating_sub(shape_width)) / 2;
+
+                            for (sy, line) in shape_lines.iter().enumerate() {
+                                let ty = cell_y + offset_y + sy;
+                                if ty >= board_height + 2 {
+                                    break;
+                                }
+                                for (sx, ch) in line.chars().enumerate() {
+                                    let tx = cell_x + offset_x + sx;
+                                    if tx >= board_width + 2 {
+                                        break;
+                                    }
+                                    buffer.put(tx, ty, ch, piece_style);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        // The arrow over the squares: dots along the way, the head in a corner of
+        // the destination pointing the move's way
+        if let Some((from, to)) = arrow {
+            use std::cmp::Ordering;
+            let center = |(x, y): (usize, usize)| (x + (cell_width - 1) / 2, y + cell_height / 2);
+            for sq in shakmaty::attacks::between(from, to) {
+                let (x, y) = center(cell_of(sq));
+                if self.board.piece_at(sq).is_none() {
+                    let style = buffer.style(x, y).fg(ARROW_TO);
+                    buffer.put(x, y, '•', style);
+                }
+            }
+            let (from_x, from_y) = cell_of(from);
+            let (to_x, to_y) = cell_of(to);
+            let head = match (to_x.cmp(&from_x), to_y.cmp(&from_y)) {
+                (Ordering::Less, Ordering::Less) => '↖',
+                (Ordering::Equal, Ordering::Less) => '↑',
+                (Ordering::Greater, Ordering::Less) => '↗',
+                (Ordering::Less, Ordering::Equal) => '←',
+                (Ordering::Greater, Ordering::Equal) => '→',
+                (Ordering::Less, _) => '↙',
+                (Ordering::Equal, _) => '↓',
+                (Ordering::Greater, _) => '↘',
+            };
+            let style = buffer.style(to_x, to_y).fg(palette.white_piece);
+            buffer.put(to_x, to_y, head, style);
+        }
+
+        // Now we also want rank and file indicators.
+        // Ranks on left: row => (8-row), or row+1 when flipped
+        for row in 0..8 {
+            let label = format!("{}", if self.flipped { row + 1 } else { 8 - row });
+            // place at x=0, level with the middle of the rank
+            // we only place it if there's space
+            let py = row * cell_height + 1 + cell_height / 2;
+            for (i, ch) in label.chars().enumerate() {
+                if i < board_height + 2 {
+                    buffer.put(i, py, ch, label_style);
+                }
+            }
+        }
+
+        // Files on bottom: col => A..H
+        let file_labels = ['A', 'B', 'C', 'D', 'E', 'F', 'G', 'H'];
+        let bottom_y = board_height + 1;
+        for (col, &ch) in file_labels.iter().enumerate() {
+            let col = if self.flipped { 7 - col } else { col };
+            // under the middle of the file, where simple pieces are drawn
+            let px = col * cell_width + 1 + (cell_width - 1) / 2;
+            if px < board_width + 2 {
+                buffer.put(px, bottom_y, ch, label_style);
+            }
+        }
+
+        // Side to move: a marker right of the board, on that side's edge
+        let at_bottom = (self.turn == ChessColor::White) != self.flipped;
+        let row = if at_bottom { 7 } else { 0 };
+        let marker_style = if self.turn == ChessColor::White {
+            white_piece_style
+        } else {
+            black_piece_style
+        };
+        let marker_y = row * cell_height + 1 + cell_height / 2;
+        // the last column has no room for a wide marker
+        if self.plain || !buffer.put(board_width + 1, marker_y, '●', marker_style) {
+            buffer.put(board_width + 1, marker_y, '<', marker_style);
+        }
+        buffer
+    }
+}
+
+impl Widget for BoardWidget<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let grid = self.grid();
+        let hidden = grid.hidden();
+        Paragraph::new(grid.lines(true)).render(area, buf);
+        // the terminal draws the wide character over those cells, writing
+        // them would push the rest of the row right
+        for (x, y) in hidden {
+            if x < area.width && y < area.height {
+                buf[(area.x + x, area.y + y)].set_skip(true);
+            }
+        }
+    }
+}
+
+// ----------------------------------------------
+// The cells of the board, one per terminal column: a wide character takes
+// its own and covers the next, so the squares after it stay in place
+// ----------------------------------------------
+
+#[derive(Clone, Copy)]
+struct Cell {
+    ch: char,
+    style: Style,
+    // taken by the wide character on the left
+    covered: bool,
+}
+
+struct Grid {
+    rows: Vec<Vec<Cell>>,
+    wide_symbols: bool,
+}
+
+impl Grid {
+    fn new(width: usize, height: usize, wide_symbols: bool) -> Self {
+        let blank = Cell {
+            ch: ' ',
+            style: Style::default(),
+            covered: false,
+        };
+        Self {
+            rows: vec![vec![blank; width]; height],
+            wide_symbols,
+        }
+    }
+
+    // Columns `ch` takes on the terminal: as unicode-width has it, or as CJK
+    // fonts draw them, with the chess symbols wide too
+    fn width(&self, ch: char) -> usize {
+        if !self.wide_symbols {
+            ch.width().unwrap_or(0)
+        } else if ('♔'..='♟').contains(&ch) {
+            2
+        } else {
+            ch.width_cjk().unwrap_or(0)
+        }
+    }
+
+    fn style(&self, x: usize, y: usize) -> Style {
+        self.rows[y][x].style
+    }
+
+    // Put `ch` at column `x` of row `y`; false, leaving the row as it was, if
+    // it takes no column or more than are left
+    fn put(&mut self, x: usize, y: usize, ch: char, style: Style) -> bool {
+        let width = self.width(ch);
+        let row = &mut self.rows[y];
+        if width == 0 || x + width > row.len() {
+            return false;
+        }
+        // a wide character partly overwritten is blanked
+        if row[x].covered {
+            row[x - 1].ch = ' ';
+        }
+        let end = x + width;
+        if let Some(next) = row.get_mut(end).filter(|next| next.covered) {
+            *next = Cell {
+                ch: ' ',
+                covered: false,
+                ..*next
+            };
+        }
+        row[x] = Cell {
+            ch,
+            style,
+            covered: false,
+        };
+        for cell in &mut row[x + 1..end] {
+            *cell = Cell {
+                ch: ' ',
+                style,
+                covered: true,
+            };
+        }
+        true
+    }
+
+    // Whether ratatui, which measures with unicode-width, counts the cell as
+    // part of the wide character before it
+    fn counted(row: &[Cell], x: usize) -> bool {
+        row[x].covered && row[x - 1].ch.width().unwrap_or(0) > 1
+    }
+
+    // The covered cells ratatui does not know of, by column and row
+    fn hidden(&self) -> Vec<(u16, u16)> {
+        let mut hidden = Vec::new();
+        for (y, row) in self.rows.iter().enumerate() {
+            for x in 0..row.len() {
+                if row[x].covered && !Grid::counted(row, x) {
+                    hidden.push((x as u16, y as u16));
+                }
+            }
+        }
+        hidden
+    }
+
+    // One line per row, consecutive cells of a style in one span; the covered
+    // cells as spaces with `keep_covered`, where ratatui does not count them
+    fn lines(&self, keep_covered: bool) -> Vec<Line<'static>> {
+        self.rows
+            .iter()
+            .map(|row| {
+                let mut spans: Vec<Span> = Vec::new();
+                let mut current_style = row[0].style;
+                let mut current_text = String::new();
+                for (x, cell) in row.iter().enumerate() {
+                    if cell.covered && (!keep_covered || Grid::counted(row, x)) {
+                        continue;
+                    }
+                    if cell.style != current_style {
+                        spans.push(Span::styled(
+                            std::mem::take(&mut current_text),
+                            current_style,
+                        ));
+                        current_style = cell.style;
+                    }
+                    current_text.push(cell.ch);
+                }
+                spans.push(Span::styled(current_text, current_style));
+                Line::from(spans)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shakmaty::Chess;
+
+    fn text(widget: &BoardWidget) -> Vec<String> {
+        widget
+            .lines()
+            .iter()
+            .map(|line| line.spans.iter().map(|span| &*span.content).collect())
+            .collect()
+    }
+
+    #[test]
+    fn draws_the_start_position_from_either_side() {
+        let pos = Chess::default();
+        let board = text(&BoardWidget::new(&pos).plain(true));
+        assert_eq!(board[1], "8r n.b q.k b.n r. ");
+        assert_eq!(board[8], "1R.N B.Q K.B N.R <");
+        assert_eq!(board[9], " A B C D E F G H  ");
+
+        let flipped = text(&BoardWidget::new(&pos).plain(true).flipped(true));
+        assert_eq!(flipped[1], "1R N.B K.Q B.N R.<");
+        assert_eq!(flipped[9], " H G F E D C B A  ");
+    }
+
+    #[test]
+    fn wide_symbols_keep_the_squares_in_place() {
+        let pos = Chess::default();
+        let board = BoardWidget::new(&pos).wide_symbols(true);
+        let printed: Vec<String> = board
+            .printed_lines()
+            .iter()
+            .map(|line| line.spans.iter().map(|span| &*span.content).collect())
+            .collect();
+        // each symbol takes its whole square, no room is left for the marker's dot
+        assert_eq!(printed[1], "8♖♘♗♕♔♗♘♖ ");
+        assert_eq!(printed[8], "1♖♘♗♕♔♗♘♖<");
+
+        let area = Rect::new(0, 0, 18, 10);
+        let mut buf = Buffer::empty(area);
+        board.render(area, &mut buf);
+        assert_eq!(buf[(1, 1)].symbol(), "♖");
+        assert!(buf[(2, 1)].skip);
+        assert_eq!(buf[(3, 1)].symbol(), "♘");
+        assert_eq!(buf[(17, 8)].symbol(), "<");
+        assert_eq!(buf[(1, 9)].symbol(), "A");
+    }
+
+    #[test]
+    fn finds_the_square_under_a_cell() {
+        let pos = Chess::default();
+        let board = BoardWidget::new(&pos).cell_size(4, 2);
+        assert_eq!(board.square_at(1, 1), Some(Square::A8));
+        assert_eq!(board.square_at(8, 16), Some(Square::B1));
+        assert_eq!(board.square_at(0, 5), None);
+        assert_eq!(board.square_at(33, 5), None);
+        let flipped = BoardWidget::new(&pos).cell_size(4, 2).flipped(true);
+        assert_eq!(flipped.square_at(1, 1), Some(Square::H1));
+    }
+}
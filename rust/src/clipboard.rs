@@ -0,0 +1,66 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+// ----------------------------------------------
+// Clipboard: the platform's copy tool when there is one, otherwise
+// the OSC 52 escape, which most terminals (and SSH sessions) honour
+// ----------------------------------------------
+
+// tool, its arguments, and the variable that has to be set for it to work
+const TOOLS: &[(&str, &[&str], Option<&str>)] = &[
+    ("pbcopy", &[], None),
+    ("wl-copy", &[], Some("WAYLAND_DISPLAY")),
+    ("xclip", &["-selection", "clipboard"], Some("DISPLAY")),
+    ("xsel", &["--clipboard", "--input"], Some("DISPLAY")),
+];
+
+/// Copy `text`, returning how it was done
+pub fn copy(text: &str) -> anyhow::Result<&'static str> {
+    for (tool, args, needs) in TOOLS {
+        if needs.is_some_and(|var| std::env::var_os(var).is_none()) {
+            continue;
+        }
+        if pipe_to(tool, args, text).is_ok() {
+            return Ok(tool);
+        }
+    }
+    let mut out = std::io::stdout();
+    write!(out, "\x1b]52;c;{}\x07", base64(text.as_bytes()))?;
+    out.flush()?;
+    Ok("the terminal (OSC 52)")
+}
+
+fn pipe_to(tool: &str, args: &[&str], text: &str) -> anyhow::Result<()> {
+    let mut child = Command::new(tool)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+    if !child.wait()?.success() {
+        anyhow::bail!("{tool} failed");
+    }
+    Ok(())
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
@@ -0,0 +1,208 @@
+use serde::{Deserialize, Serialize};
+use shakmaty::Color as ChessColor;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use crate::session_log;
+
+// ----------------------------------------------
+// Chess clock with Fischer increment, simple delay and Bronstein delay
+// ----------------------------------------------
+
+#[derive(Debug, clap::ValueEnum, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum IncrementKind {
+    /// the increment is added after every move
+    Fischer,
+    /// the clock only starts running after the delay (US delay)
+    Delay,
+    /// time used is given back, up to the increment
+    Bronstein,
+}
+
+/// Base time plus per-move increment or delay, written like "5+3"
+/// (minutes + seconds)
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TimeControl {
+    pub base: Duration,
+    pub increment: Duration,
+}
+
+impl TimeControl {
+    /// "10+0.1", base seconds + increment seconds, as engine matches give it
+    pub fn from_seconds(s: &str) -> Result<Self, String> {
+        parse(s, 1.0, "seconds")
+    }
+}
+
+// The base time in `unit` seconds, the increment always in seconds
+fn parse(s: &str, unit: f64, unit_name: &str) -> Result<TimeControl, String> {
+    let (base, increment) = s.split_once('+').unwrap_or((s, "0"));
+    let base_time: f64 = base
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid base time '{base}', expected {unit_name}"))?;
+    let seconds: f64 = increment
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid increment '{increment}', expected seconds"))?;
+    if base_time <= 0.0 || seconds < 0.0 {
+        return Err("time control must have a positive base time".to_string());
+    }
+    Ok(TimeControl {
+        base: Duration::from_secs_f64(base_time * unit),
+        increment: Duration::from_secs_f64(seconds),
+    })
+}
+
+impl FromStr for TimeControl {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse(s, 60.0, "minutes")
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Clock {
+    pub control: TimeControl,
+    pub kind: IncrementKind,
+    white: Duration,
+    black: Duration,
+    // side whose clock runs and when its turn started
+    running: Option<(ChessColor, Instant)>,
+    // time the turn used before a pause, already off the stored time
+    earlier: Duration,
+}
+
+impl Clock {
+    pub fn new(control: TimeControl, kind: IncrementKind) -> Self {
+        Self {
+            control,
+            kind,
+            white: control.base,
+            black: control.base,
+            running: None,
+            earlier: Duration::ZERO,
+        }
+    }
+
+    /// A stopped clock showing these times, as an adjourned game left it
+    pub fn with_remaining(mut self, white: Duration, black: Duration) -> Self {
+        self.white = white;
+        self.black = black;
+        self
+    }
+
+    /// With `used` of the turn gone before it was adjourned, for `resume`
+    pub fn with_turn_used(mut self, used: Duration) -> Self {
+        self.earlier = used;
+        self
+    }
+
+    pub fn start(&mut self, color: ChessColor) {
+        self.earlier = Duration::ZERO;
+        self.running = Some((color, session_log::now()));
+    }
+
+    pub fn stop(&mut self) {
+        self.pause();
+        self.earlier = Duration::ZERO;
+    }
+
+    /// Stop the clock in the middle of a turn, remembering the time the turn used
+    pub fn pause(&mut self) {
+        if let Some((color, since)) = self.running {
+            *self.stored_mut(color) = self.remaining(color);
+            self.earlier += session_log::elapsed(since);
+        }
+        self.running = None;
+    }
+
+    /// Go on with the turn of `color` that `pause` stopped: a delay it used
+    /// up is not given again
+    pub fn resume(&mut self, color: ChessColor) {
+        self.running = Some((color, session_log::now()));
+    }
+
+    /// The time used in the turn a pause stopped
+    pub fn turn_used(&self) -> Duration {
+        self.earlier
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.is_some()
+    }
+
+    pub fn active(&self) -> Option<ChessColor> {
+        self.running.map(|(color, _)| color)
+    }
+
+    fn stored_mut(&mut self, color: ChessColor) -> &mut Duration {
+        match color {
+            ChessColor::White => &mut self.white,
+            ChessColor::Black => &mut self.black,
+        }
+    }
+
+    fn stored(&self, color: ChessColor) -> Duration {
+        color.fold_wb(self.white, self.black)
+    }
+
+    // Time charged for `used` more of the current turn, after what it used before a pause
+    fn charged(&self, used: Duration) -> Duration {
+        match self.kind {
+            IncrementKind::Delay => {
+                let delay = self.control.increment;
+                (self.earlier + used)
+                    .saturating_sub(delay)
+                    .saturating_sub(self.earlier.saturating_sub(delay))
+            }
+            IncrementKind::Fischer | IncrementKind::Bronstein => used,
+        }
+    }
+
+    /// Time left for `color`, counting down live for the side to move
+    pub fn remaining(&self, color: ChessColor) -> Duration {
+        match self.running {
+            Some((active, since)) if active == color => self
+                .stored(color)
+                .saturating_sub(self.charged(session_log::elapsed(since))),
+            _ => self.stored(color),
+        }
+    }
+
+    /// `color` finished a move: settle its time and start the opponent's clock
+    pub fn press(&mut self, color: ChessColor) {
+        let used = match self.running {
+            Some((active, since)) if active == color => session_log::elapsed(since),
+            _ => Duration::ZERO,
+        };
+        let left = self.stored(color).saturating_sub(self.charged(used));
+        let bonus = match self.kind {
+            IncrementKind::Fischer => self.control.increment,
+            IncrementKind::Bronstein => (self.earlier + used).min(self.control.increment),
+            IncrementKind::Delay => Duration::ZERO,
+        };
+        *self.stored_mut(color) = left + bonus;
+        self.start(color.other());
+    }
+
+    /// The side whose flag fell, if any
+    pub fn flagged(&self) -> Option<ChessColor> {
+        [ChessColor::White, ChessColor::Black]
+            .into_iter()
+            .find(|color| self.remaining(*color).is_zero())
+    }
+}
+
+/// "4:59", with tenths below ten seconds ("0:09.4")
+pub fn format_duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    if secs < 10 {
+        format!("0:{:02}.{}", secs, d.subsec_millis() / 100)
+    } else if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
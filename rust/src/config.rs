@@ -0,0 +1,195 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::notation::Language;
+use crate::opponents::Profile;
+use tess_rs::board::{DisplayMode, PieceSet};
+use tess_rs::theme::Background;
+
+// ----------------------------------------------
+// User configuration, read from $XDG_CONFIG_HOME/tess/config.json
+// (or ~/.config/tess/config.json). Every field is optional.
+// ----------------------------------------------
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Language of the piece letters accepted in SAN input
+    pub input_language: Option<Language>,
+    /// Play a move once its source and destination square are typed, without Enter
+    pub quick_input: bool,
+    /// Terminal background the board colors are picked for
+    pub background: Option<Background>,
+    /// How pieces are drawn, "big" or "simple"
+    pub display: Option<DisplayMode>,
+    /// The characters of the pieces in the simple display, "symbols" or "letters"
+    pub pieces: Option<PieceSet>,
+    /// The terminal's font draws the chess symbols and characters like • two
+    /// columns wide, as CJK fonts do
+    pub wide_symbols: bool,
+    /// A UCI engine to evaluate the position on screen all the time, as with
+    /// `--analysis-engine`
+    pub analysis_engine: Option<PathBuf>,
+    /// Pane sizes chosen with the resize keys
+    pub layout: Layout,
+    /// Score lost for each kind of hint taken in a puzzle
+    pub hint_penalties: HintPenalties,
+    /// Time of day, UTC like the activity days ("18:30"), to be reminded of
+    /// training when nothing was trained yet; by the TUI and by `tess daemon`
+    pub reminder: Option<String>,
+    /// What 'H' shows in a game: "piece" (the piece to move, again for the
+    /// whole move) or "move"
+    pub game_hint: GameHint,
+    /// Needed by `:unlock` to leave the spectator lock
+    pub lock_password: Option<String>,
+    /// Personal API token (scope `preference:read`) to take settings from the lichess account
+    pub lichess_token: Option<String>,
+    /// Keep the token but do not take over the account's preferences
+    pub ignore_lichess_preferences: bool,
+    /// UCI options for every engine, e.g. {"Hash": "256", "Threads": "2"}
+    pub engine_options: BTreeMap<String, String>,
+    /// Opponents for `--opponent` by name, e.g. {"maia1500": {"engine": "lc0",
+    /// "weights": "maia-1500.pb.gz", "nodes": 1}}
+    pub opponents: BTreeMap<String, Profile>,
+    /// Directories of Syzygy tablebases, ':' separated like the engines' SyzygyPath
+    pub syzygy_path: Option<String>,
+    /// Without `syzygy_path`, do not ask the lichess tablebase about the endgames on screen
+    pub no_tablebase_lookup: bool,
+    /// Keep what the analysis engine found in $XDG_STATE_HOME/tess, so the
+    /// positions of the next session start from it
+    pub persist_analysis: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Layout {
+    /// Extra size of the board squares over each display's default
+    pub board_scale: BoardScale,
+    /// Width of the move list in columns
+    pub moves_width: u16,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self {
+            board_scale: BoardScale::default(),
+            moves_width: 24,
+        }
+    }
+}
+
+/// Square size steps for the big and the simple display, which suit different fonts
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, from = "ScaleSetting")]
+pub struct BoardScale {
+    pub big: usize,
+    pub simple: usize,
+}
+
+impl BoardScale {
+    /// The entry of `display`
+    pub fn of(&mut self, display: DisplayMode) -> &mut usize {
+        match display {
+            DisplayMode::Big => &mut self.big,
+            DisplayMode::Simple => &mut self.simple,
+        }
+    }
+}
+
+// Older config files have a single scale for both displays
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ScaleSetting {
+    Both(usize),
+    PerDisplay {
+        #[serde(default)]
+        big: usize,
+        #[serde(default)]
+        simple: usize,
+    },
+}
+
+impl From<ScaleSetting> for BoardScale {
+    fn from(setting: ScaleSetting) -> Self {
+        match setting {
+            ScaleSetting::Both(scale) => Self {
+                big: scale,
+                simple: scale,
+            },
+            ScaleSetting::PerDisplay { big, simple } => Self { big, simple },
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HintPenalties {
+    /// Showing which piece moves
+    pub piece: f64,
+    /// Showing the whole move
+    pub full_move: f64,
+}
+
+impl Default for HintPenalties {
+    fn default() -> Self {
+        Self {
+            piece: 0.3,
+            full_move: 0.7,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GameHint {
+    #[default]
+    Piece,
+    Move,
+}
+
+pub fn config_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join("tess"))
+}
+
+fn config_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("config.json"))
+}
+
+/// No config file yet, where there could be one: the first run
+pub fn is_missing() -> bool {
+    config_path().is_some_and(|path| !path.exists())
+}
+
+/// Load the config file; a missing file yields the defaults
+pub fn load() -> anyhow::Result<Config> {
+    let Some(path) = config_path() else {
+        return Ok(Config::default());
+    };
+    match std::fs::read_to_string(&path) {
+        Ok(text) => serde_json::from_str(&text)
+            .map_err(|err| anyhow::anyhow!("invalid config file {}: {err}", path.display())),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Write the config file, creating the directory if needed
+pub fn save(config: &Config) -> anyhow::Result<()> {
+    let path = config_path().ok_or_else(|| anyhow::anyhow!("no config directory (HOME unset)"))?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(&path, serde_json::to_string_pretty(config)? + "\n")?;
+    Ok(())
+}
+
+/// Remember a new pane layout, keeping the rest of the config file as it is
+pub fn save_layout(layout: Layout) -> anyhow::Result<()> {
+    let mut config = load()?;
+    config.layout = layout;
+    save(&config)
+}
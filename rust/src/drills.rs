@@ -0,0 +1,76 @@
+use crate::{Game, LichessNextPuzzle, Puzzle};
+
+// ----------------------------------------------
+// Built-in drills: hand-picked positions for one technique, played
+// like a puzzle set but without lichess
+// ----------------------------------------------
+
+#[derive(Debug, clap::ValueEnum, Clone, Copy, PartialEq)]
+pub enum Drill {
+    /// promote to a rook or a knight where a queen does not win
+    Underpromotion,
+}
+
+struct DrillPuzzle {
+    id: &'static str,
+    rating: u16,
+    fen: &'static str,
+    // UCI moves, the opponent's replies included
+    solution: &'static [&'static str],
+}
+
+const UNDERPROMOTION: &[DrillPuzzle] = &[
+    DrillPuzzle {
+        id: "knight-mate",
+        rating: 1300,
+        fen: "8/pP6/kp6/8/1K2B3/8/8/8 w - - 0 1",
+        solution: &["b7b8n"],
+    },
+    DrillPuzzle {
+        id: "knight-mate-black",
+        rating: 1300,
+        fen: "8/8/8/1k2b3/8/KP6/Pp6/8 b - - 0 1",
+        solution: &["b2b1n"],
+    },
+    // Saavedra: c8=Q is met by Rc4+ and stalemate after Qxc4
+    DrillPuzzle {
+        id: "saavedra",
+        rating: 1900,
+        fen: "8/2P5/8/8/3r4/8/2K5/k7 w - - 0 1",
+        solution: &["c7c8r", "d4a4", "c2b3"],
+    },
+];
+
+impl Drill {
+    pub fn name(self) -> &'static str {
+        match self {
+            Drill::Underpromotion => "underpromotion",
+        }
+    }
+
+    fn puzzles(self) -> &'static [DrillPuzzle] {
+        match self {
+            Drill::Underpromotion => UNDERPROMOTION,
+        }
+    }
+
+    pub fn count(self) -> usize {
+        self.puzzles().len()
+    }
+
+    /// The puzzle at `index` in the shape lichess sends
+    pub fn puzzle(self, index: usize) -> Option<LichessNextPuzzle> {
+        let drill = self.puzzles().get(index)?;
+        Some(LichessNextPuzzle {
+            puzzle: Puzzle {
+                id: format!("{}-{}", self.name(), drill.id),
+                rating: drill.rating,
+                solution: drill.solution.iter().map(|m| m.to_string()).collect(),
+                initial_ply: 0,
+            },
+            game: Game {
+                pgn: format!("[FEN \"{}\"]\n\n*", drill.fen),
+            },
+        })
+    }
+}
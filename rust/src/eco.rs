@@ -0,0 +1,184 @@
+use shakmaty::fen::Epd;
+use shakmaty::san::SanPlus;
+use shakmaty::{Chess, EnPassantMode, Move, Position};
+use std::collections::HashMap;
+
+use crate::config;
+use crate::eval_quiz::random;
+
+// ----------------------------------------------
+// ECO classification: "B90 Sicilian Defense: Najdorf Variation" for the
+// position on screen, from a table of named lines. A small table is
+// built in; the lichess chess-openings files (a.tsv to e.tsv) put in
+// $XDG_CONFIG_HOME/tess/eco add theirs over it
+// ----------------------------------------------
+
+// Tab separated: code, name and the line's moves, under a header
+const BUILT_IN: &str = include_str!("eco.tsv");
+// No line of the tables is longer
+const MAX_PLIES: usize = 40;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Opening {
+    pub eco: String,
+    pub name: String,
+}
+
+impl Opening {
+    /// "B90 Sicilian Defense: Najdorf Variation"
+    pub fn describe(&self) -> String {
+        format!("{} {}", self.eco, self.name)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Eco {
+    // by the EPD at the end of each line, so transpositions find it too
+    positions: HashMap<String, Opening>,
+    // the moves of every line, for starting games from them
+    lines: Vec<Vec<Move>>,
+}
+
+fn key(pos: &Chess) -> String {
+    Epd::from_position(pos.clone(), EnPassantMode::Legal).to_string()
+}
+
+impl Eco {
+    /// The built-in table and the files in the config directory; lines that
+    /// do not read are left out
+    pub fn load() -> Self {
+        let mut eco = Self::default();
+        eco.add_table(BUILT_IN);
+        let files = config::config_dir()
+            .and_then(|dir| std::fs::read_dir(dir.join("eco")).ok())
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "tsv"));
+        let mut files: Vec<_> = files.collect();
+        files.sort();
+        for path in files {
+            if let Ok(text) = std::fs::read_to_string(path) {
+                eco.add_table(&text);
+            }
+        }
+        eco
+    }
+
+    fn add_table(&mut self, text: &str) {
+        for line in text.lines() {
+            let mut fields = line.split('\t');
+            let (Some(eco), Some(name), Some(moves)) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                continue;
+            };
+            if let Some((pos, moves)) = play_line(moves) {
+                let opening = Opening {
+                    eco: eco.to_string(),
+                    name: name.to_string(),
+                };
+                self.positions.insert(key(&pos), opening);
+                self.lines.push(moves);
+            }
+        }
+    }
+
+    /// The opening of a game from `start`: that of the last position along
+    /// `moves` in the tables
+    pub fn classify(&self, start: &Chess, moves: &[Move]) -> Option<&Opening> {
+        let mut pos = start.clone();
+        let mut found = self.positions.get(&key(&pos));
+        for mv in moves.iter().take(MAX_PLIES) {
+            pos.play_unchecked(mv);
+            found = self.positions.get(&key(&pos)).or(found);
+        }
+        found
+    }
+
+    /// The moves of a line picked at random, its first `depth` plies of one at
+    /// least as long if given; None if no line is
+    pub fn random_line(&self, seed: &mut u64, depth: Option<usize>) -> Option<&[Move]> {
+        let depth = depth.unwrap_or(0);
+        let lines: Vec<&Vec<Move>> = self
+            .lines
+            .iter()
+            .filter(|line| !line.is_empty() && line.len() >= depth)
+            .collect();
+        if lines.is_empty() {
+            return None;
+        }
+        let line = lines[random(seed, lines.len())];
+        Some(if depth == 0 { line } else { &line[..depth] })
+    }
+}
+
+// The position at the end of "1. e4 c5 2. Nf3" and the moves there, None
+// for a header or a line with a move that does not play
+fn play_line(moves: &str) -> Option<(Chess, Vec<Move>)> {
+    let mut pos = Chess::default();
+    let mut line = Vec::new();
+    for word in moves.split_whitespace() {
+        // move numbers, "1." and "1..."
+        if word.ends_with('.') {
+            continue;
+        }
+        let san: SanPlus = word.parse().ok()?;
+        let mv = san.san.to_move(&pos).ok()?;
+        pos.play_unchecked(&mv);
+        line.push(mv);
+    }
+    Some((pos, line))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_built_in_line_plays() {
+        for line in BUILT_IN.lines().skip(1) {
+            let moves = line.split('\t').nth(2).unwrap_or_default();
+            assert!(play_line(moves).is_some(), "{line}");
+        }
+    }
+
+    #[test]
+    fn deepest_known_position_names_the_game() {
+        let mut eco = Eco::default();
+        eco.add_table(BUILT_IN);
+        let mut pos = Chess::default();
+        let mut moves = Vec::new();
+        for san in [
+            "e4", "c5", "Nf3", "d6", "d4", "cxd4", "Nxd4", "Nf6", "Nc3", "a6", "h3",
+        ] {
+            let mv = san.parse::<SanPlus>().unwrap().san.to_move(&pos).unwrap();
+            pos.play_unchecked(&mv);
+            moves.push(mv);
+        }
+        let opening = eco.classify(&Chess::default(), &moves).unwrap();
+        assert_eq!(
+            opening.describe(),
+            "B90 Sicilian Defense: Najdorf Variation"
+        );
+        assert_eq!(
+            eco.classify(&Chess::default(), &moves[..1]).unwrap().eco,
+            "B00"
+        );
+    }
+
+    #[test]
+    fn random_lines_are_cut_to_the_depth() {
+        let mut eco = Eco::default();
+        eco.add_table(BUILT_IN);
+        let mut seed = 1;
+        for _ in 0..20 {
+            let line = eco.random_line(&mut seed, Some(6)).unwrap();
+            assert_eq!(line.len(), 6);
+            assert!(eco
+                .random_line(&mut seed, None)
+                .is_some_and(|line| !line.is_empty()));
+        }
+        assert!(eco.random_line(&mut seed, Some(MAX_PLIES + 1)).is_none());
+    }
+}
@@ -0,0 +1,442 @@
+use serde::{Deserialize, Serialize};
+use shakmaty::fen::Fen;
+use shakmaty::san::{San, SanPlus};
+use shakmaty::{CastlingMode, Chess, Color, Move, Outcome, Position};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::engine::{Engine, Score};
+use crate::tablebase;
+use crate::{session, stats, uci};
+
+// ----------------------------------------------
+// Endgame drills: the classic endings, from mating with a queen to the
+// Philidor defence, played against perfect play. After each of my moves
+// the lichess tablebase, or an engine if one is given, tells whether the
+// result still stands and answers with the best defence; the first move
+// that gives it up fails the drill. A win is scored by the moves it took
+// against the fewest possible. The runs are kept in
+// $XDG_STATE_HOME/tess/endgames.jsonl
+// ----------------------------------------------
+
+// Moves of mine a drawn ending is to be held for
+const HOLD: u32 = 20;
+// Without tables, an engine's evaluation this far up is taken for a win
+const WINNING_CP: i32 = 300;
+
+#[derive(Debug, clap::ValueEnum, Clone, Copy, PartialEq)]
+pub enum Ending {
+    /// KQvK: mate with king and queen
+    Queen,
+    /// KRvK: mate with king and rook
+    Rook,
+    /// KBNvK: mate with bishop and knight, in the corner of the bishop's color
+    BishopKnight,
+    /// the Lucena position: build a bridge to promote the pawn
+    Lucena,
+    /// the Philidor position: hold the draw a pawn down with the rook on the sixth
+    Philidor,
+}
+
+impl Ending {
+    pub fn name(self) -> &'static str {
+        match self {
+            Ending::Queen => "KQvK",
+            Ending::Rook => "KRvK",
+            Ending::BishopKnight => "KBNvK",
+            Ending::Lucena => "Lucena",
+            Ending::Philidor => "Philidor",
+        }
+    }
+
+    // I play the side to move
+    fn fen(self) -> &'static str {
+        match self {
+            Ending::Queen => "8/8/8/4k3/8/8/8/KQ6 w - - 0 1",
+            Ending::Rook => "8/8/8/4k3/8/8/8/KR6 w - - 0 1",
+            Ending::BishopKnight => "8/8/8/4k3/8/8/8/KBN5 w - - 0 1",
+            Ending::Lucena => "1K1k4/1P6/8/8/8/8/r7/2R5 w - - 0 1",
+            Ending::Philidor => "4k3/R7/1r6/3KP3/8/8/8/8 b - - 0 1",
+        }
+    }
+
+    /// Win, or hold the draw
+    fn to_win(self) -> bool {
+        self != Ending::Philidor
+    }
+
+    pub fn position(self) -> Chess {
+        Fen::from_ascii(self.fen().as_bytes())
+            .ok()
+            .and_then(|fen| fen.into_position(CastlingMode::Standard).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// One run of a drill
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Run {
+    /// seconds since the Unix epoch
+    pub at: u64,
+    /// "KQvK", "Lucena"
+    pub ending: String,
+    /// "won", "held" or "failed"
+    pub result: String,
+    /// my moves in it
+    pub moves: u32,
+    /// my moves to mate with best play, where it is known
+    pub optimal: Option<u32>,
+}
+
+fn runs_path() -> Option<PathBuf> {
+    session::state_dir().map(|dir| dir.join("endgames.jsonl"))
+}
+
+fn append(run: &Run) -> anyhow::Result<()> {
+    let Some(path) = runs_path() else {
+        return Ok(());
+    };
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    writeln!(file, "{}", serde_json::to_string(run)?)?;
+    Ok(())
+}
+
+// The result with best play, for the side to move, worst first
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+enum Outlook {
+    Loss,
+    Draw,
+    Win,
+}
+
+impl Outlook {
+    fn other(self) -> Self {
+        match self {
+            Outlook::Win => Outlook::Loss,
+            Outlook::Draw => Outlook::Draw,
+            Outlook::Loss => Outlook::Win,
+        }
+    }
+}
+
+// What the judge makes of a position
+struct Look {
+    outlook: Outlook,
+    // moves of the winner to mate, where it is known
+    mate: Option<u32>,
+    best: Option<Move>,
+}
+
+enum Judge {
+    // lichess's tablebase server
+    Tablebase,
+    Engine {
+        engine: Box<Engine>,
+        movetime: Duration,
+    },
+}
+
+impl Judge {
+    fn look(&mut self, pos: &Chess) -> anyhow::Result<Look> {
+        match self {
+            Judge::Tablebase => {
+                let lookup = tablebase::lookup(pos)
+                    .map_err(|err| anyhow::anyhow!("the lichess tablebase: {err}"))?;
+                let outlook = match lookup.category.as_str() {
+                    "win" | "syzygy-win" | "maybe-win" => Outlook::Win,
+                    "loss" | "syzygy-loss" | "maybe-loss" => Outlook::Loss,
+                    _ => Outlook::Draw,
+                };
+                let best = match lookup.moves.first() {
+                    Some(best) => Some(best.san.parse::<San>()?.to_move(pos)?),
+                    None => None,
+                };
+                Ok(Look {
+                    outlook,
+                    // plies, of both sides
+                    mate: lookup.dtm.map(|plies| plies.unsigned_abs().div_ceil(2)),
+                    best,
+                })
+            }
+            Judge::Engine { engine, movetime } => {
+                let evaluation = engine
+                    .evaluate(pos, *movetime)?
+                    .ok_or_else(|| anyhow::anyhow!("{} gave no evaluation", engine.name))?;
+                let (outlook, mate) = match evaluation.score {
+                    Score::Mate(moves) if moves > 0 => (Outlook::Win, Some(moves.unsigned_abs())),
+                    Score::Mate(moves) => (Outlook::Loss, Some(moves.unsigned_abs())),
+                    Score::Centipawns(cp) if cp >= WINNING_CP => (Outlook::Win, None),
+                    Score::Centipawns(cp) if cp <= -WINNING_CP => (Outlook::Loss, None),
+                    Score::Centipawns(_) => (Outlook::Draw, None),
+                };
+                Ok(Look {
+                    outlook,
+                    mate,
+                    best: evaluation.pv.first().and_then(|mv| uci::parse(pos, mv)),
+                })
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Finish {
+    Won,
+    Held,
+    Failed,
+}
+
+/// What my move came to
+pub struct Answer {
+    pub verdict: String,
+    /// the defence, unless the drill is over
+    pub reply: Option<Move>,
+}
+
+pub struct Trainer {
+    pub ending: Ending,
+    /// the side I play
+    pub color: Color,
+    judge: Judge,
+    // my moves to mate from the start with best play, where it is known
+    optimal: Option<u32>,
+    // this run: my moves to mate from the position now, my moves, the
+    // moves lost on the fastest mate, how it ended and the last verdict
+    mate: Option<u32>,
+    moves: u32,
+    lost: u32,
+    finish: Option<Finish>,
+    last: Option<String>,
+}
+
+impl Trainer {
+    /// Drill `ending` against the lichess tablebase, or `engine` searching
+    /// `movetime` a move
+    pub fn new(ending: Ending, engine: Option<Engine>, movetime: Duration) -> Self {
+        Self {
+            ending,
+            color: ending.position().turn(),
+            judge: match engine {
+                Some(engine) => Judge::Engine {
+                    engine: Box::new(engine),
+                    movetime,
+                },
+                None => Judge::Tablebase,
+            },
+            optimal: None,
+            mate: None,
+            moves: 0,
+            lost: 0,
+            finish: None,
+            last: None,
+        }
+    }
+
+    /// Find the fewest moves to mate from the start, once the engine has the
+    /// tables if there are any
+    pub fn look_at_start(&mut self) -> anyhow::Result<()> {
+        self.optimal = self.judge.look(&self.ending.position())?.mate;
+        self.mate = self.optimal;
+        Ok(())
+    }
+
+    /// The engine judging, to point at the tables
+    pub fn engine_mut(&mut self) -> Option<&mut Engine> {
+        match &mut self.judge {
+            Judge::Engine { engine, .. } => Some(engine.as_mut()),
+            Judge::Tablebase => None,
+        }
+    }
+
+    /// A new run from the start
+    pub fn restart(&mut self) {
+        self.mate = self.optimal;
+        self.moves = 0;
+        self.lost = 0;
+        self.finish = None;
+        self.last = None;
+    }
+
+    pub fn over(&self) -> bool {
+        self.finish.is_some()
+    }
+
+    /// "KQvK: White to win."
+    pub fn task(&self) -> String {
+        let side = self.color.fold_wb("White", "Black");
+        if self.ending.to_win() {
+            format!("{}: {side} to win.", self.ending.name())
+        } else {
+            format!(
+                "{}: {side} to hold the draw for {HOLD} moves.",
+                self.ending.name()
+            )
+        }
+    }
+
+    // End the run, keep it and tell how it went
+    fn end(&mut self, finish: Finish, verdict: String) -> Answer {
+        self.finish = Some(finish);
+        let run = Run {
+            at: stats::now(),
+            ending: self.ending.name().to_string(),
+            result: match finish {
+                Finish::Won => "won",
+                Finish::Held => "held",
+                Finish::Failed => "failed",
+            }
+            .to_string(),
+            moves: self.moves,
+            optimal: self.optimal,
+        };
+        // best effort, the run still counts
+        let _ = append(&run);
+        let verdict = format!("{verdict} {} 'n' tries again.", self.score());
+        self.last = Some(verdict.clone());
+        Answer {
+            verdict,
+            reply: None,
+        }
+    }
+
+    /// My move `mv` in `pos`, judged
+    pub fn answer(&mut self, pos: &Chess, mv: &Move) -> anyhow::Result<Answer> {
+        if self.over() {
+            anyhow::bail!("the drill is over");
+        }
+        let san = SanPlus::from_move(pos.clone(), mv).to_string();
+        let after = pos.clone().play(mv)?;
+        self.moves += 1;
+        match after.outcome() {
+            Some(Outcome::Decisive { .. }) => {
+                return Ok(self.end(Finish::Won, format!("{san}, mate.")))
+            }
+            Some(Outcome::Draw) if self.ending.to_win() => {
+                return Ok(self.end(Finish::Failed, format!("{san} draws.")))
+            }
+            Some(Outcome::Draw) => return Ok(self.end(Finish::Held, format!("{san} draws."))),
+            None => {}
+        }
+        if after.halfmoves() >= 100 && self.ending.to_win() {
+            return Ok(self.end(
+                Finish::Failed,
+                format!("{san}, and the 50-move rule draws the game."),
+            ));
+        }
+        let look = self.judge.look(&after)?;
+        let mine = look.outlook.other();
+        let goal = if self.ending.to_win() {
+            Outlook::Win
+        } else {
+            Outlook::Draw
+        };
+        if mine < goal {
+            let result = if mine == Outlook::Draw {
+                "draws"
+            } else {
+                "loses"
+            };
+            return Ok(self.end(
+                Finish::Failed,
+                format!("{san} gives it up, it {result} with best play."),
+            ));
+        }
+        if !self.ending.to_win() && self.moves >= HOLD {
+            return Ok(self.end(Finish::Held, format!("{san}, and the draw is held.")));
+        }
+        let verdict = match (self.mate, look.mate.filter(|_| mine == Outlook::Win)) {
+            (Some(before), Some(now)) if now >= before => {
+                self.lost += now + 1 - before;
+                format!(
+                    "{san} keeps the win, mate in {now}; the best move mates {} sooner.",
+                    moves(now + 1 - before)
+                )
+            }
+            (_, Some(now)) => format!("{san}, mate in {now}."),
+            _ if mine == Outlook::Win => format!("{san} keeps the win."),
+            _ => format!("{san} holds."),
+        };
+        self.mate = look.mate.filter(|_| mine == Outlook::Win);
+        let Some(reply) = look.best else {
+            anyhow::bail!("no defence to {san} was found");
+        };
+        self.last = Some(verdict.clone());
+        Ok(Answer {
+            verdict,
+            reply: Some(reply),
+        })
+    }
+
+    /// After the defence in `pos`, whether I was mated or the game is drawn
+    pub fn replied(&mut self, pos: &Chess) -> Option<String> {
+        let verdict = match pos.outcome()? {
+            Outcome::Decisive { .. } => self.end(Finish::Failed, "Mate.".to_string()),
+            Outcome::Draw if self.ending.to_win() => {
+                self.end(Finish::Failed, "A draw.".to_string())
+            }
+            Outcome::Draw => self.end(Finish::Held, "A draw.".to_string()),
+        };
+        Some(verdict.verdict)
+    }
+
+    /// "Mate in 12 moves, 9 with best play (75%)."
+    pub fn score(&self) -> String {
+        match (self.finish, self.optimal) {
+            (Some(Finish::Won), Some(optimal)) => format!(
+                "Mate in {}, {optimal} with best play ({:.0}%).",
+                moves(self.moves),
+                100.0 * f64::from(optimal) / f64::from(self.moves.max(1))
+            ),
+            (Some(Finish::Won), None) => format!("Mate in {}.", moves(self.moves)),
+            (Some(Finish::Held), _) => format!("Held for {}.", moves(self.moves)),
+            (Some(Finish::Failed), _) => format!("Failed after {}.", moves(self.moves)),
+            (None, _) => format!("{} played.", moves(self.moves)),
+        }
+    }
+
+    /// The pane, one line each
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = vec![
+            self.task(),
+            match &self.judge {
+                Judge::Tablebase => "Defended by the lichess tablebase.".to_string(),
+                Judge::Engine { engine, .. } => format!("Defended by {}.", engine.name),
+            },
+        ];
+        if let Some(optimal) = self.optimal.filter(|_| self.ending.to_win()) {
+            lines.push(format!(
+                "Best play mates in {}; {} lost so far.",
+                moves(optimal),
+                moves(self.lost)
+            ));
+        }
+        lines.extend(self.last.clone());
+        lines
+    }
+}
+
+// "1 move", "3 moves"
+fn moves(count: u32) -> String {
+    format!("{count} move{}", if count == 1 { "" } else { "s" })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::ValueEnum;
+
+    #[test]
+    fn every_ending_starts_from_its_position() {
+        for ending in Ending::value_variants() {
+            let fen = Fen::from_position(ending.position(), shakmaty::EnPassantMode::Legal);
+            assert_eq!(fen.to_string(), ending.fen(), "{}", ending.name());
+        }
+    }
+}
@@ -0,0 +1,685 @@
+use serde::{Deserialize, Serialize};
+use shakmaty::fen::{Epd, Fen};
+use shakmaty::zobrist::{Zobrist64, ZobristHash};
+use shakmaty::{Chess, Color as ChessColor, EnPassantMode, Move};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, TryRecvError};
+use std::time::{Duration, Instant};
+
+use crate::eval_cache::EvalCache;
+use crate::{clock, uci};
+
+// ----------------------------------------------
+// A UCI engine, as the opponent or analysing the position on screen:
+// the process is spawned once, a thread forwards its output lines, and
+// the event loop picks up best moves and evaluations
+// ----------------------------------------------
+
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+/// Thinking time per move without a clock or a limit
+const DEFAULT_MOVETIME: Duration = Duration::from_secs(1);
+
+/// Ways to weaken the opponent, for players below full engine strength
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Strength {
+    /// play at about this Elo, with UCI_LimitStrength and UCI_Elo
+    pub elo: Option<u32>,
+    /// the engine's `Skill Level` option, 0 to 20 in Stockfish
+    pub skill: Option<u8>,
+    /// think at most this long per move, also under a clock
+    pub movetime: Option<Duration>,
+    /// search this many nodes per move instead, whatever the clock; 1 for a
+    /// Maia network, which then plays its first instinct like a human
+    pub nodes: Option<u64>,
+}
+
+pub struct Engine {
+    /// the engine's `id name`, the file name if it sends none
+    pub name: String,
+    /// the side the engine plays as an opponent
+    pub color: ChessColor,
+    /// where it was started from and the options set since, to start more like it
+    pub path: PathBuf,
+    pub settings: Vec<(String, String)>,
+    // longest thinking time, or nodes, per move as an opponent
+    movetime: Option<Duration>,
+    nodes: Option<u64>,
+    // the options the engine reported
+    options: Vec<UciOption>,
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+    // searches started and not answered yet, and how many of those were cancelled
+    pending: usize,
+    cancelled: usize,
+    // the position of the running analysis, as EPD, and its Zobrist hash
+    analysing: Option<String>,
+    analysing_key: u64,
+    /// the latest evaluation of the running search
+    pub evaluation: Option<Evaluation>,
+    // what the analysis found in the positions it left, and where it is kept
+    cache: EvalCache,
+    cache_file: Option<PathBuf>,
+}
+
+/// Score, search depth and principal variation from an `info` line, with
+/// what the engine tells of its search besides
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Evaluation {
+    pub depth: u32,
+    pub score: Score,
+    /// the principal variation in UCI
+    pub pv: Vec<String>,
+    /// positions the search found in the tablebases
+    pub tbhits: u64,
+    /// the deepest line searched, quiescence included
+    pub seldepth: Option<u32>,
+    pub nodes: Option<u64>,
+    /// nodes per second
+    pub nps: Option<u64>,
+    /// how full the hash table is, in permille
+    pub hashfull: Option<u32>,
+}
+
+/// From the point of view of the side to move, as UCI sends it
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum Score {
+    Centipawns(i32),
+    /// moves to mate, negative if the side to move gets mated
+    Mate(i32),
+}
+
+impl Score {
+    /// "+0.35", "-1.20", "#3" or "#-2", from white's side
+    pub fn display(self, turn: ChessColor) -> String {
+        let sign = turn.fold_wb(1, -1);
+        match self {
+            Score::Centipawns(cp) => format!("{:+.2}", f64::from(cp * sign) / 100.0),
+            Score::Mate(moves) => format!("#{}", moves * sign),
+        }
+    }
+}
+
+fn parse_info(info: &str) -> Option<Evaluation> {
+    let mut words = info.split_whitespace();
+    let (mut depth, mut score, mut pv, mut tbhits) = (None, None, Vec::new(), 0);
+    let (mut seldepth, mut nodes, mut nps, mut hashfull) = (None, None, None, None);
+    while let Some(word) = words.next() {
+        match word {
+            "depth" => depth = words.next().and_then(|d| d.parse().ok()),
+            "tbhits" => tbhits = words.next().and_then(|n| n.parse().ok()).unwrap_or(0),
+            "seldepth" => seldepth = words.next().and_then(|d| d.parse().ok()),
+            "nodes" => nodes = words.next().and_then(|n| n.parse().ok()),
+            "nps" => nps = words.next().and_then(|n| n.parse().ok()),
+            "hashfull" => hashfull = words.next().and_then(|n| n.parse().ok()),
+            "score" => {
+                let kind = words.next();
+                let value = words.next().and_then(|v| v.parse().ok());
+                score = match (kind, value) {
+                    (Some("cp"), Some(cp)) => Some(Score::Centipawns(cp)),
+                    (Some("mate"), Some(moves)) => Some(Score::Mate(moves)),
+                    _ => None,
+                };
+            }
+            // the rest of the line
+            "pv" => pv = words.by_ref().map(str::to_string).collect(),
+            _ => {}
+        }
+    }
+    Some(Evaluation {
+        depth: depth?,
+        score: score?,
+        pv,
+        tbhits,
+        seldepth,
+        nodes,
+        nps,
+        hashfull,
+    })
+}
+
+// ----------------------------------------------
+// Engine options, as the engine describes them in its `option` lines
+// ----------------------------------------------
+
+#[derive(Debug)]
+pub struct UciOption {
+    pub name: String,
+    pub kind: OptionKind,
+    /// the value the engine starts with, none for a button
+    pub default: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum OptionKind {
+    Check,
+    Spin { min: i64, max: i64 },
+    Combo(Vec<String>),
+    Button,
+    String,
+}
+
+// "Hash type spin default 16 min 1 max 33554432", after "option name "
+fn parse_option(line: &str) -> Option<UciOption> {
+    let (name, rest) = line.split_once(" type ")?;
+    let mut words = rest.split_whitespace();
+    let kind = words.next()?;
+    // default, min, max and var with their values, which may have spaces
+    let mut fields: Vec<(&str, String)> = Vec::new();
+    for word in words {
+        match (word, fields.last_mut()) {
+            ("default" | "min" | "max" | "var", _) => fields.push((word, String::new())),
+            (word, Some((_, value))) => {
+                if !value.is_empty() {
+                    value.push(' ');
+                }
+                value.push_str(word);
+            }
+            (_, None) => {}
+        }
+    }
+    let field = |key: &str| {
+        fields
+            .iter()
+            .find(|(k, _)| *k == key)
+            .and_then(|(_, value)| value.parse().ok())
+    };
+    let kind = match kind {
+        "check" => OptionKind::Check,
+        "spin" => OptionKind::Spin {
+            min: field("min").unwrap_or(i64::MIN),
+            max: field("max").unwrap_or(i64::MAX),
+        },
+        "combo" => OptionKind::Combo(
+            fields
+                .iter()
+                .filter(|(key, _)| *key == "var")
+                .map(|(_, value)| value.clone())
+                .collect(),
+        ),
+        "button" => OptionKind::Button,
+        _ => OptionKind::String,
+    };
+    let default = fields
+        .iter()
+        .find(|(key, _)| *key == "default")
+        .map(|(_, value)| value.clone());
+    Some(UciOption {
+        name: name.trim().to_string(),
+        kind,
+        default,
+    })
+}
+
+impl UciOption {
+    /// "spin 1-1024", "combo Off/On", "check", "button" or "string"
+    pub fn describe(&self) -> String {
+        match &self.kind {
+            OptionKind::Check => "check".to_string(),
+            OptionKind::Spin { min, max } => format!("spin {min}-{max}"),
+            OptionKind::Combo(vars) => format!("combo {}", vars.join("/")),
+            OptionKind::Button => "button".to_string(),
+            OptionKind::String => "string".to_string(),
+        }
+    }
+
+    // The value to send, None for a button; what is wrong with it otherwise
+    fn check(&self, value: &str) -> Result<Option<String>, String> {
+        match &self.kind {
+            OptionKind::Check => match value.to_ascii_lowercase().as_str() {
+                "true" | "false" => Ok(Some(value.to_ascii_lowercase())),
+                _ => Err(format!("is true or false, not '{value}'")),
+            },
+            OptionKind::Spin { min, max } => match value.parse::<i64>() {
+                Ok(n) if (*min..=*max).contains(&n) => Ok(Some(n.to_string())),
+                _ => Err(format!("takes a number from {min} to {max}, not '{value}'")),
+            },
+            OptionKind::Combo(vars) => vars
+                .iter()
+                .find(|var| var.eq_ignore_ascii_case(value))
+                .map(|var| Some(var.clone()))
+                .ok_or_else(|| format!("is one of {}, not '{value}'", vars.join(", "))),
+            OptionKind::Button => Ok(None),
+            OptionKind::String => Ok(Some(value.to_string())),
+        }
+    }
+}
+
+/// An `--engine-option`, "Hash=512"; a button has no value ("Clear Hash")
+pub fn parse_setting(text: &str) -> Result<(String, String), String> {
+    let (name, value) = text.split_once('=').unwrap_or((text, ""));
+    if name.trim().is_empty() {
+        return Err(format!("expected NAME=VALUE, not '{text}'"));
+    }
+    Ok((name.trim().to_string(), value.trim().to_string()))
+}
+
+// What a line of the running search says
+enum Reply {
+    BestMove(String),
+    Info,
+}
+
+impl Engine {
+    /// Start the engine at `path` and wait for it to be ready
+    pub fn start(path: &Path) -> anyhow::Result<Self> {
+        let mut child = Command::new(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|err| anyhow::anyhow!("{}: {err}", path.display()))?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let (sender, lines) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        let mut engine = Self {
+            name: path
+                .file_name()
+                .map_or("engine".into(), |name| name.to_string_lossy().into_owned()),
+            color: ChessColor::Black,
+            path: path.to_path_buf(),
+            settings: Vec::new(),
+            movetime: None,
+            nodes: None,
+            options: Vec::new(),
+            child,
+            stdin,
+            lines,
+            pending: 0,
+            cancelled: 0,
+            analysing: None,
+            analysing_key: 0,
+            evaluation: None,
+            cache: EvalCache::default(),
+            cache_file: None,
+        };
+        engine.send("uci")?;
+        loop {
+            let line = engine.wait_line()?;
+            if let Some(name) = line.strip_prefix("id name ") {
+                engine.name = name.trim().to_string();
+            } else if let Some(option) = line.strip_prefix("option name ").and_then(parse_option) {
+                engine.options.push(option);
+            } else if line.trim() == "uciok" {
+                break;
+            }
+        }
+        engine.send("isready")?;
+        while engine.wait_line()?.trim() != "readyok" {}
+        Ok(engine)
+    }
+
+    /// Play `color` as an opponent, weakened to `strength`
+    pub fn playing(mut self, color: ChessColor, strength: Strength) -> anyhow::Result<Self> {
+        self.color = color;
+        self.movetime = strength.movetime;
+        self.nodes = strength.nodes;
+        if let Some(elo) = strength.elo {
+            self.set_option("UCI_LimitStrength", "true")?;
+            self.set_option("UCI_Elo", &elo.to_string())?;
+        }
+        if let Some(skill) = strength.skill {
+            self.set_option("Skill Level", &skill.to_string())?;
+        }
+        Ok(self)
+    }
+
+    /// The limits it plays with; its Elo and skill are among the `settings`
+    pub fn strength(&self) -> Strength {
+        Strength {
+            movetime: self.movetime,
+            nodes: self.nodes,
+            ..Strength::default()
+        }
+    }
+
+    /// Keep the analysis cache in `dir` between sessions, one file per engine
+    pub fn with_saved_cache(mut self, dir: &Path) -> Self {
+        let name: String = self
+            .name
+            .chars()
+            .map(|ch| if ch.is_ascii_alphanumeric() { ch } else { '_' })
+            .collect();
+        let path = dir.join(format!("analysis-{name}.jsonl"));
+        self.cache = EvalCache::load(&path);
+        self.cache_file = Some(path);
+        self
+    }
+
+    /// Set the options from the command line and the config file, in order
+    pub fn with_options(mut self, options: &[(String, String)]) -> anyhow::Result<Self> {
+        for (name, value) in options {
+            self.set_option(name, value)?;
+        }
+        Ok(self)
+    }
+
+    /// The options the engine reported in its `uci` handshake, in its order
+    pub fn options(&self) -> &[UciOption] {
+        &self.options
+    }
+
+    /// The value `option` was last set to, or its default
+    pub fn option_value<'a>(&'a self, option: &'a UciOption) -> Option<&'a str> {
+        self.settings
+            .iter()
+            .rev()
+            .find(|(name, _)| name.eq_ignore_ascii_case(&option.name))
+            .map(|(_, value)| value.as_str())
+            .or(option.default.as_deref())
+    }
+
+    /// Whether the engine reported the option `name`
+    pub fn has_option(&self, name: &str) -> bool {
+        self.options
+            .iter()
+            .any(|option| option.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Set an option the engine reported, checking the value against its type.
+    /// Option names are not case sensitive.
+    pub fn set_option(&mut self, name: &str, value: &str) -> anyhow::Result<()> {
+        let Some(option) = self
+            .options
+            .iter()
+            .find(|option| option.name.eq_ignore_ascii_case(name))
+        else {
+            anyhow::bail!("{} has no option {name}", self.name);
+        };
+        let command = match option.check(value.trim()) {
+            Ok(Some(value)) => format!("setoption name {} value {value}", option.name),
+            Ok(None) => format!("setoption name {}", option.name),
+            Err(problem) => anyhow::bail!("{}: {} {problem}", self.name, option.name),
+        };
+        self.send(&command)?;
+        self.settings.push((name.to_string(), value.to_string()));
+        Ok(())
+    }
+
+    fn send(&mut self, command: &str) -> anyhow::Result<()> {
+        writeln!(self.stdin, "{command}")
+            .map_err(|err| anyhow::anyhow!("{} stopped: {err}", self.name))
+    }
+
+    // The next line of the handshake, an error if the engine is silent for too long
+    fn wait_line(&mut self) -> anyhow::Result<String> {
+        match self.next_line(Some(HANDSHAKE_TIMEOUT))? {
+            Some(line) => Ok(line),
+            None => anyhow::bail!("{} does not answer as a UCI engine", self.name),
+        }
+    }
+
+    // The next output line, waiting up to `wait` for it; None if there is none by then
+    fn next_line(&mut self, wait: Option<Duration>) -> anyhow::Result<Option<String>> {
+        let line = match wait {
+            Some(wait) => match self.lines.recv_timeout(wait) {
+                Ok(line) => Some(line),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => anyhow::bail!("{} exited", self.name),
+            },
+            None => match self.lines.try_recv() {
+                Ok(line) => Some(line),
+                Err(TryRecvError::Empty) => None,
+                Err(TryRecvError::Disconnected) => anyhow::bail!("{} exited", self.name),
+            },
+        };
+        Ok(line)
+    }
+
+    /// Whether a search started by `analyse` is running
+    pub fn is_analysing(&self) -> bool {
+        self.analysing.is_some()
+    }
+
+    /// Whether a move is on its way
+    pub fn thinking(&self) -> bool {
+        self.pending > self.cancelled
+    }
+
+    /// Search the position after `history`, with the clock's times if the game has one
+    pub fn go(
+        &mut self,
+        start: &Chess,
+        history: &[Move],
+        clock: Option<&clock::Clock>,
+    ) -> anyhow::Result<()> {
+        self.cancel()?;
+        let fen = Fen::from_position(start.clone(), EnPassantMode::Legal);
+        let mut position = format!("position fen {fen}");
+        if !history.is_empty() {
+            position.push_str(" moves");
+            for mv in history {
+                position.push(' ');
+                position.push_str(&uci::format(mv));
+            }
+        }
+        self.send(&position)?;
+        let go = match (self.nodes, clock, self.movetime) {
+            (Some(nodes), _, _) => format!("go nodes {nodes}"),
+            (None, Some(clock), None) => format!(
+                "go wtime {} btime {} winc {inc} binc {inc}",
+                clock.remaining(ChessColor::White).as_millis(),
+                clock.remaining(ChessColor::Black).as_millis(),
+                // a delay is not an increment
+                inc = match clock.kind {
+                    clock::IncrementKind::Fischer => clock.control.increment.as_millis(),
+                    clock::IncrementKind::Delay | clock::IncrementKind::Bronstein => 0,
+                }
+            ),
+            // the cap, but never more than a tenth of the time left
+            (None, Some(clock), Some(cap)) => format!(
+                "go movetime {}",
+                cap.min(clock.remaining(self.color) / 10).as_millis().max(1)
+            ),
+            (None, None, cap) => format!(
+                "go movetime {}",
+                cap.unwrap_or(DEFAULT_MOVETIME).as_millis()
+            ),
+        };
+        self.send(&go)?;
+        self.pending += 1;
+        Ok(())
+    }
+
+    /// Like `go`, but wait for the move (UCI), for games played without a screen
+    pub fn search_move(
+        &mut self,
+        start: &Chess,
+        history: &[Move],
+        clock: Option<&clock::Clock>,
+    ) -> anyhow::Result<String> {
+        self.go(start, history, clock)?;
+        let wait = match clock {
+            Some(clock) => clock.remaining(self.color),
+            None => self.movetime.unwrap_or(DEFAULT_MOVETIME),
+        } + HANDSHAKE_TIMEOUT;
+        loop {
+            match self.read(Some(wait))? {
+                Some(Reply::BestMove(mv)) => return Ok(mv),
+                Some(Reply::Info) => {}
+                None => anyhow::bail!("{} did not answer with a move", self.name),
+            }
+        }
+    }
+
+    /// Stop a search whose answer is no longer wanted
+    pub fn cancel(&mut self) -> anyhow::Result<()> {
+        if self.thinking() {
+            self.send("stop")?;
+            self.cancelled = self.pending;
+        }
+        Ok(())
+    }
+
+    /// A new game begins, the engine forgets the last one
+    pub fn new_game(&mut self) -> anyhow::Result<()> {
+        self.cancel()?;
+        self.send("ucinewgame")
+    }
+
+    /// Search `pos` until told otherwise, None stops the analysis.
+    /// False if that is the position already being analysed.
+    pub fn analyse(&mut self, pos: Option<&Chess>) -> anyhow::Result<bool> {
+        let epd = pos.map(|pos| Epd::from_position(pos.clone(), EnPassantMode::Legal).to_string());
+        if epd == self.analysing {
+            return Ok(false);
+        }
+        self.cancel()?;
+        if let (Some(_), Some(evaluation)) = (&self.analysing, self.evaluation.take()) {
+            self.cache.insert(self.analysing_key, evaluation);
+        }
+        if let Some(pos) = pos {
+            self.analysing_key = zobrist(pos);
+            self.evaluation = self.cache.get(self.analysing_key).cloned();
+        }
+        self.analysing = epd;
+        if let Some(epd) = &self.analysing {
+            let position = format!("position fen {epd} 0 1");
+            self.send(&position)?;
+            self.send("go infinite")?;
+            self.pending += 1;
+        }
+        Ok(true)
+    }
+
+    /// The analysis so far if `pos` is the position being analysed, what
+    /// the cache kept of an earlier one otherwise
+    pub fn evaluation_of(&self, pos: &Chess) -> Option<&Evaluation> {
+        let epd = Epd::from_position(pos.clone(), EnPassantMode::Legal).to_string();
+        if self.analysing.as_ref() == Some(&epd) {
+            return self.evaluation.as_ref();
+        }
+        self.cache.peek(zobrist(pos))
+    }
+
+    // The next line that belongs to the running search, None when there is none
+    // yet, or none within `wait`
+    fn read(&mut self, wait: Option<Duration>) -> anyhow::Result<Option<Reply>> {
+        loop {
+            let Some(line) = self.next_line(wait)? else {
+                return Ok(None);
+            };
+            if let Some(rest) = line.strip_prefix("bestmove ") {
+                self.pending = self.pending.saturating_sub(1);
+                if self.cancelled > 0 {
+                    self.cancelled -= 1;
+                    continue;
+                }
+                let mv = rest.split_whitespace().next().unwrap_or_default();
+                return Ok(Some(Reply::BestMove(mv.to_string())));
+            }
+            // lines of a cancelled search come before its best move
+            if let Some(info) = line.strip_prefix("info ").filter(|_| self.cancelled == 0) {
+                if let Some(evaluation) = parse_info(info) {
+                    // an analysis shows what the cache had until it searches deeper
+                    let shallower = self.analysing.is_some()
+                        && self
+                            .evaluation
+                            .as_ref()
+                            .is_some_and(|kept| kept.depth > evaluation.depth);
+                    if shallower {
+                        continue;
+                    }
+                    self.evaluation = Some(evaluation);
+                    return Ok(Some(Reply::Info));
+                }
+            }
+        }
+    }
+
+    /// The best move (UCI) of the last search, once it has arrived
+    pub fn best_move(&mut self) -> anyhow::Result<Option<String>> {
+        while let Some(reply) = self.read(None)? {
+            if let Reply::BestMove(mv) = reply {
+                return Ok(Some(mv));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Take in the analysis lines that have arrived, true if the evaluation changed
+    pub fn update(&mut self) -> anyhow::Result<bool> {
+        let mut changed = false;
+        while let Some(reply) = self.read(None)? {
+            changed |= matches!(reply, Reply::Info);
+        }
+        Ok(changed)
+    }
+
+    /// Search `pos` for `movetime` and wait for the last evaluation
+    pub fn evaluate(
+        &mut self,
+        pos: &Chess,
+        movetime: Duration,
+    ) -> anyhow::Result<Option<Evaluation>> {
+        self.evaluate_moves(pos, movetime, &[])
+    }
+
+    /// Like `evaluate`, but only `moves` are searched, every move if it is empty
+    pub fn evaluate_moves(
+        &mut self,
+        pos: &Chess,
+        movetime: Duration,
+        moves: &[Move],
+    ) -> anyhow::Result<Option<Evaluation>> {
+        self.cancel()?;
+        self.evaluation = None;
+        let fen = Fen::from_position(pos.clone(), EnPassantMode::Legal);
+        self.send(&format!("position fen {fen}"))?;
+        let mut go = format!("go movetime {}", movetime.as_millis());
+        if !moves.is_empty() {
+            go.push_str(" searchmoves");
+            for mv in moves {
+                go.push(' ');
+                go.push_str(&uci::format(mv));
+            }
+        }
+        self.send(&go)?;
+        self.pending += 1;
+        loop {
+            match self.read(Some(movetime + HANDSHAKE_TIMEOUT))? {
+                Some(Reply::BestMove(_)) => return Ok(self.evaluation.take()),
+                Some(Reply::Info) => {}
+                None => anyhow::bail!("{} did not finish its search", self.name),
+            }
+        }
+    }
+}
+
+// The key of `pos` in the analysis cache
+fn zobrist(pos: &Chess) -> u64 {
+    pos.zobrist_hash::<Zobrist64>(EnPassantMode::Legal).0
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        // best effort, the cache is only a head start for the next session
+        if let Some(path) = &self.cache_file {
+            if let (Some(_), Some(evaluation)) = (&self.analysing, self.evaluation.take()) {
+                self.cache.insert(self.analysing_key, evaluation);
+            }
+            let _ = self.cache.save(path);
+        }
+        // give it a moment to exit on its own
+        let deadline = Instant::now() + Duration::from_millis(500);
+        while Instant::now() < deadline {
+            if let Ok(Some(_)) = self.child.try_wait() {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
@@ -0,0 +1,136 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+use crate::engine::Evaluation;
+
+// ----------------------------------------------
+// The analysis engine's evaluations of the positions it has searched,
+// keyed by Zobrist hash, so stepping back to a position shows what was
+// found there at once. The least recently analysed positions make room
+// for new ones; the cache can be kept between sessions as JSON lines.
+// ----------------------------------------------
+
+/// Positions kept, a few games' worth
+pub const CAPACITY: usize = 4096;
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    key: u64,
+    evaluation: Evaluation,
+}
+
+#[derive(Default)]
+pub struct EvalCache {
+    // each evaluation with the time it was last used
+    entries: HashMap<u64, (u64, Evaluation)>,
+    used: u64,
+}
+
+impl EvalCache {
+    /// The evaluation of the position `key`, now the most recently used
+    pub fn get(&mut self, key: u64) -> Option<&Evaluation> {
+        self.used += 1;
+        let (used, evaluation) = self.entries.get_mut(&key)?;
+        *used = self.used;
+        Some(evaluation)
+    }
+
+    /// Like `get`, leaving the order of use as it is
+    pub fn peek(&self, key: u64) -> Option<&Evaluation> {
+        self.entries.get(&key).map(|(_, evaluation)| evaluation)
+    }
+
+    /// Keep `evaluation` unless a deeper one is kept already
+    pub fn insert(&mut self, key: u64, evaluation: Evaluation) {
+        if self
+            .peek(key)
+            .is_some_and(|kept| kept.depth > evaluation.depth)
+        {
+            return;
+        }
+        if self.entries.len() >= CAPACITY && !self.entries.contains_key(&key) {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (used, _))| *used)
+                .map(|(key, _)| *key);
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.used += 1;
+        self.entries.insert(key, (self.used, evaluation));
+    }
+
+    /// The cache saved at `path`, empty if there is none; lines that do not
+    /// read are left out
+    pub fn load(path: &Path) -> Self {
+        let mut cache = Self::default();
+        let Ok(file) = std::fs::File::open(path) else {
+            return cache;
+        };
+        for line in std::io::BufReader::new(file).lines().map_while(Result::ok) {
+            if let Ok(entry) = serde_json::from_str::<Entry>(&line) {
+                cache.insert(entry.key, entry.evaluation);
+            }
+        }
+        cache
+    }
+
+    /// Write the cache to `path`, least recently used first so a load keeps the order
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut entries: Vec<_> = self.entries.iter().collect();
+        entries.sort_by_key(|(_, (used, _))| *used);
+        let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        for (&key, (_, evaluation)) in entries {
+            let entry = Entry {
+                key,
+                evaluation: evaluation.clone(),
+            };
+            writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+        }
+        file.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::Score;
+
+    fn at_depth(depth: u32) -> Evaluation {
+        Evaluation {
+            depth,
+            score: Score::Centipawns(0),
+            pv: Vec::new(),
+            tbhits: 0,
+            seldepth: None,
+            nodes: None,
+            nps: None,
+            hashfull: None,
+        }
+    }
+
+    #[test]
+    fn keeps_the_deeper_and_the_recently_used() {
+        let mut cache = EvalCache::default();
+        cache.insert(1, at_depth(20));
+        cache.insert(1, at_depth(12));
+        assert_eq!(cache.peek(1).map(|e| e.depth), Some(20));
+        for key in 2..=CAPACITY as u64 {
+            cache.insert(key, at_depth(1));
+        }
+        // 1 was used last, 2 is the oldest and makes room
+        cache.get(1);
+        cache.insert(0, at_depth(1));
+        assert!(cache.peek(1).is_some());
+        assert!(cache.peek(2).is_none());
+        assert!(cache.peek(0).is_some());
+    }
+}
@@ -0,0 +1,273 @@
+use serde::{Deserialize, Serialize};
+use shakmaty::fen::Fen;
+use shakmaty::{Chess, EnPassantMode, Position, Role};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::engine::{Engine, Score};
+use crate::review::CP_CEILING;
+use crate::{session, session_log, stats, LastPosition};
+
+// ----------------------------------------------
+// Guess the evaluation: middlegame positions from PGN files or lichess
+// puzzles, my guess against the engine's, every guess kept in
+// $XDG_STATE_HOME/tess/eval_guesses.jsonl to follow my calibration
+// ----------------------------------------------
+
+// Plies of a game that count as the middlegame
+const FIRST_PLY: usize = 20;
+const LAST_PLY: usize = 70;
+// Knights, bishops, rooks and queens of both sides worth at least this many pawns
+const MIDDLEGAME_MATERIAL: u32 = 20;
+// Guesses the "recent" calibration goes back
+const RECENT: usize = 20;
+
+/// One guess, centipawns from white's side
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Guess {
+    /// seconds since the Unix epoch
+    pub at: u64,
+    pub fen: String,
+    pub guess: i32,
+    pub engine: i32,
+    pub tolerance: i32,
+}
+
+impl Guess {
+    fn error(&self) -> i32 {
+        self.guess - self.engine
+    }
+
+    pub fn within(&self) -> bool {
+        self.error().abs() <= self.tolerance
+    }
+}
+
+fn guesses_path() -> Option<PathBuf> {
+    session::state_dir().map(|dir| dir.join("eval_guesses.jsonl"))
+}
+
+/// Every guess so far, oldest first
+fn load() -> Vec<Guess> {
+    guesses_path()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .map(|text| {
+            text.lines()
+                .filter_map(|line| serde_json::from_str(line).ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn append(guess: &Guess) -> anyhow::Result<()> {
+    let Some(path) = guesses_path() else {
+        return Ok(());
+    };
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    writeln!(file, "{}", serde_json::to_string(guess)?)?;
+    Ok(())
+}
+
+/// How close a run of guesses came
+pub struct Calibration {
+    pub guesses: usize,
+    /// share within the tolerance, 0 to 1
+    pub within: f64,
+    /// average distance to the engine in pawns
+    pub error: f64,
+    /// average guess less the engine's in pawns, positive if white is overrated
+    pub bias: f64,
+}
+
+/// None without guesses
+pub fn calibration(guesses: &[Guess]) -> Option<Calibration> {
+    if guesses.is_empty() {
+        return None;
+    }
+    let count = guesses.len() as f64;
+    let pawns = |cp: i32| f64::from(cp) / 100.0;
+    Some(Calibration {
+        guesses: guesses.len(),
+        within: guesses.iter().filter(|g| g.within()).count() as f64 / count,
+        error: guesses.iter().map(|g| pawns(g.error().abs())).sum::<f64>() / count,
+        bias: guesses.iter().map(|g| pawns(g.error())).sum::<f64>() / count,
+    })
+}
+
+impl Calibration {
+    /// "12 guesses: 58% within, off by 0.64 on average, white overrated by 0.20"
+    pub fn describe(&self) -> String {
+        let bias = match self.bias {
+            b if b.abs() < 0.05 => "no bias".to_string(),
+            b if b > 0.0 => format!("white overrated by {b:.2}"),
+            b => format!("black overrated by {:.2}", -b),
+        };
+        format!(
+            "{} {}: {:.0}% within, off by {:.2} on average, {bias}",
+            self.guesses,
+            if self.guesses == 1 {
+                "guess"
+            } else {
+                "guesses"
+            },
+            self.within * 100.0,
+            self.error
+        )
+    }
+}
+
+/// The last guesses of `guesses`
+pub fn recent(guesses: &[Guess]) -> &[Guess] {
+    &guesses[guesses.len().saturating_sub(RECENT)..]
+}
+
+// ----------------------------------------------
+// The quiz itself: where the positions come from and the engine that
+// knows the answer
+// ----------------------------------------------
+
+pub enum Source {
+    /// middlegame positions of the games in PGN files
+    Games(Vec<Chess>),
+    /// the positions of random lichess puzzles, before the tactic
+    Lichess,
+}
+
+pub struct Quiz {
+    engine: Engine,
+    source: Source,
+    pub movetime: Duration,
+    /// in centipawns
+    pub tolerance: i32,
+    /// the position to guess, None once it was answered
+    pub position: Option<Chess>,
+    /// every guess so far, this session's last
+    pub history: Vec<Guess>,
+    // where this session's guesses start in `history`
+    first_of_session: usize,
+    // for picking positions, seeded from the clock
+    seed: u64,
+}
+
+impl Quiz {
+    pub fn new(engine: Engine, source: Source, movetime: Duration, tolerance: i32) -> Self {
+        let nanos = session_log::system_time()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.subsec_nanos());
+        let history = load();
+        Self {
+            engine,
+            source,
+            movetime,
+            tolerance,
+            position: None,
+            first_of_session: history.len(),
+            history,
+            seed: u64::from(nanos) | 1,
+        }
+    }
+
+    pub fn engine_name(&self) -> &str {
+        &self.engine.name
+    }
+
+    /// Pick the next position to guess
+    pub fn next(&mut self) -> anyhow::Result<&Chess> {
+        let pos = match &self.source {
+            Source::Games(positions) if positions.is_empty() => {
+                anyhow::bail!("the games have no middlegame positions")
+            }
+            Source::Games(positions) => positions[random(&mut self.seed, positions.len())].clone(),
+            Source::Lichess => crate::load_puzzle(None)?.0,
+        };
+        Ok(self.position.insert(pos))
+    }
+
+    /// Compare `guess` (centipawns from white's side) with the engine, and keep it
+    pub fn answer(&mut self, guess: i32) -> anyhow::Result<Guess> {
+        let Some(pos) = self.position.take() else {
+            anyhow::bail!("there is no position to guess");
+        };
+        let evaluation = self
+            .engine
+            .evaluate(&pos, self.movetime)?
+            .ok_or_else(|| anyhow::anyhow!("{} gave no evaluation", self.engine.name))?;
+        let cp = match evaluation.score {
+            Score::Centipawns(cp) => cp,
+            Score::Mate(moves) if moves > 0 => CP_CEILING,
+            Score::Mate(_) => -CP_CEILING,
+        };
+        let guess = Guess {
+            at: stats::now(),
+            fen: Fen::from_position(pos.clone(), EnPassantMode::Legal).to_string(),
+            guess: guess.clamp(-CP_CEILING, CP_CEILING),
+            engine: (cp * pos.turn().fold_wb(1, -1)).clamp(-CP_CEILING, CP_CEILING),
+            tolerance: self.tolerance,
+        };
+        // best effort, the session still counts it
+        let _ = append(&guess);
+        self.history.push(guess.clone());
+        Ok(guess)
+    }
+
+    /// The guesses since the quiz started
+    pub fn session(&self) -> &[Guess] {
+        &self.history[self.first_of_session..]
+    }
+}
+
+// xorshift, good enough to pick positions
+pub fn random(seed: &mut u64, below: usize) -> usize {
+    *seed ^= *seed << 13;
+    *seed ^= *seed >> 7;
+    *seed ^= *seed << 17;
+    (*seed % below as u64) as usize
+}
+
+/// A guess typed as pawns from white's side, "+1.5", "-0.3" or "0"
+pub fn parse_guess(text: &str) -> Option<i32> {
+    let pawns: f64 = text.trim().trim_start_matches('+').parse().ok()?;
+    pawns.is_finite().then(|| (pawns * 100.0).round() as i32)
+}
+
+/// The quiet middlegame positions of every game in PGN `files`
+pub fn middlegames(files: &[PathBuf]) -> anyhow::Result<Vec<Chess>> {
+    let mut positions = Vec::new();
+    for path in files {
+        positions.extend(middlegames_of(path)?);
+    }
+    Ok(positions)
+}
+
+fn middlegames_of(path: &Path) -> anyhow::Result<Vec<Chess>> {
+    let text = std::fs::read_to_string(path)?;
+    let mut reader = pgn_reader::BufferedReader::new(text.as_bytes());
+    let mut positions = Vec::new();
+    while let Some(game) = reader.read_game(&mut LastPosition::new(None))? {
+        let mut pos = game.start;
+        for (ply, mv) in game.moves.iter().enumerate() {
+            pos.play_unchecked(mv);
+            if (FIRST_PLY..=LAST_PLY).contains(&(ply + 1)) && is_middlegame(&pos) {
+                positions.push(pos.clone());
+            }
+        }
+    }
+    Ok(positions)
+}
+
+// Enough pieces left, and no check to answer
+fn is_middlegame(pos: &Chess) -> bool {
+    let board = pos.board();
+    let material: u32 = [Role::Knight, Role::Bishop, Role::Rook, Role::Queen]
+        .into_iter()
+        .map(|role| board.by_role(role).count() as u32 * crate::analysis::piece_value(role))
+        .sum();
+    material >= MIDDLEGAME_MATERIAL && !pos.is_check() && pos.outcome().is_none()
+}
@@ -0,0 +1,196 @@
+use serde::Serialize;
+use shakmaty::fen::Fen;
+use shakmaty::san::SanPlus;
+use shakmaty::{Chess, EnPassantMode, Move, Position};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+
+use crate::{session_log, uci, App, AppMode};
+
+// ----------------------------------------------
+// Event stream for overlays and streaming tools: what happens in the
+// tabs as JSON lines, one object per event, written to a file, a FIFO or
+// an inherited file descriptor given by number. The events are found by
+// comparing each tab with what was last told of it
+// ----------------------------------------------
+
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Event {
+    PuzzleStarted {
+        id: String,
+        // 0 for puzzles without one, like study chapters
+        rating: u16,
+        fen: String,
+    },
+    MovePlayed {
+        // from the start of the game, the first move is 1
+        ply: usize,
+        color: String,
+        san: String,
+        uci: String,
+        // the position after the move
+        fen: String,
+    },
+    PuzzleSolved {
+        id: String,
+    },
+    PuzzleFailed {
+        id: String,
+    },
+    GameOver {
+        // "1-0", "0-1" or "1/2-1/2"
+        result: String,
+        // "White wins by checkmate"
+        description: String,
+    },
+}
+
+#[derive(Serialize)]
+struct Line {
+    // milliseconds since the Unix epoch
+    at_ms: u64,
+    #[serde(flatten)]
+    event: Event,
+}
+
+/// What the stream last told of one tab
+#[derive(Default)]
+pub struct Seen {
+    // false until the tab was first looked at, what it held then is not news
+    known: bool,
+    // the FEN of the game's start
+    start: String,
+    moves: Vec<Move>,
+    // the puzzle id and its start
+    puzzle: Option<(String, String)>,
+    scored: bool,
+    over: bool,
+}
+
+pub struct Stream {
+    out: Option<File>,
+}
+
+fn fen(pos: &Chess) -> String {
+    Fen::from_position(pos.clone(), EnPassantMode::Legal).to_string()
+}
+
+impl Stream {
+    /// Write to `target`, a file descriptor if it is a number, else a file
+    /// appended to; None writes nothing
+    pub fn open(target: Option<&str>) -> io::Result<Self> {
+        let out = match target {
+            None => None,
+            Some(target) => Some(match target.parse::<u32>() {
+                Ok(fd) => OpenOptions::new()
+                    .write(true)
+                    .open(format!("/dev/fd/{fd}"))?,
+                Err(_) => OpenOptions::new().create(true).append(true).open(target)?,
+            }),
+        };
+        Ok(Self { out })
+    }
+
+    /// Write the events of `app` since the last look at it
+    pub fn update(&mut self, app: &mut App) {
+        // the analysis of a game is not part of what happens in it
+        if self.out.is_none() || app.analysing {
+            return;
+        }
+        let mut seen = std::mem::take(&mut app.events_seen);
+        let events = changes(&mut seen, app);
+        app.events_seen = seen;
+        for event in events {
+            self.write(event);
+        }
+    }
+
+    fn write(&mut self, event: Event) {
+        let Some(out) = &mut self.out else {
+            return;
+        };
+        let at_ms = session_log::system_time()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64);
+        let written = serde_json::to_string(&Line { at_ms, event })
+            .map_err(io::Error::from)
+            .and_then(|line| writeln!(out, "{line}"))
+            .and_then(|()| out.flush());
+        // a reader that went away stops the stream, not the session
+        if written.is_err() {
+            self.out = None;
+        }
+    }
+}
+
+// What changed in `app` since `seen`, which is brought up to date
+fn changes(seen: &mut Seen, app: &App) -> Vec<Event> {
+    let mut events = Vec::new();
+    let puzzle = match &app.mode {
+        AppMode::Puzzle { lichess, .. } => Some(&lichess.puzzle),
+        _ => None,
+    };
+    let puzzle_key = puzzle.map(|puzzle| (puzzle.id.clone(), fen(&app.start)));
+    // a set may bring the same puzzle again, it starts over unscored
+    let again = seen.scored && !app.puzzle_scored;
+    if let Some(puzzle) = puzzle.filter(|_| seen.puzzle != puzzle_key || again) {
+        events.push(Event::PuzzleStarted {
+            id: puzzle.id.clone(),
+            rating: puzzle.rating,
+            fen: fen(&app.board),
+        });
+    }
+    seen.puzzle = puzzle_key;
+
+    // the moves past those already told of; new ones after a takeback count,
+    // those of a new game or puzzle do not until it goes on
+    if seen.known && seen.start == fen(&app.start) {
+        let kept = seen
+            .moves
+            .iter()
+            .zip(&app.history)
+            .take_while(|(a, b)| a == b)
+            .count();
+        let mut pos = app.start.clone();
+        for (ply, mv) in app.history.iter().enumerate() {
+            if ply >= kept {
+                let color = pos.turn();
+                let san = SanPlus::from_move_and_play_unchecked(&mut pos, mv);
+                events.push(Event::MovePlayed {
+                    ply: ply + 1,
+                    color: color.to_string(),
+                    san: san.to_string(),
+                    uci: uci::format(mv),
+                    fen: fen(&pos),
+                });
+            } else {
+                pos.play_unchecked(mv);
+            }
+        }
+    }
+    seen.start = fen(&app.start);
+    seen.moves = app.history.clone();
+
+    if let (AppMode::Puzzle { completed, .. }, Some(puzzle)) = (&app.mode, puzzle) {
+        if app.puzzle_scored && !seen.scored && seen.known {
+            let id = puzzle.id.clone();
+            events.push(if *completed {
+                Event::PuzzleSolved { id }
+            } else {
+                Event::PuzzleFailed { id }
+            });
+        }
+    }
+    seen.scored = app.puzzle_scored;
+
+    if let Some(over) = app.game_over.as_ref().filter(|_| !seen.over && seen.known) {
+        events.push(Event::GameOver {
+            result: over.outcome.to_string(),
+            description: over.describe(),
+        });
+    }
+    seen.over = app.game_over.is_some();
+    seen.known = true;
+    events
+}
@@ -0,0 +1,189 @@
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Color as ScreenColor, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::Terminal;
+use shakmaty::san::SanPlus;
+use shakmaty::{Chess, Color, Move, Position};
+use std::io;
+
+use tess_rs::board::{BoardWidget, DisplayMode, PieceSet};
+use tess_rs::theme::Palette;
+
+use crate::eco::Eco;
+use crate::opening_tree::{OpeningTree, Results};
+
+// ----------------------------------------------
+// Offline explorer: the database moves of the opening tree walked from the
+// start position, each with how often it was played and how those games
+// ended, on a screen of its own
+// ----------------------------------------------
+
+/// How the board of the explorer is drawn
+pub struct Look {
+    pub display: DisplayMode,
+    pub pieces: PieceSet,
+    pub wide_symbols: bool,
+    pub palette: Palette,
+}
+
+// "1. e4 c5 2. Nf3", from "1..." when black starts
+fn numbered(start: &Chess, moves: &[Move]) -> String {
+    let mut pos = start.clone();
+    let mut words = Vec::new();
+    for mv in moves {
+        match pos.turn() {
+            Color::White => words.push(format!("{}.", pos.fullmoves())),
+            Color::Black if words.is_empty() => words.push(format!("{}...", pos.fullmoves())),
+            Color::Black => {}
+        }
+        words.push(SanPlus::from_move_and_play_unchecked(&mut pos, mv).to_string());
+    }
+    words.join(" ")
+}
+
+// "e4   1234  51%  W 38% D 26% B 36%", the share of the games in the position first
+fn describe(pos: &Chess, mv: &Move, results: &Results, total: u32) -> String {
+    let percent = |count: u32| 100.0 * f64::from(count) / f64::from(results.games.max(1));
+    format!(
+        "{:<8}{:>7} {:>4.0}%   W {:>3.0}%  D {:>3.0}%  B {:>3.0}%",
+        SanPlus::from_move(pos.clone(), mv).to_string(),
+        results.games,
+        100.0 * f64::from(results.games) / f64::from(total.max(1)),
+        percent(results.white),
+        percent(results.draws),
+        percent(results.black),
+    )
+}
+
+/// Walk the database moves of `tree` until Esc or q. Up/Down select a move,
+/// Enter or Right plays it, Left or Backspace takes the last one back.
+pub fn run(tree: &OpeningTree, eco: &Eco, look: &Look) -> anyhow::Result<()> {
+    let start = Chess::default();
+    if tree.database_moves(&start).is_empty() {
+        anyhow::bail!("the opening tree has no database games, add some with `tess tree database`");
+    }
+    enable_raw_mode()?;
+    crossterm::execute!(io::stdout(), EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+    let result = explore(&mut terminal, tree, eco, look, &start);
+    disable_raw_mode()?;
+    crossterm::execute!(io::stdout(), LeaveAlternateScreen)?;
+    result
+}
+
+fn explore(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    tree: &OpeningTree,
+    eco: &Eco,
+    look: &Look,
+    start: &Chess,
+) -> anyhow::Result<()> {
+    let mut line: Vec<Move> = Vec::new();
+    // the move selected in each position of the line, kept for going back
+    let mut selected: Vec<usize> = vec![0];
+    let mut flipped = false;
+    loop {
+        let mut pos = start.clone();
+        for mv in &line {
+            pos.play_unchecked(mv);
+        }
+        let moves = tree.database_moves(&pos);
+        let total: u32 = moves.iter().map(|(_, results)| results.games).sum();
+        let mut state = ListState::default()
+            .with_selected((!moves.is_empty()).then(|| selected.last().copied().unwrap_or(0)));
+
+        terminal.draw(|f| {
+            let rows = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(3), Constraint::Length(1)])
+                .split(f.area());
+            let board = BoardWidget::new(&pos)
+                .flipped(flipped)
+                .theme(&look.palette)
+                .display(look.display)
+                .pieces(look.pieces)
+                .wide_symbols(look.wide_symbols);
+            let (board_width, _) = board.size();
+            let columns = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Length(board_width + 2), Constraint::Min(20)])
+                .split(rows[0]);
+            f.render_widget(board, columns[0]);
+
+            let side = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(4), Constraint::Min(3)])
+                .split(columns[1]);
+            let mut about = vec![Line::raw(if line.is_empty() {
+                "Start position".to_string()
+            } else {
+                numbered(start, &line)
+            })];
+            if let Some(opening) = eco.classify(start, &line) {
+                about.push(Line::styled(
+                    opening.describe(),
+                    Style::default().fg(ScreenColor::Cyan),
+                ));
+            }
+            f.render_widget(
+                Paragraph::new(about)
+                    .wrap(Wrap { trim: false })
+                    .block(Block::bordered().title("Line")),
+                side[0],
+            );
+
+            let items: Vec<ListItem> = moves
+                .iter()
+                .map(|(mv, results)| ListItem::new(describe(&pos, mv, results, total)))
+                .collect();
+            let title = match total {
+                0 => "No database games reach this position".to_string(),
+                1 => "1 game".to_string(),
+                total => format!("{total} games"),
+            };
+            let list = List::new(items)
+                .block(Block::bordered().title(title))
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+            f.render_stateful_widget(list, side[1], &mut state);
+
+            let help = Paragraph::new(
+                "Up/Down select, Enter plays the move, Left takes it back, f flips, Esc quits.",
+            )
+            .style(Style::default().fg(ScreenColor::Gray));
+            f.render_widget(help, rows[1]);
+        })?;
+
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        let current = selected.last_mut().expect("a selection per position");
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => return Ok(()),
+            KeyCode::Up => *current = current.saturating_sub(1),
+            KeyCode::Down => *current = (*current + 1).min(moves.len().saturating_sub(1)),
+            KeyCode::Home => *current = 0,
+            KeyCode::End => *current = moves.len().saturating_sub(1),
+            KeyCode::Enter | KeyCode::Right => {
+                if let Some((mv, _)) = moves.get(*current) {
+                    line.push(mv.clone());
+                    selected.push(0);
+                }
+            }
+            KeyCode::Left | KeyCode::Backspace if !line.is_empty() => {
+                line.pop();
+                selected.pop();
+            }
+            KeyCode::Char('f') => flipped = !flipped,
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use shakmaty::fen::Fen;
+use shakmaty::san::SanPlus;
+use shakmaty::{Chess, Color as ChessColor, EnPassantMode, File, Move, Position, Rank, Square};
+use std::fmt::Write;
+
+// ----------------------------------------------
+// Plain-text export: the move list followed by an ASCII diagram
+// after every Nth move, for emails, gists and plain-text blogs,
+// and finished games as PGN
+// ----------------------------------------------
+
+/// Text diagram of a position, white at the bottom
+pub fn ascii_diagram(pos: &Chess) -> String {
+    let board = pos.board();
+    let mut out = String::from("  +-----------------+\n");
+    for rank in Rank::ALL.into_iter().rev() {
+        let _ = write!(out, "{} |", rank.char());
+        for file in File::ALL {
+            let ch = board
+                .piece_at(Square::from_coords(file, rank))
+                .map_or('.', |piece| piece.char());
+            let _ = write!(out, " {ch}");
+        }
+        out.push_str(" |\n");
+    }
+    out.push_str("  +-----------------+\n");
+    out.push_str("    a b c d e f g h\n");
+    out
+}
+
+/// The move list and a diagram after every `every` plies (and the last one)
+pub fn diagram_sequence(start: &Chess, moves: &[Move], every: usize) -> String {
+    let every = every.max(1);
+    let mut pos = start.clone();
+    let mut list = Vec::new();
+    let mut diagrams = format!("Start position\n\n{}", ascii_diagram(&pos));
+
+    for (i, mv) in moves.iter().enumerate() {
+        let number = pos.fullmoves();
+        let prefix = match pos.turn() {
+            ChessColor::White => format!("{number}."),
+            ChessColor::Black => format!("{number}..."),
+        };
+        let san = SanPlus::from_move_and_play_unchecked(&mut pos, mv);
+        // black's moves only carry their number at the very start
+        if pos.turn() == ChessColor::Black || i == 0 {
+            list.push(format!("{prefix}{san}"));
+        } else {
+            list.push(san.to_string());
+        }
+        if (i + 1) % every == 0 || i + 1 == moves.len() {
+            let _ = write!(diagrams, "\nAfter {prefix}{san}\n\n{}", ascii_diagram(&pos));
+        }
+    }
+
+    if list.is_empty() {
+        diagrams
+    } else {
+        format!("{}\n\n{diagrams}", wrap(&list, 72))
+    }
+}
+
+/// Tags of an exported game that vary, with the result as in "1-0" or "*"
+pub struct Tags<'a> {
+    pub event: &'a str,
+    /// "2024.03.09", unknown if not given
+    pub date: Option<String>,
+    pub round: String,
+    pub white: &'a str,
+    pub black: &'a str,
+    pub result: String,
+    /// "normal" or "time forfeit", left out if not known
+    pub termination: Option<&'a str>,
+}
+
+/// A PGN of a casual game between `players`, white first
+pub fn pgn(
+    start: &Chess,
+    moves: &[Move],
+    players: [&str; 2],
+    result: &str,
+    termination: Option<&str>,
+) -> String {
+    let tags = Tags {
+        event: "Casual game",
+        date: None,
+        round: "-".to_string(),
+        white: players[0],
+        black: players[1],
+        result: result.to_string(),
+        termination,
+    };
+    pgn_with_tags(start, moves, &tags)
+}
+
+/// A PGN of the game with the seven tag roster
+pub fn pgn_with_tags(start: &Chess, moves: &[Move], tags: &Tags) -> String {
+    let mut out = String::new();
+    for (tag, value) in [
+        ("Event", tags.event),
+        ("Site", "tess"),
+        ("Date", tags.date.as_deref().unwrap_or("????.??.??")),
+        ("Round", &tags.round),
+        ("White", tags.white),
+        ("Black", tags.black),
+        ("Result", &tags.result),
+    ] {
+        let _ = writeln!(out, "[{tag} \"{value}\"]");
+    }
+    if let Some(termination) = tags.termination {
+        let _ = writeln!(out, "[Termination \"{termination}\"]");
+    }
+    if *start != Chess::default() {
+        let fen = Fen::from_position(start.clone(), EnPassantMode::Legal);
+        let _ = writeln!(out, "[SetUp \"1\"]\n[FEN \"{fen}\"]");
+    }
+
+    let mut pos = start.clone();
+    let mut words = Vec::new();
+    for (i, mv) in moves.iter().enumerate() {
+        match pos.turn() {
+            ChessColor::White => words.push(format!("{}.", pos.fullmoves())),
+            ChessColor::Black if i == 0 => words.push(format!("{}...", pos.fullmoves())),
+            ChessColor::Black => {}
+        }
+        words.push(SanPlus::from_move_and_play_unchecked(&mut pos, mv).to_string());
+    }
+    words.push(tags.result.clone());
+    format!("{out}\n{}\n", wrap(&words, 80))
+}
+
+/// Join words into lines of at most `width` columns
+pub fn wrap(words: &[String], width: usize) -> String {
+    let mut out = String::new();
+    let mut line_len = 0;
+    for word in words {
+        if line_len > 0 && line_len + 1 + word.len() > width {
+            out.push('\n');
+            line_len = 0;
+        } else if line_len > 0 {
+            out.push(' ');
+            line_len += 1;
+        }
+        out.push_str(word);
+        line_len += word.len();
+    }
+    out
+}
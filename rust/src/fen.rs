@@ -0,0 +1,192 @@
+use shakmaty::fen::{Fen, ParseFenError};
+use shakmaty::{
+    CastlingMode, Chess, EnPassantMode, File, Position, PositionError, PositionErrorKinds, Rank,
+    Square,
+};
+
+// ----------------------------------------------
+// FEN validation and normalization (`tess fen check`)
+// ----------------------------------------------
+
+/// Outcome of checking a FEN string.
+pub struct FenReport {
+    pub errors: Vec<String>,
+    pub notes: Vec<String>,
+    /// Normalized FEN, if the position could be set up (possibly after
+    /// discarding invalid castling rights or en passant squares).
+    pub normalized: Option<String>,
+}
+
+impl FenReport {
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+// Indexes of the space separated FEN fields, used to quote the offending part
+const FIELD_NAMES: [&str; 6] = [
+    "piece placement",
+    "side to move",
+    "castling",
+    "en passant",
+    "halfmove clock",
+    "fullmove number",
+];
+
+fn parse_error_field(err: &ParseFenError) -> Option<usize> {
+    match err {
+        ParseFenError::InvalidBoard | ParseFenError::InvalidPocket => Some(0),
+        ParseFenError::InvalidTurn => Some(1),
+        ParseFenError::InvalidCastling => Some(2),
+        ParseFenError::InvalidEpSquare => Some(3),
+        ParseFenError::InvalidHalfmoveClock => Some(4),
+        ParseFenError::InvalidFullmoves => Some(5),
+        _ => None,
+    }
+}
+
+// Render a castling right (rook square) the way it appears in a FEN
+fn castling_right_name(sq: Square) -> String {
+    let ch = match (sq.file(), sq.rank()) {
+        (File::H, Rank::First) => 'K',
+        (File::A, Rank::First) => 'Q',
+        (File::H, Rank::Eighth) => 'k',
+        (File::A, Rank::Eighth) => 'q',
+        (file, Rank::First) => file.upper_char(),
+        (file, _) => file.char(),
+    };
+    ch.to_string()
+}
+
+fn position_error_messages(err: &PositionError<Chess>, setup_fen: &Fen) -> Vec<String> {
+    let kinds = err.kinds();
+    let mut errors = Vec::new();
+
+    if kinds.contains(PositionErrorKinds::EMPTY_BOARD) {
+        errors.push("there are no pieces on the board".to_string());
+    }
+    if kinds.contains(PositionErrorKinds::MISSING_KING) {
+        errors.push("a king is missing".to_string());
+    }
+    if kinds.contains(PositionErrorKinds::TOO_MANY_KINGS) {
+        errors.push("a side has more than one king".to_string());
+    }
+    if kinds.contains(PositionErrorKinds::PAWNS_ON_BACKRANK) {
+        errors.push("there are pawns on the first or eighth rank".to_string());
+    }
+    if kinds.contains(PositionErrorKinds::INVALID_CASTLING_RIGHTS) {
+        let claimed = setup_fen.0.castling_rights;
+        let valid = err
+            .clone()
+            .ignore_invalid_castling_rights()
+            .map(|pos| pos.castles().castling_rights())
+            .unwrap_or_default();
+        let invalid: Vec<String> = (claimed & !valid)
+            .into_iter()
+            .map(castling_right_name)
+            .collect();
+        errors.push(format!(
+            "castling rights {} do not match king and rook placement",
+            if invalid.is_empty() {
+                "(some)".to_string()
+            } else {
+                invalid.join("")
+            }
+        ));
+    }
+    if kinds.contains(PositionErrorKinds::INVALID_EP_SQUARE) {
+        let ep = setup_fen
+            .0
+            .ep_square
+            .map(|sq| sq.to_string())
+            .unwrap_or_default();
+        errors.push(format!(
+            "en passant square {ep} is impossible: wrong rank, occupied, or no pawn was just pushed past it"
+        ));
+    }
+    if kinds.contains(PositionErrorKinds::OPPOSITE_CHECK) {
+        errors.push("the side not to move is in check".to_string());
+    }
+    if kinds.contains(PositionErrorKinds::IMPOSSIBLE_CHECK) {
+        errors.push("the check on the board cannot arise from a legal move".to_string());
+    }
+    if kinds.contains(PositionErrorKinds::TOO_MUCH_MATERIAL) {
+        errors.push("there is more material than can be reached by promotions".to_string());
+    }
+    errors
+}
+
+/// Validate `fen`, collecting precise errors plus a normalized form.
+pub fn check_fen(fen: &str) -> FenReport {
+    let mut report = FenReport {
+        errors: Vec::new(),
+        notes: Vec::new(),
+        normalized: None,
+    };
+
+    let trimmed = fen.trim();
+    let parsed = match Fen::from_ascii(trimmed.as_bytes()) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            let detail = parse_error_field(&err)
+                .map(|i| {
+                    let part = trimmed.split_whitespace().nth(i).unwrap_or("");
+                    format!("{} field '{part}'", FIELD_NAMES[i])
+                })
+                .unwrap_or_else(|| "wrong number of fields".to_string());
+            report.errors.push(format!("{err}: {detail}"));
+            return report;
+        }
+    };
+
+    if trimmed.split_whitespace().count() < FIELD_NAMES.len() {
+        report
+            .notes
+            .push("missing fields were filled in with defaults".to_string());
+    }
+
+    let claimed_ep = parsed.0.ep_square;
+    let mode = CastlingMode::detect(&parsed.0);
+    if mode == CastlingMode::Chess960 {
+        report
+            .notes
+            .push("castling rights use Chess960 (Shredder-FEN) notation".to_string());
+    }
+
+    let pos: Chess = match parsed.clone().into_position(mode) {
+        Ok(pos) => pos,
+        Err(err) => {
+            report.errors = position_error_messages(&err, &parsed);
+            // Still offer a normalized form if only recoverable parts were wrong
+            let recovered = match err.ignore_invalid_castling_rights() {
+                Ok(pos) => Some(pos),
+                Err(err) => err.ignore_invalid_ep_square().ok(),
+            };
+            report.normalized = recovered.as_ref().map(normalize);
+            return report;
+        }
+    };
+
+    if let Some(ep) = claimed_ep {
+        if pos.ep_square(EnPassantMode::Legal).is_none() {
+            report.notes.push(format!(
+                "en passant square {ep} dropped: no legal en passant capture"
+            ));
+        }
+    }
+    if pos.legal_moves().is_empty() {
+        let what = if pos.is_check() {
+            "checkmated"
+        } else {
+            "stalemated"
+        };
+        report.notes.push(format!("{} is {what}", pos.turn()));
+    }
+
+    report.normalized = Some(normalize(&pos));
+    report
+}
+
+fn normalize(pos: &Chess) -> String {
+    Fen::from_position(pos.clone(), EnPassantMode::Legal).to_string()
+}
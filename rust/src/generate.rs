@@ -0,0 +1,300 @@
+use shakmaty::fen::Fen;
+use shakmaty::{CastlingMode, Chess, EnPassantMode, Move, Position};
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::engine::{Engine, Score};
+use crate::{uci, Game, LastPosition, LichessNextPuzzle, Puzzle};
+
+// ----------------------------------------------
+// Puzzles made offline: every position of PGN games or FEN lines searched
+// by an engine and kept where a single move mates or wins, written one
+// per line in the shape lichess sends so the puzzle mode plays them
+// ----------------------------------------------
+
+// A tactic wins at least this much for the side to move
+const WINNING: i32 = 300;
+// and the second best move keeps less than this
+const SECOND_BEST: i32 = 100;
+// Plies of a tactic's solution at most: move, reply, move
+const TACTIC_PLIES: usize = 3;
+
+pub struct Limits {
+    pub movetime: Duration,
+    /// the longest forced mate kept, in moves
+    pub max_mate: u32,
+    /// also winning tactics, not only mates
+    pub tactics: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    Mate(usize),
+    Tactic,
+}
+
+impl Kind {
+    /// "mateIn2" or "tactic", after the lichess themes
+    pub fn name(self) -> String {
+        match self {
+            Kind::Mate(moves) => format!("mateIn{moves}"),
+            Kind::Tactic => "tactic".to_string(),
+        }
+    }
+
+    // Whether `score` still carries the puzzle, for the side to move
+    fn holds(self, score: Score) -> bool {
+        match (self, score) {
+            (_, Score::Mate(moves)) => moves > 0,
+            (Kind::Mate(_), Score::Centipawns(_)) => false,
+            (Kind::Tactic, Score::Centipawns(cp)) => cp >= WINNING,
+        }
+    }
+
+    // Whether an alternative scoring `score` would solve the puzzle as well
+    fn also_solves(self, score: Score) -> bool {
+        match (self, score) {
+            (_, Score::Mate(moves)) => moves > 0,
+            (Kind::Mate(_), Score::Centipawns(_)) => false,
+            (Kind::Tactic, Score::Centipawns(cp)) => cp >= SECOND_BEST,
+        }
+    }
+}
+
+/// A position worth a puzzle, where it came from, and the moves that solve it
+pub struct Found {
+    pub kind: Kind,
+    pub source: String,
+    pub pos: Chess,
+    pub solution: Vec<Move>,
+}
+
+impl Found {
+    pub fn puzzle(&self) -> LichessNextPuzzle {
+        let fen = Fen::from_position(self.pos.clone(), EnPassantMode::Legal);
+        LichessNextPuzzle {
+            puzzle: Puzzle {
+                id: format!("{} {}", self.kind.name(), self.source),
+                // nothing to rate them against, like study chapters
+                rating: 0,
+                solution: self.solution.iter().map(uci::format).collect(),
+                initial_ply: 0,
+            },
+            game: Game {
+                pgn: format!("[FEN \"{fen}\"]\n\n*"),
+            },
+        }
+    }
+}
+
+/// Every position of the PGN games or FEN lines of `path`, each with where it is
+/// from ("games.pgn game 3 ply 41", "set.epd line 7")
+pub fn positions(path: &Path) -> anyhow::Result<Vec<(String, Chess)>> {
+    let text = std::fs::read_to_string(path)?;
+    let name = path.file_name().map_or_else(
+        || path.display().to_string(),
+        |name| name.to_string_lossy().to_string(),
+    );
+    let mut positions = Vec::new();
+    if path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("pgn"))
+    {
+        let mut reader = pgn_reader::BufferedReader::new(text.as_bytes());
+        let mut number = 0;
+        while let Some(game) = reader.read_game(&mut LastPosition::new(None))? {
+            number += 1;
+            let mut pos = game.start;
+            for (ply, mv) in game.moves.iter().enumerate() {
+                pos.play_unchecked(mv);
+                positions.push((format!("{name} game {number} ply {}", ply + 1), pos.clone()));
+            }
+        }
+    } else {
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            // the four fields of the position, without the operations after them
+            let fields: Vec<&str> = line.split_whitespace().take(4).collect();
+            let fen = Fen::from_ascii(fields.join(" ").as_bytes())?;
+            positions.push((
+                format!("{name} line {}", index + 1),
+                fen.into_position(CastlingMode::Standard)?,
+            ));
+        }
+    }
+    Ok(positions)
+}
+
+/// Search every position once, and the promising ones further, returning the
+/// puzzles found. `progress` is told how many positions are done.
+pub fn generate(
+    engine: &mut Engine,
+    positions: Vec<(String, Chess)>,
+    limits: &Limits,
+    progress: &dyn Fn(usize, usize),
+) -> anyhow::Result<Vec<Found>> {
+    let total = positions.len();
+    let mut seen = HashSet::new();
+    let mut found = Vec::new();
+    for (done, (source, pos)) in positions.into_iter().enumerate() {
+        progress(done, total);
+        // the same position from several games makes one puzzle
+        let key = Fen::from_position(pos.clone(), EnPassantMode::Legal).to_string();
+        if pos.outcome().is_some() || !seen.insert(key) {
+            continue;
+        }
+        if let Some((kind, solution)) = solve(engine, &pos, limits)? {
+            found.push(Found {
+                kind,
+                source,
+                pos,
+                solution,
+            });
+        }
+    }
+    progress(total, total);
+    Ok(found)
+}
+
+/// The puzzle in `pos`, if the engine finds one move alone that mates or wins.
+/// The solution goes on while the side to move keeps a single good move, and
+/// always ends with its move; a mate must be played out to the end.
+pub fn solve(
+    engine: &mut Engine,
+    pos: &Chess,
+    limits: &Limits,
+) -> anyhow::Result<Option<(Kind, Vec<Move>)>> {
+    let Some(mut evaluation) = engine.evaluate(pos, limits.movetime)? else {
+        return Ok(None);
+    };
+    let kind = match evaluation.score {
+        Score::Mate(moves) if moves > 0 && moves as u32 <= limits.max_mate => {
+            Kind::Mate(moves as usize)
+        }
+        Score::Centipawns(cp) if limits.tactics && cp >= WINNING => Kind::Tactic,
+        _ => return Ok(None),
+    };
+    let plies = match kind {
+        Kind::Mate(moves) => 2 * moves - 1,
+        Kind::Tactic => TACTIC_PLIES,
+    };
+    let mut line = pos.clone();
+    let mut solution = Vec::new();
+    while let Some(best) = evaluation
+        .pv
+        .first()
+        .and_then(|text| uci::parse(&line, text))
+    {
+        if !kind.holds(evaluation.score) || !only_move(engine, &line, &best, kind, limits)? {
+            break;
+        }
+        line.play_unchecked(&best);
+        solution.push(best);
+        if solution.len() >= plies || line.is_game_over() {
+            break;
+        }
+        // the defence the engine expects, or its own search for one
+        let reply = match evaluation
+            .pv
+            .get(1)
+            .and_then(|text| uci::parse(&line, text))
+        {
+            Some(reply) => Some(reply),
+            None => engine
+                .evaluate(&line, limits.movetime)?
+                .and_then(|defence| defence.pv.first().and_then(|text| uci::parse(&line, text))),
+        };
+        let Some(reply) = reply else {
+            break;
+        };
+        line.play_unchecked(&reply);
+        solution.push(reply);
+        let Some(next) = engine.evaluate(&line, limits.movetime)? else {
+            break;
+        };
+        evaluation = next;
+    }
+    // a defence without the answer to it is no puzzle move
+    if solution.len() % 2 == 0 {
+        solution.pop();
+    }
+    match kind {
+        _ if solution.is_empty() => Ok(None),
+        Kind::Mate(_) => {
+            let mut end = pos.clone();
+            for mv in &solution {
+                end.play_unchecked(mv);
+            }
+            Ok(end
+                .is_checkmate()
+                .then(|| (Kind::Mate(solution.len().div_ceil(2)), solution)))
+        }
+        Kind::Tactic => Ok(Some((kind, solution))),
+    }
+}
+
+// Whether `best` is the only move solving the puzzle in `pos`; the puzzle mode
+// takes nothing but the expected move
+fn only_move(
+    engine: &mut Engine,
+    pos: &Chess,
+    best: &Move,
+    kind: Kind,
+    limits: &Limits,
+) -> anyhow::Result<bool> {
+    let others: Vec<Move> = pos
+        .legal_moves()
+        .into_iter()
+        .filter(|mv| mv != best)
+        .collect();
+    if others.is_empty() {
+        return Ok(true);
+    }
+    // a mate on the board needs no search
+    let mates = |mv: &Move| {
+        let mut after = pos.clone();
+        after.play_unchecked(mv);
+        after.is_checkmate()
+    };
+    if mates(best) {
+        return Ok(!others.iter().any(mates));
+    }
+    Ok(
+        match engine.evaluate_moves(pos, limits.movetime, &others)? {
+            Some(second) => !kind.also_solves(second.score),
+            None => false,
+        },
+    )
+}
+
+/// Append `puzzles` to `out`, one JSON object per line
+pub fn write(out: &mut dyn Write, puzzles: &[Found]) -> anyhow::Result<()> {
+    for found in puzzles {
+        writeln!(out, "{}", serde_json::to_string(&found.puzzle())?)?;
+    }
+    Ok(())
+}
+
+/// The puzzles of a file written by `tess generate`
+pub fn load(path: &Path) -> anyhow::Result<Vec<LichessNextPuzzle>> {
+    let text = std::fs::read_to_string(path)?;
+    let mut puzzles = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        puzzles.push(
+            serde_json::from_str(line)
+                .map_err(|err| anyhow::anyhow!("{} line {}: {err}", path.display(), index + 1))?,
+        );
+    }
+    if puzzles.is_empty() {
+        anyhow::bail!("{} has no puzzles", path.display());
+    }
+    Ok(puzzles)
+}
@@ -0,0 +1,64 @@
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+
+use crate::review::winning_chances;
+
+// ----------------------------------------------
+// The evaluation over a whole game as two rows of bars: white's advantage
+// rises from the middle, black's hangs down from it
+// ----------------------------------------------
+
+// A cell filled from the bottom in eighths
+const EIGHTHS: [char; 9] = [' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// The graph of `values` (centipawns from white's side for every position, None
+/// where unknown) squeezed into `width` columns, with the column of `marked` in
+/// `highlight`. Empty without a single value.
+pub fn eval_graph(
+    values: &[Option<i32>],
+    width: usize,
+    marked: usize,
+    color: Color,
+    highlight: Color,
+) -> Vec<Line<'static>> {
+    if width == 0 || values.iter().all(Option::is_none) {
+        return Vec::new();
+    }
+    // several positions share a column in a long game, the last known one
+    // counts; a short one spreads each position over several
+    let columns = values.len().min(width);
+    let repeat = (width / values.len()).max(1);
+    let bucket =
+        |column: usize| column * values.len() / columns..(column + 1) * values.len() / columns;
+    let (mut upper, mut lower) = (Vec::new(), Vec::new());
+    for column in 0..columns {
+        let range = bucket(column);
+        let style = Style::default().fg(if range.contains(&marked) {
+            highlight
+        } else {
+            color
+        });
+        let value = values[range].iter().rev().find_map(|value| *value);
+        // -8 to 8 eighths of a row
+        let eighths = value.map(|cp| ((winning_chances(cp) - 50.0) / 50.0 * 8.0).round() as i32);
+        let (top, bottom) = match eighths {
+            None => (Span::raw(" "), Span::raw(" ")),
+            Some(0) => (Span::styled("·", style), Span::raw(" ")),
+            Some(up) if up > 0 => (
+                Span::styled(EIGHTHS[up as usize].to_string(), style),
+                Span::raw(" "),
+            ),
+            // a reversed cell fills from the top
+            Some(down) => (
+                Span::raw(" "),
+                Span::styled(
+                    EIGHTHS[(8 + down) as usize].to_string(),
+                    style.add_modifier(Modifier::REVERSED),
+                ),
+            ),
+        };
+        upper.extend(std::iter::repeat_n(top, repeat));
+        lower.extend(std::iter::repeat_n(bottom, repeat));
+    }
+    vec![Line::from(upper), Line::from(lower)]
+}
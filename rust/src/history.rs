@@ -0,0 +1,202 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use shakmaty::fen::Fen;
+use shakmaty::{CastlingMode, Chess, Move, Position};
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use crate::{activity, export, session, uci};
+
+// ----------------------------------------------
+// Training history: the games played out here are kept in
+// $XDG_STATE_HOME/tess/games.pgn and every puzzle tried in
+// puzzles.jsonl. `archive` writes them, with the other records of the
+// state directory, in formats other tools read: the games and the
+// puzzles as PGN for a lichess import or scid, the puzzles also in the
+// shape of lichess's puzzle activity, and everything else as NDJSON
+// ----------------------------------------------
+
+/// The records of the state directory that go into training.ndjson, each
+/// line with its type
+const RECORDS: &[(&str, &str)] = &[
+    ("stats.jsonl", "session"),
+    ("reviews.jsonl", "review"),
+    ("eval_guesses.jsonl", "eval_guess"),
+    ("move_guesses.jsonl", "move_guess"),
+    ("endgames.jsonl", "endgame"),
+    ("blunder_puzzles.jsonl", "blunder_puzzle"),
+];
+
+/// One puzzle tried, the moves in UCI
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Attempt {
+    /// seconds since the Unix epoch
+    pub at: u64,
+    pub id: String,
+    /// 0 for puzzles without one, like study chapters
+    pub rating: u16,
+    /// the position the puzzle starts from
+    pub fen: String,
+    pub solution: Vec<String>,
+    /// the moves on the board when it was scored, the replies included
+    pub moves: Vec<String>,
+    pub solved: bool,
+    /// 1 for a clean solve, less the hints taken, 0 for a failure
+    pub score: f64,
+}
+
+fn path(name: &str) -> Option<PathBuf> {
+    session::state_dir().map(|dir| dir.join(name))
+}
+
+fn append(name: &str, text: &str) -> anyhow::Result<()> {
+    let Some(path) = path(name) else {
+        return Ok(());
+    };
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    writeln!(file, "{text}")?;
+    Ok(())
+}
+
+/// Keep a finished game, as PGN
+pub fn keep_game(pgn: &str) -> anyhow::Result<()> {
+    append("games.pgn", pgn)
+}
+
+/// Keep a scored puzzle
+pub fn keep_puzzle(attempt: &Attempt) -> anyhow::Result<()> {
+    append("puzzles.jsonl", &serde_json::to_string(attempt)?)
+}
+
+/// The PGN Date of a Unix time, "2024.03.09"
+pub fn pgn_date(unix: u64) -> String {
+    activity::date(unix).replace('-', ".")
+}
+
+// The lines of a JSON lines file of the state directory, none if it is not there
+fn json_lines(name: &str) -> anyhow::Result<Vec<Value>> {
+    let Some(path) = path(name).filter(|path| path.exists()) else {
+        return Ok(Vec::new());
+    };
+    let mut lines = Vec::new();
+    for line in std::io::BufReader::new(std::fs::File::open(&path)?).lines() {
+        let line = line?;
+        // a line cut short by a crash is left out, not the archive
+        if let Ok(value) = serde_json::from_str(&line) {
+            lines.push(value);
+        }
+    }
+    Ok(lines)
+}
+
+// The attempt as a game from the puzzle's position, for the PGN tools
+fn attempt_pgn(attempt: &Attempt) -> anyhow::Result<String> {
+    let start: Chess =
+        Fen::from_ascii(attempt.fen.as_bytes())?.into_position(CastlingMode::Standard)?;
+    let mut pos = start.clone();
+    let mut moves: Vec<Move> = Vec::new();
+    for text in &attempt.moves {
+        let Some(mv) = uci::parse(&pos, text) else {
+            anyhow::bail!("puzzle {}: {text} is not a legal move", attempt.id);
+        };
+        pos.play_unchecked(&mv);
+        moves.push(mv);
+    }
+    let event = format!(
+        "Puzzle {}, {}",
+        attempt.id,
+        if attempt.solved { "solved" } else { "failed" }
+    );
+    let tags = export::Tags {
+        event: &event,
+        date: Some(pgn_date(attempt.at)),
+        round: "-".to_string(),
+        white: "?",
+        black: "?",
+        result: "*".to_string(),
+        termination: None,
+    };
+    Ok(export::pgn_with_tags(&start, &moves, &tags))
+}
+
+/// What went into an archive
+pub struct Summary {
+    pub games: usize,
+    pub puzzles: usize,
+    pub records: usize,
+}
+
+/// Write the whole history to `dir`: games.pgn, puzzles.pgn, puzzles.ndjson
+/// and training.ndjson
+pub fn archive(dir: &Path) -> anyhow::Result<Summary> {
+    std::fs::create_dir_all(dir)
+        .map_err(|err| anyhow::anyhow!("cannot create {}: {err}", dir.display()))?;
+
+    let games = path("games.pgn")
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .unwrap_or_default();
+    std::fs::write(dir.join("games.pgn"), &games)?;
+
+    let attempts: Vec<Attempt> = json_lines("puzzles.jsonl")?
+        .into_iter()
+        .filter_map(|value| serde_json::from_value(value).ok())
+        .collect();
+    let mut pgn = String::new();
+    let mut ndjson = String::new();
+    for attempt in &attempts {
+        pgn.push_str(&attempt_pgn(attempt)?);
+        pgn.push('\n');
+        let activity = json!({
+            "date": attempt.at * 1000,
+            "win": attempt.solved,
+            "puzzle": {
+                "id": attempt.id,
+                "fen": attempt.fen,
+                "rating": attempt.rating,
+                "solution": attempt.solution,
+            },
+            "moves": attempt.moves,
+            "score": attempt.score,
+        });
+        ndjson.push_str(&format!("{activity}\n"));
+    }
+    std::fs::write(dir.join("puzzles.pgn"), pgn)?;
+    std::fs::write(dir.join("puzzles.ndjson"), ndjson)?;
+
+    let mut records = 0;
+    let mut training = String::new();
+    for (name, kind) in RECORDS {
+        for mut value in json_lines(name)? {
+            if let Value::Object(fields) = &mut value {
+                fields.insert("type".to_string(), json!(kind));
+            }
+            training.push_str(&format!("{value}\n"));
+            records += 1;
+        }
+    }
+    for (date, day) in activity::load() {
+        let mut value = serde_json::to_value(day)?;
+        if let Value::Object(fields) = &mut value {
+            fields.insert("type".to_string(), json!("activity"));
+            fields.insert("date".to_string(), json!(date));
+        }
+        training.push_str(&format!("{value}\n"));
+        records += 1;
+    }
+    std::fs::write(dir.join("training.ndjson"), training)?;
+
+    Ok(Summary {
+        games: games
+            .lines()
+            .filter(|line| line.starts_with("[Event "))
+            .count(),
+        puzzles: attempts.len(),
+        records,
+    })
+}
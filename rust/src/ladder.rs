@@ -0,0 +1,155 @@
+use serde::{Deserialize, Serialize};
+use shakmaty::{Color, Outcome};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::clock::TimeControl;
+use crate::engine::{Engine, Strength};
+use crate::session;
+
+// ----------------------------------------------
+// The ladder: blitz games against one engine at rising strength. A win
+// or a draw climbs to the next level, a loss ends the climb. The
+// highest level ever cleared is kept in $XDG_STATE_HOME/tess/ladder.json
+// ----------------------------------------------
+
+// UCI_Elo of the first level, Stockfish's lowest, and the step to the next
+const FIRST_ELO: u32 = 1350;
+const ELO_STEP: u32 = 150;
+/// Levels to climb, the last at 2850
+pub const LEVELS: usize = 11;
+/// The time control without --clock, 3+2
+pub const BLITZ: TimeControl = TimeControl {
+    base: Duration::from_secs(180),
+    increment: Duration::from_secs(2),
+};
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Record {
+    /// the highest level cleared, 0 for none
+    best: usize,
+}
+
+fn record_path() -> Option<PathBuf> {
+    session::state_dir().map(|dir| dir.join("ladder.json"))
+}
+
+fn load_record() -> Record {
+    record_path()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+fn save_record(record: &Record) -> anyhow::Result<()> {
+    let Some(path) = record_path() else {
+        return Ok(());
+    };
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, serde_json::to_vec(record)?)?;
+    Ok(())
+}
+
+/// The engine's rating at `level`, from 1
+pub fn elo(level: usize) -> u32 {
+    FIRST_ELO + ELO_STEP * (level.clamp(1, LEVELS) as u32 - 1)
+}
+
+/// How strong the engine plays at `level`
+pub fn strength(level: usize) -> Strength {
+    Strength {
+        elo: Some(elo(level)),
+        ..Strength::default()
+    }
+}
+
+/// My result of a game on the ladder
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Step {
+    Won,
+    Drawn,
+    Lost,
+}
+
+impl Step {
+    pub fn name(self) -> &'static str {
+        match self {
+            Step::Won => "won",
+            Step::Drawn => "drawn",
+            Step::Lost => "lost",
+        }
+    }
+}
+
+pub struct Ladder {
+    /// the level being played, from 1
+    pub level: usize,
+    /// every game of this climb with its level
+    pub steps: Vec<(usize, Step)>,
+    /// the highest level cleared before this climb
+    pub best_before: usize,
+}
+
+impl Ladder {
+    /// A climb starting at `level`
+    pub fn new(level: usize) -> Self {
+        Self {
+            level: level.clamp(1, LEVELS),
+            steps: Vec::new(),
+            best_before: load_record().best,
+        }
+    }
+
+    /// Set a running engine to the current level's strength, for the next game
+    pub fn tune(&self, engine: &mut Engine) -> anyhow::Result<()> {
+        engine.set_option("UCI_LimitStrength", "true")?;
+        engine.set_option("UCI_Elo", &elo(self.level).to_string())
+    }
+
+    /// Count a finished game in which I played `me`, climbing on if it did not
+    /// lose and keeping a new best. Returns my result.
+    pub fn record(&mut self, outcome: Outcome, me: Color) -> Step {
+        let step = match outcome {
+            Outcome::Decisive { winner } if winner == me => Step::Won,
+            Outcome::Decisive { .. } => Step::Lost,
+            Outcome::Draw => Step::Drawn,
+        };
+        self.steps.push((self.level, step));
+        if self.is_new_best() {
+            // best effort, the climb goes on without the record
+            let _ = save_record(&Record {
+                best: self.cleared(),
+            });
+        }
+        if step != Step::Lost && self.level < LEVELS {
+            self.level += 1;
+        }
+        step
+    }
+
+    /// A loss, or the top level cleared
+    pub fn is_over(&self) -> bool {
+        match self.steps.last() {
+            Some((_, Step::Lost)) => true,
+            Some((level, _)) => *level == LEVELS,
+            None => false,
+        }
+    }
+
+    /// The highest level cleared on this climb, 0 for none
+    pub fn cleared(&self) -> usize {
+        self.steps
+            .iter()
+            .filter(|(_, step)| *step != Step::Lost)
+            .map(|(level, _)| *level)
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub fn is_new_best(&self) -> bool {
+        self.cleared() > self.best_before
+    }
+}
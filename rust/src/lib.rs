@@ -0,0 +1,15 @@
+//! The chess board of tess as a ratatui widget, for other terminal applications:
+//!
+//! ```no_run
+//! use tess_rs::{BoardWidget, Background, Palette};
+//!
+//! let pos = shakmaty::Chess::default();
+//! let palette = Palette::for_background(Background::Light);
+//! let board = BoardWidget::new(&pos).flipped(true).theme(&palette);
+//! ```
+
+pub mod board;
+pub mod theme;
+
+pub use board::{BoardWidget, DisplayMode, PieceSet};
+pub use theme::{Background, Palette};
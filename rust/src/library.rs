@@ -0,0 +1,292 @@
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use pgn_reader::{BufferedReader, RawHeader, Skip, Visitor};
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph};
+use ratatui::Terminal;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::session;
+
+// ----------------------------------------------
+// PGN library: every game in a directory of PGN files, fuzzy
+// searchable by file name, players and event
+// ----------------------------------------------
+
+const MAX_RECENT: usize = 10;
+
+#[derive(Clone, Debug)]
+pub struct Entry {
+    pub path: PathBuf,
+    /// position of the game within its file
+    pub index: usize,
+    white: String,
+    black: String,
+    event: String,
+    date: String,
+}
+
+impl Entry {
+    fn file_name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    fn search_text(&self) -> String {
+        format!(
+            "{} {} {} {}",
+            self.file_name(),
+            self.white,
+            self.black,
+            self.event
+        )
+    }
+
+    fn label(&self) -> String {
+        format!(
+            "{:<20} {} - {}  {} {}",
+            self.file_name(),
+            self.white,
+            self.black,
+            self.event,
+            self.date
+        )
+    }
+}
+
+// Collects the headers of each game and skips the moves
+#[derive(Default)]
+struct Headers {
+    white: String,
+    black: String,
+    event: String,
+    date: String,
+}
+
+impl Visitor for Headers {
+    type Result = Headers;
+
+    fn begin_game(&mut self) {
+        *self = Headers::default();
+    }
+
+    fn header(&mut self, key: &[u8], value: RawHeader<'_>) {
+        let value = value.decode_utf8_lossy().into_owned();
+        match key {
+            b"White" => self.white = value,
+            b"Black" => self.black = value,
+            b"Event" => self.event = value,
+            b"Date" => self.date = value,
+            _ => {}
+        }
+    }
+
+    fn end_headers(&mut self) -> Skip {
+        Skip(true)
+    }
+
+    fn end_game(&mut self) -> Self::Result {
+        std::mem::take(self)
+    }
+}
+
+/// All games of the `.pgn` files in `dir`, ordered by file name
+pub fn scan(dir: &Path) -> anyhow::Result<Vec<Entry>> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("pgn"))
+        })
+        .collect();
+    files.sort();
+
+    let mut entries = Vec::new();
+    for path in files {
+        // canonical, to compare with the recent files
+        let path = path.canonicalize().unwrap_or(path);
+        // unreadable files are left out rather than failing the whole library
+        let Ok(file) = std::fs::File::open(&path) else {
+            continue;
+        };
+        let mut reader = BufferedReader::new(file);
+        let mut headers = Headers::default();
+        let mut index = 0;
+        while let Ok(Some(game)) = reader.read_game(&mut headers) {
+            entries.push(Entry {
+                path: path.clone(),
+                index,
+                white: game.white,
+                black: game.black,
+                event: game.event,
+                date: game.date,
+            });
+            index += 1;
+        }
+    }
+    Ok(entries)
+}
+
+/// Score of `query` as a subsequence of `text`, None if it does not match.
+/// Consecutive characters and word starts score higher.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut pos = 0;
+    let mut last_match: Option<usize> = None;
+    for q in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = (pos..text.len()).find(|&i| text[i] == q)?;
+        score += 1;
+        if last_match.is_some_and(|last| last + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        last_match = Some(found);
+        pos = found + 1;
+    }
+    Some(score)
+}
+
+// ----------------------------------------------
+// Recently opened files, most recent first
+// ----------------------------------------------
+
+fn recent_path() -> Option<PathBuf> {
+    session::state_dir().map(|dir| dir.join("recent.json"))
+}
+
+pub fn recent_files() -> Vec<PathBuf> {
+    recent_path()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+pub fn remember(path: &Path) -> anyhow::Result<()> {
+    let Some(recent_path) = recent_path() else {
+        return Ok(());
+    };
+    let path = path.canonicalize()?;
+    let mut recent = recent_files();
+    recent.retain(|p| *p != path);
+    recent.insert(0, path);
+    recent.truncate(MAX_RECENT);
+    if let Some(dir) = recent_path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(recent_path, serde_json::to_vec(&recent)?)?;
+    Ok(())
+}
+
+// Matching entries, best first; with an empty query recent files come first
+fn filter<'a>(entries: &'a [Entry], query: &str, recent: &[PathBuf]) -> Vec<&'a Entry> {
+    let rank = |entry: &Entry| {
+        recent
+            .iter()
+            .position(|r| *r == entry.path)
+            .unwrap_or(usize::MAX)
+    };
+    if query.trim().is_empty() {
+        let mut all: Vec<&Entry> = entries.iter().collect();
+        all.sort_by_key(|entry| rank(entry));
+        return all;
+    }
+    let mut scored: Vec<(i32, &Entry)> = entries
+        .iter()
+        .filter_map(|entry| fuzzy_score(query, &entry.search_text()).map(|s| (s, entry)))
+        .collect();
+    scored.sort_by_key(|(score, entry)| (-score, rank(entry)));
+    scored.into_iter().map(|(_, entry)| entry).collect()
+}
+
+// ----------------------------------------------
+// Browser: type to search, Up/Down to select, Enter opens, Esc quits
+// ----------------------------------------------
+
+/// Let the user pick a game, None if the browser was left without one
+pub fn browse(dir: &Path) -> anyhow::Result<Option<Entry>> {
+    let entries = scan(dir)?;
+    if entries.is_empty() {
+        anyhow::bail!("no PGN games found in {}", dir.display());
+    }
+    let recent = recent_files();
+
+    enable_raw_mode()?;
+    crossterm::execute!(io::stdout(), EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+    let result = browse_loop(&mut terminal, &entries, &recent);
+    disable_raw_mode()?;
+    crossterm::execute!(io::stdout(), LeaveAlternateScreen)?;
+    result
+}
+
+fn browse_loop(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    entries: &[Entry],
+    recent: &[PathBuf],
+) -> anyhow::Result<Option<Entry>> {
+    let mut query = String::new();
+    let mut state = ListState::default().with_selected(Some(0));
+    loop {
+        let matches = filter(entries, &query, recent);
+        if state.selected().is_none_or(|i| i >= matches.len()) {
+            state.select(Some(0));
+        }
+        terminal.draw(|f| {
+            let rows = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(3), Constraint::Length(2)])
+                .split(f.area());
+            let items: Vec<ListItem> = matches
+                .iter()
+                .map(|entry| ListItem::new(entry.label()))
+                .collect();
+            let title = format!("Library ({} of {} games)", matches.len(), entries.len());
+            let list = List::new(items)
+                .block(Block::bordered().title(title))
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+            f.render_stateful_widget(list, rows[0], &mut state);
+            let help = Paragraph::new(format!(
+                "Search: {query}\nEnter opens the game, Up/Down select, Esc quits."
+            ))
+            .style(Style::default().fg(Color::Gray));
+            f.render_widget(help, rows[1]);
+        })?;
+
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Esc => return Ok(None),
+            KeyCode::Enter => {
+                return Ok(state
+                    .selected()
+                    .and_then(|i| matches.get(i))
+                    .map(|e| (*e).clone()))
+            }
+            KeyCode::Up => state.select_previous(),
+            KeyCode::Down => state.select_next(),
+            KeyCode::Backspace => {
+                query.pop();
+                state.select(Some(0));
+            }
+            KeyCode::Char(c) => {
+                query.push(c);
+                state.select(Some(0));
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Style};
+use ratatui::widgets::Paragraph;
+
+use crate::App;
+
+// ----------------------------------------------
+// Spectator lock for shared screens: only the move list, the board
+// history and the tabs can be browsed until `:unlock` is entered,
+// with the `lock_password` from the config file if one is set
+// ----------------------------------------------
+
+const UNLOCK: &str = ":unlock";
+
+pub struct Lock {
+    pub locked: bool,
+    password: Option<String>,
+}
+
+impl Lock {
+    pub fn new(locked: bool, password: Option<String>) -> Self {
+        Self { locked, password }
+    }
+
+    /// Whether `key` goes on to the tabs and the active tab. Handles `:lock` and `:unlock`.
+    pub fn allows(&mut self, app: &mut App, key: KeyEvent) -> bool {
+        if key.code == KeyCode::Enter {
+            let input = std::mem::take(&mut app.input_buffer);
+            let mut words = input.split_whitespace();
+            match words.next() {
+                Some(":lock") => {
+                    self.locked = true;
+                    app.message = "Locked: only browsing works, :unlock to type again.".to_string();
+                    return false;
+                }
+                Some(UNLOCK) => {
+                    let given = words.next();
+                    app.message = match &self.password {
+                        Some(password) if given != Some(password.as_str()) => {
+                            "Wrong password, still locked.".to_string()
+                        }
+                        _ => {
+                            self.locked = false;
+                            "Unlocked.".to_string()
+                        }
+                    };
+                    return false;
+                }
+                _ if self.locked => {
+                    app.message = "Locked: only browsing works, :unlock to type again.".to_string();
+                    return false;
+                }
+                _ => app.input_buffer = input,
+            }
+        }
+        if !self.locked {
+            return true;
+        }
+        let typing_command = app.input_buffer.starts_with(':');
+        match key.code {
+            KeyCode::Left
+            | KeyCode::Right
+            | KeyCode::Home
+            | KeyCode::End
+            | KeyCode::Up
+            | KeyCode::Down
+            | KeyCode::PageUp
+            | KeyCode::PageDown => true,
+            // digits switch tabs, ':' starts the unlock command
+            KeyCode::Char(ch) if app.input_buffer.is_empty() => ch == ':' || ch.is_ascii_digit(),
+            KeyCode::Char(_) | KeyCode::Backspace => typing_command,
+            KeyCode::Esc => {
+                app.input_buffer.clear();
+                false
+            }
+            _ => false,
+        }
+    }
+
+    /// A marker in the top right corner of `area` while locked
+    pub fn draw_badge(&self, f: &mut ratatui::Frame, area: Rect) {
+        if !self.locked || area.width < 10 {
+            return;
+        }
+        let badge = Rect::new(area.right() - 8, area.y, 8, 1);
+        f.render_widget(
+            Paragraph::new(" LOCKED ").style(Style::default().fg(Color::Black).bg(Color::Red)),
+            badge,
+        );
+    }
+}
+
+/// The input line as shown, with the unlock password hidden
+pub fn masked(input: &str) -> String {
+    match input.strip_prefix(UNLOCK) {
+        Some(rest) if rest.starts_with(' ') => {
+            format!("{UNLOCK} {}", "*".repeat(rest.chars().count() - 1))
+        }
+        _ => input.to_string(),
+    }
+}
@@ -1,62 +1,95 @@
+mod account;
+mod activity;
+mod adjourn;
+mod analysis;
+mod annotation;
+mod beginner;
+mod blunders;
+mod clipboard;
+mod clock;
+mod config;
+mod drills;
+mod eco;
+mod endgames;
+mod engine;
+mod eval_cache;
+mod eval_quiz;
+mod events;
+mod explorer;
+mod export;
+mod fen;
+mod generate;
+mod graph;
+mod history;
+mod ladder;
+mod library;
+mod lock;
+mod move_guess;
+mod notation;
+mod online;
+mod opening_tree;
+mod opponents;
+mod performance;
+mod pgn_tree;
+mod phase;
+mod positions;
+mod presentation;
+mod puzzle_set;
+mod repertoire;
+mod replay;
+mod review;
+mod scouting;
+mod series;
+mod session;
+mod session_log;
+mod setup;
+mod stats;
+mod status;
+mod study;
+mod tablebase;
+mod tabs;
+mod uci;
+
 use clap::{Parser, Subcommand};
-use crossterm::event::{self, Event, KeyCode, KeyEvent};
+use crossterm::event::{
+    DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, MouseButton, MouseEvent,
+    MouseEventKind,
+};
+use crossterm::style::{Print, ResetColor, SetBackgroundColor, SetForegroundColor};
 use crossterm::terminal::{
     disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
 };
-use pgn_reader::{RawHeader, SanPlus, Skip, Visitor};
+use pgn_reader::{RawComment, RawHeader, SanPlus, Skip, Visitor};
 use ratatui::text::Line;
 use ratatui::{
     backend::{Backend, CrosstermBackend},
-    layout::{Constraint, Direction, Layout},
-    style::{Color, Style},
+    layout::{Constraint, Direction, Layout, Position as ScreenPosition, Rect},
+    style::{Color, Modifier, Style},
     text::Span,
-    widgets::{Block, Paragraph},
-    Terminal,
+    widgets::{Block, Clear, Gauge, Paragraph, Wrap},
+    Terminal, TerminalOptions, Viewport,
 };
-use serde::Deserialize;
-use shakmaty::fen::Fen;
-use shakmaty::{san, CastlingMode, Chess, Color as ChessColor, File, Move, Position, Rank, Role};
-use std::{collections::HashMap, io, time::Duration, time::Instant};
-
-// ----------------------------------------------
-// Piece ASCII definitions
-// ----------------------------------------------
-static ASCII_PIECES: &[(&str, &[&str])] = &[
-    ("P", &[" ^ ", "(P)", "/_\\"]),
-    ("N", &[" __", "/ N", "\\_/"]),
-    ("B", &["  ^", " /B\\", " \\_/"]),
-    ("R", &["[R]", "[R]", "[R]"]),
-    ("Q", &[" Q ", "( )", " \\|"]),
-    ("K", &[" K ", "(. )", " | "]),
-    ("p", &[" ^ ", "(p)", "/_\\"]),
-    ("n", &[" __", "/ n", "\\_/"]),
-    ("b", &["  ^", " /b\\", " \\_/"]),
-    ("r", &["[r]", "[r]", "[r]"]),
-    ("q", &[" q ", "( )", " \\|"]),
-    ("k", &[" k ", "(. )", " | "]),
-];
-
-fn piece_ascii_map() -> HashMap<char, Vec<String>> {
-    let mut map = HashMap::new();
-    for (symbol, lines) in ASCII_PIECES {
-        map.insert(
-            symbol.chars().next().unwrap(),
-            lines.iter().map(|s| s.to_string()).collect(),
-        );
-    }
-    map
-}
+use serde::{Deserialize, Serialize};
+use shakmaty::fen::{Epd, Fen};
+use shakmaty::{
+    san, CastlingMode, Chess, Color as ChessColor, EnPassantMode, Move, Outcome, Position, Role,
+};
+use std::io::{self, Write};
+use std::rc::Rc;
+use std::{cell::Cell, time::Duration, time::Instant};
+use tess_rs::board::{BoardWidget, DisplayMode, PieceSet};
+use tess_rs::theme;
 
 // ----------------------------------------------
 // Lichess puzzle JSON structure for `lichess.org/api/puzzle/next`
 // ----------------------------------------------
-#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 struct LichessNextPuzzle {
     puzzle: Puzzle,
     game: Game,
 }
 
-#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 struct Puzzle {
     id: String,
@@ -65,7 +98,7 @@ struct Puzzle {
     initial_ply: u16,
 }
 
-#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 struct Game {
     pgn: String,
 }
@@ -76,6 +109,8 @@ struct Game {
 #[derive(Clone, PartialEq)]
 enum AppMode {
     StandardGame,
+    // analysis board: any move for either side, new lines from earlier positions
+    Board,
     Puzzle {
         solution: Vec<Move>,
         solution_index: usize,
@@ -84,46 +119,401 @@ enum AppMode {
     },
 }
 
-#[derive(Debug, clap::ValueEnum, Clone, PartialEq)]
-enum DisplayMode {
-    Big,
-    Simple,
+// How a finished game ended, for the game-over overlay
+#[derive(Debug, Clone)]
+struct GameOver {
+    outcome: Outcome,
+    ending: Ending,
+}
+
+// What ended a game, the side it happened to where it matters
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Ending {
+    Checkmate,
+    // the side to move has no legal move and is not in check
+    Stalemate(ChessColor),
+    InsufficientMaterial,
+    // the flag fell, against a side that could still be mated or not
+    Time(ChessColor),
+    TimeVsInsufficientMaterial(ChessColor),
+    Resignation,
+    FiftyMoves,
+    Repetition,
+    // the ends only an online game has: a draw the players agreed, a
+    // player leaving the game and the server deciding it
+    Agreement,
+    Abandoned(ChessColor),
+    Adjudicated,
 }
 
-impl DisplayMode {
-    fn default_cell_dimensions(&self) -> (usize, usize) {
-        match self {
-            DisplayMode::Big => (5, 3),
-            DisplayMode::Simple => (2, 1),
+impl GameOver {
+    // The end of the game on the board, if it is over
+    fn of_position(board: &Chess) -> Option<GameOver> {
+        let outcome = board.outcome()?;
+        let ending = if board.is_checkmate() {
+            Ending::Checkmate
+        } else if board.is_stalemate() {
+            Ending::Stalemate(board.turn())
+        } else {
+            Ending::InsufficientMaterial
+        };
+        Some(GameOver { outcome, ending })
+    }
+
+    // The flag of `color` fell: a loss, unless the other side has nothing left to mate with
+    fn on_time(board: &Chess, color: ChessColor) -> GameOver {
+        if board.has_insufficient_material(!color) {
+            GameOver {
+                outcome: Outcome::Draw,
+                ending: Ending::TimeVsInsufficientMaterial(color),
+            }
+        } else {
+            GameOver {
+                outcome: Outcome::Decisive { winner: !color },
+                ending: Ending::Time(color),
+            }
+        }
+    }
+
+    // The draws a player could claim: the fifty-move rule and threefold repetition
+    fn claimable(start: &Chess, history: &[Move], board: &Chess) -> Option<GameOver> {
+        let ending = if board.halfmoves() >= 100 {
+            Ending::FiftyMoves
+        } else {
+            let epd = |pos: &Chess| Epd::from_position(pos.clone(), EnPassantMode::Legal);
+            let current = epd(board);
+            let mut pos = start.clone();
+            let mut seen = usize::from(epd(&pos) == current);
+            for mv in history {
+                pos.play_unchecked(mv);
+                seen += usize::from(epd(&pos) == current);
+            }
+            if seen < 3 {
+                return None;
+            }
+            Ending::Repetition
+        };
+        Some(GameOver {
+            outcome: Outcome::Draw,
+            ending,
+        })
+    }
+
+    // How lichess says an online game ended, None for a game aborted
+    fn of_online(state: &online::State, board: &Chess) -> Option<GameOver> {
+        let winner = state.winner();
+        let ending = match state.status.as_str() {
+            "aborted" | "noStart" => return None,
+            "mate" => Ending::Checkmate,
+            "resign" => Ending::Resignation,
+            "stalemate" => Ending::Stalemate(board.turn()),
+            "draw" => Ending::Agreement,
+            "outoftime" => {
+                return Some(GameOver::on_time(
+                    board,
+                    winner.map_or(board.turn(), |winner| !winner),
+                ))
+            }
+            "timeout" => Ending::Abandoned(winner.map_or(board.turn(), |winner| !winner)),
+            _ => Ending::Adjudicated,
+        };
+        let outcome = match winner {
+            Some(winner) => Outcome::Decisive { winner },
+            None => Outcome::Draw,
+        };
+        Some(GameOver { outcome, ending })
+    }
+
+    fn describe(&self) -> String {
+        let side = |color: ChessColor| color.fold_wb("White", "Black");
+        let reason = match self.ending {
+            Ending::Checkmate => "by checkmate".to_string(),
+            Ending::Stalemate(color) => format!(
+                "by stalemate, {} has no legal move and is not in check",
+                side(color)
+            ),
+            Ending::InsufficientMaterial => {
+                "by insufficient material, neither side can mate".to_string()
+            }
+            Ending::Time(color) => format!("on time, {}'s flag fell", side(color)),
+            Ending::TimeVsInsufficientMaterial(color) => format!(
+                "as {}'s flag fell but {} has no mating material left",
+                side(color),
+                side(!color)
+            ),
+            Ending::Resignation => "by resignation".to_string(),
+            Ending::FiftyMoves => "by the fifty-move rule".to_string(),
+            Ending::Repetition => "by threefold repetition".to_string(),
+            Ending::Agreement => "by agreement".to_string(),
+            Ending::Abandoned(color) => format!("as {} left the game", side(color)),
+            Ending::Adjudicated => "by the decision of lichess".to_string(),
+        };
+        match self.outcome {
+            Outcome::Decisive { winner } => format!("{} wins {reason}", side(winner)),
+            Outcome::Draw => format!("Draw {reason}"),
+        }
+    }
+
+    // The Termination tag of the saved game
+    fn termination(&self) -> &'static str {
+        match self.ending {
+            Ending::Time(_) | Ending::TimeVsInsufficientMaterial(_) => "time forfeit",
+            Ending::Abandoned(_) => "abandoned",
+            Ending::Adjudicated => "adjudication",
+            _ => "normal",
         }
     }
 }
 
+// Puzzle hints, weaker first; only the strongest one taken is charged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Hint {
+    Piece,
+    Move,
+}
+
+// One game's position and moves, swapped in and out by the play/analysis toggle
+struct GameState {
+    board: Chess,
+    start: Chess,
+    history: Vec<Move>,
+    comments: Vec<Option<String>>,
+    times: Vec<Option<Duration>>,
+    evals: Vec<Option<i32>>,
+    view: Option<(usize, Chess)>,
+    moves_scroll: Option<usize>,
+    mode: AppMode,
+}
+
 // ----------------------------------------------
 // Application state
 // ----------------------------------------------
 
 struct App {
     board: Chess,
+    // position the session started from and the moves played since
+    start: Chess,
+    history: Vec<Move>,
+    // PGN comment after each move of the history
+    comments: Vec<Option<String>>,
+    // time taken for each move of the history, None where it is not known
+    times: Vec<Option<Duration>>,
+    // evaluation after each move from the PGN, centipawns from white's side
+    evals: Vec<Option<i32>>,
+    // when the side to move got the move in the live game
+    move_started: Instant,
+    // color the think times that stand out in the move list
+    highlight_times: bool,
+    // earlier position being looked at (plies from start) instead of the live board
+    view: Option<(usize, Chess)>,
+    // first visible line of the move list, None follows the current move
+    moves_scroll: Option<usize>,
+    // scroll offset and height of the move list as last drawn
+    moves_layout: Cell<(usize, usize)>,
+    // screen areas of the board, the move list and info pane, for mouse events
+    board_area: Cell<Rect>,
+    moves_area: Cell<Rect>,
+    info_area: Cell<Rect>,
+    // first visible line of the info pane and the largest useful offset
+    info_scroll: usize,
+    info_max_scroll: Cell<usize>,
     mode: AppMode,
+    // exploring freely instead of playing; `parked` holds the other state,
+    // the live game while analysing and the last analysis while playing
+    analysing: bool,
+    parked: Option<Box<GameState>>,
+    // puzzles of a `puzzle set` run, with their scores
+    puzzle_set: Option<puzzle_set::PuzzleSet>,
+    // positions of a `guess` run on the analysis board, with the engine to check
+    quiz: Option<eval_quiz::Quiz>,
+    // the prepared moves of a `repertoire` run on the analysis board
+    repertoire: Option<repertoire::Repertoire>,
+    // the master game of a `guess-move` run on the analysis board
+    move_guess: Option<move_guess::Training>,
+    // the ending of an `endgame` drill on the analysis board, with its judge
+    endgame: Option<endgames::Trainer>,
+    // a `present` run: the game on its own screen, only browsing works
+    presentation: Option<presentation::Presentation>,
+    // what the --events-json stream last told of this game
+    events_seen: events::Seen,
+    // the PGN game was loaded from, variations, NAGs and all, for `:save`
+    annotated: Option<pgn_tree::Game>,
+    // what earlier games tell of the opponent, shown until a key is pressed
+    briefing: Option<Vec<String>>,
+    // how the live game ended, and whether its overlay is showing
+    game_over: Option<GameOver>,
+    show_game_over: bool,
+    // score of the rematches played since the first game ended
+    series: Option<series::Series>,
+    // the climb in progress, if the game is one of the ladder
+    ladder: Option<ladder::Ladder>,
+    // a promotion typed without its piece, waiting for q, r, b or n
+    pending_promotion: Option<Move>,
+    // the moves an ambiguous SAN could mean, and the one selected
+    pending_choice: Option<(Vec<Move>, usize)>,
+    // the engine options being looked at with :options
+    options_editor: Option<OptionsEditor>,
+    // hotseat: turn the board to the side to move, hiding it until they are ready
+    rotate: bool,
+    handover: bool,
+    // strongest hint taken for the puzzle on the board, and whether it was rated yet
+    hint: Option<Hint>,
+    puzzle_scored: bool,
+    // the keys typed at the puzzle on the board, and their playback once it is over
+    attempt: replay::Recording,
+    replay: Option<replay::Replay>,
+    hint_penalties: config::HintPenalties,
+    // what 'H' shows in a game, and the ply of the piece it showed last
+    game_hint: config::GameHint,
+    piece_hinted: Option<usize>,
+    // local puzzle rating as of the last rated puzzle
+    puzzle_rating: stats::PuzzleRating,
+    // my repertoire and played moves, shared by all tabs
+    opening_tree: Rc<opening_tree::OpeningTree>,
+    // the named openings, shared by all tabs
+    eco: Rc<eco::Eco>,
+    clock: Option<clock::Clock>,
+    // :pause stopped the clock and the engines' search
+    paused: bool,
+    // I just moved in the live game, for a simul to go on to the next board
+    moved: bool,
+    // UCI engines playing sides of the live game, one for each side at most
+    engines: Vec<engine::Engine>,
+    // a lichess game played through the Board API
+    online: Option<online::Game>,
+    // UCI engine evaluating the position on screen
+    analyser: Option<engine::Engine>,
+    // Syzygy tables the engines read, and their verdict on the position on
+    // screen as last told in the message
+    tablebase: Option<tablebase::Tablebase>,
+    tablebase_verdict: Option<Outcome>,
+    // lichess's tablebase instead, without tables of my own
+    online_tablebase: Option<tablebase::Online>,
+    // an engine review of the whole game under way, and the last one finished
+    reviewing: Option<review::Background>,
+    review: Option<review::Review>,
+    // ring the terminal bell once per side when it drops under ten seconds
+    clock_bell: bool,
+    bell_rung: shakmaty::ByColor<bool>,
     display: DisplayMode,
     input_buffer: String,
     message: String,
     cell_width: usize,
     cell_height: usize,
+    // board size and move list width, adjustable from the keyboard
+    layout: config::Layout,
+    show_attack_map: bool,
+    // squares colored by who controlled them over the last few moves, stepping
+    // through the game by itself
+    heatmap: Option<Heatmap>,
+    // piece names, move patterns and tips for beginners
+    beginner: Option<beginner::Assist>,
+    // square, piece and label colors for the terminal background
+    palette: theme::Palette,
+    pieces: PieceSet,
+    // the font draws the chess symbols two columns wide
+    wide_symbols: bool,
+    // draw the board from black's side
+    flipped: bool,
+    notation: notation::Notation,
+    input_language: notation::Language,
+    // a source and a destination square play the move without Enter
+    quick_input: bool,
+    // plain rendering and redraws only after input, for slow links
+    low_bandwidth: bool,
+    // squares to color on the board, cleared on the next input
+    highlights: Vec<(shakmaty::Square, Color)>,
 }
 
 impl App {
+    fn new_board(display: DisplayMode) -> Self {
+        Self {
+            mode: AppMode::Board,
+            ..Self::new_standard(Chess::default(), display)
+        }
+    }
+
     fn new_standard(board: Chess, display: DisplayMode) -> Self {
         let (width, height) = display.default_cell_dimensions();
         Self {
+            start: board.clone(),
+            history: Vec::new(),
+            comments: Vec::new(),
+            times: Vec::new(),
+            evals: Vec::new(),
+            move_started: session_log::now(),
+            highlight_times: false,
+            view: None,
+            moves_scroll: None,
+            moves_layout: Cell::new((0, 1)),
+            board_area: Cell::new(Rect::default()),
+            moves_area: Cell::new(Rect::default()),
+            info_area: Cell::new(Rect::default()),
+            info_scroll: 0,
+            info_max_scroll: Cell::new(0),
             board,
             mode: AppMode::StandardGame,
+            analysing: false,
+            parked: None,
+            puzzle_set: None,
+            quiz: None,
+            repertoire: None,
+            move_guess: None,
+            endgame: None,
+            presentation: None,
+            events_seen: events::Seen::default(),
+            annotated: None,
+            game_over: None,
+            show_game_over: false,
+            rotate: false,
+            handover: false,
+            series: None,
+            ladder: None,
+            briefing: None,
+            pending_promotion: None,
+            pending_choice: None,
+            options_editor: None,
+            hint: None,
+            puzzle_scored: false,
+            attempt: replay::Recording::default(),
+            replay: None,
+            hint_penalties: config::HintPenalties::default(),
+            game_hint: config::GameHint::default(),
+            piece_hinted: None,
+            opening_tree: Rc::default(),
+            eco: Rc::default(),
+            puzzle_rating: stats::puzzle_rating(),
+            clock: None,
+            paused: false,
+            moved: false,
+            engines: Vec::new(),
+            online: None,
+            analyser: None,
+            tablebase: None,
+            tablebase_verdict: None,
+            online_tablebase: None,
+            reviewing: None,
+            review: None,
+            clock_bell: false,
+            bell_rung: Default::default(),
             display,
             input_buffer: String::new(),
             message: String::new(),
             cell_width: width,
             cell_height: height,
+            layout: config::Layout::default(),
+            show_attack_map: false,
+            heatmap: None,
+            beginner: None,
+            palette: theme::Palette::for_background(theme::Background::Dark),
+            pieces: PieceSet::Symbols,
+            wide_symbols: false,
+            flipped: false,
+            low_bandwidth: false,
+            notation: notation::Notation::Letters,
+            input_language: notation::Language::En,
+            quick_input: false,
+            highlights: Vec::new(),
         }
     }
 
@@ -133,516 +523,6763 @@ impl App {
         display: DisplayMode,
         puzzle: LichessNextPuzzle,
     ) -> Self {
-        let (width, height) = display.default_cell_dimensions();
         Self {
-            board,
             mode: AppMode::Puzzle {
                 solution,
                 solution_index: 0,
                 completed: false,
                 lichess: puzzle,
             },
-            display,
-            input_buffer: String::new(),
-            message: String::new(),
-            cell_width: width,
-            cell_height: height,
+            ..Self::new_standard(board, display)
         }
     }
 
-    fn start_message(&self) -> String {
-        let turn = self.board.turn().to_string();
-        match &self.mode {
-            AppMode::StandardGame => format!("New Game, {turn} to move."),
-            AppMode::Puzzle { lichess, .. } => {
-                let rating = lichess.puzzle.rating.to_string();
-
-                format!(
-                "Puzzle {}, rating: {rating}, please enter moves in simplified UCI (e.g. e2e4). {turn} to move.",
-                lichess.puzzle.id
-                )
-            }
-        }
+    // Size the board and the move list, one scale step adds two columns and a row per square
+    fn set_layout(&mut self, mut layout: config::Layout) {
+        let (width, height) = self.display.default_cell_dimensions();
+        let scale = *layout.board_scale.of(self.display);
+        self.cell_width = width + 2 * scale;
+        self.cell_height = height + scale;
+        self.layout = layout;
     }
-}
-
-// ----------------------------------------------
-// Command-line argument parsing with clap
-// ----------------------------------------------
-#[derive(Parser)]
-#[command(name = "Chess App")]
-#[command(about = "A chess application", long_about = None)]
-struct Cli {
-    #[command(subcommand)]
-    command: Commands,
 
-    #[arg(long, short, default_value = "simple")]
-    display: DisplayMode,
-}
+    /// The smallest terminal area the board, the move list, the info pane and the
+    /// input line fit in, as `ui` lays them out
+    fn min_size(&self) -> (u16, u16) {
+        let width = 8 * self.cell_width + 4 + usize::from(self.layout.moves_width) + 20;
+        let height = 8 * self.cell_height + 4 + 3;
+        (width as u16, height as u16)
+    }
 
-#[derive(Subcommand)]
-enum Commands {
-    #[command(about = "Start a puzzle game")]
-    Puzzle {
-        /// load this puzzle id, if not specified, load random
-        id: Option<String>,
-    },
-    #[command(about = "Load a PGN file")]
-    Load {
-        #[arg(required = true)]
-        filename: String,
-    },
-    #[command(about = "Start a new standard game")]
-    Standard,
-}
+    // The loaded PGN's moves along the game, as far as it follows them
+    fn annotations(&self) -> Vec<&pgn_tree::Node> {
+        match &self.annotated {
+            Some(game) if !self.analysing => game.path(&self.history),
+            _ => Vec::new(),
+        }
+    }
 
-// ----------------------------------------------
-// Main entry
-// ----------------------------------------------
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let cli = Cli::parse();
+    // A quiz position waits for its evaluation to be typed
+    fn guessing(&self) -> bool {
+        self.quiz
+            .as_ref()
+            .is_some_and(|quiz| quiz.position.is_some())
+    }
 
-    let mut app = match cli.command {
-        Commands::Puzzle { id } => {
-            let (board, solution, puzzle) = load_puzzle(id)?;
-            App::new_puzzle(board, solution, cli.display, puzzle)
+    // Replace the position, starting a fresh move history
+    fn reset_board(&mut self, board: Chess) {
+        self.start = board.clone();
+        self.board = board;
+        self.history.clear();
+        self.comments.clear();
+        self.times.clear();
+        self.evals.clear();
+        self.piece_hinted = None;
+        self.view = None;
+        self.moves_scroll = None;
+        if self.analysing {
+            return;
         }
-        Commands::Load { filename } => {
-            let board = load_pgn_position(&filename)?;
-            App::new_standard(board, cli.display)
+        self.move_started = session_log::now();
+        self.hint = None;
+        self.puzzle_scored = false;
+        self.attempt = replay::Recording::default();
+        self.replay = None;
+        self.game_over = None;
+        self.show_game_over = false;
+        self.handover = false;
+        self.paused = false;
+        // a new game is not the online one
+        self.online = None;
+        if self.rotate {
+            self.flipped = self.board.turn() == ChessColor::Black;
+        }
+        if let Some(clock) = &mut self.clock {
+            *clock = clock::Clock::new(clock.control, clock.kind);
+            clock.start(self.board.turn());
+            self.bell_rung = Default::default();
+        }
+        for engine in &mut self.engines {
+            // a lost engine shows up on its next move
+            let _ = engine.new_game();
         }
-        Commands::Standard => App::new_standard(Chess::default(), cli.display),
-    };
-
-    app.message = app.start_message();
-
-    // Setup terminal
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    crossterm::execute!(stdout, EnterAlternateScreen)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
-
-    // Run the main loop
-    let res = run_app(&mut terminal, &mut app);
-
-    // Cleanup
-    disable_raw_mode()?;
-    crossterm::execute!(terminal.backend_mut(), LeaveAlternateScreen,)?;
-    terminal.show_cursor()?;
-
-    if let Err(err) = res {
-        eprintln!("Error: {:?}", err);
     }
 
-    Ok(())
-}
-
-// ----------------------------------------------
-// The core event loop
-// ----------------------------------------------
-fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> anyhow::Result<()> {
-    let mut last_tick = Instant::now();
-    let tick_rate = Duration::from_millis(250);
-
-    loop {
-        // Draw
-        terminal.draw(|f| ui(f, app))?;
-
-        // Handle input, non-blocking
-        let timeout = tick_rate
-            .checked_sub(last_tick.elapsed())
-            .unwrap_or_else(|| Duration::from_secs(0));
+    // Let the engine search when it has the move in the live game
+    fn engine_turn(&mut self) {
+        let turn = self.board.turn();
+        let Some(engine) = self.engines.iter_mut().find(|e| e.color == turn) else {
+            return;
+        };
+        if self.analysing
+            || self.paused
+            || self.mode != AppMode::StandardGame
+            || self.game_over.is_some()
+            || engine.thinking()
+        {
+            return;
+        }
+        match engine.go(&self.start, &self.history, self.clock.as_ref()) {
+            Ok(()) => self
+                .message
+                .push_str(&format!(" {} is thinking.", engine.name)),
+            Err(err) => self.message = format!("{err}."),
+        }
+    }
 
-        if crossterm::event::poll(timeout)? {
-            if let Event::Key(key) = event::read()? {
-                if !handle_key_event(app, key)? {
-                    // false => exit signal
-                    return Ok(());
-                }
+    // Play an engine's move once it has arrived, true if anything changed.
+    // While analysing, the move waits for the return to the game.
+    fn poll_engine(&mut self) -> bool {
+        if self.analysing {
+            return false;
+        }
+        let arrived = self
+            .engines
+            .iter_mut()
+            .enumerate()
+            .find_map(|(index, engine)| Some((index, engine.best_move().transpose()?)));
+        let (name, reply) = match arrived {
+            None => return false,
+            Some((index, Ok(reply))) => (self.engines[index].name.clone(), reply),
+            Some((index, Err(err))) => {
+                self.message = format!("{err}, the game goes on without it.");
+                self.engines.remove(index);
+                return true;
             }
+        };
+        // a search that outlived its game
+        if self.mode != AppMode::StandardGame || self.game_over.is_some() {
+            return false;
         }
-
-        // on_tick if needed
-        if last_tick.elapsed() >= tick_rate {
-            last_tick = Instant::now();
+        let Some(mv) = uci::parse(&self.board, &reply) else {
+            self.message = format!("{name} answered with the illegal move '{reply}'.");
+            return true;
+        };
+        let san = SanPlus::from_move(self.board.clone(), &mv);
+        if let Err(err) = play_game_move(self, &mv, format!("{name} played {san}.")) {
+            self.message = format!("{err}");
         }
+        // the other engine's turn when two play each other
+        self.engine_turn();
+        true
     }
-}
-
-// ----------------------------------------------
-// Draw the UI with ratatui
-// ----------------------------------------------
-fn ui(f: &mut ratatui::Frame, app: &App) {
-    // Layout: top for board, bottom for user input / messages
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length((8 * app.cell_height + 4) as u16), // board area + labels
-            Constraint::Min(3),                                   // input area
-        ])
-        .split(f.area());
-
-    // 1) Render the chessboard as a Paragraph of styled text
-    let board_lines = make_board_text(app);
-    let board_paragraph = Paragraph::new(board_lines).block(Block::default());
-    f.render_widget(board_paragraph, chunks[0]);
-
-    let input_paragraph =
-        Paragraph::new(format!("{}\nMove input: {}", app.message, app.input_buffer));
-    f.render_widget(input_paragraph, chunks[1]);
-}
-// ----------------------------------------------
-// Build the board ASCII with styling
-// ----------------------------------------------
-fn make_board_text(app: &App) -> Vec<Line> {
-    // We'll build a 2D buffer of styled chars
-    let board_width = 8 * app.cell_width;
-    let board_height = 8 * app.cell_height;
-
-    let pink_style = Style::default().fg(Color::White).bg(Color::Magenta);
-    let yellow_style = Style::default().fg(Color::White).bg(Color::Yellow);
-    let label_style = Style::default().fg(Color::White).bg(Color::Reset);
-    let white_piece_style = Style::default().fg(Color::White);
-    let black_piece_style = Style::default().fg(Color::Black);
 
-    // Prepare piece ASCII map
-    let ascii_map = piece_ascii_map();
-
-    // We'll create a 2D array of (char, Style).
-    let mut buffer: Vec<Vec<(char, Style)>> =
-        vec![vec![(' ', Style::default()); board_width + 2]; board_height + 2];
-
-    // Fill squares
-    for row in 0..8 {
-        for col in 0..8 {
-            // top-left corner of this cell in the buffer
-            let cell_x = col * app.cell_width + 1;
-            let cell_y = row * app.cell_height + 1;
+    // Take in what lichess sent about the online game, true if anything came
+    fn poll_online(&mut self) -> bool {
+        let mut changed = false;
+        while let Some(event) = self.online.as_mut().and_then(online::Game::poll) {
+            changed = true;
+            match event {
+                online::Event::State(state) => self.sync_online(&state),
+                online::Event::OpponentGone(true) => {
+                    self.message = "Your opponent left the game.".to_string()
+                }
+                online::Event::OpponentGone(false) => {
+                    self.message = "Your opponent is back.".to_string()
+                }
+                // the game keeps it for the lichess pane
+                online::Event::Arena(_) => {}
+                online::Event::Closed(why) => {
+                    self.message = format!("The connection to the game was lost: {why}.")
+                }
+            }
+        }
+        changed
+    }
 
-            // color
-            let style = if (row + col) % 2 == 0 {
-                // "light" square => yellow
-                yellow_style
+    // Bring the live game in line with the state lichess sent: the moves, the
+    // clock counting down from its times until the next state, and the end
+    fn sync_online(&mut self, state: &online::State) {
+        let Some(game) = &self.online else {
+            return;
+        };
+        let mut pos = self.start.clone();
+        let mut moves = Vec::new();
+        for text in state.moves() {
+            let Some(mv) = uci::parse(&pos, text) else {
+                self.message = format!("lichess sent the illegal move '{text}'.");
+                return;
+            };
+            pos.play_unchecked(&mv);
+            moves.push(mv);
+        }
+        let opponent = game.opponent().to_string();
+        let color = game.color;
+        let taken_back = moves.len() < self.history.len() && self.history.starts_with(&moves);
+        if taken_back {
+            // a takeback lichess made
+            self.board = pos;
+            self.history.truncate(moves.len());
+            self.comments.truncate(moves.len());
+            self.times.truncate(moves.len());
+            self.evals.truncate(moves.len());
+            self.view = None;
+            self.message = format!("Moves taken back, {} to move.", self.board.turn());
+        } else if !moves.starts_with(&self.history) {
+            // out of step: the game from the start again
+            self.board = self.start.clone();
+            self.history.clear();
+            self.comments.clear();
+            self.times.clear();
+            self.evals.clear();
+            self.view = None;
+        }
+        for mv in &moves[self.history.len()..] {
+            let san = SanPlus::from_move(self.board.clone(), mv);
+            let played = if self.board.turn() == color {
+                format!("Move {san} played.")
             } else {
-                pink_style
+                format!("{opponent} played {san}.")
             };
-
-            // fill with spaces
-            for dy in 0..app.cell_height {
-                for dx in 0..app.cell_width {
-                    buffer[cell_y + dy][cell_x + dx] = (' ', style);
-                }
+            if let Err(err) = play_game_move(self, mv, played) {
+                self.message = format!("{err}");
+                return;
             }
-
-            // place piece ASCII if any
-            let sq =
-                shakmaty::Square::from_coords(File::new(col as u32), Rank::new((7 - row) as u32));
-
-            if let Some(piece) = app.board.board().piece_at(sq) {
-                let piece_style = if piece.color == ChessColor::White {
-                    white_piece_style
-                } else {
-                    black_piece_style
+        }
+        let Some(game) = &mut self.online else {
+            return;
+        };
+        let offer = state.takeback();
+        if offer != game.takeback {
+            match (game.takeback, offer) {
+                (_, Some(side)) if side != color => {
+                    self.message = format!(
+                        "{opponent} proposes a takeback, :takeback accepts it and :takeback no declines."
+                    )
                 }
-                .bg(style.bg.unwrap_or(Color::Reset)); // Use the same background color as the square
-
-                if app.display == DisplayMode::Simple {
-                    let symbol_char = piece_unicode(piece);
-                    buffer[cell_y][cell_x] = (symbol_char, piece_style);
-                } else {
-                    let symbol_char = piece_char(piece);
-                    if let Some(shape_lines) = ascii_map.get(&symbol_char) {
-                        let shape_height = shape_lines.len();
-                        let shape_width = shape_lines.iter().map(|l| l.len()).max().unwrap_or(0);
-
-                        let offset_y = (app.cell_height.saturating_sub(shape_height)) / 2;
-                        let offset_x = (app.cell_width.saturating_sub(shape_width)) / 2;
-
-                        for (sy, line) in shape_lines.iter().enumerate() {
-                            let ty = cell_y + offset_y + sy;
-                            if ty >= board_height + 2 {
-                                break;
-                            }
-                            let mut tx = cell_x + offset_x;
-                            for ch in line.chars() {
-                                if tx >= board_width + 2 {
-                                    break;
-                                }
-                                buffer[ty][tx] = (ch, piece_style);
-                                tx += 1;
-                            }
-                        }
-                    }
+                (Some(side), None) if side == color && !taken_back => {
+                    self.message = format!("{opponent} declined the takeback.")
                 }
+                _ => {}
             }
+            game.takeback = offer;
         }
-    }
-
-    // Now we also want rank and file indicators.
-    // Ranks on left: row => (8-row)
-    for row in 0..8 {
-        let label = format!("{}", 8 - row);
-        // place at x=0, y = (row+1)*cell_height
-        // we only place it if there's space
-        let py = (row + 1) * app.cell_height;
-        for (i, ch) in label.chars().enumerate() {
-            if i < board_height + 2 {
-                buffer[py][i].0 = ch;
-                buffer[py][i].1 = label_style;
+        if let Some(control) = game.control {
+            let mut clock = clock::Clock::new(control, clock::IncrementKind::Fischer)
+                .with_remaining(
+                    state.remaining(ChessColor::White),
+                    state.remaining(ChessColor::Black),
+                );
+            // lichess starts the clocks once both sides have moved
+            if game.in_play() && self.history.len() >= 2 {
+                clock.start(self.board.turn());
             }
+            self.clock = Some(clock);
         }
-    }
-
-    // Files on bottom: col => A..H
-    let file_labels = ['A', 'B', 'C', 'D', 'E', 'F', 'G', 'H'];
-    let bottom_y = board_height + 1;
-    for col in 0..8 {
-        let ch = file_labels[col];
-        let px = (col + 1) * app.cell_width;
-        if px < board_width + 2 {
-            buffer[bottom_y][px].0 = ch;
-            buffer[bottom_y][px].1 = label_style;
+        if !game.in_play() && self.game_over.is_none() {
+            match GameOver::of_online(state, &self.board) {
+                Some(over) => self.end_game(over),
+                None => {
+                    if let Some(clock) = &mut self.clock {
+                        clock.stop();
+                    }
+                    self.message = "The game was aborted on lichess.".to_string();
+                }
+            }
         }
     }
 
-    // Convert 2D buffer into Vec<Line>
-    buffer
-        .into_iter()
-        .map(|row_vec| {
-            let mut spans: Vec<Span> = Vec::with_capacity(row_vec.len());
-            // We can group consecutive (char, style) that have the same style
-            let mut current_style = row_vec[0].1;
-            let mut current_text = String::new();
-
-            for &(ch, st) in &row_vec {
-                if st == current_style {
-                    current_text.push(ch);
-                } else {
-                    // flush
-                    spans.push(Span::styled(current_text, current_style));
-                    // start new group
-                    current_text = ch.to_string();
-                    current_style = st;
-                }
+    // An unsolved puzzle, a position to guess, an endgame drill or an online
+    // game in play, which an engine or the tablebase would give away
+    fn position_hidden(&self) -> bool {
+        matches!(
+            self.mode,
+            AppMode::Puzzle {
+                completed: false,
+                ..
             }
-            // flush last group
-            spans.push(Span::styled(current_text, current_style));
-            Line::from(spans)
-        })
-        .collect()
-}
-
-// Convert a shakmaty piece into a single ASCII letter for ASCII_PIECES map
-fn piece_char(piece: shakmaty::Piece) -> char {
-    let ch = match piece.role {
-        Role::Pawn => 'P',
-        Role::Knight => 'N',
-        Role::Bishop => 'B',
-        Role::Rook => 'R',
-        Role::Queen => 'Q',
-        Role::King => 'K',
-    };
-    if piece.color == ChessColor::Black {
-        ch.to_ascii_lowercase()
-    } else {
-        ch
+        ) || self.guessing()
+            || self.endgame.as_ref().is_some_and(|trainer| !trainer.over())
+            || self.online.as_ref().is_some_and(online::Game::in_play)
     }
-}
 
-// Convert a shakmaty piece into a Unicode character
-fn piece_unicode(piece: shakmaty::Piece) -> char {
-    match piece.role {
-        Role::Pawn => '♙',
-        Role::Knight => '♘',
-        Role::Bishop => '♗',
-        Role::Rook => '♖',
-        Role::Queen => '♕',
-        Role::King => '♔',
+    // Ask lichess's tablebase about the position on screen, true once it answered
+    fn poll_online_tablebase(&mut self) -> bool {
+        let hidden = self.position_hidden();
+        let pos = self.shown_board().clone();
+        let Some(online) = self.online_tablebase.as_mut() else {
+            return false;
+        };
+        if !hidden {
+            online.request(&pos);
+        }
+        online.poll()
     }
-}
 
-// ----------------------------------------------
-// Handle keyboard events (for move input, etc.)
-// Return false if we should quit
-// ----------------------------------------------
-fn handle_key_event(app: &mut App, key: KeyEvent) -> anyhow::Result<bool> {
-    match key.code {
-        KeyCode::Char('n') => match app.mode.clone() {
-            AppMode::StandardGame => app.board = Chess::default(),
-            AppMode::Puzzle { .. } => {
-                let (board, solution, puzzle) = load_puzzle(None)?;
-                app.board = board;
-                app.mode = AppMode::Puzzle {
-                    solution,
-                    solution_index: 0,
-                    completed: false,
-                    lichess: puzzle,
-                };
-                app.message = app.start_message()
-            }
-        },
-        KeyCode::Esc | KeyCode::Char('q') => {
-            // Quit on 'q'
-            return Ok(false);
+    // Keep the analysis engine on the position on screen, true if its view changed
+    fn poll_analysis(&mut self) -> bool {
+        if self.analyser.is_none() {
+            return false;
         }
-        KeyCode::Enter => {
-            // User pressed Enter => parse the input as a move
-            let input = app.input_buffer.clone();
-            if !input.is_empty() {
-                match app.mode.clone() {
-                    AppMode::StandardGame => handle_standard_move(app, input.trim())?,
-                    AppMode::Puzzle {
-                        solution,
-                        solution_index,
-                        lichess,
-                        ..
-                    } => {
-                        let (new_index, completed) =
-                            handle_puzzle_move(app, input.trim(), &solution, &solution_index)?;
-                        app.mode = AppMode::Puzzle {
-                            solution,
-                            solution_index: new_index,
-                            completed,
-                            lichess,
-                        };
-                        ()
-                    }
-                }
+        // a finished game has nothing to search
+        let pos = Some(self.shown_board().clone())
+            .filter(|pos| !self.position_hidden() && pos.outcome().is_none());
+        let Some(analyser) = self.analyser.as_mut() else {
+            return false;
+        };
+        let result = analyser
+            .analyse(pos.as_ref())
+            .and_then(|moved| Ok(moved | analyser.update()?));
+        match result {
+            Ok(changed) => {
+                self.note_tablebase_verdict();
+                changed
+            }
+            Err(err) => {
+                self.message = format!("{err}, the analysis stopped.");
+                self.analyser = None;
+                true
             }
-            app.input_buffer.clear();
         }
-        KeyCode::Backspace => {
-            app.input_buffer.pop();
+    }
+
+    // Tell in the message when the tables' verdict on the position on screen,
+    // as the engine reports it, is new or changed, a win thrown away by the
+    // last move
+    fn note_tablebase_verdict(&mut self) {
+        let (Some(tablebase), Some(analyser)) = (&self.tablebase, &self.analyser) else {
+            return;
+        };
+        let pos = self.shown_board();
+        if !tablebase.covers(pos) {
+            self.tablebase_verdict = None;
+            return;
         }
-        KeyCode::Char(c) => {
-            app.input_buffer.push(c);
+        let Some(verdict) = analyser
+            .evaluation_of(pos)
+            .and_then(|evaluation| tablebase.verdict(pos, evaluation))
+        else {
+            return;
+        };
+        if self.tablebase_verdict != Some(verdict) {
+            self.message = match self.tablebase_verdict {
+                None => format!(
+                    "Engine-reported tablebase result: {}.",
+                    tablebase::describe(verdict)
+                ),
+                Some(before) => format!(
+                    "Engine-reported tablebase result: {} now, {} before the last move.",
+                    tablebase::describe(verdict),
+                    tablebase::describe(before)
+                ),
+            };
+            self.tablebase_verdict = Some(verdict);
         }
-        _ => {}
     }
 
-    Ok(true)
-}
-
-// Handle moves for standard game mode
-fn handle_standard_move(app: &mut App, input: &str) -> anyhow::Result<()> {
-    // Try parse as SAN first
-    let parse_result = san::San::from_ascii(input.as_bytes());
-    if let Ok(san_move) = parse_result {
-        if let Ok(mv) = san_move.to_move(&app.board) {
-            // Check if legal
-            if app.board.is_legal(&mv) {
-                app.board = app.board.clone().play(&mv)?;
-                let turn = app.board.turn();
-                app.message = format!("Move {} played. {turn} to move.", input);
-                if app.board.is_game_over() {
-                    app.message = format!("Game over. {:?}", app.board.outcome());
-                }
+    // Take in the review once it is done, true if there is more to show
+    fn poll_review(&mut self) -> bool {
+        let Some(background) = &mut self.reviewing else {
+            return false;
+        };
+        let Some(result) = background.finished() else {
+            return background.advanced();
+        };
+        self.reviewing = None;
+        match result {
+            Ok(review) => {
+                self.message = format!(
+                    "Review done: {}. ']' and '[' go to the next and previous flagged move.",
+                    describe_errors(&review)
+                );
+                self.review = Some(review);
             }
+            Err(err) => self.message = format!("The review failed: {err}."),
         }
-    } else {
-        app.message = format!("Illegal or unrecognized move: {}", input);
+        true
     }
-    Ok(())
-}
-
-// Handle puzzle logic
-fn handle_puzzle_move(
-    app: &mut App,
-    input: &str,
-    solution: &Vec<Move>,
-    solution_index: &usize,
-) -> anyhow::Result<(usize, bool)> {
-    let expected_move = &solution[*solution_index];
-    let mut new_index = *solution_index;
 
-    // Try parse the user input as a UCI move
-    let maybe_move = parse_uci_move(&app.board, input);
-    match maybe_move {
-        Some(user_move) if user_move.eq(expected_move) => {
-            // correct
-            app.board = app.board.clone().play(&user_move)?;
-            new_index += 1;
+    /// The finished review, if it is of the game on the board
+    fn current_review(&self) -> Option<&review::Review> {
+        self.review.as_ref().filter(|review| {
+            review.moves == self.history && review.positions[0].board() == self.start.board()
+        })
+    }
 
-            // Check if puzzle finished
-            if new_index >= solution.len() {
-                app.message =
-                    "Puzzle solved! Congratulations. Press 'n' for a new puzzle.".to_string();
-                return Ok((new_index, true));
+    /// Square control around the position on screen, averaged over it and the
+    /// positions of the few moves before
+    fn control_heat(&self) -> [f64; 64] {
+        let shown = self.shown_ply();
+        let mut pos = self.start.clone();
+        let mut window = std::collections::VecDeque::from([pos.clone()]);
+        for mv in &self.history[..shown] {
+            pos.play_unchecked(mv);
+            if window.len() == HEAT_WINDOW {
+                window.pop_front();
             }
-
-            // next move from the puzzle belongs to the "opponent", auto-play it
-            let next: &Move = &solution[new_index];
-            app.board = app.board.clone().play(&next)?;
-            new_index += 1;
-            app.message = format!(
-                "Move {} was correct! Opponent played: {}",
-                input,
-                move_to_uci(next)
-            );
-        }
-        _ => {
-            app.message = format!(
-                "Incorrect move. Expected UCI: {}. Puzzle failed. Press 'n' for a new puzzle.",
-                move_to_uci(expected_move)
-            );
+            window.push_back(pos.clone());
         }
+        analysis::control_heat(&window)
     }
-    Ok((new_index, false))
-}
 
-// ----------------------------------------------
-// Utility: parse user input as UCI in the current position
-// ----------------------------------------------
-fn parse_uci_move(board: &Chess, input: &str) -> Option<Move> {
-    let all_moves = board.legal_moves();
-    for m in all_moves {
-        let uci_str = move_to_uci(&m);
-        if uci_str == input.to_lowercase() {
-            return Some(m);
+    /// The evaluation of the start and every position after it, centipawns from
+    /// white's side: the review's, else the PGN's, with the analysis engine's
+    /// for the position on screen
+    fn eval_curve(&self) -> Vec<Option<i32>> {
+        let mut curve: Vec<Option<i32>> = match self.current_review() {
+            Some(review) => (0..review.positions.len())
+                .map(|index| review.white_cp(index))
+                .collect(),
+            None => std::iter::once(None)
+                .chain(self.evals.iter().copied())
+                .collect(),
+        };
+        let pos = self.shown_board();
+        let live = self
+            .analyser
+            .as_ref()
+            .and_then(|analyser| analyser.evaluation_of(pos));
+        if let (Some(evaluation), Some(value)) = (live, curve.get_mut(self.shown_ply())) {
+            let cp = match evaluation.score {
+                engine::Score::Centipawns(cp) => cp,
+                engine::Score::Mate(moves) if moves > 0 => review::CP_CEILING,
+                engine::Score::Mate(_) => -review::CP_CEILING,
+            };
+            let cp = cp * pos.turn().fold_wb(1, -1);
+            *value = Some(cp.clamp(-review::CP_CEILING, review::CP_CEILING));
         }
+        curve
     }
-    None
-}
 
-// Convert Move to "e2e4" style string
-fn move_to_uci(mv: &Move) -> String {
-    //mv.to_string() // shakmaty uses UCI by default
+    // Score lost to the hints taken for the current puzzle, None without hints
+    fn hint_cost(&self) -> Option<f64> {
+        self.hint.map(|hint| match hint {
+            Hint::Piece => self.hint_penalties.piece,
+            Hint::Move => self.hint_penalties.full_move,
+        })
+    }
+
+    // Exchange the game on screen with `state`
+    fn swap_game(&mut self, state: &mut GameState) {
+        std::mem::swap(&mut self.board, &mut state.board);
+        std::mem::swap(&mut self.start, &mut state.start);
+        std::mem::swap(&mut self.history, &mut state.history);
+        std::mem::swap(&mut self.comments, &mut state.comments);
+        std::mem::swap(&mut self.times, &mut state.times);
+        std::mem::swap(&mut self.evals, &mut state.evals);
+        std::mem::swap(&mut self.view, &mut state.view);
+        std::mem::swap(&mut self.moves_scroll, &mut state.moves_scroll);
+        std::mem::swap(&mut self.mode, &mut state.mode);
+    }
+
+    /// Switch between the live game and free analysis, keeping both.
+    /// A new analysis starts from the position on screen.
+    fn toggle_analysis(&mut self) {
+        if !self.analysing && self.online.as_ref().is_some_and(online::Game::in_play) {
+            self.message = "No analysis while the online game is on.".to_string();
+            return;
+        }
+        let mut other = self.parked.take().unwrap_or_else(|| {
+            let pos = self.shown_board().clone();
+            Box::new(GameState {
+                board: pos.clone(),
+                start: pos,
+                history: Vec::new(),
+                comments: Vec::new(),
+                times: Vec::new(),
+                evals: Vec::new(),
+                view: None,
+                moves_scroll: None,
+                mode: AppMode::StandardGame,
+            })
+        });
+        self.swap_game(&mut other);
+        self.parked = Some(other);
+        self.analysing = !self.analysing;
+        self.message = if self.analysing {
+            "Analysis: moves are not played in the game. Tab returns to the game, 'n' restarts the analysis."
+                .to_string()
+        } else {
+            format!("Back to the game, {} to move.", self.board.turn())
+        };
+    }
+
+    // Called every tick: end of a sprint, flag fall and the low-time bell
+    fn on_tick(&mut self) {
+        if self.puzzle_set.as_mut().is_some_and(|set| set.expire()) {
+            finish_set(self);
+        }
+        let Some(clock) = &mut self.clock else {
+            return;
+        };
+        if let Some(color) = clock.flagged() {
+            // lichess tells when a flag falls online
+            if clock.is_running() && self.online.is_none() {
+                clock.stop();
+                let over = GameOver::on_time(&self.board, color);
+                self.end_game(over);
+            }
+            return;
+        }
+        if let Some(color) = clock.active() {
+            if self.clock_bell
+                && !*self.bell_rung.get(color)
+                && clock.remaining(color) < Duration::from_secs(10)
+            {
+                *self.bell_rung.get_mut(color) = true;
+                let _ = io::stdout().write_all(b"\x07");
+            }
+        }
+    }
+
+    // The players of the live game, an engine's side by its name so the
+    // games can be scouted later
+    fn player_names(&self) -> [&str; 2] {
+        if let Some(game) = &self.online {
+            return [&game.players[0], &game.players[1]];
+        }
+        let name = |color| {
+            self.engines
+                .iter()
+                .find(|engine| engine.color == color)
+                .map_or("?", |engine| engine.name.as_str())
+        };
+        [name(ChessColor::White), name(ChessColor::Black)]
+    }
+
+    // Finish the live game and show the overlay
+    fn end_game(&mut self, over: GameOver) {
+        if let Some(clock) = &mut self.clock {
+            clock.stop();
+        }
+        let [white, black] = self.player_names();
+        let tags = export::Tags {
+            event: "Casual game",
+            date: Some(history::pgn_date(stats::now())),
+            round: "-".to_string(),
+            white,
+            black,
+            result: over.outcome.to_string(),
+            termination: Some(over.termination()),
+        };
+        // best effort, an unwritable state dir only loses the archive
+        let _ = history::keep_game(&export::pgn_with_tags(&self.start, &self.history, &tags));
+        let bottom = if self.flipped {
+            ChessColor::Black
+        } else {
+            ChessColor::White
+        };
+        let series = self
+            .series
+            .get_or_insert_with(|| series::Series::new(bottom));
+        series.record(&self.start, &self.history, over.outcome);
+        if let (Some(ladder), [engine]) = (&mut self.ladder, self.engines.as_slice()) {
+            ladder.record(over.outcome, !engine.color);
+        }
+        let what = if series.is_over() {
+            "Match over"
+        } else {
+            "Series"
+        };
+        self.message = match &self.ladder {
+            Some(ladder) if ladder.is_over() => format!(
+                "Game over: {}. The climb is over, {} cleared.",
+                over.describe(),
+                match ladder.cleared() {
+                    0 => "no level".to_string(),
+                    level => format!("up to level {level}"),
+                }
+            ),
+            Some(ladder) => format!(
+                "Game over: {}. On to level {} of the ladder.",
+                over.describe(),
+                ladder.level
+            ),
+            None => format!("Game over: {}. {what} {}.", over.describe(), series.score()),
+        };
+        // engines play a match of set length through, the overlay waits for
+        // its end; an open series, as `tess watch` plays, stops after each game
+        if self.engines.len() > 1 && series.length.is_some() && !series.is_over() {
+            let message = format!("{} Game {} of the match.", self.message, series.games() + 1);
+            rematch(self);
+            self.message = message;
+            self.engine_turn();
+            return;
+        }
+        self.game_over = Some(over);
+        self.show_game_over = true;
+    }
+
+    fn play(&mut self, mv: &Move) -> anyhow::Result<()> {
+        self.board = self.board.clone().play(mv)?;
+        self.history.push(mv.clone());
+        self.comments.push(None);
+        self.times.push(None);
+        self.evals.push(None);
+        Ok(())
+    }
+
+    /// The position on screen: an earlier one while browsing the move list
+    fn shown_board(&self) -> &Chess {
+        self.view.as_ref().map_or(&self.board, |(_, pos)| pos)
+    }
+
+    /// The named opening the game on screen has reached
+    fn opening(&self) -> Option<&eco::Opening> {
+        let shown = self.shown_ply().min(self.history.len());
+        self.eco.classify(&self.start, &self.history[..shown])
+    }
+
+    /// Plies from the start of the position on screen
+    fn shown_ply(&self) -> usize {
+        self.view
+            .as_ref()
+            .map_or(self.history.len(), |(ply, _)| *ply)
+    }
+
+    // Show the position after `ply` moves, the live board once past the end
+    fn set_view(&mut self, ply: usize) {
+        self.moves_scroll = None;
+        if ply >= self.history.len() {
+            self.view = None;
+            return;
+        }
+        let mut pos = self.start.clone();
+        for mv in &self.history[..ply] {
+            pos.play_unchecked(mv);
+        }
+        self.view = Some((ply, pos));
+    }
+
+    // SAN of every move in the history, with the side that played it
+    fn san_history(&self) -> Vec<(ChessColor, String)> {
+        let mut pos = self.start.clone();
+        self.history
+            .iter()
+            .map(|mv| {
+                let color = pos.turn();
+                let san = san::SanPlus::from_move_and_play_unchecked(&mut pos, mv);
+                (color, san.to_string())
+            })
+            .collect()
+    }
+
+    fn start_message(&self) -> String {
+        let turn = self.board.turn().to_string();
+        match &self.mode {
+            AppMode::StandardGame => match self.engines.as_slice() {
+                [] => format!("New Game, {turn} to move."),
+                [engine] => format!("New Game against {}, {turn} to move.", engine.name),
+                [first, second, ..] => {
+                    let (white, black) = first.color.fold_wb((first, second), (second, first));
+                    format!("{} vs {}, {turn} to move.", white.name, black.name)
+                }
+            },
+            AppMode::Board => format!(
+                "Analysis board, {turn} to move. A move from an earlier position starts a new line, Backspace takes one back."
+            ),
+            AppMode::Puzzle { lichess, .. } => {
+                // study chapters come without a rating
+                let rating = match lichess.puzzle.rating {
+                    0 => String::new(),
+                    rating => format!(", rating: {rating}"),
+                };
+                let phase = phase::Phase::of(&self.start).name();
+
+                format!(
+                "Puzzle {}{rating}, {phase}, please enter moves in UCI (e.g. e2e4, e7e8n) or SAN. {turn} to move.",
+                lichess.puzzle.id
+                )
+            }
+        }
+    }
+}
+
+// ----------------------------------------------
+// Command-line argument parsing with clap
+// ----------------------------------------------
+#[derive(Parser)]
+#[command(name = "Chess App")]
+#[command(about = "A chess application", long_about = None)]
+struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+
+    /// how pieces are drawn, simple unless `display` in the config file says otherwise
+    #[arg(long, short, global = true)]
+    display: Option<DisplayMode>,
+
+    /// the characters of the pieces in the simple display,
+    /// overrides `pieces` from the config file
+    #[arg(long, global = true)]
+    pieces: Option<PieceSet>,
+
+    /// the terminal's font draws the chess symbols two columns wide, as CJK
+    /// fonts do; also `wide_symbols` in the config file
+    #[arg(long, global = true)]
+    wide_symbols: bool,
+
+    /// write the recovery file after this many moves
+    #[arg(long, default_value_t = 1)]
+    autosave_moves: usize,
+
+    /// write the recovery file at least this often (seconds) while moves are pending
+    #[arg(long, default_value_t = 30)]
+    autosave_seconds: u64,
+
+    /// also publish the game state as the tmux user option @tess_status
+    #[arg(long)]
+    tmux_status: bool,
+
+    /// a UCI option for the engines, NAME=VALUE (e.g. Hash=512), may be
+    /// repeated; set after `engine_options` from the config file
+    #[arg(long = "engine-option", value_name = "NAME=VALUE", global = true, value_parser = engine::parse_setting)]
+    engine_options: Vec<(String, String)>,
+
+    /// a UCI engine (e.g. stockfish) to evaluate the position on screen
+    /// all the time, shown above the position pane; overrides
+    /// `analysis_engine` from the config file
+    #[arg(long)]
+    analysis_engine: Option<std::path::PathBuf>,
+
+    /// directories of Syzygy tablebases (':' separated) for the engines to
+    /// play and judge endgames from, their results shown as the engines
+    /// report them; overrides `syzygy_path` from the config file
+    #[arg(long, global = true)]
+    syzygy_path: Option<String>,
+
+    /// play with a clock, minutes + increment seconds (e.g. 5+3)
+    #[arg(long)]
+    clock: Option<clock::TimeControl>,
+
+    /// how the clock's increment is applied
+    #[arg(long, default_value = "fischer")]
+    increment_type: clock::IncrementKind,
+
+    /// ring the terminal bell when a clock drops under ten seconds
+    #[arg(long)]
+    clock_bell: bool,
+
+    /// how moves are written in the move list, letters unless the
+    /// lichess account prefers symbols
+    #[arg(long)]
+    notation: Option<notation::Notation>,
+
+    /// language of the piece letters in SAN input (e.g. de: K D T L S),
+    /// overrides `input_language` from the config file
+    #[arg(long)]
+    input_language: Option<notation::Language>,
+
+    /// play a move as soon as its source and destination square are typed
+    /// (e2e4), without Enter; also `quick_input` in the config file
+    #[arg(long)]
+    quick_input: bool,
+
+    /// help for beginners: the name and moves of the piece pointed at or
+    /// typed, and a tip after a move that loses material; also `:beginner`
+    #[arg(long)]
+    beginner: bool,
+
+    /// terminal background the board colors are picked for,
+    /// overrides `background` from the config file
+    #[arg(long, global = true)]
+    background: Option<theme::Background>,
+
+    /// plain rendering with fewer redraws for slow (e.g. SSH) connections,
+    /// enabled automatically when the terminal responds slowly over SSH
+    #[arg(long)]
+    low_bandwidth: bool,
+
+    /// two players at one screen: turn the board to the side to move after
+    /// every move, hiding the position until the next player presses a key
+    #[arg(long)]
+    rotate: bool,
+
+    /// start with the spectator lock on: only browsing until `:unlock`,
+    /// which asks for `lock_password` from the config file if it is set
+    #[arg(long)]
+    lock: bool,
+
+    /// write every input, network answer and screen of the session to this
+    /// file, for `tess replay`
+    #[arg(long, value_name = "FILE")]
+    record: Option<std::path::PathBuf>,
+
+    /// write puzzle_started, move_played, puzzle_solved, puzzle_failed and
+    /// game_over events as JSON lines to this file, or this file descriptor
+    /// if a number, for overlays
+    #[arg(long, value_name = "FILE_OR_FD")]
+    events_json: Option<String>,
+}
+
+/// The engine to play against in a game
+#[derive(clap::Args)]
+struct OpponentArgs {
+    /// a UCI engine to play against, e.g. stockfish
+    #[arg(long)]
+    engine: Option<std::path::PathBuf>,
+    /// the engine's longest time per move in milliseconds, one second
+    /// without --clock and as the clock allows with one if not given
+    #[arg(long)]
+    movetime: Option<u64>,
+    /// let the engine play at about this Elo (UCI_Elo)
+    #[arg(long)]
+    engine_elo: Option<u32>,
+    /// the engine's Skill Level, 0 to 20
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=20))]
+    engine_skill: Option<u8>,
+    /// play an opponent from `opponents` in the config file instead, e.g.
+    /// maia1500; --movetime overrides its own
+    #[arg(long, conflicts_with_all = ["engine", "engine_elo", "engine_skill"])]
+    opponent: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    #[command(about = "Start a puzzle game")]
+    #[command(args_conflicts_with_subcommands = true)]
+    Puzzle {
+        #[command(subcommand)]
+        command: Option<PuzzleCommands>,
+        /// load this puzzle id, if not specified, load random
+        id: Option<String>,
+    },
+    #[command(about = "Load a PGN file")]
+    Load {
+        #[arg(required = true)]
+        filename: String,
+    },
+    #[command(
+        about = "Present a PGN game over a shared screen: a huge board, only browsing works"
+    )]
+    Present {
+        #[arg(required = true)]
+        filename: String,
+        /// the game of the file to present, from 1
+        #[arg(long, default_value_t = 1)]
+        game: usize,
+        /// play through the game, one move every that many seconds
+        #[arg(long)]
+        advance: Option<f64>,
+    },
+    #[command(about = "Browse and search a directory of PGN files")]
+    Library {
+        #[arg(required = true)]
+        dir: std::path::PathBuf,
+    },
+    #[command(about = "Keep named positions with tags, to play, drill or analyse again")]
+    Positions {
+        #[command(subcommand)]
+        command: PositionCommands,
+    },
+    #[command(about = "Start a new standard game")]
+    Standard {
+        #[command(flatten)]
+        opponent: OpponentArgs,
+        /// the side I play against the engine
+        #[arg(long, default_value = "white")]
+        color: Side,
+        /// PGN files of earlier games against the engine, for a briefing on
+        /// how it plays before the game
+        #[arg(long, value_name = "PGN")]
+        briefing: Vec<std::path::PathBuf>,
+        /// start from the end of a random line of the ECO tables, or after
+        /// its first DEPTH plies, to practise varied middlegames
+        #[arg(long, value_name = "DEPTH", num_args = 0..=1)]
+        random_opening: Option<Option<usize>>,
+    },
+    #[command(about = "Climb a ladder of blitz games against rising engine levels")]
+    Ladder {
+        /// the UCI engine to climb against, e.g. stockfish; it needs UCI_Elo
+        #[arg(long)]
+        engine: std::path::PathBuf,
+        /// the level to start at, 1 to 11
+        #[arg(long, default_value_t = 1)]
+        from: usize,
+    },
+    #[command(about = "Open an analysis board to explore moves freely")]
+    Board,
+    #[command(about = "Play on a game adjourned with :adjourn")]
+    Resume {
+        /// the file given to :adjourn, adjourned.json in the state directory if not given
+        file: Option<std::path::PathBuf>,
+    },
+    #[command(
+        about = "Play a lichess game through the Board API, with the lichess_token of the config file"
+    )]
+    Online {
+        /// the id of a game of the account already started; the next one
+        /// to start, e.g. from a challenge accepted on lichess, if not given
+        game: Option<String>,
+    },
+    #[command(about = "Watch two UCI engines play each other")]
+    Watch {
+        /// the engine playing white, e.g. stockfish
+        white: std::path::PathBuf,
+        /// the engine playing black
+        black: std::path::PathBuf,
+        /// longest time per move in milliseconds, one second without --clock
+        /// and as the clock allows with one if not given
+        #[arg(long)]
+        movetime: Option<u64>,
+    },
+    #[command(about = "Play a match of games with colors swapped after each one")]
+    Match {
+        /// number of games in the match
+        #[arg(long, default_value_t = 4)]
+        games: usize,
+        /// end the match when a player reaches this score instead
+        #[arg(long)]
+        first_to: Option<f64>,
+        /// a UCI engine to play the match against, or two to play it
+        /// between them; the games between engines follow one another
+        /// without a break
+        #[arg(value_name = "ENGINE", num_args = 0..=2)]
+        engines: Vec<std::path::PathBuf>,
+        /// let a single engine play at about this Elo (UCI_Elo)
+        #[arg(long)]
+        engine_elo: Option<u32>,
+        /// move a single engine's Elo up 100 after each game I win and down
+        /// 100 after each loss, from --engine-elo or 1500
+        #[arg(long)]
+        adaptive: bool,
+        /// start the games from the positions of an EPD file, or the final
+        /// positions of a PGN file's games, each played once with either color
+        #[arg(long)]
+        openings: Option<std::path::PathBuf>,
+        /// the engines' longest time per move in milliseconds, one second
+        /// without a clock and as the clock allows with one if not given
+        #[arg(long)]
+        movetime: Option<u64>,
+        /// play every game with a clock, base seconds + increment seconds
+        /// (e.g. 10+0.1); --clock takes minutes instead
+        #[arg(long, value_parser = clock::TimeControl::from_seconds)]
+        tc: Option<clock::TimeControl>,
+        /// play a match between two engines without the board, writing the
+        /// games to --pgn and printing the results when it is over
+        #[arg(long)]
+        headless: bool,
+        /// where --headless writes the games, tess-match-<time>.pgn if not given
+        #[arg(long, requires = "headless")]
+        pgn: Option<std::path::PathBuf>,
+    },
+    #[command(about = "Give a simul: white on several boards against engines, one tab each")]
+    Simul {
+        /// the UCI engines to play, a board each
+        #[arg(required = true, value_name = "ENGINE")]
+        engines: Vec<std::path::PathBuf>,
+        /// boards in all, the engines taking them in turn; one per engine
+        /// if not given
+        #[arg(long, value_parser = clap::value_parser!(u16).range(1..=9))]
+        boards: Option<u16>,
+        /// let the engines play at about this Elo (UCI_Elo)
+        #[arg(long)]
+        engine_elo: Option<u32>,
+        /// the engines' longest time per move in milliseconds, one second
+        /// without --clock and as the clock allows with one if not given
+        #[arg(long)]
+        movetime: Option<u64>,
+    },
+    #[command(about = "Inspect FEN strings")]
+    #[command(args_conflicts_with_subcommands = true)]
+    Fen {
+        #[command(subcommand)]
+        command: Option<FenCommands>,
+        /// play this position, quoted as a single argument
+        fen: Option<String>,
+        /// the side to play it from, the board is turned for black; an
+        /// engine plays the other
+        #[arg(long, default_value = "white")]
+        side: Side,
+        #[command(flatten)]
+        opponent: OpponentArgs,
+    },
+    #[command(about = "Print the FEN after a ply of a PGN game")]
+    FenAt {
+        #[arg(required = true)]
+        filename: String,
+        /// plies played before the position, 0 for the start
+        #[arg(long, required_unless_present = "all", conflicts_with = "all")]
+        ply: Option<usize>,
+        /// the FEN of every position instead, one per line from the start
+        #[arg(long)]
+        all: bool,
+        /// the game of the file, counting from 1
+        #[arg(long, default_value_t = 1)]
+        game: usize,
+    },
+    #[command(about = "Build the opening tree from my repertoire, my games and PGN databases")]
+    Tree {
+        #[command(subcommand)]
+        command: TreeCommands,
+    },
+    #[command(about = "Evaluate every position of a PGN game with a UCI engine")]
+    Analyze {
+        #[arg(required = true)]
+        filename: String,
+        /// the game to analyse, counting from 1
+        #[arg(long, default_value_t = 1)]
+        game: usize,
+        /// the UCI engine, e.g. stockfish
+        #[arg(long)]
+        engine: std::path::PathBuf,
+        /// search time per position in milliseconds
+        #[arg(long, default_value_t = 1000)]
+        movetime: u64,
+        /// engines searching at the same time, one per CPU if not given
+        #[arg(long)]
+        jobs: Option<usize>,
+    },
+    #[command(about = "Guess the engine's evaluation of middlegame positions")]
+    Guess {
+        /// the UCI engine that knows the answer, e.g. stockfish
+        engine: std::path::PathBuf,
+        /// PGN files of my games to take the positions from, random lichess
+        /// puzzles if none are given
+        files: Vec<std::path::PathBuf>,
+        /// search time per position in milliseconds
+        #[arg(long, default_value_t = 1000)]
+        movetime: u64,
+        /// how far off a guess may be and still count, in pawns
+        #[arg(long, default_value_t = 0.5)]
+        tolerance: f64,
+    },
+    #[command(
+        about = "Train an opening repertoire: the file plays the opponent, I play my prepared moves"
+    )]
+    Repertoire {
+        /// a PGN file with the repertoire as variations, all its games together
+        file: std::path::PathBuf,
+        /// the side the repertoire is for
+        #[arg(long, default_value = "white")]
+        color: Side,
+    },
+    #[command(
+        about = "Guess the moves of a master game from one side, scored against the moves played"
+    )]
+    GuessMove {
+        /// the PGN file of the game
+        filename: String,
+        /// the game of the file, from 1
+        #[arg(long, default_value_t = 1)]
+        game: usize,
+        /// the side whose moves I guess
+        #[arg(long, default_value = "white")]
+        color: Side,
+        /// the first move to guess, the moves before it are played out
+        #[arg(long, default_value_t = 1)]
+        from: u32,
+        /// a UCI engine, e.g. stockfish, to give half a point for a move
+        /// about as good as the one played
+        #[arg(long)]
+        engine: Option<std::path::PathBuf>,
+        /// its search time for each of the two moves in milliseconds
+        #[arg(long, default_value_t = 500)]
+        movetime: u64,
+        /// how much worse than the move played a move may be for half a
+        /// point, in pawns
+        #[arg(long, default_value_t = 0.3)]
+        tolerance: f64,
+    },
+    #[command(about = "Drill a classic endgame against perfect defence")]
+    Endgame {
+        /// the ending to drill
+        ending: endgames::Ending,
+        /// a UCI engine, e.g. stockfish, to defend and judge the moves
+        /// instead of the lichess tablebase; it uses --syzygy-path if given
+        #[arg(long)]
+        engine: Option<std::path::PathBuf>,
+        /// its search time for each move in milliseconds
+        #[arg(long, default_value_t = 1000)]
+        movetime: u64,
+    },
+    #[command(about = "Make puzzles from PGN games or FEN positions with a UCI engine")]
+    Generate {
+        /// PGN files, or files of FEN or EPD positions one per line
+        #[arg(required = true)]
+        files: Vec<std::path::PathBuf>,
+        /// the UCI engine, e.g. stockfish
+        #[arg(long)]
+        engine: std::path::PathBuf,
+        /// search time per position in milliseconds
+        #[arg(long, default_value_t = 500)]
+        movetime: u64,
+        /// the longest forced mate to keep, in moves
+        #[arg(long, default_value_t = 3)]
+        max_mate: u32,
+        /// only forced mates, no winning tactics
+        #[arg(long)]
+        mates_only: bool,
+        /// append the puzzles to this file instead of printing them, to be
+        /// solved with `tess puzzle file`
+        #[arg(long)]
+        output: Option<std::path::PathBuf>,
+    },
+    #[command(about = "Find my blunders in PGN games with a UCI engine and keep them as puzzles")]
+    Blunders {
+        /// my name in the White and Black tags
+        #[arg(long)]
+        player: String,
+        /// PGN files of my games
+        #[arg(required = true)]
+        files: Vec<std::path::PathBuf>,
+        /// the UCI engine, e.g. stockfish
+        #[arg(long)]
+        engine: std::path::PathBuf,
+        /// search time per position in milliseconds
+        #[arg(long, default_value_t = 500)]
+        movetime: u64,
+        /// engines searching at the same time, one per CPU if not given
+        #[arg(long)]
+        jobs: Option<usize>,
+    },
+    #[command(about = "Report on an opponent's openings, move times and errors from PGN files")]
+    Scout {
+        /// the opponent's name in the White and Black tags, an engine, a
+        /// persona from `opponents` or a lichess user
+        name: String,
+        /// PGN files of games against them
+        #[arg(required = true)]
+        files: Vec<std::path::PathBuf>,
+        /// a UCI engine to review the games that have no [%eval] comments
+        #[arg(long)]
+        engine: Option<std::path::PathBuf>,
+        /// search time per position in milliseconds
+        #[arg(long, default_value_t = 500)]
+        movetime: u64,
+        /// engines searching at the same time, one per CPU if not given
+        #[arg(long)]
+        jobs: Option<usize>,
+    },
+    #[command(about = "Show my results and errors by opening, from PGN files of my games")]
+    Openings {
+        /// my name in the White and Black tags, "?" for the games tess saved
+        name: String,
+        /// PGN files of my games, the tess-game-*.pgn and tess-match-*.pgn
+        /// files of the working directory if none are given
+        files: Vec<std::path::PathBuf>,
+        /// a UCI engine to review the games that have no [%eval] comments
+        #[arg(long)]
+        engine: Option<std::path::PathBuf>,
+        /// search time per position in milliseconds
+        #[arg(long, default_value_t = 500)]
+        movetime: u64,
+        /// engines searching at the same time, one per CPU if not given
+        #[arg(long)]
+        jobs: Option<usize>,
+        /// print the report instead of showing it
+        #[arg(long)]
+        print: bool,
+    },
+    #[command(about = "Show a calendar of the time spent training")]
+    Activity {
+        /// number of weeks to show
+        #[arg(long, default_value_t = 12)]
+        weeks: usize,
+        /// also write every recorded day to this CSV file
+        #[arg(long)]
+        export: Option<std::path::PathBuf>,
+    },
+    #[command(
+        about = "Write the games played, the puzzles tried and every training record kept here as PGN and NDJSON, for lichess, scid and other tools"
+    )]
+    Archive {
+        /// the directory for games.pgn, puzzles.pgn, puzzles.ndjson and training.ndjson
+        dir: std::path::PathBuf,
+    },
+    #[command(
+        about = "Stay in the background and remind me to train at `reminder` from the config file"
+    )]
+    Daemon,
+    #[command(about = "Choose the board size, pieces, colors and analysis engine again")]
+    Setup,
+    #[command(about = "Play back a session written with --record, checking every screen")]
+    Replay {
+        /// the file written by --record
+        path: std::path::PathBuf,
+        /// as fast as it goes, not at the recorded pace
+        #[arg(long)]
+        fast: bool,
+    },
+    #[command(about = "Print a board to stdout and exit")]
+    Render {
+        /// the position to draw, the start position if not given
+        #[arg(long)]
+        fen: Option<String>,
+        /// draw the board from black's side
+        #[arg(long)]
+        flip: bool,
+        /// no colors, dark squares marked with '.' and pieces as letters
+        #[arg(long)]
+        plain: bool,
+    },
+}
+
+#[derive(Debug, clap::ValueEnum, Clone, Copy, PartialEq)]
+enum Side {
+    White,
+    Black,
+}
+
+#[derive(Subcommand)]
+enum PuzzleCommands {
+    #[command(about = "Solve a fixed number of puzzles, then see the score")]
+    Set {
+        /// number of puzzles in the set
+        #[arg(long, default_value_t = 20)]
+        count: usize,
+        /// lichess puzzle theme, e.g. endgame, mateIn2, fork
+        #[arg(long)]
+        theme: Option<String>,
+        /// only puzzles of this phase of the game
+        #[arg(long)]
+        phase: Option<phase::Phase>,
+    },
+    #[command(about = "Solve as many puzzles as possible before the time runs out")]
+    Sprint {
+        /// length of the sprint
+        #[arg(long, default_value_t = 3.0)]
+        minutes: f64,
+        /// lichess puzzle theme, e.g. endgame, mateIn2, fork
+        #[arg(long)]
+        theme: Option<String>,
+        /// only puzzles of this phase of the game
+        #[arg(long)]
+        phase: Option<phase::Phase>,
+    },
+    #[command(about = "Work through a built-in set of positions for one technique")]
+    Drill {
+        /// the technique to practise
+        drill: drills::Drill,
+    },
+    #[command(about = "Play through the chapters of a lichess study, each one as a puzzle")]
+    Study {
+        /// a study's PGN export, or the id of a public lichess study
+        source: String,
+        /// only the chapters of this phase of the game
+        #[arg(long)]
+        phase: Option<phase::Phase>,
+    },
+    #[command(about = "Solve the puzzles of a file written by `tess generate`, in order")]
+    File {
+        path: std::path::PathBuf,
+        /// only the puzzles of this phase of the game
+        #[arg(long)]
+        phase: Option<phase::Phase>,
+    },
+    #[command(about = "Refute the blunders found in my games by `tess blunders`, in order")]
+    Blunders {
+        /// only the blunders of this phase of the game, e.g. "endgame" for an
+        /// evening of endgames
+        #[arg(long)]
+        phase: Option<phase::Phase>,
+    },
+}
+
+#[derive(Subcommand)]
+enum PositionCommands {
+    #[command(about = "Save a position under a name, in place of one of that name")]
+    Add {
+        name: String,
+        /// the position, quoted as a single argument
+        fen: String,
+        /// a tag to find it by, may be repeated
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+    },
+    #[command(about = "List the saved positions, those a search finds if given")]
+    List {
+        /// part of a name or a tag
+        search: Option<String>,
+    },
+    #[command(about = "Forget a saved position")]
+    Remove { name: String },
+    #[command(about = "Open a saved position on the board, to play out or analyse")]
+    Open {
+        /// its name, or part of a name or a tag
+        search: String,
+    },
+    #[command(about = "Play the saved positions a search finds against an engine, one per run")]
+    Drill {
+        /// part of a name or a tag; the position drilled longest ago comes next
+        search: String,
+        /// a UCI engine to play against, e.g. stockfish
+        #[arg(long)]
+        engine: std::path::PathBuf,
+        /// the engine's longest time per move in milliseconds
+        #[arg(long)]
+        movetime: Option<u64>,
+    },
+}
+
+#[derive(Subcommand)]
+enum TreeCommands {
+    #[command(about = "Add the moves of one color from repertoire PGN files, variations included")]
+    Repertoire {
+        /// the color the repertoire is for
+        #[arg(long)]
+        color: Side,
+        #[arg(required = true)]
+        files: Vec<std::path::PathBuf>,
+    },
+    #[command(about = "Add my opening moves from PGN files of played games")]
+    Games {
+        /// my name in the White and Black tags
+        #[arg(long)]
+        player: String,
+        #[arg(required = true)]
+        files: Vec<std::path::PathBuf>,
+    },
+    #[command(
+        about = "Add the opening moves of both sides from a PGN database, e.g. master games"
+    )]
+    Database {
+        #[arg(required = true)]
+        files: Vec<std::path::PathBuf>,
+    },
+    #[command(about = "Walk the database moves with how often each was played and how it went")]
+    Explore,
+    #[command(about = "Forget everything that was imported")]
+    Clear,
+}
+
+#[derive(Subcommand)]
+enum FenCommands {
+    #[command(about = "Validate a FEN and print its normalized form")]
+    Check {
+        /// the FEN to check, quoted as a single argument
+        fen: String,
+    },
+}
+
+// ----------------------------------------------
+// Main entry
+// ----------------------------------------------
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli = Cli::parse();
+    // a replay runs the recorded command line with the recorded config
+    let (cli, config, replay_size) = match &cli.command {
+        Commands::Replay { path, fast } => {
+            if cli.record.is_some() {
+                return Err("a replay is not recorded again".into());
+            }
+            let recorded = session_log::replay(path, *fast)?;
+            let args = std::iter::once("tess".to_string()).chain(recorded.args);
+            let mut replayed = Cli::try_parse_from(args)?;
+            replayed.record = None;
+            (replayed, recorded.config, Some(recorded.size))
+        }
+        _ => (cli, config::load()?, None),
+    };
+    // commands that print and exit
+    let batch = matches!(
+        cli.command,
+        Commands::Fen {
+            command: Some(_),
+            ..
+        } | Commands::Render { .. }
+            | Commands::FenAt { .. }
+            | Commands::Tree { .. }
+            | Commands::Analyze { .. }
+            | Commands::Generate { .. }
+            | Commands::Blunders { .. }
+            | Commands::Scout { .. }
+            | Commands::Openings { .. }
+            | Commands::Activity { .. }
+            | Commands::Archive { .. }
+            | Commands::Match { headless: true, .. }
+            | Commands::Daemon
+            | Commands::Setup
+            | Commands::Positions {
+                command: PositionCommands::Add { .. }
+                    | PositionCommands::List { .. }
+                    | PositionCommands::Remove { .. }
+            }
+    );
+
+    // The first run asks how the board looks before anything is shown; leaving
+    // the setup keeps the defaults, without asking again
+    let config = if !batch
+        && replay_size.is_none()
+        && config::is_missing()
+        && io::IsTerminal::is_terminal(&io::stdin())
+        && io::IsTerminal::is_terminal(&io::stdout())
+    {
+        let config = setup::run(config)?.unwrap_or_default();
+        config::save(&config)?;
+        config
+    } else {
+        config
+    };
+    let display = cli
+        .display
+        .or(config.display)
+        .unwrap_or(DisplayMode::Simple);
+    if let Some(path) = &cli.record {
+        if batch {
+            return Err("--record records sessions in the terminal, not this command".into());
+        }
+        session_log::record(path, std::env::args().skip(1).collect(), &config)?;
+    }
+    if batch && cli.events_json.is_some() {
+        return Err("--events-json streams the events of the terminal, not this command".into());
+    }
+    let mut events = events::Stream::open(cli.events_json.as_deref())
+        .map_err(|err| format!("--events-json: {err}"))?;
+
+    let reminder = match &config.reminder {
+        Some(text) => match activity::parse_time_of_day(text) {
+            Some(time) => Some(time),
+            None => {
+                return Err(format!(
+                    "reminder {text:?} in the config file is not a time like 18:30"
+                )
+                .into())
+            }
+        },
+        None => None,
+    };
+
+    let engine_options: Vec<(String, String)> = config
+        .engine_options
+        .clone()
+        .into_iter()
+        .chain(cli.engine_options.iter().cloned())
+        .collect();
+
+    // Settings of the lichess account, below the command line and the config file
+    let (lichess, lichess_error) = match &config.lichess_token {
+        Some(token) if !batch && !config.ignore_lichess_preferences => {
+            match account::preferences(token) {
+                Ok(prefs) => (prefs, None),
+                Err(err) => (Default::default(), Some(err)),
+            }
+        }
+        _ => (Default::default(), None),
+    };
+    let background = cli
+        .background
+        .or(config.background)
+        .or(lichess.background)
+        .unwrap_or(theme::Background::Auto);
+    let pieces = cli.pieces.or(config.pieces).unwrap_or_default();
+    let wide_symbols = cli.wide_symbols || config.wide_symbols;
+
+    // Offer to pick up a session that did not end cleanly
+    let restored = match session::load_recovery() {
+        _ if batch => None,
+        Some(saved) if session_log::answer(|| confirm_restore(&saved))? => {
+            Some(app_from_session(saved, &display)?)
+        }
+        Some(_) => {
+            session::clear_recovery();
+            None
+        }
+        None => None,
+    };
+
+    // the boards of a simul after the first, each in a tab of its own
+    let mut simul_boards = None;
+    let mut app = match (restored, cli.command) {
+        (Some(app), _) => app,
+        (
+            None,
+            Commands::Puzzle {
+                command: Some(command),
+                ..
+            },
+        ) => {
+            let mut set = match command {
+                PuzzleCommands::Set {
+                    count,
+                    theme,
+                    phase,
+                } => puzzle_set::PuzzleSet::new(count.max(1), theme).of_phase(phase),
+                PuzzleCommands::Sprint {
+                    minutes,
+                    theme,
+                    phase,
+                } => puzzle_set::PuzzleSet::sprint(
+                    Duration::from_secs_f64(minutes.max(0.1) * 60.0),
+                    theme,
+                )
+                .of_phase(phase),
+                PuzzleCommands::Drill { drill } => puzzle_set::PuzzleSet::drill(drill),
+                PuzzleCommands::Study { source, phase } => puzzle_set::PuzzleSet::study(
+                    source.clone(),
+                    in_phase(study::load(&source)?, phase)?,
+                ),
+                PuzzleCommands::File { path, phase } => puzzle_set::PuzzleSet::study(
+                    path.display().to_string(),
+                    in_phase(generate::load(&path)?, phase)?,
+                ),
+                PuzzleCommands::Blunders { phase } => puzzle_set::PuzzleSet::study(
+                    "My blunders".to_string(),
+                    in_phase(blunders::load()?, phase)?,
+                ),
+            };
+            let (board, solution, puzzle) = load_set_puzzle(&set)?;
+            set.begin(puzzle.puzzle.rating);
+            let mut app = App::new_puzzle(board, solution, display, puzzle);
+            app.message = format!("{}. {}", set.progress(), app.start_message());
+            app.puzzle_set = Some(set);
+            app
+        }
+        (None, Commands::Puzzle { id, .. }) => {
+            let (board, solution, puzzle) = load_puzzle(id)?;
+            App::new_puzzle(board, solution, display, puzzle)
+        }
+        (None, Commands::Ladder { engine, from }) => {
+            let ladder = ladder::Ladder::new(from);
+            let mut app = App::new_standard(Chess::default(), display);
+            app.engines.push(
+                engine::Engine::start(&engine)?
+                    .with_options(&engine_options)?
+                    .playing(ChessColor::Black, ladder::strength(ladder.level))?,
+            );
+            app.message = ladder_message(&ladder, &app.engines[0]);
+            app.ladder = Some(ladder);
+            app
+        }
+        (
+            None,
+            Commands::Simul {
+                engines,
+                boards,
+                engine_elo,
+                movetime,
+            },
+        ) => {
+            let strength = engine::Strength {
+                elo: engine_elo,
+                skill: None,
+                movetime: movetime.map(|ms| Duration::from_millis(ms.max(1))),
+                nodes: None,
+            };
+            let boards = boards.map_or(engines.len(), usize::from);
+            let mut simul = Vec::new();
+            for path in engines.iter().cycle().take(boards) {
+                let mut board = App::new_standard(Chess::default(), display);
+                board.engines.push(
+                    engine::Engine::start(path)?
+                        .with_options(&engine_options)?
+                        .playing(ChessColor::Black, strength)?,
+                );
+                // every board on its own clock, running from the start
+                if let Some(control) = cli.clock {
+                    let mut clock = clock::Clock::new(control, cli.increment_type);
+                    clock.start(ChessColor::White);
+                    board.clock = Some(clock);
+                    board.clock_bell = cli.clock_bell;
+                }
+                simul.push(board);
+            }
+            let mut app = simul.remove(0);
+            simul_boards = Some(simul);
+            app.message = format!(
+                "Simul on {boards} boards, you have white on each. Once you move, the next board where it is your move comes up; the keys 1-{boards} go to any of them."
+            );
+            app
+        }
+        (None, Commands::Load { filename }) => app_from_pgn(&filename, 0, display)?,
+        (
+            None,
+            Commands::Present {
+                filename,
+                game,
+                advance,
+            },
+        ) => {
+            let index = game.saturating_sub(1);
+            let mut app = app_from_pgn(&filename, index, display)?;
+            let step = match advance {
+                Some(seconds) if seconds > 0.0 && seconds.is_finite() => {
+                    Some(Duration::from_secs_f64(seconds))
+                }
+                Some(_) => return Err("--advance takes a number of seconds above 0".into()),
+                None => None,
+            };
+            let game = load_pgn_game(&filename, index)?;
+            let title = if game.white.is_empty() && game.black.is_empty() {
+                filename.clone()
+            } else {
+                format!("{} - {}, {}", game.white, game.black, game.result)
+            };
+            app.presentation = Some(presentation::Presentation::new(title, step));
+            app.set_view(0);
+            app
+        }
+        (
+            None,
+            Commands::Positions {
+                command: PositionCommands::Open { search },
+            },
+        ) => {
+            let saved = positions::pick(&search)?;
+            position_app(&saved, display)?
+        }
+        (
+            None,
+            Commands::Positions {
+                command:
+                    PositionCommands::Drill {
+                        search,
+                        engine,
+                        movetime,
+                    },
+            },
+        ) => {
+            let (saved, count) = positions::next_drill(&search)?;
+            let mut app = position_app(&saved, display)?;
+            let strength = engine::Strength {
+                movetime: movetime.map(|ms| Duration::from_millis(ms.max(1))),
+                ..engine::Strength::default()
+            };
+            app.engines.push(
+                engine::Engine::start(&engine)?
+                    .with_options(&engine_options)?
+                    .playing(!app.board.turn(), strength)?,
+            );
+            let which = if count > 1 {
+                format!(", of the {count} found the one drilled longest ago")
+            } else {
+                String::new()
+            };
+            app.message = format!(
+                "{}{which}: {} to play against {}.",
+                saved.name,
+                app.board.turn(),
+                app.engines[0].name
+            );
+            app
+        }
+        (None, Commands::Positions { command }) => return run_positions_command(command),
+        (None, Commands::Library { dir }) => match library::browse(&dir)? {
+            Some(entry) => {
+                // best effort, an unwritable state dir only loses the history
+                let _ = library::remember(&entry.path);
+                let path = entry.path.to_string_lossy();
+                app_from_pgn(&path, entry.index, display)?
+            }
+            None => return Ok(()),
+        },
+        (
+            None,
+            Commands::Standard {
+                opponent,
+                color,
+                briefing,
+                random_opening,
+            },
+        ) => {
+            let mut app = App::new_standard(Chess::default(), display);
+            if let Some(engine) = start_opponent(opponent, color, &config, &engine_options)? {
+                app.engines.push(engine);
+                app.flipped = color == Side::Black;
+            }
+            if !briefing.is_empty() {
+                let Some(engine) = app.engines.first() else {
+                    return Err("a briefing needs an opponent, --engine or --opponent".into());
+                };
+                let report = scouting::scout(&engine.name, &briefing, None, &|_, _| {})?;
+                app.briefing = Some(report.lines());
+            }
+            if let Some(depth) = random_opening {
+                let eco = eco::Eco::load();
+                let nanos = session_log::system_time()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map_or(0, |d| d.subsec_nanos());
+                let Some(line) = eco.random_line(&mut (u64::from(nanos) | 1), depth) else {
+                    return Err(format!(
+                        "no line of the ECO tables is {} plies long",
+                        depth.unwrap_or(1)
+                    )
+                    .into());
+                };
+                for mv in line {
+                    app.play(mv)?;
+                }
+                if let Some(opening) = eco.classify(&app.start, &app.history) {
+                    app.message = format!("{} {}.", app.start_message(), opening.describe());
+                }
+            }
+            app
+        }
+        (None, Commands::Board) => App::new_board(display),
+        (None, Commands::Online { game }) => {
+            let Some(token) = &config.lichess_token else {
+                return Err(
+                    "tess online plays with the lichess_token of the config file, one with the board:play scope"
+                        .into(),
+                );
+            };
+            if cli.clock.is_some() {
+                return Err("an online game has the clock lichess gives it, not --clock".into());
+            }
+            if game.is_none() {
+                println!("Waiting for a game to start on lichess...");
+            }
+            let game = online::Game::join(token, game.as_deref())?;
+            let start: Chess = match &game.initial_fen {
+                Some(fen) => {
+                    Fen::from_ascii(fen.as_bytes())?.into_position(CastlingMode::Standard)?
+                }
+                None => Chess::default(),
+            };
+            let mut app = App::new_standard(start, display);
+            app.flipped = game.color == ChessColor::Black;
+            app.message = format!(
+                "Online game against {}, you play {}.",
+                game.opponent(),
+                game.color.fold_wb("White", "Black")
+            );
+            app.online = Some(game);
+            app
+        }
+        (None, Commands::Resume { file }) => {
+            let Some(path) = file.or_else(adjourn::default_path) else {
+                return Err("no state directory for the adjourned game, give its file".into());
+            };
+            let mut app = adjourn::resume(&path, display)?;
+            app.message = format!(
+                "Adjourned game resumed, {} to move.",
+                app.board.turn().fold_wb("White", "Black")
+            );
+            app
+        }
+        (
+            None,
+            Commands::Guess {
+                engine,
+                files,
+                movetime,
+                tolerance,
+            },
+        ) => {
+            let source = if files.is_empty() {
+                eval_quiz::Source::Lichess
+            } else {
+                eval_quiz::Source::Games(eval_quiz::middlegames(&files)?)
+            };
+            let engine = engine::Engine::start(&engine)?.with_options(&engine_options)?;
+            let mut app = App::new_board(display);
+            app.quiz = Some(eval_quiz::Quiz::new(
+                engine,
+                source,
+                Duration::from_millis(movetime.max(1)),
+                (tolerance.abs() * 100.0).round() as i32,
+            ));
+            next_guess(&mut app)?;
+            app
+        }
+        (
+            None,
+            Commands::GuessMove {
+                filename,
+                game,
+                color,
+                from,
+                engine,
+                movetime,
+                tolerance,
+            },
+        ) => {
+            let color = match color {
+                Side::White => ChessColor::White,
+                Side::Black => ChessColor::Black,
+            };
+            let engine = match engine {
+                Some(path) => Some(engine::Engine::start(&path)?.with_options(&engine_options)?),
+                None => None,
+            };
+            let mut app = App::new_board(display);
+            app.move_guess = Some(move_guess::Training::new(
+                &load_pgn_game(&filename, game.max(1) - 1)?,
+                color,
+                from,
+                engine,
+                Duration::from_millis(movetime.max(1)),
+                (tolerance.abs() * 100.0).round() as i32,
+            )?);
+            app.flipped = color == ChessColor::Black;
+            start_move_guess(&mut app)?;
+            app
+        }
+        (
+            None,
+            Commands::Endgame {
+                ending,
+                engine,
+                movetime,
+            },
+        ) => {
+            let engine = match engine {
+                Some(path) => Some(engine::Engine::start(&path)?.with_options(&engine_options)?),
+                None => None,
+            };
+            let mut app = App::new_board(display);
+            let trainer =
+                endgames::Trainer::new(ending, engine, Duration::from_millis(movetime.max(1)));
+            app.flipped = trainer.color == ChessColor::Black;
+            app.endgame = Some(trainer);
+            start_endgame(&mut app);
+            app
+        }
+        (None, Commands::Repertoire { file, color }) => {
+            let color = match color {
+                Side::White => ChessColor::White,
+                Side::Black => ChessColor::Black,
+            };
+            let mut app = App::new_board(display);
+            app.repertoire = Some(repertoire::Repertoire::load(&file, color)?);
+            app.flipped = color == ChessColor::Black;
+            next_line(&mut app)?;
+            app
+        }
+        (
+            None,
+            Commands::Watch {
+                white,
+                black,
+                movetime,
+            },
+        ) => {
+            let mut app = App::new_standard(Chess::default(), display);
+            for (path, color) in [(white, ChessColor::White), (black, ChessColor::Black)] {
+                let strength = engine::Strength {
+                    elo: None,
+                    skill: None,
+                    movetime: movetime.map(|ms| Duration::from_millis(ms.max(1))),
+                    nodes: None,
+                };
+                app.engines.push(
+                    engine::Engine::start(&path)?
+                        .with_options(&engine_options)?
+                        .playing(color, strength)?,
+                );
+            }
+            app
+        }
+        (
+            None,
+            Commands::Match {
+                games,
+                first_to,
+                engines,
+                engine_elo,
+                adaptive,
+                openings,
+                movetime,
+                tc,
+                headless,
+                pgn,
+            },
+        ) => {
+            let length = match first_to {
+                Some(points) => series::MatchLength::FirstTo(points.max(0.5)),
+                None => series::MatchLength::Games(games.max(1)),
+            };
+            let mut series = series::Series::with_length(ChessColor::White, length);
+            if let Some(path) = openings {
+                series = series.with_openings(load_openings(&path)?);
+            }
+            if tc.is_some() && cli.clock.is_some() {
+                return Err("--clock and --tc both set the match's clock, give one of them".into());
+            }
+            let movetime = movetime.map(|ms| Duration::from_millis(ms.max(1)));
+            if headless {
+                let [first, second] = engines.as_slice() else {
+                    return Err("a match --headless is played between two engines".into());
+                };
+                let control = tc.or(cli.clock);
+                let engines = start_match_engines([first, second], movetime, &engine_options)?;
+                let pgn = pgn.unwrap_or_else(|| format!("tess-match-{}.pgn", stats::now()).into());
+                return Ok(run_headless_match(
+                    series,
+                    engines,
+                    control.map(|control| clock::Clock::new(control, cli.increment_type)),
+                    &pgn,
+                )?);
+            }
+            let mut app = App::new_standard(series.opening().unwrap_or_default(), display);
+            match engines.as_slice() {
+                [] => {}
+                [path] => {
+                    if adaptive {
+                        series = series.with_adaptive(engine_elo.unwrap_or(1500));
+                    }
+                    let strength = engine::Strength {
+                        elo: series.engine_elo().or(engine_elo),
+                        skill: None,
+                        movetime,
+                        nodes: None,
+                    };
+                    let engine = engine::Engine::start(path)?
+                        .with_options(&engine_options)?
+                        .playing(ChessColor::Black, strength)?;
+                    series = series.with_names("Me".to_string(), engine.name.clone());
+                    app.engines.push(engine);
+                }
+                [first, second] => {
+                    app.engines =
+                        start_match_engines([first, second], movetime, &engine_options)?.into();
+                }
+                _ => unreachable!("clap takes at most two engines"),
+            }
+            if let [first, second] = app.engines.as_slice() {
+                series = name_match_engines(series, [first, second]);
+            }
+            if let Some(control) = tc {
+                let mut clock = clock::Clock::new(control, cli.increment_type);
+                clock.start(ChessColor::White);
+                app.clock = Some(clock);
+                app.clock_bell = cli.clock_bell;
+            }
+            app.series = Some(series);
+            app.message = format!("{} Game 1 of the match.", app.start_message());
+            app
+        }
+        (
+            None,
+            Commands::Fen {
+                command: Some(command),
+                ..
+            },
+        ) => return run_fen_command(command),
+        (
+            None,
+            Commands::Fen {
+                command: None,
+                fen,
+                side,
+                opponent,
+            },
+        ) => {
+            let Some(fen) = fen else {
+                eprintln!("error: give a FEN to play, or a subcommand (see tess fen --help)");
+                std::process::exit(2);
+            };
+            let mut app = App::new_standard(position_or_exit(&fen), display);
+            app.flipped = side == Side::Black;
+            app.engines
+                .extend(start_opponent(opponent, side, &config, &engine_options)?);
+            app
+        }
+        (
+            None,
+            Commands::FenAt {
+                filename,
+                ply,
+                game,
+                ..
+            },
+        ) => return print_fens_at(&filename, game.max(1) - 1, ply),
+        (None, Commands::Tree { command }) => {
+            let look = explorer::Look {
+                display,
+                pieces,
+                wide_symbols,
+                palette: theme::Palette::for_background(background),
+            };
+            return run_tree_command(command, &look);
+        }
+        (
+            None,
+            Commands::Analyze {
+                filename,
+                game,
+                engine,
+                movetime,
+                jobs,
+            },
+        ) => {
+            let jobs = jobs
+                .or_else(|| std::thread::available_parallelism().ok().map(usize::from))
+                .unwrap_or(1);
+            let movetime = Duration::from_millis(movetime.max(1));
+            return run_analyze_command(
+                &filename,
+                game.max(1) - 1,
+                &engine,
+                &engine_options,
+                movetime,
+                jobs,
+            );
+        }
+        (
+            None,
+            Commands::Generate {
+                files,
+                engine,
+                movetime,
+                max_mate,
+                mates_only,
+                output,
+            },
+        ) => {
+            let limits = generate::Limits {
+                movetime: Duration::from_millis(movetime.max(1)),
+                max_mate: max_mate.max(1),
+                tactics: !mates_only,
+            };
+            return run_generate_command(
+                &files,
+                &engine,
+                &engine_options,
+                &limits,
+                output.as_deref(),
+            );
+        }
+        (
+            None,
+            Commands::Blunders {
+                player,
+                files,
+                engine,
+                movetime,
+                jobs,
+            },
+        ) => {
+            let scan = blunders::Scan {
+                engine: &engine,
+                options: &engine_options,
+                limits: generate::Limits {
+                    movetime: Duration::from_millis(movetime.max(1)),
+                    max_mate: 5,
+                    tactics: true,
+                },
+                jobs: jobs
+                    .or_else(|| std::thread::available_parallelism().ok().map(usize::from))
+                    .unwrap_or(1),
+            };
+            return run_blunders_command(&scan, &player, &files);
+        }
+        (
+            None,
+            Commands::Scout {
+                name,
+                files,
+                engine,
+                movetime,
+                jobs,
+            },
+        ) => {
+            let analysis = engine.as_deref().map(|engine| scouting::Analysis {
+                engine,
+                options: &engine_options,
+                movetime: Duration::from_millis(movetime.max(1)),
+                jobs: jobs
+                    .or_else(|| std::thread::available_parallelism().ok().map(usize::from))
+                    .unwrap_or(1),
+            });
+            let report = scouting::scout(&name, &files, analysis.as_ref(), &review::show_progress)?;
+            for line in report.lines() {
+                println!("{line}");
+            }
+            return Ok(());
+        }
+        (
+            None,
+            Commands::Openings {
+                name,
+                files,
+                engine,
+                movetime,
+                jobs,
+                print,
+            },
+        ) => {
+            let files = if files.is_empty() {
+                saved_games()?
+            } else {
+                files
+            };
+            let analysis = engine.as_deref().map(|engine| scouting::Analysis {
+                engine,
+                options: &engine_options,
+                movetime: Duration::from_millis(movetime.max(1)),
+                jobs: jobs
+                    .or_else(|| std::thread::available_parallelism().ok().map(usize::from))
+                    .unwrap_or(1),
+            });
+            let report = performance::report(
+                &name,
+                &files,
+                &eco::Eco::load(),
+                analysis.as_ref(),
+                &review::show_progress,
+            )?;
+            if print {
+                for line in report.lines() {
+                    println!("{line}");
+                }
+            } else {
+                report.show()?;
+            }
+            return Ok(());
+        }
+        (None, Commands::Activity { weeks, export }) => {
+            let days = activity::load();
+            print!("{}", activity::calendar(&days, weeks.clamp(1, 52)));
+            if let Some(path) = export {
+                activity::export_csv(&days, &path)?;
+                println!("Written to {}.", path.display());
+            }
+            return Ok(());
+        }
+        (None, Commands::Archive { dir }) => {
+            let summary = history::archive(&dir)?;
+            println!(
+                "{} games, {} puzzle attempts and {} training records written to {}.",
+                summary.games,
+                summary.puzzles,
+                summary.records,
+                dir.display()
+            );
+            return Ok(());
+        }
+        (None, Commands::Daemon) => return run_daemon(reminder),
+        (None, Commands::Setup) => return run_setup(config.clone()),
+        (None, Commands::Replay { .. }) => return Err("the recorded session is a replay".into()),
+        (None, Commands::Render { fen, flip, plain }) => {
+            return render_board(
+                fen.as_deref(),
+                display,
+                (pieces, wide_symbols),
+                background,
+                flip,
+                plain,
+            )
+        }
+    };
+
+    if app.message.is_empty() {
+        app.message = app.start_message();
+    }
+    if let Some(err) = lichess_error {
+        app.message
+            .push_str(&format!(" Lichess preferences not loaded: {err}"));
+    }
+    let streak = activity::streak(&activity::load(), activity::Day::default());
+    if streak.days > 0 && !streak.today {
+        app.message.push(' ');
+        app.message.push_str(&streak.describe());
+    }
+    if let Some(path) = cli
+        .analysis_engine
+        .as_ref()
+        .or(config.analysis_engine.as_ref())
+    {
+        let mut analyser = engine::Engine::start(path)?.with_options(&engine_options)?;
+        if let Some(dir) = session::state_dir().filter(|_| config.persist_analysis) {
+            analyser = analyser.with_saved_cache(&dir);
+        }
+        app.analyser = Some(analyser);
+    }
+    if let Some(path) = cli.syzygy_path.as_ref().or(config.syzygy_path.as_ref()) {
+        let tablebase = tablebase::Tablebase::open(path)?;
+        let mut blind = Vec::new();
+        let judge = app.endgame.as_mut().and_then(endgames::Trainer::engine_mut);
+        for engine in app
+            .engines
+            .iter_mut()
+            .chain(app.analyser.as_mut())
+            .chain(judge)
+        {
+            if !tablebase.setup(engine)? {
+                blind.push(engine.name.clone());
+            }
+        }
+        if !blind.is_empty() {
+            app.message.push_str(&format!(
+                " No SyzygyPath option in {}, the tablebases are not used there.",
+                blind.join(", ")
+            ));
+        }
+        app.tablebase = Some(tablebase);
+    } else if !config.no_tablebase_lookup {
+        app.online_tablebase = Some(tablebase::Online::default());
+    }
+    if let Some(trainer) = app.endgame.as_mut() {
+        trainer.look_at_start()?;
+    }
+
+    // Setup terminal
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    crossterm::execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = match replay_size {
+        // a replay draws at the recorded size, whatever the size of this terminal
+        Some((columns, rows)) => Terminal::with_options(
+            backend,
+            TerminalOptions {
+                viewport: Viewport::Fixed(Rect::new(0, 0, columns, rows)),
+            },
+        )?,
+        None => Terminal::new(backend)?,
+    };
+    app.low_bandwidth = cli.low_bandwidth || session_log::answer(|| Ok(slow_ssh_link()))?;
+    app.notation = cli
+        .notation
+        .or(lichess.notation)
+        .unwrap_or(notation::Notation::Letters)
+        .effective();
+    app.palette = theme::Palette::for_background(background);
+    app.pieces = pieces;
+    app.wide_symbols = wide_symbols;
+    app.set_layout(config.layout);
+    app.hint_penalties = config.hint_penalties;
+    app.game_hint = config.game_hint;
+    app.opening_tree = Rc::new(opening_tree::OpeningTree::load());
+    app.eco = Rc::new(eco::Eco::load());
+    // the ladder is blitz unless told otherwise; a simul's boards have
+    // their clocks already
+    let clock = cli.clock.or(app.ladder.as_ref().map(|_| ladder::BLITZ));
+    if let (Some(control), AppMode::StandardGame, None) = (clock, &app.mode, &simul_boards) {
+        let mut clock = clock::Clock::new(control, cli.increment_type);
+        clock.start(app.board.turn());
+        app.clock = Some(clock);
+        app.clock_bell = cli.clock_bell;
+    }
+    if let (true, AppMode::StandardGame, [], None) =
+        (cli.rotate, &app.mode, app.engines.as_slice(), &app.online)
+    {
+        app.rotate = true;
+        app.flipped = app.board.turn() == ChessColor::Black;
+    }
+    app.input_language = cli
+        .input_language
+        .or(config.input_language)
+        .or(lichess.input_language)
+        .unwrap_or(notation::Language::En);
+    app.quick_input = cli.quick_input || config.quick_input;
+    if cli.beginner {
+        app.beginner = Some(beginner::Assist::default());
+    }
+    // the engine opens when it has white
+    app.engine_turn();
+
+    // Restore the terminal before the panic message is printed
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = disable_raw_mode();
+        let _ = crossterm::execute!(io::stdout(), DisableMouseCapture, LeaveAlternateScreen);
+        default_hook(info);
+    }));
+
+    // Run the main loop
+    let mut autosave = session::Autosave::new(cli.autosave_moves, cli.autosave_seconds);
+    let mut status = status::StatusReporter::new(cli.tmux_status);
+    let mut tabs = tabs::Tabs::new(app);
+    if let Some(boards) = simul_boards {
+        tabs.start_simul(boards);
+    }
+    let mut lock = lock::Lock::new(cli.lock, config.lock_password.clone());
+    let mut activity = activity::Tracker::new().with_reminder(reminder);
+    let res = run_app(
+        &mut terminal,
+        &mut tabs,
+        &mut lock,
+        &mut autosave,
+        &mut status,
+        &mut events,
+        &mut activity,
+    );
+    drop(status);
+    drop(activity);
+
+    // Cleanup
+    disable_raw_mode()?;
+    crossterm::execute!(
+        terminal.backend_mut(),
+        DisableMouseCapture,
+        LeaveAlternateScreen
+    )?;
+    terminal.show_cursor()?;
+    if let Some(line) = session_log::finish() {
+        println!("{line}");
+    }
+
+    match res {
+        // only a clean exit discards the recovery file
+        Ok(()) => session::clear_recovery(),
+        Err(err) => eprintln!("Error: {:?}", err),
+    }
+
+    Ok(())
+}
+
+// The engine or configured opponent of `args`, playing against `side`;
+// None without either
+fn start_opponent(
+    args: OpponentArgs,
+    side: Side,
+    config: &config::Config,
+    engine_options: &[(String, String)],
+) -> anyhow::Result<Option<engine::Engine>> {
+    let color = match side {
+        Side::White => ChessColor::Black,
+        Side::Black => ChessColor::White,
+    };
+    let movetime = args.movetime.map(|ms| Duration::from_millis(ms.max(1)));
+    if let Some(name) = args.opponent {
+        let registry = opponents::Registry::new(&config.opponents);
+        return Ok(Some(registry.start(
+            &name,
+            color,
+            engine_options,
+            movetime,
+        )?));
+    }
+    let Some(path) = args.engine else {
+        return Ok(None);
+    };
+    let strength = engine::Strength {
+        elo: args.engine_elo,
+        skill: args.engine_skill,
+        movetime,
+        nodes: None,
+    };
+    Ok(Some(
+        engine::Engine::start(&path)?
+            .with_options(engine_options)?
+            .playing(color, strength)?,
+    ))
+}
+
+// The two engines of a match, the first with white in the first game
+fn start_match_engines(
+    paths: [&std::path::PathBuf; 2],
+    movetime: Option<Duration>,
+    engine_options: &[(String, String)],
+) -> anyhow::Result<[engine::Engine; 2]> {
+    let start = |path, color| {
+        let strength = engine::Strength {
+            elo: None,
+            skill: None,
+            movetime,
+            nodes: None,
+        };
+        engine::Engine::start(path)?
+            .with_options(engine_options)?
+            .playing(color, strength)
+    };
+    Ok([
+        start(paths[0], ChessColor::White)?,
+        start(paths[1], ChessColor::Black)?,
+    ])
+}
+
+// Name the players of a match between engines, numbered when they are the same
+fn name_match_engines(series: series::Series, engines: [&engine::Engine; 2]) -> series::Series {
+    let [first, second] = engines.map(|engine| &engine.name);
+    if first == second {
+        series.with_names(format!("{first} (1)"), format!("{second} (2)"))
+    } else {
+        series.with_names(first.clone(), second.clone())
+    }
+}
+
+// `tess match --headless`: the engines play the match through without the
+// board, each game a line of output, then the games go to `pgn` and the
+// results table is printed
+fn run_headless_match(
+    series: series::Series,
+    mut engines: [engine::Engine; 2],
+    clock: Option<clock::Clock>,
+    pgn: &std::path::Path,
+) -> anyhow::Result<()> {
+    let mut series = name_match_engines(series, [&engines[0], &engines[1]]);
+    while !series.is_over() {
+        let start = series.opening().unwrap_or_default();
+        let (moves, over) = play_headless_game(&mut engines, &start, clock.clone())?;
+        series.record(&start, &moves, over.outcome);
+        let [white, black] = series.white_and_black();
+        println!(
+            "Game {}: {white} - {black} {}, {}. Match {}.",
+            series.games(),
+            over.outcome,
+            over.describe(),
+            series.score()
+        );
+        series.swap_colors();
+        for engine in &mut engines {
+            engine.color = !engine.color;
+            engine.new_game()?;
+        }
+    }
+    std::fs::write(pgn, series.report_pgn())
+        .map_err(|err| anyhow::anyhow!("cannot write {}: {err}", pgn.display()))?;
+    println!();
+    for line in make_results_lines(&series) {
+        println!(
+            "{}",
+            line.spans
+                .iter()
+                .map(|span| &*span.content)
+                .collect::<String>()
+        );
+    }
+    println!("Games saved to {}.", pgn.display());
+    Ok(())
+}
+
+// One game between the engines from `start`, draws that could be claimed
+// ending it at once
+fn play_headless_game(
+    engines: &mut [engine::Engine; 2],
+    start: &Chess,
+    mut clock: Option<clock::Clock>,
+) -> anyhow::Result<(Vec<Move>, GameOver)> {
+    let mut board = start.clone();
+    let mut history = Vec::new();
+    if let Some(clock) = &mut clock {
+        clock.start(board.turn());
+    }
+    loop {
+        if let Some(over) =
+            GameOver::of_position(&board).or_else(|| GameOver::claimable(start, &history, &board))
+        {
+            return Ok((history, over));
+        }
+        let turn = board.turn();
+        let Some(engine) = engines.iter_mut().find(|engine| engine.color == turn) else {
+            anyhow::bail!("no engine plays {turn}");
+        };
+        let reply = engine.search_move(start, &history, clock.as_ref())?;
+        if let Some(color) = clock.as_ref().and_then(|clock| clock.flagged()) {
+            return Ok((history, GameOver::on_time(&board, color)));
+        }
+        let Some(mv) = uci::parse(&board, &reply) else {
+            anyhow::bail!("{} answered with the illegal move '{reply}'", engine.name);
+        };
+        if let Some(clock) = &mut clock {
+            clock.press(turn);
+        }
+        board.play_unchecked(&mv);
+        history.push(mv);
+    }
+}
+
+// The position of a FEN given on the command line, exits explaining what is wrong otherwise
+fn position_or_exit(fen: &str) -> Chess {
+    let position = Fen::from_ascii(fen.as_bytes())
+        .ok()
+        .and_then(|parsed| parsed.into_position(CastlingMode::Standard).ok());
+    position.unwrap_or_else(|| {
+        for error in fen::check_fen(fen).errors {
+            eprintln!("error: {error}");
+        }
+        std::process::exit(1);
+    })
+}
+
+// ----------------------------------------------
+// `render`: one board to stdout, for shell scripts and other TUIs
+// ----------------------------------------------
+fn render_board(
+    fen: Option<&str>,
+    display: DisplayMode,
+    (pieces, wide_symbols): (PieceSet, bool),
+    background: theme::Background,
+    flip: bool,
+    plain: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let board = fen.map_or_else(Chess::default, position_or_exit);
+    let mut app = App::new_standard(board, display);
+    app.flipped = flip;
+    app.palette = theme::Palette::for_background(background);
+    app.pieces = pieces;
+    app.wide_symbols = wide_symbols;
+    // the plain look is the one used for slow links
+    app.low_bandwidth = plain;
+    print!("{}", board_as_text(&app, !plain)?);
+    io::stdout().flush()?;
+    Ok(())
+}
+
+// The board as drawn in the TUI, with ANSI colors or without
+fn board_as_text(app: &App, ansi: bool) -> io::Result<String> {
+    let mut out = Vec::new();
+    for line in board_widget(app).printed_lines() {
+        for span in line.spans {
+            if !ansi {
+                write!(out, "{}", span.content)?;
+                continue;
+            }
+            if let Some(fg) = span.style.fg {
+                crossterm::queue!(out, SetForegroundColor(fg.into()))?;
+            }
+            if let Some(bg) = span.style.bg {
+                crossterm::queue!(out, SetBackgroundColor(bg.into()))?;
+            }
+            crossterm::queue!(out, Print(&span.content), ResetColor)?;
+        }
+        writeln!(out)?;
+    }
+    Ok(String::from_utf8_lossy(&out).into_owned())
+}
+
+// A standard game holding the moves and comments of game `index` in a PGN file
+fn app_from_pgn(path: &str, index: usize, display: DisplayMode) -> anyhow::Result<App> {
+    let game = load_pgn_game(path, index)?;
+    let mut app = App::new_standard(game.start, display);
+    for mv in &game.moves {
+        app.play(mv)?;
+    }
+    app.comments = game.comments;
+    app.times = game.times;
+    app.evals = game.evals;
+    app.annotated = Some(pgn_tree::load(std::path::Path::new(path), index)?);
+    Ok(app)
+}
+
+// ----------------------------------------------
+// Crash recovery
+// ----------------------------------------------
+fn confirm_restore(saved: &session::SavedSession) -> anyhow::Result<bool> {
+    print!(
+        "tess did not exit cleanly last time ({}). Restore that session? [Y/n] ",
+        saved.summary()
+    );
+    io::Write::flush(&mut io::stdout())?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(!answer.trim().to_lowercase().starts_with('n'))
+}
+
+fn app_from_session(saved: session::SavedSession, display: &DisplayMode) -> anyhow::Result<App> {
+    let (start, history) = saved.replay()?;
+    let mut app = match saved.mode {
+        session::SavedMode::Standard => App::new_standard(start, *display),
+        session::SavedMode::Board => App {
+            mode: AppMode::Board,
+            ..App::new_standard(start, *display)
+        },
+        session::SavedMode::Puzzle {
+            lichess,
+            solution_index,
+            completed,
+        } => {
+            let (_, solution) = puzzle_from_lichess(&lichess)?;
+            let mut app = App::new_puzzle(start, solution, *display, lichess);
+            if let AppMode::Puzzle {
+                solution_index: index,
+                completed: done,
+                ..
+            } = &mut app.mode
+            {
+                *index = solution_index;
+                *done = completed;
+            }
+            app
+        }
+    };
+    for mv in &history {
+        app.play(mv)?;
+    }
+    app.message = format!("Session restored. {} to move.", app.board.turn());
+    Ok(app)
+}
+
+// ----------------------------------------------
+// Setup
+// ----------------------------------------------
+// `tess setup`: the first-run questions again, the answers replacing those in the config file
+fn run_setup(config: config::Config) -> Result<(), Box<dyn std::error::Error>> {
+    match setup::run(config)? {
+        Some(config) => {
+            config::save(&config)?;
+            println!("Setup saved.");
+        }
+        None => println!("Setup left, the config file is as it was."),
+    }
+    Ok(())
+}
+
+// ----------------------------------------------
+// Non-interactive FEN commands
+// ----------------------------------------------
+fn run_fen_command(command: FenCommands) -> Result<(), Box<dyn std::error::Error>> {
+    match command {
+        FenCommands::Check { fen } => {
+            let report = fen::check_fen(&fen);
+            for error in &report.errors {
+                eprintln!("error: {error}");
+            }
+            for note in &report.notes {
+                eprintln!("note: {note}");
+            }
+            match (&report.normalized, report.is_valid()) {
+                (Some(normalized), true) => println!("{normalized}"),
+                (Some(normalized), false) => {
+                    println!("normalized after discarding invalid parts: {normalized}")
+                }
+                (None, _) => {}
+            }
+            if !report.is_valid() {
+                std::process::exit(1);
+            }
+        }
+    }
+    Ok(())
+}
+
+// The FEN after `ply` plies of game `index`, or of every position without a ply
+fn print_fens_at(
+    path: &str,
+    index: usize,
+    ply: Option<usize>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let game = read_pgn_game(path, index, LastPosition::after(ply))?;
+    let fen = |pos: &Chess| Fen::from_position(pos.clone(), EnPassantMode::Legal);
+    match ply {
+        Some(ply) if game.moves.len() < ply => {
+            return Err(format!(
+                "game {} of {path} has only {} plies",
+                index + 1,
+                game.moves.len()
+            )
+            .into())
+        }
+        Some(_) => println!("{}", fen(&game.position)),
+        None => {
+            let mut pos = game.start;
+            println!("{}", fen(&pos));
+            for mv in &game.moves {
+                pos.play_unchecked(mv);
+                println!("{}", fen(&pos));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn run_tree_command(
+    command: TreeCommands,
+    look: &explorer::Look,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut tree = opening_tree::OpeningTree::load();
+    match command {
+        TreeCommands::Repertoire { color, files } => {
+            let color = match color {
+                Side::White => ChessColor::White,
+                Side::Black => ChessColor::Black,
+            };
+            for path in files {
+                let added = tree.import_repertoire(&path, color)?;
+                println!("{}: {added} repertoire moves added", path.display());
+            }
+        }
+        TreeCommands::Games { player, files } => {
+            for path in files {
+                let games = tree.import_games(&path, &player)?;
+                println!("{}: {games} games of {player} added", path.display());
+            }
+        }
+        TreeCommands::Database { files } => {
+            for path in files {
+                let games = tree.import_database(&path)?;
+                println!("{}: {games} games added", path.display());
+            }
+        }
+        TreeCommands::Explore => {
+            explorer::run(&tree, &eco::Eco::load(), look)?;
+            return Ok(());
+        }
+        TreeCommands::Clear => tree = Default::default(),
+    }
+    tree.save()?;
+    Ok(())
+}
+
+fn run_positions_command(command: PositionCommands) -> Result<(), Box<dyn std::error::Error>> {
+    match command {
+        PositionCommands::Add { name, fen, tags } => {
+            let pos = position_or_exit(&fen);
+            let replaced = positions::save(&name, &pos, tags)?;
+            println!("{} {name}.", if replaced { "Replaced" } else { "Saved" });
+        }
+        PositionCommands::List { search } => {
+            let saved = positions::load()?;
+            let shown = match &search {
+                Some(search) => positions::find(&saved, search),
+                None => saved,
+            };
+            if shown.is_empty() {
+                println!(
+                    "No saved positions{}.",
+                    if search.is_some() { " found" } else { "" }
+                );
+            }
+            for saved in shown {
+                println!("{}\n    {}", saved.describe(), saved.fen);
+            }
+        }
+        PositionCommands::Remove { name } => {
+            if !positions::remove(&name)? {
+                return Err(format!("no saved position is named '{name}'").into());
+            }
+            println!("Removed {name}.");
+        }
+        PositionCommands::Open { .. } | PositionCommands::Drill { .. } => unreachable!("not batch"),
+    }
+    Ok(())
+}
+
+// A game from a saved position, the board turned for the side to move
+fn position_app(saved: &positions::Saved, display: DisplayMode) -> anyhow::Result<App> {
+    let pos = saved.position()?;
+    let mut app = App::new_standard(pos, display);
+    app.flipped = app.board.turn() == ChessColor::Black;
+    app.message = format!("{}, {} to move.", saved.name, app.board.turn());
+    Ok(app)
+}
+
+fn run_analyze_command(
+    path: &str,
+    index: usize,
+    engine: &std::path::Path,
+    options: &[(String, String)],
+    movetime: Duration,
+    jobs: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let game = load_pgn_game(path, index)?;
+    let started = Instant::now();
+    let review = review::analyse(
+        &game.start,
+        &game.moves,
+        engine,
+        options,
+        movetime,
+        jobs,
+        &review::show_progress,
+    )?;
+    print!("{}", review.report());
+    println!(
+        "{} positions analysed in {:.1}s by {} engine{}.",
+        review.positions.len(),
+        started.elapsed().as_secs_f64(),
+        review.engines,
+        if review.engines == 1 { "" } else { "s" }
+    );
+    let scores = review.scores();
+    let errors = review.error_counts();
+    println!();
+    for (color, name) in [
+        (ChessColor::White, &game.white),
+        (ChessColor::Black, &game.black),
+    ] {
+        let side = color.fold_wb("White", "Black");
+        let side = if name.is_empty() {
+            side.to_string()
+        } else {
+            format!("{side} ({name})")
+        };
+        let [inaccuracies, mistakes, blunders] = *errors.get(color);
+        match scores.get(color) {
+            Some(score) => println!(
+                "{side}: accuracy {:.1}%, average centipawn loss {:.0}, {}, {}, {}",
+                score.accuracy,
+                score.acpl,
+                review::Judgement::Inaccuracy.count(inaccuracies),
+                review::Judgement::Mistake.count(mistakes),
+                review::Judgement::Blunder.count(blunders),
+            ),
+            None => println!("{side}: no evaluated moves"),
+        }
+    }
+    let record = review::ReviewRecord {
+        analysed_at: stats::now(),
+        file: path.to_string(),
+        game: index + 1,
+        white: game.white,
+        black: game.black,
+        white_scores: scores.white,
+        black_scores: scores.black,
+    };
+    // best effort, like the training statistics
+    if let Err(err) = review::append(&record) {
+        eprintln!("warning: review not saved: {err}");
+    }
+    Ok(())
+}
+
+fn run_generate_command(
+    files: &[std::path::PathBuf],
+    engine: &std::path::Path,
+    options: &[(String, String)],
+    limits: &generate::Limits,
+    output: Option<&std::path::Path>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut positions = Vec::new();
+    for path in files {
+        positions
+            .extend(generate::positions(path).map_err(|err| format!("{}: {err}", path.display()))?);
+    }
+    let total = positions.len();
+    let mut engine = engine::Engine::start(engine)?.with_options(options)?;
+    let started = Instant::now();
+    let found = generate::generate(&mut engine, positions, limits, &review::show_progress)?;
+    match output {
+        Some(path) => {
+            let mut file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)?;
+            generate::write(&mut file, &found)?;
+        }
+        None => generate::write(&mut io::stdout(), &found)?,
+    }
+    let mates = found
+        .iter()
+        .filter(|found| matches!(found.kind, generate::Kind::Mate(_)))
+        .count();
+    let count = |n: usize, what: &str| format!("{n} {what}{}", if n == 1 { "" } else { "s" });
+    eprintln!(
+        "{} ({}, {}) from {total} positions in {:.1}s.",
+        count(found.len(), "puzzle"),
+        count(mates, "mate"),
+        count(found.len() - mates, "tactic"),
+        started.elapsed().as_secs_f64()
+    );
+    Ok(())
+}
+
+fn run_blunders_command(
+    scan: &blunders::Scan,
+    player: &str,
+    files: &[std::path::PathBuf],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let started = Instant::now();
+    let mut found = Vec::new();
+    for path in files {
+        found.extend(
+            blunders::scan(scan, path, player, &review::show_progress)
+                .map_err(|err| format!("{}: {err}", path.display()))?,
+        );
+    }
+    for blunder in &found {
+        println!(
+            "{}: {}, {}",
+            blunder.source,
+            blunder.blunder,
+            blunder.phase.name()
+        );
+    }
+    let added = blunders::save(&found)?;
+    let count = |n: usize| format!("{n} blunder{}", if n == 1 { "" } else { "s" });
+    println!(
+        "{} with a clear refutation in {:.1}s, {added} new, solve them with `tess puzzle blunders`.",
+        count(found.len()),
+        started.elapsed().as_secs_f64()
+    );
+    Ok(())
+}
+
+// ----------------------------------------------
+// The core event loop
+// ----------------------------------------------
+fn run_app<B: Backend>(
+    terminal: &mut Terminal<B>,
+    tabs: &mut tabs::Tabs,
+    lock: &mut lock::Lock,
+    autosave: &mut session::Autosave,
+    status: &mut status::StatusReporter,
+    events: &mut events::Stream,
+    activity: &mut activity::Tracker,
+) -> anyhow::Result<()> {
+    let mut last_tick = Instant::now();
+    let tick_rate = Duration::from_millis(250);
+    let mut needs_redraw = true;
+
+    loop {
+        // Draw, in low-bandwidth mode only when something happened
+        if needs_redraw || !tabs.active().low_bandwidth {
+            let frame = terminal.draw(|f| {
+                let area = tabs.draw_bar(f);
+                ui(f, tabs.active(), area);
+                lock.draw_badge(f, area);
+            })?;
+            session_log::screen(frame.buffer);
+            needs_redraw = false;
+        }
+
+        // Handle input, non-blocking; a replay needs a finer step than the tick
+        let mut timeout = tick_rate
+            .checked_sub(last_tick.elapsed())
+            .unwrap_or_else(|| Duration::from_secs(0));
+        if tabs.active().replay.is_some() {
+            timeout = timeout.min(Duration::from_millis(40));
+        }
+        if let Some(heatmap) = tabs.active().heatmap.as_ref().filter(|h| h.playing) {
+            timeout = timeout.min(HEAT_STEP.saturating_sub(session_log::elapsed(heatmap.stepped)));
+        }
+        if let Some(due) = tabs.active().presentation.as_ref().and_then(|p| p.due_in()) {
+            timeout = timeout.min(due);
+        }
+
+        if session_log::poll(timeout)? {
+            let event = session_log::read()?;
+            // pointer motion alone changes nothing on screen
+            needs_redraw |= !matches!(
+                event,
+                Event::Mouse(MouseEvent {
+                    kind: MouseEventKind::Moved,
+                    ..
+                })
+            );
+            if let Event::Resize(columns, rows) = event {
+                // lay out for the new size at once, not on the next draw
+                if session_log::replaying() {
+                    terminal.resize(Rect::new(0, 0, columns, rows))?;
+                } else {
+                    terminal.autoresize()?;
+                }
+            } else if tabs.active().presentation.is_some() {
+                // a presentation takes the browsing keys and nothing else
+                if let Event::Key(key) = event {
+                    if !presentation::handle_key(tabs.active_mut(), key) {
+                        return Ok(());
+                    }
+                }
+            } else if let Event::Mouse(mouse) = event {
+                let pointed = tabs.active().beginner.as_ref().map(|b| b.pointed);
+                handle_mouse_event(tabs.active_mut(), mouse);
+                // the beginner help follows the pointer over the board
+                needs_redraw |= tabs.active().beginner.as_ref().map(|b| b.pointed) != pointed;
+            } else if let Event::Key(key) = event {
+                activity.input();
+                // keys a spectator lock holds back go nowhere
+                if lock.allows(tabs.active_mut(), key)
+                    && !tabs.handle_key(key)?
+                    && !handle_recorded_key(tabs.active_mut(), key)?
+                {
+                    // false => exit signal
+                    return Ok(());
+                }
+            }
+        }
+
+        if let Some(summary) = session_log::replay_summary() {
+            tabs.active_mut().message = summary;
+            needs_redraw = true;
+        }
+
+        // only the tab on screen is autosaved, and never an analysis
+        if !tabs.active().analysing {
+            autosave.tick(tabs.active());
+        }
+        status.update(tabs.active());
+        activity.tick(tabs.active());
+
+        // engine replies, in every tab, and the analysis of the one on screen
+        for app in tabs.apps_mut() {
+            needs_redraw |= app.poll_engine();
+            needs_redraw |= app.poll_online();
+        }
+        needs_redraw |= tabs.follow_simul();
+        needs_redraw |= tabs.active_mut().poll_analysis();
+        needs_redraw |= tabs.active_mut().poll_online_tablebase();
+        for app in tabs.apps_mut() {
+            needs_redraw |= app.poll_review();
+        }
+        needs_redraw |= advance_replay(tabs.active_mut(), false)?;
+        needs_redraw |= advance_heatmap(tabs.active_mut());
+        needs_redraw |= presentation::advance(tabs.active_mut());
+        needs_redraw |= beginner::update(tabs.active_mut());
+        for app in tabs.apps_mut() {
+            events.update(app);
+        }
+
+        // on_tick if needed
+        if last_tick.elapsed() >= tick_rate {
+            last_tick = Instant::now();
+            if let Some(nudge) = activity.nudge() {
+                tabs.active_mut().message = nudge;
+                needs_redraw = true;
+            }
+            // clocks keep running in the background tabs
+            for app in tabs.apps_mut() {
+                app.on_tick();
+            }
+            let app = tabs.active();
+            // a running clock has to be redrawn even without input
+            needs_redraw |= app.clock.as_ref().is_some_and(|c| c.is_running());
+        }
+    }
+}
+
+// `tess daemon`: check every minute whether the reminder is due, and tell the
+// desktop with notify-send, or this terminal if there is none
+fn run_daemon(reminder: Option<u64>) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(time) = reminder else {
+        return Err("set `reminder` (e.g. \"18:30\", UTC) in the config file first".into());
+    };
+    println!(
+        "Reminding at {:02}:{:02} UTC on days without training, Ctrl-C stops.",
+        time / 3600,
+        time % 3600 / 60
+    );
+    let mut reminder = activity::Reminder::new(Some(time));
+    loop {
+        if let Some(nudge) = reminder.due(activity::Day::default()) {
+            let notified = std::process::Command::new("notify-send")
+                .args(["tess", &nudge])
+                .status()
+                .is_ok_and(|status| status.success());
+            if !notified {
+                println!("\x07{nudge}");
+            }
+        }
+        std::thread::sleep(Duration::from_secs(60));
+    }
+}
+
+// Probe the terminal round trip when connected over SSH, slow links get low-bandwidth mode
+fn slow_ssh_link() -> bool {
+    if std::env::var_os("SSH_CONNECTION").is_none() {
+        return false;
+    }
+    let started = Instant::now();
+    match crossterm::cursor::position() {
+        Ok(_) => started.elapsed() > Duration::from_millis(150),
+        Err(_) => false,
+    }
+}
+
+// ----------------------------------------------
+// Draw the UI with ratatui
+// ----------------------------------------------
+fn ui(f: &mut ratatui::Frame, app: &App, area: Rect) {
+    if let Some(presentation) = &app.presentation {
+        presentation::draw(f, app, presentation, area);
+        return;
+    }
+    let (width, height) = app.min_size();
+    if area.width < width || area.height < height {
+        draw_size_guard(f, app, area);
+        return;
+    }
+
+    // Layout: top for board, bottom for user input / messages
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length((8 * app.cell_height + 4) as u16), // board area + labels
+            Constraint::Min(3),                                   // input area
+        ])
+        .split(area);
+
+    // Board on the left, info pane on the right
+    let top = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Length((8 * app.cell_width + 4) as u16), // board + labels
+            Constraint::Length(app.layout.moves_width),          // move list
+            Constraint::Min(20),                                 // info pane
+        ])
+        .split(chunks[0]);
+
+    // 1) Render the chessboard
+    let board = board_widget(app);
+    let board_rows = board.size().1;
+    app.board_area.set(top[0]);
+    f.render_widget(board, top[0]);
+
+    // the evaluation over the game in the rows left under the board, lined up with
+    // the squares
+    let graph_area = Rect {
+        x: top[0].x + 2,
+        y: top[0].y + board_rows,
+        width: (8 * app.cell_width as u16).min(top[0].width.saturating_sub(2)),
+        height: top[0].height.saturating_sub(board_rows),
+    };
+    let graph = graph::eval_graph(
+        &app.eval_curve(),
+        graph_area.width as usize,
+        app.shown_ply(),
+        app.palette.label,
+        Color::Yellow,
+    );
+    if graph_area.height as usize >= graph.len() {
+        f.render_widget(Paragraph::new(graph), graph_area);
+    }
+
+    // 2) Moves played so far, scrolled to the move on the board unless scrolled by hand
+    let MoveList {
+        lines: move_lines,
+        focus,
+        ..
+    } = make_moves_text(app);
+    let visible = (top[1].height.saturating_sub(2) as usize).max(1);
+    let max_scroll = move_lines.len().saturating_sub(visible);
+    let scroll = app
+        .moves_scroll
+        .unwrap_or(focus.saturating_sub(visible - 1))
+        .min(max_scroll);
+    app.moves_layout.set((scroll, visible));
+    app.moves_area.set(top[1]);
+    let moves_paragraph = Paragraph::new(move_lines)
+        .block(Block::bordered().title(if app.analysing { "Analysis" } else { "Moves" }))
+        .scroll((scroll as u16, 0));
+    f.render_widget(moves_paragraph, top[1]);
+
+    // 3) Static assessment of the current position, below the results of a match, the
+    // engines' view if they play each other and the analysis if one analyses
+    let mut info_area = top[2];
+    if let Some(series) = app.series.as_ref().filter(|s| s.length.is_some()) {
+        let results = make_results_lines(series);
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(results.len() as u16 + 2),
+                Constraint::Min(3),
+            ])
+            .split(info_area);
+        let results_paragraph = Paragraph::new(results).block(Block::bordered().title("Match"));
+        f.render_widget(results_paragraph, rows[0]);
+        info_area = rows[1];
+    }
+    if app.engines.len() > 1 {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(4), Constraint::Min(3)])
+            .split(info_area);
+        let players_paragraph =
+            Paragraph::new(make_players_lines(app)).block(Block::bordered().title("Engines"));
+        f.render_widget(players_paragraph, rows[0]);
+        info_area = rows[1];
+    }
+    if let Some(game) = &app.online {
+        let lines = make_online_lines(game, app.history.len());
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(lines.len() as u16 + 2),
+                Constraint::Min(3),
+            ])
+            .split(info_area);
+        let online_paragraph = Paragraph::new(lines)
+            .wrap(Wrap { trim: true })
+            .block(Block::bordered().title("lichess"));
+        f.render_widget(online_paragraph, rows[0]);
+        info_area = rows[1];
+    }
+    if let Some(repertoire) = &app.repertoire {
+        let lines: Vec<Line> = repertoire.lines().into_iter().map(Line::raw).collect();
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(lines.len() as u16 + 2),
+                Constraint::Min(3),
+            ])
+            .split(info_area);
+        let repertoire_paragraph = Paragraph::new(lines)
+            .wrap(Wrap { trim: true })
+            .block(Block::bordered().title("Repertoire"));
+        f.render_widget(repertoire_paragraph, rows[0]);
+        info_area = rows[1];
+    }
+    if let Some(training) = &app.move_guess {
+        let lines: Vec<Line> = training.lines().into_iter().map(Line::raw).collect();
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(lines.len() as u16 + 3),
+                Constraint::Min(3),
+            ])
+            .split(info_area);
+        let guess_paragraph = Paragraph::new(lines)
+            .wrap(Wrap { trim: true })
+            .block(Block::bordered().title("Guess the move"));
+        f.render_widget(guess_paragraph, rows[0]);
+        info_area = rows[1];
+    }
+    if let Some(trainer) = &app.endgame {
+        let lines: Vec<Line> = trainer.lines().into_iter().map(Line::raw).collect();
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(lines.len() as u16 + 3),
+                Constraint::Min(3),
+            ])
+            .split(info_area);
+        let endgame_paragraph = Paragraph::new(lines)
+            .wrap(Wrap { trim: true })
+            .block(Block::bordered().title("Endgame"));
+        f.render_widget(endgame_paragraph, rows[0]);
+        info_area = rows[1];
+    }
+    if let Some(quiz) = &app.quiz {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(5), Constraint::Min(3)])
+            .split(info_area);
+        let quiz_paragraph = Paragraph::new(make_quiz_lines(quiz))
+            .wrap(Wrap { trim: true })
+            .block(Block::bordered().title("Eval guesses"));
+        f.render_widget(quiz_paragraph, rows[0]);
+        info_area = rows[1];
+    }
+    let review_lines = make_review_lines(app);
+    if !review_lines.is_empty() {
+        let height = (review_lines.len() + 2).min(MAX_REVIEW_HEIGHT) as u16;
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(height), Constraint::Min(3)])
+            .split(info_area);
+        let review_paragraph =
+            Paragraph::new(review_lines).block(Block::bordered().title("Review"));
+        f.render_widget(review_paragraph, rows[0]);
+        info_area = rows[1];
+    }
+    if let Some(analyser) = &app.analyser {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(6), Constraint::Min(3)])
+            .split(info_area);
+        let engine_paragraph = Paragraph::new(make_engine_lines(app, analyser))
+            .wrap(Wrap { trim: true })
+            .block(Block::bordered().title(analyser.name.as_str()));
+        f.render_widget(engine_paragraph, rows[0]);
+        info_area = rows[1];
+    }
+    if app.beginner.is_some() {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(9), Constraint::Min(3)])
+            .split(info_area);
+        let beginner_paragraph = Paragraph::new(beginner::lines(app))
+            .wrap(Wrap { trim: true })
+            .block(Block::bordered().title("Beginner"));
+        f.render_widget(beginner_paragraph, rows[0]);
+        info_area = rows[1];
+    }
+    let info_lines = make_info_text(app);
+    let info_visible = info_area.height.saturating_sub(2) as usize;
+    app.info_max_scroll
+        .set(info_lines.len().saturating_sub(info_visible));
+    app.info_area.set(info_area);
+    let info_paragraph = Paragraph::new(info_lines)
+        .block(Block::bordered().title("Position"))
+        .scroll((app.info_scroll.min(app.info_max_scroll.get()) as u16, 0));
+    f.render_widget(info_paragraph, info_area);
+
+    // 4) Clocks or puzzle set progress above the input line, with the opening
+    let mut bottom = chunks[1];
+    let opening = app
+        .opening()
+        .map(|opening| Span::styled(opening.describe(), Style::default().fg(Color::Cyan)));
+    let with_opening = |mut line: Line<'static>| {
+        if let Some(opening) = opening.clone() {
+            if !line.spans.is_empty() {
+                line.spans.push(Span::raw("  "));
+            }
+            line.spans.push(opening);
+        }
+        line
+    };
+    if app.clock.is_some() || app.puzzle_set.is_some() || app.series.is_some() || opening.is_some()
+    {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(2)])
+            .split(chunks[1]);
+        match (&app.clock, &app.puzzle_set) {
+            (Some(clock), _) => {
+                let mut line = make_clock_line(clock);
+                if let Some(series) = &app.series {
+                    line.spans.push(Span::raw(make_series_text(series)));
+                }
+                f.render_widget(Paragraph::new(with_opening(line)), rows[0])
+            }
+            (None, Some(set)) => match (set.limit, set.remaining()) {
+                // sprints count down in a gauge
+                (Some(limit), Some(remaining)) => {
+                    let ratio = remaining.as_secs_f64() / limit.as_secs_f64().max(0.001);
+                    let gauge = Gauge::default()
+                        .gauge_style(Style::default().fg(if ratio < 0.2 {
+                            Color::Red
+                        } else {
+                            Color::Green
+                        }))
+                        .ratio(ratio.clamp(0.0, 1.0))
+                        .label(format!(
+                            "{} left, {}",
+                            clock::format_duration(remaining),
+                            set.progress()
+                        ));
+                    f.render_widget(gauge, rows[0]);
+                }
+                _ => f.render_widget(
+                    Paragraph::new(format!(
+                        "{}, {}",
+                        set.progress(),
+                        clock::format_duration(set.elapsed())
+                    )),
+                    rows[0],
+                ),
+            },
+            (None, None) => {
+                let mut line = Line::default();
+                if let Some(series) = &app.series {
+                    line.spans.push(Span::raw(make_series_text(series)));
+                }
+                f.render_widget(Paragraph::new(with_opening(line)), rows[0]);
+            }
+        }
+        bottom = rows[1];
+    }
+
+    // 5) Score screen over the board once a puzzle set is done
+    if let Some(set) = app.puzzle_set.as_ref().filter(|set| set.is_finished()) {
+        let summary = make_set_summary(set);
+        let popup = centered(chunks[0], 48, summary.len() as u16 + 3);
+        let title = format!("{} finished", set.label());
+        f.render_widget(Clear, popup);
+        f.render_widget(
+            Paragraph::new(summary)
+                .wrap(Wrap { trim: false })
+                .block(Block::bordered().title(title)),
+            popup,
+        );
+    }
+
+    // 6) Hotseat handover, the position stays hidden until the next player is ready
+    if app.handover && !app.analysing {
+        f.render_widget(Clear, chunks[0]);
+        let popup = centered(chunks[0], 40, 5);
+        f.render_widget(
+            Paragraph::new(vec![
+                Line::raw(format!(
+                    "{} to move.",
+                    app.board.turn().fold_wb("White", "Black")
+                )),
+                Line::raw(""),
+                Line::raw("Press any key when ready."),
+            ])
+            .block(Block::bordered().title("Next player")),
+            popup,
+        );
+    }
+
+    // 7) The moves an ambiguous SAN could mean
+    if let Some((candidates, selected)) = &app.pending_choice {
+        let lines: Vec<Line> = candidates
+            .iter()
+            .enumerate()
+            .map(|(i, mv)| {
+                let san = SanPlus::from_move(app.board.clone(), mv);
+                let from = mv.from().map_or(String::new(), |sq| sq.to_string());
+                let text = format!(" {san}  (from {from})");
+                if i == *selected {
+                    Line::styled(text, Style::default().add_modifier(Modifier::REVERSED))
+                } else {
+                    Line::raw(text)
+                }
+            })
+            .collect();
+        let popup = centered(chunks[0], 24, lines.len() as u16 + 2);
+        f.render_widget(Clear, popup);
+        f.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title("Which piece?")),
+            popup,
+        );
+    }
+
+    // 8) Result and what to do next once the live game is over
+    if let Some(over) = app
+        .game_over
+        .as_ref()
+        .filter(|_| app.show_game_over && !app.analysing)
+    {
+        let lines = match (&app.ladder, app.engines.first()) {
+            (Some(ladder), Some(engine)) => make_ladder_text(over, ladder, &engine.name),
+            _ => make_game_over_text(over, app.series.as_ref()),
+        };
+        // wide enough for the longer descriptions of how the game ended
+        let width = lines.iter().map(Line::width).max().unwrap_or(0).max(38) as u16 + 2;
+        let popup = centered(chunks[0], width, lines.len() as u16 + 2);
+        f.render_widget(Clear, popup);
+        f.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title("Game over")),
+            popup,
+        );
+    }
+
+    // 9) The engine options of :options
+    if let Some(editor) = &app.options_editor {
+        let lines = make_options_lines(app, editor);
+        let width = lines.iter().map(Line::width).max().unwrap_or(0) as u16 + 3;
+        let height = lines.len() as u16 + 2;
+        let popup = centered(f.area(), width, height);
+        let title = option_engines(app)
+            .nth(editor.engine)
+            .map_or(String::new(), |engine| format!("Options: {}", engine.name));
+        f.render_widget(Clear, popup);
+        f.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(title)),
+            popup,
+        );
+    }
+
+    // 10) What earlier games tell of the opponent, before the game
+    if let (Some(briefing), Some(engine)) = (&app.briefing, app.engines.first()) {
+        let mut lines: Vec<Line> = briefing
+            .iter()
+            .map(|line| Line::raw(line.as_str()))
+            .collect();
+        lines.push(Line::raw(""));
+        lines.push(Line::styled(
+            "Any key starts the game.",
+            Style::default().fg(Color::DarkGray),
+        ));
+        let width = lines.iter().map(Line::width).max().unwrap_or(0) as u16 + 2;
+        let popup = centered(chunks[0], width, lines.len() as u16 + 2);
+        f.render_widget(Clear, popup);
+        f.render_widget(
+            Paragraph::new(lines)
+                .block(Block::bordered().title(format!("Briefing: {}", engine.name))),
+            popup,
+        );
+    }
+
+    let prompt = if app.analysing {
+        Span::styled(
+            "ANALYSIS",
+            Style::default().fg(Color::Black).bg(Color::Cyan),
+        )
+    } else {
+        Span::raw("Move input:")
+    };
+    // a move being typed is green while legal moves still match it, red once none does
+    let typed = format!(" {}", lock::masked(&app.input_buffer));
+    let input = match matching_moves(app) {
+        Some(0) => vec![
+            Span::styled(typed, Style::default().fg(Color::Red)),
+            Span::styled("  no legal move", Style::default().fg(Color::DarkGray)),
+        ],
+        Some(count) => vec![
+            Span::styled(typed, Style::default().fg(Color::Green)),
+            Span::styled(
+                format!("  {count} matching"),
+                Style::default().fg(Color::DarkGray),
+            ),
+        ],
+        None => vec![Span::raw(typed)],
+    };
+    let input_paragraph = Paragraph::new(vec![
+        Line::raw(app.message.as_str()),
+        Line::from([vec![prompt], input].concat()),
+    ]);
+    f.render_widget(input_paragraph, bottom);
+}
+
+// In place of a clipped board: the size the current display needs
+fn draw_size_guard(f: &mut ratatui::Frame, app: &App, area: Rect) {
+    let (width, height) = app.min_size();
+    let lines = vec![
+        Line::styled(
+            format!("Please enlarge the terminal to {width}x{height}"),
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+        Line::raw(format!("It is {}x{} now.", area.width, area.height)),
+        Line::raw(""),
+        Line::raw("'-' shrinks the board, '<' narrows the move list,"),
+        Line::raw("--display simple draws a smaller board. 'q' quits."),
+    ];
+    let guard = centered(area, 52, lines.len() as u16);
+    f.render_widget(
+        Paragraph::new(lines)
+            .alignment(ratatui::layout::Alignment::Center)
+            .wrap(Wrap { trim: true }),
+        guard,
+    );
+}
+
+// A `width` x `height` rectangle in the middle of `area`, clipped to it
+fn centered(area: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+fn make_game_over_text(over: &GameOver, series: Option<&series::Series>) -> Vec<Line<'static>> {
+    let key = |key: &'static str, action: &'static str| {
+        Line::from(vec![
+            Span::styled(format!("{key:>5}  "), Style::default().fg(Color::Cyan)),
+            Span::raw(action),
+        ])
+    };
+    let mut lines = vec![
+        Line::styled(
+            format!("{}.", over.describe()),
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+        Line::raw(format!("Result: {}", over.outcome)),
+    ];
+    let in_match = series.is_some_and(|s| s.length.is_some());
+    match series {
+        Some(series) if series.is_over() => {
+            lines.push(Line::raw(format!(
+                "Match over: {} (bottom-top)",
+                series.score()
+            )));
+        }
+        Some(series) => lines.push(Line::raw(format!(
+            "{} {} (bottom-top)",
+            if in_match { "Match" } else { "Series" },
+            series.score()
+        ))),
+        None => {}
+    }
+    lines.push(Line::raw(""));
+    if !series.is_some_and(|s| s.is_over()) {
+        lines.push(key("r", "rematch with colors swapped"));
+    }
+    lines.push(key("Tab", "analyse the final position"));
+    lines.push(if in_match {
+        key("s", "save the match as PGN")
+    } else {
+        key("s", "save the game as PGN")
+    });
+    lines.push(key("v", "review the game with the engine"));
+    lines.push(key("p", "solve a puzzle"));
+    lines.push(key("Esc", "close"));
+    lines
+}
+
+// The game's result on the ladder, and all of the climb once it is over
+fn make_ladder_text(over: &GameOver, ladder: &ladder::Ladder, engine: &str) -> Vec<Line<'static>> {
+    let key = |key: &'static str, action: String| {
+        Line::from(vec![
+            Span::styled(format!("{key:>5}  "), Style::default().fg(Color::Cyan)),
+            Span::raw(action),
+        ])
+    };
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let mut lines = vec![Line::styled(format!("{}.", over.describe()), bold)];
+    if !ladder.is_over() {
+        let (level, step) = ladder
+            .steps
+            .last()
+            .copied()
+            .unwrap_or((1, ladder::Step::Won));
+        lines.push(Line::styled(
+            format!(
+                "Level {level} cleared with a {}!",
+                if step == ladder::Step::Drawn {
+                    "draw"
+                } else {
+                    "win"
+                }
+            ),
+            bold.fg(Color::Green),
+        ));
+        lines.push(Line::raw(format!(
+            "Next: level {} of {}, {engine} at Elo {}",
+            ladder.level,
+            ladder::LEVELS,
+            ladder::elo(ladder.level)
+        )));
+    } else {
+        let banner = if ladder.cleared() == ladder::LEVELS {
+            "*  *  *  TOP OF THE LADDER  *  *  *"
+        } else {
+            "*  *  *  THE CLIMB IS OVER  *  *  *"
+        };
+        lines.insert(0, Line::styled(banner, bold.fg(Color::Yellow)));
+        lines.push(Line::raw(""));
+        for &(level, step) in &ladder.steps {
+            let color = match step {
+                ladder::Step::Won => Color::Green,
+                ladder::Step::Drawn => Color::Yellow,
+                ladder::Step::Lost => Color::Red,
+            };
+            lines.push(Line::from(vec![
+                Span::raw(format!("Level {level:>2}  Elo {}  ", ladder::elo(level))),
+                Span::styled(step.name(), Style::default().fg(color)),
+            ]));
+        }
+        lines.push(Line::raw(""));
+        lines.push(match ladder.cleared() {
+            0 => Line::raw(format!("No level cleared against {engine}.")),
+            cleared if ladder.is_new_best() => Line::styled(
+                format!(
+                    "Level {cleared} cleared, a new record! (best before: {})",
+                    ladder.best_before
+                ),
+                bold.fg(Color::Green),
+            ),
+            cleared => Line::raw(format!(
+                "Level {cleared} cleared, the record stays at {}.",
+                ladder.best_before
+            )),
+        });
+    }
+    lines.push(Line::raw(""));
+    lines.push(if ladder.is_over() {
+        key("r", "climb again from level 1".to_string())
+    } else {
+        key("r", format!("play level {}", ladder.level))
+    });
+    lines.push(key("Tab", "analyse the final position".to_string()));
+    lines.push(key("s", "save the games as PGN".to_string()));
+    lines.push(key("v", "review the game with the engine".to_string()));
+    lines.push(key("Esc", "close".to_string()));
+    lines
+}
+
+fn make_set_summary(set: &puzzle_set::PuzzleSet) -> Vec<Line<'static>> {
+    let total = set.results.len();
+    let elapsed = set.elapsed();
+    let average = elapsed.checked_div(total as u32).unwrap_or_default();
+    let theme = match (&set.theme, set.phase) {
+        (Some(theme), Some(phase)) if theme != phase.name() => format!("{theme}, {}", phase.name()),
+        (Some(theme), _) => theme.clone(),
+        (None, Some(phase)) => phase.name().to_string(),
+        (None, None) => "any theme".to_string(),
+    };
+    let times = set
+        .results
+        .iter()
+        .map(|r| {
+            let failed = if r.solved { "" } else { " (failed)" };
+            format!("{}s{failed}", r.time.as_secs())
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    let ratings = match set.rating_range() {
+        Some((min, max)) => format!("{min}-{max}"),
+        None => "-".to_string(),
+    };
+    vec![
+        Line::raw(format!("Theme:    {theme}")),
+        Line::raw(format!("Solved:   {} of {total}", set.solved())),
+        Line::raw(format!(
+            "Score:    {:.1} ({} with hints)",
+            set.score(),
+            set.hinted()
+        )),
+        Line::raw(format!("Time:     {}", clock::format_duration(elapsed))),
+        Line::raw(format!(
+            "Average:  {} per puzzle",
+            clock::format_duration(average)
+        )),
+        Line::raw(format!("Ratings:  {ratings}")),
+        Line::raw(format!("Puzzles:  {times}")),
+        Line::raw(""),
+        Line::raw("Press 'q' to quit."),
+    ]
+}
+
+// The score of the series or match, bottom player first
+fn make_series_text(series: &series::Series) -> String {
+    let played = series.games();
+    let text = match series.length {
+        Some(series::MatchLength::Games(games)) => format!(
+            "Match {} (bottom-top), game {} of {games}",
+            series.score(),
+            (played + 1).min(games)
+        ),
+        Some(series::MatchLength::FirstTo(points)) => {
+            format!("Match {} (bottom-top), first to {points}", series.score())
+        }
+        None => {
+            let games = if played == 1 { "game" } else { "games" };
+            format!(
+                "Series {} (bottom-top) after {played} {games}",
+                series.score()
+            )
+        }
+    };
+    match series.engine_elo() {
+        Some(elo) => format!("{text}, engine at Elo {elo}"),
+        None => text,
+    }
+}
+
+// ----------------------------------------------
+// Clock line: yellow under a minute, red and blinking under ten seconds
+// ----------------------------------------------
+fn make_clock_line(clock: &clock::Clock) -> Line<'static> {
+    let mut spans = Vec::new();
+    for color in [ChessColor::White, ChessColor::Black] {
+        let remaining = clock.remaining(color);
+        let active = clock.active() == Some(color);
+        let mut style = Style::default();
+        if remaining < Duration::from_secs(10) {
+            style = style.fg(Color::Red);
+            // toggled by hand, not every terminal supports SLOW_BLINK
+            if active && remaining.subsec_millis() >= 500 {
+                style = style.add_modifier(Modifier::REVERSED);
+            }
+        } else if remaining < Duration::from_secs(60) {
+            style = style.fg(Color::Yellow);
+        }
+        if active {
+            style = style.add_modifier(Modifier::BOLD);
+        }
+        let marker = if active { "\u{25b6} " } else { "  " };
+        spans.push(Span::styled(
+            format!("{marker}{color} {}", clock::format_duration(remaining)),
+            style,
+        ));
+        spans.push(Span::raw("   "));
+    }
+    Line::from(spans)
+}
+// ----------------------------------------------
+// Move list, one line per move number plus comment lines.
+// Returns the lines and the line of the move shown on the board.
+// ----------------------------------------------
+struct MoveList<'a> {
+    lines: Vec<Line<'a>>,
+    // line of the move on the board
+    focus: usize,
+    // where each move is written, to map mouse clicks to plies
+    targets: Vec<MoveTarget>,
+}
+
+struct MoveTarget {
+    line: usize,
+    columns: std::ops::Range<usize>,
+    ply: usize,
+}
+
+fn make_moves_text(app: &App) -> MoveList<'_> {
+    let shown = app.shown_ply();
+    let current_style = Style::default().add_modifier(Modifier::REVERSED);
+    let comment_style = Style::default().fg(Color::DarkGray);
+
+    let mut lines = Vec::new();
+    let mut focus = 0;
+    let mut number = app.start.fullmoves().get();
+    let mut spans: Vec<Span> = Vec::new();
+    let mut targets = Vec::new();
+
+    let history = app.san_history();
+    // each side's average think time, to tell the long and short ones
+    let mut totals: shakmaty::ByColor<(Duration, u32)> = Default::default();
+    for ((color, _), time) in history.iter().zip(&app.times) {
+        if let Some(time) = time {
+            let (sum, count) = totals.get_mut(*color);
+            *sum += *time;
+            *count += 1;
+        }
+    }
+    let averages = totals.map(|(sum, count)| sum.checked_div(count));
+    let judgements = app
+        .current_review()
+        .map(review::Review::judgements)
+        .unwrap_or_default();
+    let annotations = app.annotations();
+    // black's moves line up in a second column, a wider one with times
+    let column: usize = if app.times.iter().any(Option::is_some) {
+        15
+    } else {
+        12
+    };
+
+    for (i, (color, san)) in history.into_iter().enumerate() {
+        let mut text = notation::format_san(&san, color, app.notation);
+        if let Some(node) = annotations.get(i) {
+            text.push_str(&node.glyphs());
+        }
+        let style = if i + 1 == shown {
+            current_style
+        } else {
+            Style::default()
+        };
+        if i + 1 == shown {
+            focus = lines.len();
+        }
+
+        let prefix = match color {
+            ChessColor::White => format!("{number}. "),
+            ChessColor::Black if spans.is_empty() => format!("{number}... "),
+            ChessColor::Black => String::new(),
+        };
+        let symbol = judgements.get(i).copied().flatten().map(|judgement| {
+            Span::styled(
+                judgement.symbol(),
+                Style::default().fg(judgement_color(judgement)),
+            )
+        });
+        let time = app.times.get(i).copied().flatten().map(|time| {
+            let style = match *averages.get(color) {
+                Some(average) if app.highlight_times && time > average * 5 / 2 => {
+                    Style::default().fg(Color::Red)
+                }
+                Some(average) if app.highlight_times && time < average / 4 => {
+                    Style::default().fg(Color::Cyan)
+                }
+                _ => comment_style,
+            };
+            Span::styled(format!(" {}", format_think_time(time)), style)
+        });
+        let width = prefix.chars().count()
+            + text.chars().count()
+            + symbol
+                .as_ref()
+                .map_or(0, |symbol| symbol.content.chars().count())
+            + time.as_ref().map_or(0, |time| time.content.chars().count());
+        let start: usize = spans
+            .iter()
+            .map(|s| s.content.chars().count())
+            .sum::<usize>()
+            + prefix.chars().count();
+        targets.push(MoveTarget {
+            line: lines.len(),
+            columns: start..start + text.chars().count(),
+            ply: i + 1,
+        });
+        spans.push(Span::raw(prefix));
+        spans.push(Span::styled(text, style));
+        spans.extend(symbol);
+        spans.extend(time);
+
+        let comment = app.comments.get(i).and_then(|c| c.as_deref());
+        if color == ChessColor::White && comment.is_none() {
+            // pad so black's moves line up in a second column
+            spans.push(Span::raw(" ".repeat(column.saturating_sub(width))));
+        } else {
+            lines.push(Line::from(std::mem::take(&mut spans)));
+        }
+        if let Some(comment) = comment {
+            lines.push(Line::styled(format!("  {comment}"), comment_style));
+        }
+        if color == ChessColor::Black {
+            number += 1;
+        }
+    }
+    if !spans.is_empty() {
+        lines.push(Line::from(spans));
+    }
+    MoveList {
+        lines,
+        focus,
+        targets,
+    }
+}
+
+// "2.5s", "42s", "1:48", short enough for the move list
+fn format_think_time(time: Duration) -> String {
+    let secs = time.as_secs();
+    if secs < 10 {
+        format!("{}.{}s", secs, time.subsec_millis() / 100)
+    } else if secs < 60 {
+        format!("{secs}s")
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+// ----------------------------------------------
+// Material and pawn structure summary for the info pane
+// ----------------------------------------------
+fn make_info_text(app: &App) -> Vec<Line<'_>> {
+    let board = app.shown_board().board();
+    let white = analysis::side_summary(board, ChessColor::White);
+    let black = analysis::side_summary(board, ChessColor::Black);
+    let balance = white.material as i32 - black.material as i32;
+
+    let squares = |list: &[shakmaty::Square]| {
+        if list.is_empty() {
+            "-".to_string()
+        } else {
+            list.iter()
+                .map(|sq| sq.to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        }
+    };
+
+    let mut lines = Vec::new();
+    if let AppMode::Puzzle { .. } = app.mode {
+        lines.extend(make_rating_lines(&app.puzzle_rating));
+    }
+    if let Some(online) = app
+        .online_tablebase
+        .as_ref()
+        .filter(|_| !app.position_hidden())
+    {
+        lines.extend(make_lookup_lines(online, app.shown_board()));
+    }
+    lines.push(Line::from(format!("Material balance: {balance:+}")));
+    for (name, side) in [("White", &white), ("Black", &black)] {
+        lines.push(Line::from(""));
+        lines.push(Line::from(format!(
+            "{name}: {} points{}",
+            side.material,
+            if side.bishop_pair {
+                ", bishop pair"
+            } else {
+                ""
+            }
+        )));
+        lines.push(Line::from(format!(
+            "  doubled pawns: {}, isolated: {}",
+            side.doubled_pawns,
+            squares(&side.isolated_pawns)
+        )));
+        lines.push(Line::from(format!(
+            "  passed: {}",
+            squares(&side.passed_pawns)
+        )));
+    }
+    if let Some(summary) = app.opening_tree.summary(app.shown_board()) {
+        lines.extend(make_tree_lines(app, &summary));
+    }
+    lines
+}
+
+// The lichess tablebase's verdict and its best moves, for an endgame it answered
+fn make_lookup_lines(online: &tablebase::Online, pos: &Chess) -> Vec<Line<'static>> {
+    const MOVES: usize = 5;
+    let lookup = match online.get(pos) {
+        Some(Ok(lookup)) => lookup,
+        Some(Err(err)) => {
+            return vec![
+                Line::raw(format!("Tablebase lookup failed: {err}")),
+                Line::raw(""),
+            ]
+        }
+        None => return Vec::new(),
+    };
+    let mut verdict = format!(
+        "Tablebase: {}",
+        tablebase::describe_category(&lookup.category, pos.turn())
+    );
+    if let Some(dtz) = lookup.dtz.filter(|&dtz| dtz != 0) {
+        verdict.push_str(&format!(", DTZ {}", dtz.abs()));
+    }
+    if let Some(dtm) = lookup.dtm.filter(|&dtm| dtm != 0) {
+        verdict.push_str(&format!(", DTM {}", dtm.abs()));
+    }
+    let best: Vec<String> = lookup
+        .moves
+        .iter()
+        .take(MOVES)
+        .map(|mv| match mv.dtz.filter(|&dtz| dtz != 0) {
+            Some(dtz) => format!("{} {} DTZ {}", mv.san, mv.result(), dtz.abs()),
+            None => format!("{} {}", mv.san, mv.result()),
+        })
+        .collect();
+    let mut lines = vec![Line::styled(
+        verdict,
+        Style::default().add_modifier(Modifier::BOLD),
+    )];
+    if !best.is_empty() {
+        lines.push(Line::raw(format!("  {}", best.join(", "))));
+    }
+    lines.push(Line::raw(""));
+    lines
+}
+
+// "1.2M", "850k", "312": node counts for the telemetry line
+fn format_count(count: u64) -> String {
+    match count {
+        0..=9_999 => count.to_string(),
+        10_000..=999_999 => format!("{}k", count / 1000),
+        _ => format!("{:.1}M", count as f64 / 1e6),
+    }
+}
+
+// What the engine reports of its search besides the score: "seldepth 24
+// nodes 1.2M nps 850k hash 43% tbhits 12", each part only if it was sent
+fn make_telemetry_line(evaluation: &engine::Evaluation) -> Option<Line<'static>> {
+    let mut parts = Vec::new();
+    if let Some(seldepth) = evaluation.seldepth {
+        parts.push(format!("seldepth {seldepth}"));
+    }
+    if let Some(nodes) = evaluation.nodes {
+        parts.push(format!("nodes {}", format_count(nodes)));
+    }
+    if let Some(nps) = evaluation.nps {
+        parts.push(format!("nps {}", format_count(nps)));
+    }
+    if let Some(hashfull) = evaluation.hashfull {
+        parts.push(format!("hash {}%", hashfull / 10));
+    }
+    if evaluation.tbhits > 0 {
+        parts.push(format!("tbhits {}", format_count(evaluation.tbhits)));
+    }
+    (!parts.is_empty())
+        .then(|| Line::styled(parts.join("  "), Style::default().fg(Color::DarkGray)))
+}
+
+// Score and depth of the engine's search, its telemetry, then its principal
+// variation in SAN
+fn make_engine_lines(app: &App, analyser: &engine::Engine) -> Vec<Line<'static>> {
+    const PV_PLIES: usize = 12;
+    let mut pos = app.shown_board().clone();
+    let Some(evaluation) = &analyser.evaluation else {
+        let idle = if pos.outcome().is_some() {
+            "Nothing to search, the game is over."
+        } else if let AppMode::Puzzle { .. } = app.mode {
+            "Waiting for the puzzle to be solved."
+        } else if app.guessing() {
+            "Waiting for the guess."
+        } else {
+            "Thinking..."
+        };
+        return vec![Line::from(idle)];
+    };
+    let mut score = Line::from(vec![
+        Span::styled(
+            evaluation.score.display(pos.turn()),
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+        Span::raw(format!("  depth {}", evaluation.depth)),
+    ]);
+    if let Some(tablebase) = &app.tablebase {
+        if let Some(verdict) = tablebase.verdict(&pos, evaluation) {
+            score.push_span(Span::styled(
+                format!(
+                    "  engine-reported tablebase result: {}",
+                    tablebase::describe(verdict)
+                ),
+                Style::default().fg(Color::Cyan),
+            ));
+        } else if tablebase.covers(&pos) {
+            score.push_span(Span::raw("  in the tablebase"));
+        }
+    }
+    let mut pv = Vec::new();
+    for text in evaluation.pv.iter().take(PV_PLIES) {
+        let Some(mv) = uci::parse(&pos, text) else {
+            break;
+        };
+        match pos.turn() {
+            ChessColor::White => pv.push(format!("{}.", pos.fullmoves())),
+            ChessColor::Black if pv.is_empty() => pv.push(format!("{}...", pos.fullmoves())),
+            ChessColor::Black => {}
+        }
+        pv.push(san::SanPlus::from_move_and_play_unchecked(&mut pos, &mv).to_string());
+    }
+    let mut lines = vec![score];
+    lines.extend(make_telemetry_line(evaluation));
+    lines.push(Line::from(pv.join(" ")));
+    lines
+}
+
+fn judgement_color(judgement: review::Judgement) -> Color {
+    match judgement {
+        review::Judgement::Blunder => Color::Red,
+        review::Judgement::Mistake => Color::LightRed,
+        review::Judgement::Inaccuracy => Color::Yellow,
+    }
+}
+
+// Progress of a running review, or each side's errors and the flagged moves
+fn make_review_lines(app: &App) -> Vec<Line<'static>> {
+    if let Some(background) = &app.reviewing {
+        return vec![Line::raw(format!(
+            "Evaluating {}/{} positions...",
+            background.done(),
+            background.total
+        ))];
+    }
+    let Some(review) = app.current_review() else {
+        return Vec::new();
+    };
+    let counts = review.error_counts();
+    let scores = review.scores();
+    let mut lines: Vec<Line> = [ChessColor::White, ChessColor::Black]
+        .into_iter()
+        .map(|color| {
+            let [inaccuracies, mistakes, blunders] = *counts.get(color);
+            let accuracy = scores.get(color).map_or(String::new(), |scores| {
+                format!(" accuracy {:.1}%,", scores.accuracy)
+            });
+            Line::raw(format!(
+                "{}:{accuracy} {inaccuracies} ?!  {mistakes} ?  {blunders} ??",
+                color.fold_wb("White", "Black")
+            ))
+        })
+        .collect();
+    let history = app.san_history();
+    let shown = app.shown_ply();
+    let judgements = review.judgements();
+    let flagged: Vec<usize> = (0..judgements.len())
+        .filter(|&i| judgements[i].is_some())
+        .collect();
+    // a long list starts a little before the move on screen
+    let room = MAX_REVIEW_HEIGHT - 2 - lines.len();
+    let next = flagged
+        .iter()
+        .position(|&i| i + 1 >= shown)
+        .unwrap_or(flagged.len());
+    let skip = next
+        .saturating_sub(room / 2)
+        .min(flagged.len().saturating_sub(room));
+    for &i in &flagged[skip..] {
+        let judgement = judgements[i].expect("flagged");
+        let (color, san) = &history[i];
+        let number =
+            app.start.fullmoves().get() as usize + (i + app.start.turn().fold_wb(0, 1)) / 2;
+        let dots = color.fold_wb(".", "...");
+        let style = Style::default().fg(judgement_color(judgement));
+        let phase = review
+            .positions
+            .get(i)
+            .map_or("", |pos| phase::Phase::of(pos).name());
+        lines.push(Line::styled(
+            format!(
+                "{number}{dots} {san}{} {}, {phase}",
+                judgement.symbol(),
+                judgement.name()
+            ),
+            if i + 1 == shown {
+                style.add_modifier(Modifier::REVERSED)
+            } else {
+                style
+            },
+        ));
+    }
+    lines
+}
+
+// Wins, draws, losses and points of both players of a match
+fn make_results_lines(series: &series::Series) -> Vec<Line<'static>> {
+    let standings = series.standings();
+    let width = standings.iter().map(|s| s.name.len()).max().unwrap_or(0);
+    let mut lines = vec![Line::from(format!("{:width$}    W   D   L  Score", ""))];
+    for standing in standings {
+        lines.push(Line::from(vec![
+            Span::raw(format!(
+                "{:width$}  {:>3} {:>3} {:>3}  ",
+                standing.name, standing.wins, standing.draws, standing.losses
+            )),
+            Span::styled(
+                series::points(standing.points),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+        ]));
+    }
+    if let Some((elo, error)) = series.elo_difference() {
+        let name = &series.standings()[0].name;
+        lines.push(Line::from(format!("Elo {name}: {elo:+.0} ± {error:.0}")));
+    }
+    lines
+}
+
+// Each playing engine's evaluation of its last move, white first
+fn make_players_lines(app: &App) -> Vec<Line<'static>> {
+    let mut engines: Vec<&engine::Engine> = app.engines.iter().collect();
+    engines.sort_by_key(|engine| engine.color.fold_wb(0, 1));
+    engines
+        .into_iter()
+        .map(|engine| {
+            let side = engine.color.fold_wb("White", "Black");
+            let mut spans = vec![Span::raw(format!("{side}: {}  ", engine.name))];
+            // searches are from the side of the engine, it had the move
+            if let Some(evaluation) = &engine.evaluation {
+                spans.push(Span::styled(
+                    evaluation.score.display(engine.color),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ));
+                spans.push(Span::raw(format!("  depth {}", evaluation.depth)));
+            }
+            if engine.thinking() {
+                spans.push(Span::raw("  thinking..."));
+            }
+            Line::from(spans)
+        })
+        .collect()
+}
+
+// The players of an online game, mine marked, and what lichess told of it
+// and of its arena
+fn make_online_lines(game: &online::Game, plies: usize) -> Vec<Line<'static>> {
+    let mut lines: Vec<Line> = [ChessColor::White, ChessColor::Black]
+        .into_iter()
+        .map(|color| {
+            let side = color.fold_wb("White", "Black");
+            let name = &game.players[color.fold_wb(0, 1)];
+            let you = match (color == game.color, game.berserk) {
+                (true, true) => "  (you, berserk)",
+                (true, false) => "  (you)",
+                _ => "",
+            };
+            Line::raw(format!("{side}: {name}{you}"))
+        })
+        .collect();
+    match game.takeback {
+        Some(side) if side == game.color => {
+            lines.push(Line::raw("Takeback proposed, :takeback no withdraws it."))
+        }
+        Some(_) => lines.push(Line::styled(
+            "Takeback proposed: :takeback accepts, :takeback no declines.",
+            Style::default().fg(Color::Yellow),
+        )),
+        None => {}
+    }
+    if game.opponent_gone {
+        lines.push(Line::styled(
+            "Your opponent left the game.",
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+    if let Some(arena) = &game.arena {
+        lines.push(Line::raw(format!("Arena: {}", arena.name)));
+        let mut standing = Vec::new();
+        if let Some(rank) = arena.rank {
+            standing.push(format!("Rank {rank}"));
+        }
+        if let Some(score) = arena.score {
+            standing.push(format!("{score} points"));
+        }
+        if let Some(sheet) = &arena.sheet {
+            standing.push(format!("games {sheet}"));
+        }
+        if !standing.is_empty() {
+            lines.push(Line::raw(standing.join(", ")));
+        }
+        if arena.fire {
+            lines.push(Line::styled(
+                "On a streak, a win counts double.",
+                Style::default().fg(Color::Yellow),
+            ));
+        }
+        let ends = clock::format_duration(arena.ends_in());
+        lines.push(Line::raw(if arena.pairings_closed {
+            format!("Pairings closed, the arena ends in {ends}.")
+        } else {
+            format!("The arena ends in {ends}.")
+        }));
+    } else if game.tournament.is_some() {
+        lines.push(Line::raw("Arena game."));
+    }
+    if game.tournament.is_some()
+        && !game.berserk
+        && game.in_play()
+        && plies <= game.color.fold_wb(0, 1)
+    {
+        lines.push(Line::raw(":berserk halves your time for a point more."));
+    }
+    lines
+}
+
+// Local puzzle rating with the change of the last attempt and the recent trend
+fn make_rating_lines(rating: &stats::PuzzleRating) -> Vec<Line<'static>> {
+    let change = rating
+        .last_change()
+        .map_or(String::new(), |change| format!(" ({change:+.0})"));
+    let mut lines = vec![Line::from(format!(
+        "Puzzle rating: {:.0}{change}, {} puzzles",
+        rating.rating, rating.puzzles
+    ))];
+    if rating.recent.len() > 1 {
+        lines.push(Line::from(format!(
+            "  last {}: {}",
+            rating.recent.len(),
+            rating.sparkline()
+        )));
+    }
+    lines.push(Line::from(""));
+    lines
+}
+
+// What I usually play in the position on screen, and how often I leave my repertoire there
+fn make_tree_lines(app: &App, summary: &opening_tree::Summary) -> Vec<Line<'static>> {
+    let color = app.shown_board().turn();
+    let san = |san: &str| notation::format_san(san, color, app.notation);
+    let mut lines = vec![Line::from(""), Line::from("Opening tree:")];
+    let games: u32 = summary.played.iter().map(|(_, games, _)| games).sum();
+    if !summary.played.is_empty() {
+        let moves: Vec<String> = summary
+            .played
+            .iter()
+            .map(|(mv, n, score)| format!("{} {n}x {:.0}%", san(mv), score * 100.0))
+            .collect();
+        lines.push(Line::from(format!(
+            "  I play ({games} games): {}",
+            moves.join(", ")
+        )));
+    }
+    if !summary.repertoire.is_empty() {
+        let moves: Vec<String> = summary.repertoire.iter().map(|mv| san(mv)).collect();
+        lines.push(Line::from(format!("  repertoire: {}", moves.join(", "))));
+    }
+    if summary.deviations > 0 {
+        lines.push(Line::styled(
+            format!(
+                "  left the repertoire here in {} of {games} games",
+                summary.deviations
+            ),
+            Style::default().fg(Color::Red),
+        ));
+    }
+    lines
+}
+
+// ----------------------------------------------
+// Build the board with the highlights and overlays of the app
+// ----------------------------------------------
+fn board_widget(app: &App) -> BoardWidget<'_> {
+    let mut board = BoardWidget::new(app.shown_board())
+        .flipped(app.flipped)
+        .theme(&app.palette)
+        .display(app.display)
+        .pieces(app.pieces)
+        .cell_size(app.cell_width, app.cell_height)
+        .plain(app.low_bandwidth)
+        .wide_symbols(app.wide_symbols);
+    for &(sq, bg) in &app.highlights {
+        board = board.highlight(sq, bg);
+    }
+    // the piece the beginner help is about and where it can go
+    if let Some(sq) = beginner::focus(app) {
+        if let Some((quiet, captures)) = beginner::pattern(app.shown_board(), sq) {
+            board = board.highlight(sq, Color::Cyan);
+            for target in quiet {
+                board = board.highlight(target, Color::Green);
+            }
+            for target in captures {
+                board = board.highlight(target, Color::Red);
+            }
+        }
+    }
+    let arrow = best_move_arrow(app);
+    if let Some((from, to)) = arrow {
+        board = board.arrow(from, to);
+    }
+    // the overlays where neither a highlight nor the arrow colors the square, the
+    // widget keeps the first background given
+    let heat = app.heatmap.is_some().then(|| app.control_heat());
+    let shown = app.shown_board().board();
+    for sq in shakmaty::Square::ALL {
+        let arrowed = arrow.is_some_and(|(from, to)| sq == from || sq == to);
+        if arrowed && !app.low_bandwidth {
+            continue;
+        }
+        let overlay = match heat {
+            Some(heat) => heat_color(heat[sq as usize]),
+            None if app.show_attack_map => attack_map_color(analysis::square_control(shown, sq)),
+            None => None,
+        };
+        if let Some(bg) = overlay {
+            board = board.highlight(sq, bg);
+        }
+    }
+    board
+}
+
+// The squares of the analysis engine's best move in the position on screen,
+// castling from the king's square to its destination
+fn best_move_arrow(app: &App) -> Option<(shakmaty::Square, shakmaty::Square)> {
+    let pos = app.shown_board();
+    let evaluation = app.analyser.as_ref()?.evaluation_of(pos)?;
+    let mv = uci::parse(pos, evaluation.pv.first()?)?;
+    match mv.to_uci(CastlingMode::Standard) {
+        shakmaty::uci::UciMove::Normal { from, to, .. } => Some((from, to)),
+        _ => None,
+    }
+}
+
+// Background for a square in the attack map overlay, None keeps the square color
+fn attack_map_color(control: analysis::Control) -> Option<Color> {
+    if !control.is_contested() {
+        return None;
+    }
+    Some(match control.balance() {
+        b if b >= 2 => Color::Blue,
+        1 => Color::LightBlue,
+        0 => Color::DarkGray,
+        -1 => Color::LightRed,
+        _ => Color::Red,
+    })
+}
+
+// Background for the heat of a square, brighter for more control, None if even
+fn heat_color(heat: f64) -> Option<Color> {
+    // 256-color ramps from faint to strong
+    const WHITE: [u8; 5] = [17, 19, 21, 27, 33];
+    const BLACK: [u8; 5] = [52, 88, 124, 160, 196];
+    let level = (heat.abs() * 2.0).round().min(5.0) as usize;
+    let ramp = if heat > 0.0 { WHITE } else { BLACK };
+    (level > 0).then(|| Color::Indexed(ramp[level - 1]))
+}
+
+// A key for the tab on screen, recorded when it goes into solving a puzzle:
+// typing and submitting moves and answering the promotion and piece prompts
+fn handle_recorded_key(app: &mut App, key: KeyEvent) -> anyhow::Result<bool> {
+    let solving = !app.analysing
+        && app.replay.is_none()
+        && matches!(
+            app.mode,
+            AppMode::Puzzle {
+                completed: false,
+                ..
+            }
+        );
+    let prompt = app.pending_promotion.is_some() || app.pending_choice.is_some();
+    let before = app.input_buffer.clone();
+    let keep_going = handle_key_event(app, key)?;
+    let command = |input: &str| input.starts_with(':') || input.starts_with('/');
+    let typed = (app.input_buffer != before || key.code == KeyCode::Enter && !before.is_empty())
+        && !command(&before)
+        && !command(&app.input_buffer);
+    if solving && (prompt || typed) {
+        app.attempt.record(key);
+    }
+    Ok(keep_going)
+}
+
+// ----------------------------------------------
+// Handle keyboard events (for move input, etc.)
+// Return false if we should quit
+// ----------------------------------------------
+fn handle_key_event(app: &mut App, key: KeyEvent) -> anyhow::Result<bool> {
+    // a replay keeps the keys to itself, Esc skips to its end
+    if app.replay.is_some() {
+        if key.code == KeyCode::Esc {
+            advance_replay(app, true)?;
+        }
+        return Ok(true);
+    }
+    // stepping through the moves by hand stops a playing heatmap
+    if let (Some(heatmap), KeyCode::Left | KeyCode::Right | KeyCode::Home | KeyCode::End) =
+        (&mut app.heatmap, key.code)
+    {
+        heatmap.playing = false;
+    }
+    // the piece chooser of an ambiguous SAN keeps the keys until a move is picked
+    if let Some((candidates, selected)) = app.pending_choice.take() {
+        handle_choice_key(app, candidates, selected, key)?;
+        return Ok(true);
+    }
+    // so does the options editor, until Esc
+    if let Some(editor) = app.options_editor.take() {
+        handle_options_key(app, editor, key);
+        return Ok(true);
+    }
+    // the promotion prompt takes the next key, any other than a piece cancels
+    if let Some(mv) = app.pending_promotion.take() {
+        let role = match key.code {
+            KeyCode::Char(ch) => Role::from_char(ch.to_ascii_lowercase()).filter(|role| {
+                matches!(role, Role::Queen | Role::Rook | Role::Bishop | Role::Knight)
+            }),
+            _ => None,
+        };
+        match (role, mv) {
+            (
+                Some(role),
+                Move::Normal {
+                    role: pawn,
+                    from,
+                    capture,
+                    to,
+                    ..
+                },
+            ) => {
+                let mv = Move::Normal {
+                    role: pawn,
+                    from,
+                    capture,
+                    to,
+                    promotion: Some(role),
+                };
+                play_input_move(app, mv.clone(), &uci::format(&mv))?;
+            }
+            _ => app.message = "Promotion cancelled.".to_string(),
+        }
+        return Ok(true);
+    }
+    // any key ends the handover screen, and the briefing
+    if std::mem::take(&mut app.handover) || app.briefing.take().is_some() {
+        return Ok(true);
+    }
+    if app.show_game_over && !app.analysing {
+        return handle_game_over_key(app, key);
+    }
+    match key.code {
+        // not a letter key: 'a' starts a-file moves
+        KeyCode::Tab if app.mode == AppMode::Board => {
+            app.message = "The analysis board has no game to switch to.".to_string();
+        }
+        KeyCode::Tab => app.toggle_analysis(),
+        KeyCode::Char('n') if app.input_buffer.is_empty() && app.analysing => {
+            // start over from the game's current position
+            let live = app.parked.as_ref().map(|game| game.board.clone());
+            app.reset_board(live.unwrap_or_default());
+            app.message = "Analysis restarted from the game position.".to_string();
+        }
+        KeyCode::Char('n') if app.input_buffer.is_empty() => match app.mode.clone() {
+            AppMode::StandardGame => {
+                app.series = None;
+                app.ladder = None;
+                app.reset_board(Chess::default());
+                app.engine_turn();
+            }
+            AppMode::Board if app.quiz.is_some() => next_guess(app)?,
+            AppMode::Board if app.repertoire.is_some() => next_line(app)?,
+            AppMode::Board if app.move_guess.is_some() => start_move_guess(app)?,
+            AppMode::Board if app.endgame.is_some() => start_endgame(app),
+            AppMode::Board => app.reset_board(Chess::default()),
+            AppMode::Puzzle { .. } if app.puzzle_set.is_some() => next_set_puzzle(app)?,
+            AppMode::Puzzle { .. } => {
+                let (board, solution, puzzle) = load_puzzle(None)?;
+                app.reset_board(board);
+                app.mode = AppMode::Puzzle {
+                    solution,
+                    solution_index: 0,
+                    completed: false,
+                    lichess: puzzle,
+                };
+                app.message = app.start_message()
+            }
+        },
+        KeyCode::Char('m') if app.input_buffer.is_empty() => {
+            app.show_attack_map = !app.show_attack_map;
+            app.message = if app.show_attack_map {
+                "Attack map on: blue squares are controlled by white, red by black, gray contested."
+                    .to_string()
+            } else {
+                "Attack map off.".to_string()
+            };
+        }
+        KeyCode::Char('t') if app.input_buffer.is_empty() => show_tactics(app),
+        // the Polish queen is H, there :hint is the way
+        KeyCode::Char('H')
+            if app.input_buffer.is_empty()
+                && !notation::is_piece_letter('H', app.input_language) =>
+        {
+            show_game_hint(app)
+        }
+        KeyCode::Char(ch @ ('[' | ']')) if app.input_buffer.is_empty() => {
+            step_flagged(app, ch == ']')
+        }
+        KeyCode::Char('k') if app.input_buffer.is_empty() => {
+            app.highlight_times = !app.highlight_times;
+            app.message = if app.highlight_times {
+                "Think times colored: red for unusually long, cyan for unusually short.".to_string()
+            } else {
+                "Think times uncolored.".to_string()
+            };
+        }
+        KeyCode::Char(ch @ ('y' | 'Y')) if app.input_buffer.is_empty() => {
+            copy_board(app, ch == 'Y')
+        }
+        // a guess may start with a sign
+        KeyCode::Char(ch @ ('+' | '-' | '<' | '>'))
+            if app.input_buffer.is_empty() && !app.guessing() =>
+        {
+            resize_panes(app, ch)
+        }
+        KeyCode::Esc => return Ok(false),
+        KeyCode::Char('q') if app.input_buffer.is_empty() => {
+            // Quit on 'q'
+            return Ok(false);
+        }
+        // User pressed Enter => parse the input as a move
+        KeyCode::Enter => submit_input(app)?,
+        KeyCode::Left => step_view(app, app.shown_ply().saturating_sub(1)),
+        KeyCode::Right => step_view(app, app.shown_ply() + 1),
+        KeyCode::Home => step_view(app, 0),
+        KeyCode::End => step_view(app, app.history.len()),
+        KeyCode::Up => scroll_moves(app, -1),
+        KeyCode::Down => scroll_moves(app, 1),
+        KeyCode::PageUp => scroll_moves(app, -(app.moves_layout.get().1 as isize)),
+        KeyCode::PageDown => scroll_moves(app, app.moves_layout.get().1 as isize),
+        // the game goes on as it was played, whatever was guessed, and a
+        // drill as it was defended
+        KeyCode::Backspace
+            if app.input_buffer.is_empty()
+                && app.mode == AppMode::Board
+                && app.move_guess.is_none()
+                && app.endgame.is_none() =>
+        {
+            take_back(app)
+        }
+        KeyCode::Backspace => {
+            app.input_buffer.pop();
+            if app.quick_input {
+                app.highlights.clear();
+            }
+        }
+        KeyCode::Char(c) => {
+            app.input_buffer.push(c);
+            if app.quick_input {
+                quick_input(app)?;
+            }
+        }
+        _ => {}
+    }
+
+    Ok(true)
+}
+
+// The input line as a command, a search or a move
+fn submit_input(app: &mut App) -> anyhow::Result<()> {
+    let input = std::mem::take(&mut app.input_buffer);
+    app.highlights.clear();
+    if let Some(command) = input.trim().strip_prefix(':') {
+        handle_command(app, command)?;
+    } else if let Some(query) = input.trim().strip_prefix('/') {
+        search_moves(app, query.trim());
+    } else if !input.is_empty() && app.guessing() {
+        answer_guess(app, input.trim())?;
+    } else if !input.is_empty()
+        && app.view.is_some()
+        && app.mode == AppMode::Board
+        && app.move_guess.is_none()
+        && app.endgame.is_none()
+    {
+        branch_move(app, input.trim())?;
+    } else if !input.is_empty() && app.view.is_some() {
+        app.message = "Viewing an earlier position, press End to return to the game.".to_string();
+    } else if !input.is_empty() {
+        submit_move(app, input.trim())?;
+    }
+    Ok(())
+}
+
+// Quick input: a typed source square lights up, the destination square then
+// plays the move at once. Anything else waits for Enter as usual.
+fn quick_input(app: &mut App) -> anyhow::Result<()> {
+    let input = app.input_buffer.as_str();
+    let square = |text: &str| text.parse::<shakmaty::Square>().ok();
+    let from = input.get(..2).and_then(square);
+    match (
+        from,
+        input.get(2..).filter(|_| input.len() == 4).and_then(square),
+    ) {
+        (Some(_), Some(_)) => submit_input(app),
+        (Some(from), None) if input.len() == 2 => {
+            let board = app.shown_board();
+            if board.legal_moves().iter().any(|mv| mv.from() == Some(from)) {
+                app.highlights.push((from, Color::Cyan));
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+// Keys of the game-over overlay
+fn handle_game_over_key(app: &mut App, key: KeyEvent) -> anyhow::Result<bool> {
+    match key.code {
+        KeyCode::Char('r') if app.ladder.is_some() => next_ladder_game(app)?,
+        KeyCode::Char('r') if app.online.is_some() => {
+            app.message = "A rematch of an online game is offered on lichess.".to_string();
+        }
+        KeyCode::Char('r') if app.series.as_ref().is_some_and(|s| s.is_over()) => {
+            app.message = "The match is over, 's' saves all of its games.".to_string();
+        }
+        KeyCode::Char('r') => {
+            // an adaptive match tunes its engine to the score so far; if the
+            // engine does not take it the overlay stays with the error
+            let mut tuned = String::new();
+            if let (Some(elo), [engine]) = (
+                app.series.as_ref().and_then(|s| s.engine_elo()),
+                app.engines.as_mut_slice(),
+            ) {
+                let set = engine
+                    .set_option("UCI_LimitStrength", "true")
+                    .and_then(|()| engine.set_option("UCI_Elo", &elo.to_string()));
+                if let Err(err) = set {
+                    app.message = format!("No rematch, {} kept its strength: {err}", engine.name);
+                    return Ok(true);
+                }
+                tuned = format!(" {} plays at Elo {elo}.", engine.name);
+            }
+            rematch(app);
+            app.message = format!(
+                "Rematch with colors swapped, {} to move.{tuned}",
+                app.board.turn()
+            );
+            app.engine_turn();
+        }
+        KeyCode::Tab => {
+            app.show_game_over = false;
+            app.toggle_analysis();
+        }
+        KeyCode::Char('s') => save_game(app, None),
+        KeyCode::Char('v') => {
+            app.show_game_over = false;
+            start_review(app);
+        }
+        KeyCode::Char('p') => {
+            let (board, solution, puzzle) = load_puzzle(None)?;
+            app.clock = None;
+            app.reset_board(board);
+            app.mode = AppMode::Puzzle {
+                solution,
+                solution_index: 0,
+                completed: false,
+                lichess: puzzle,
+            };
+            app.message = app.start_message();
+        }
+        KeyCode::Esc => app.show_game_over = false,
+        KeyCode::Char('q') => return Ok(false),
+        _ => {}
+    }
+    Ok(true)
+}
+
+// The ladder's next game with the colors swapped, at the next level or from
+// the first again once the climb is over
+fn next_ladder_game(app: &mut App) -> anyhow::Result<()> {
+    let (Some(ladder), [engine]) = (&mut app.ladder, app.engines.as_mut_slice()) else {
+        return Ok(());
+    };
+    if ladder.is_over() {
+        *ladder = ladder::Ladder::new(1);
+    }
+    ladder.tune(engine)?;
+    rematch(app);
+    if let (Some(ladder), [engine]) = (&app.ladder, app.engines.as_slice()) {
+        app.message = ladder_message(ladder, engine);
+    }
+    app.engine_turn();
+    Ok(())
+}
+
+// "Ladder level 3 of 11: Stockfish at Elo 1650, you play black."
+fn ladder_message(ladder: &ladder::Ladder, engine: &engine::Engine) -> String {
     format!(
-        "{}{}",
-        mv.from().map(|f| f.to_string()).unwrap_or("".to_string()),
-        mv.to().to_string()
+        "Ladder level {} of {}: {} at Elo {}, you play {}.",
+        ladder.level,
+        ladder::LEVELS,
+        engine.name,
+        ladder::elo(ladder.level),
+        !engine.color
     )
 }
 
+// The next game between the same players with the colors swapped, from the
+// match's next opening if it has them
+fn rematch(app: &mut App) {
+    let mut start = app.start.clone();
+    if let Some(series) = &mut app.series {
+        series.swap_colors();
+        start = series.opening().unwrap_or(start);
+    }
+    app.flipped = !app.flipped;
+    for engine in &mut app.engines {
+        engine.color = !engine.color;
+    }
+    app.reset_board(start);
+}
+
+// 'y' copies the board as plain text (dark squares dotted), 'Y' as it looks, with colors
+fn copy_board(app: &mut App, ansi: bool) {
+    let low_bandwidth = app.low_bandwidth;
+    app.low_bandwidth |= !ansi;
+    let text = board_as_text(app, ansi);
+    app.low_bandwidth = low_bandwidth;
+    let look = if ansi { "colored text" } else { "plain text" };
+    app.message = match text
+        .map_err(anyhow::Error::from)
+        .and_then(|t| clipboard::copy(&t))
+    {
+        Ok(how) => format!("Board copied as {look} via {how}."),
+        Err(err) => format!("Could not copy the board: {err}"),
+    };
+}
+
+// The games `save_game` wrote to the working directory, oldest first
+fn saved_games() -> anyhow::Result<Vec<std::path::PathBuf>> {
+    let mut files: Vec<std::path::PathBuf> = std::fs::read_dir(".")?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| {
+                    (name.starts_with("tess-game-") || name.starts_with("tess-match-"))
+                        && name.ends_with(".pgn")
+                })
+        })
+        .collect();
+    if files.is_empty() {
+        anyhow::bail!("no games given, and no tess-game-*.pgn files here");
+    }
+    files.sort();
+    Ok(files)
+}
+
+// Write the finished game, or every game of a match, to `path` or a new PGN
+// file in the working directory
+fn save_game(app: &mut App, path: Option<String>) {
+    if let Some(series) = app.series.as_ref().filter(|s| s.length.is_some()) {
+        let path = format!("tess-match-{}.pgn", stats::now());
+        app.message = match std::fs::write(&path, series.report_pgn()) {
+            Ok(()) => format!("Match saved to {path}."),
+            Err(err) => format!("Could not write {path}: {err}"),
+        };
+        return;
+    }
+    let path = path.unwrap_or_else(|| format!("tess-game-{}.pgn", stats::now()));
+    let (result, termination) = match &app.game_over {
+        Some(over) => (over.outcome.to_string(), Some(over.termination())),
+        None => ("*".to_string(), None),
+    };
+    // a loaded game keeps everything it had, the moves played since go in as
+    // variations or at the end
+    let text = match &app.annotated {
+        Some(game) => {
+            let mut game = game.clone();
+            if app.game_over.is_some() {
+                game.set_result(&result);
+            }
+            game.merge(if app.analysing {
+                app.parked.as_ref().map_or(&[][..], |live| &live.history)
+            } else {
+                &app.history
+            });
+            pgn_tree::write(&game)
+        }
+        None => export::pgn(
+            &app.start,
+            &app.history,
+            app.player_names(),
+            &result,
+            termination,
+        ),
+    };
+    app.message = match std::fs::write(&path, text) {
+        Ok(()) => format!("Game saved to {path}."),
+        Err(err) => format!("Could not write {path}: {err}"),
+    };
+}
+
+// ----------------------------------------------
+// Browsing the move list
+// ----------------------------------------------
+fn step_view(app: &mut App, ply: usize) {
+    app.set_view(ply);
+    app.message = describe_shown_move(app);
+}
+
+fn describe_shown_move(app: &App) -> String {
+    let ply = app.shown_ply();
+    if ply == 0 {
+        return "Start position.".to_string();
+    }
+    let (color, san) = app.san_history().swap_remove(ply - 1);
+    let number = app.start.fullmoves().get() as usize
+        + (ply - 1 + usize::from(app.start.turn() == ChessColor::Black)) / 2;
+    let dots = if color == ChessColor::White {
+        "."
+    } else {
+        "..."
+    };
+    let live = if app.view.is_none() {
+        " (current position)"
+    } else {
+        ""
+    };
+    let mut text = format!("{number}{dots}{san}");
+    let annotations = app.annotations();
+    if let Some(node) = annotations.get(ply - 1) {
+        text.push_str(&node.glyphs());
+    }
+    text.push_str(live);
+    if let Some(comment) = app.comments.get(ply - 1).and_then(|c| c.as_deref()) {
+        text.push_str(&format!(": {comment}"));
+    }
+    if let Some(node) = annotations.get(ply - 1) {
+        if node.has_diagram() {
+            text.push_str(" [Diagram]");
+        }
+        if !node.variations.is_empty() {
+            let lines: Vec<String> = node
+                .variations
+                .iter()
+                .map(|line| describe_variation(app, ply - 1, line))
+                .collect();
+            text.push_str(&format!(" Instead: {}", lines.join("; ")));
+        }
+    }
+    text
+}
+
+// The first few moves of a variation that starts at `ply`
+fn describe_variation(app: &App, ply: usize, line: &[pgn_tree::Node]) -> String {
+    const SHOWN: usize = 4;
+    let mut words = Vec::new();
+    for (i, node) in line.iter().take(SHOWN).enumerate() {
+        let at = ply + i + usize::from(app.start.turn() == ChessColor::Black);
+        let number = app.start.fullmoves().get() as usize + at / 2;
+        match at % 2 {
+            0 => words.push(format!("{number}.{}{}", node.san, node.glyphs())),
+            _ if i == 0 => words.push(format!("{number}...{}{}", node.san, node.glyphs())),
+            _ => words.push(format!("{}{}", node.san, node.glyphs())),
+        }
+    }
+    if line.len() > SHOWN {
+        words.push("...".to_string());
+    }
+    words.join(" ")
+}
+
+fn scroll_moves(app: &mut App, delta: isize) {
+    let (scroll, _) = app.moves_layout.get();
+    app.moves_scroll = Some(scroll.saturating_add_signed(delta));
+}
+
+// '+'/'-' grow and shrink the board, '<'/'>' narrow and widen the move list
+fn resize_panes(app: &mut App, key: char) {
+    const MAX_SCALE: usize = 6;
+    let mut layout = app.layout;
+    let scale = layout.board_scale.of(app.display);
+    match key {
+        '+' if *scale < MAX_SCALE => *scale += 1,
+        '-' => *scale = scale.saturating_sub(1),
+        '<' => layout.moves_width = layout.moves_width.saturating_sub(4).max(16),
+        '>' => layout.moves_width = (layout.moves_width + 4).min(80),
+        _ => {}
+    }
+    if layout == app.layout {
+        return;
+    }
+    app.set_layout(layout);
+    app.message = match config::save_layout(layout) {
+        Ok(()) => format!(
+            "Squares {}x{} for this display, move list {} columns.",
+            app.cell_width, app.cell_height, layout.moves_width
+        ),
+        Err(err) => format!("Layout changed but not saved: {err}"),
+    };
+}
+
+// Wheel scrolls the pane under the pointer, a click on a move shows that
+// position; with the beginner help on, the piece pointed at is explained
+fn handle_mouse_event(app: &mut App, mouse: MouseEvent) {
+    const WHEEL_LINES: isize = 3;
+    let at = ScreenPosition::new(mouse.column, mouse.row);
+    if app.beginner.is_some() {
+        let board = app.board_area.get();
+        let pointed = board
+            .contains(at)
+            .then(|| board_widget(app).square_at(mouse.column - board.x, mouse.row - board.y))
+            .flatten();
+        if let Some(assist) = app.beginner.as_mut() {
+            assist.pointed = pointed;
+        }
+    }
+    let in_moves = app.moves_area.get().contains(at);
+    let in_info = app.info_area.get().contains(at);
+    match mouse.kind {
+        MouseEventKind::ScrollUp if in_moves => scroll_moves(app, -WHEEL_LINES),
+        MouseEventKind::ScrollDown if in_moves => scroll_moves(app, WHEEL_LINES),
+        MouseEventKind::ScrollUp if in_info => {
+            app.info_scroll = app
+                .info_scroll
+                .min(app.info_max_scroll.get())
+                .saturating_sub(WHEEL_LINES as usize);
+        }
+        MouseEventKind::ScrollDown if in_info => {
+            app.info_scroll =
+                (app.info_scroll + WHEEL_LINES as usize).min(app.info_max_scroll.get());
+        }
+        MouseEventKind::Down(MouseButton::Left) if in_moves => {
+            let area = app.moves_area.get();
+            let (scroll, _) = app.moves_layout.get();
+            // inside the border
+            let line = (mouse.row.saturating_sub(area.y + 1)) as usize + scroll;
+            let column = mouse.column.saturating_sub(area.x + 1) as usize;
+            let ply = make_moves_text(app)
+                .targets
+                .into_iter()
+                .find(|t| t.line == line && t.columns.contains(&column))
+                .map(|t| t.ply);
+            if let Some(ply) = ply {
+                // keep the list where it is rather than jumping to the move
+                let scroll = app.moves_scroll;
+                step_view(app, ply);
+                app.moves_scroll = scroll;
+            }
+        }
+        _ => {}
+    }
+}
+
+// Jump to the next move whose SAN or comment contains `query`, wrapping around
+fn search_moves(app: &mut App, query: &str) {
+    if query.is_empty() {
+        app.message = "Usage: /<text>, searches moves and comments".to_string();
+        return;
+    }
+    let query = query.to_lowercase();
+    let sans = app.san_history();
+    let total = sans.len();
+    let from = app.shown_ply();
+    let found = (0..total).map(|offset| (from + offset) % total).find(|&i| {
+        sans[i].1.to_lowercase().contains(&query)
+            || app.comments[i]
+                .as_ref()
+                .is_some_and(|c| c.to_lowercase().contains(&query))
+    });
+    match found {
+        Some(i) => step_view(app, i + 1),
+        None => app.message = format!("No move or comment matches '{query}'."),
+    }
+}
+
+// ----------------------------------------------
+// Commands entered with a leading ':' (e.g. ":attackers e5")
+// ----------------------------------------------
+fn handle_command(app: &mut App, command: &str) -> anyhow::Result<()> {
+    let mut args = command.split_whitespace();
+    match args.next() {
+        Some("attackers") => match args.next().map(|a| a.parse::<shakmaty::Square>()) {
+            Some(Ok(sq)) => show_attackers(app, sq),
+            _ => app.message = "Usage: :attackers <square>, e.g. :attackers e5".to_string(),
+        },
+        Some("diagrams") => match (args.next(), args.next().map(|n| n.parse::<usize>())) {
+            (Some(path), None) => export_diagrams(app, path, 1),
+            (Some(path), Some(Ok(every))) if every > 0 => export_diagrams(app, path, every),
+            _ => app.message = "Usage: :diagrams <file> [every N plies]".to_string(),
+        },
+        Some("moves") => match args.next() {
+            Some(pattern) => show_moves(app, pattern),
+            None => {
+                app.message =
+                    "Usage: :moves <square or piece>, e.g. :moves e4, :moves N".to_string()
+            }
+        },
+        Some("save") => save_game(app, args.next().map(str::to_string)),
+        Some("keep") => {
+            let (tags, name): (Vec<&str>, Vec<&str>) = args.partition(|word| word.starts_with('+'));
+            if name.is_empty() {
+                app.message = "Usage: :keep <name> [+tag ...], e.g. :keep Lucena +rook".to_string();
+            } else {
+                let name = name.join(" ");
+                let tags = tags.iter().map(|tag| tag[1..].to_string()).collect();
+                app.message = match positions::save(&name, app.shown_board(), tags) {
+                    Ok(false) => format!("Position saved as '{name}'."),
+                    Ok(true) => format!("Position saved as '{name}', in place of the old one."),
+                    Err(err) => format!("Could not save the position: {err}"),
+                };
+            }
+        }
+        Some("resign") => resign(app),
+        Some("berserk") => go_berserk(app),
+        Some("takeback") => match args.next() {
+            None => answer_takeback(app, true),
+            Some("no") => answer_takeback(app, false),
+            Some(_) => app.message = "Usage: :takeback [no]".to_string(),
+        },
+        Some("pause") => toggle_pause(app),
+        Some("adjourn") => adjourn_game(app, args.next().map(std::path::PathBuf::from)),
+        Some("replay") => start_replay(app),
+        Some("review") => start_review(app),
+        Some("heatmap") => toggle_heatmap(app),
+        Some("beginner") => {
+            app.beginner = match app.beginner.take() {
+                Some(_) => None,
+                None => Some(beginner::Assist::default()),
+            };
+            app.message = if app.beginner.is_some() {
+                "Beginner help: point at a piece or type its square to see how it moves, tips follow moves that lose material."
+                    .to_string()
+            } else {
+                "Beginner help off.".to_string()
+            };
+        }
+        Some("quick") => {
+            app.quick_input = !app.quick_input;
+            app.message = if app.quick_input {
+                "Quick input: type the source and destination square, e.g. e2e4, no Enter needed."
+                    .to_string()
+            } else {
+                "Quick input off, moves are entered with Enter.".to_string()
+            };
+        }
+        Some("options") => open_options(app),
+        Some("hint") if app.mode == AppMode::StandardGame => show_game_hint(app),
+        Some("hint") => match args.next() {
+            None => show_hint(app, Hint::Piece),
+            Some("move") => show_hint(app, Hint::Move),
+            Some(_) => app.message = "Usage: :hint [move]".to_string(),
+        },
+        Some(other) => app.message = format!("Unknown command: {other}"),
+        None => {}
+    }
+    Ok(())
+}
+
+// ----------------------------------------------
+// Engine options: :options lists the options each engine reported in its
+// handshake with their values, and sets the one picked with setoption
+// ----------------------------------------------
+
+#[derive(Debug, Clone, Default)]
+struct OptionsEditor {
+    // the analysis engine first, then the players
+    engine: usize,
+    selected: usize,
+    // the value being typed for the selected option
+    value: Option<String>,
+}
+
+fn option_engines(app: &App) -> impl Iterator<Item = &engine::Engine> {
+    app.analyser.iter().chain(app.engines.iter())
+}
+
+fn option_engine_mut(app: &mut App, index: usize) -> Option<&mut engine::Engine> {
+    app.analyser
+        .iter_mut()
+        .chain(app.engines.iter_mut())
+        .nth(index)
+}
+
+fn open_options(app: &mut App) {
+    let first = option_engines(app).position(|engine| !engine.options().is_empty());
+    match first {
+        None if option_engines(app).next().is_none() => {
+            app.message = "No engine is running.".to_string()
+        }
+        None => app.message = "The engines reported no options.".to_string(),
+        Some(engine) => {
+            app.options_editor = Some(OptionsEditor {
+                engine,
+                ..Default::default()
+            });
+            app.message =
+                "Up/Down pick an option, Enter changes it, Left/Right the engine, Esc closes."
+                    .to_string();
+        }
+    }
+}
+
+// Keys of the options editor
+fn handle_options_key(app: &mut App, mut editor: OptionsEditor, key: KeyEvent) {
+    let engines = option_engines(app).count();
+    let Some(engine) = option_engine_mut(app, editor.engine) else {
+        return;
+    };
+    let count = engine.options().len();
+    match (key.code, editor.value.as_mut()) {
+        (KeyCode::Esc, Some(_)) => editor.value = None,
+        (KeyCode::Esc, None) => {
+            app.message = "Options closed.".to_string();
+            return;
+        }
+        (KeyCode::Char(ch), Some(value)) => value.push(ch),
+        (KeyCode::Backspace, Some(value)) => {
+            value.pop();
+        }
+        (KeyCode::Enter, value) => {
+            let option = &engine.options()[editor.selected];
+            let value = match (&option.kind, value) {
+                (_, Some(value)) => std::mem::take(value),
+                (engine::OptionKind::Button, None) => String::new(),
+                (engine::OptionKind::Check, None) => {
+                    let on = engine.option_value(option) == Some("true");
+                    (!on).to_string()
+                }
+                (_, None) => {
+                    editor.value = Some(engine.option_value(option).unwrap_or("").to_string());
+                    app.options_editor = Some(editor);
+                    return;
+                }
+            };
+            let name = option.name.clone();
+            let message = match set_engine_option(engine, &name, &value) {
+                Ok(()) if value.is_empty() => format!("{}: {name} sent.", engine.name),
+                Ok(()) => format!("{}: {name} set to {value}.", engine.name),
+                Err(err) => format!("{err}."),
+            };
+            app.message = message;
+            editor.value = None;
+        }
+        (KeyCode::Up, None) => {
+            editor.selected = editor.selected.checked_sub(1).unwrap_or(count - 1)
+        }
+        (KeyCode::Down, None) => editor.selected = (editor.selected + 1) % count,
+        (KeyCode::Left | KeyCode::Right, None) if engines > 1 => {
+            let step = if key.code == KeyCode::Left {
+                engines - 1
+            } else {
+                1
+            };
+            editor.selected = 0;
+            // engines without options are passed over
+            for _ in 0..engines {
+                editor.engine = (editor.engine + step) % engines;
+                if option_engines(app)
+                    .nth(editor.engine)
+                    .is_some_and(|engine| !engine.options().is_empty())
+                {
+                    break;
+                }
+            }
+        }
+        _ => {}
+    }
+    app.options_editor = Some(editor);
+}
+
+// UCI options are only set between searches: the analysis starts over with
+// the new value, a player has to make its move first
+fn set_engine_option(engine: &mut engine::Engine, name: &str, value: &str) -> anyhow::Result<()> {
+    if engine.thinking() && !engine.is_analysing() {
+        anyhow::bail!("{} is thinking, set options once it has moved", engine.name);
+    }
+    engine.analyse(None)?;
+    engine.set_option(name, value)
+}
+
+// The options of the engine in the editor, the selected one reversed
+fn make_options_lines(app: &App, editor: &OptionsEditor) -> Vec<Line<'static>> {
+    let Some(engine) = option_engines(app).nth(editor.engine) else {
+        return Vec::new();
+    };
+    let width = engine
+        .options()
+        .iter()
+        .map(|o| o.name.len())
+        .max()
+        .unwrap_or(0);
+    engine
+        .options()
+        .iter()
+        .enumerate()
+        .map(|(i, option)| {
+            let value = match (&editor.value, i == editor.selected) {
+                (Some(typed), true) => format!("{typed}_"),
+                _ => engine.option_value(option).unwrap_or("").to_string(),
+            };
+            let text = format!(" {:width$}  {value:16}  {}", option.name, option.describe());
+            if i == editor.selected {
+                Line::styled(text, Style::default().add_modifier(Modifier::REVERSED))
+            } else {
+                Line::raw(text)
+            }
+        })
+        .collect()
+}
+
+// ----------------------------------------------
+// Control heatmap: who held which squares, played through the game move by
+// move so the shifts of space show
+// ----------------------------------------------
+
+// Positions the heat of a square is averaged over, the shown one and those before
+const HEAT_WINDOW: usize = 4;
+// Time each position stays on screen while the heatmap plays
+const HEAT_STEP: Duration = Duration::from_millis(700);
+
+struct Heatmap {
+    playing: bool,
+    stepped: Instant,
+}
+
+// :heatmap plays the game from the start with the heatmap on, or turns it off
+fn toggle_heatmap(app: &mut App) {
+    if app.heatmap.take().is_some() {
+        app.message = "Heatmap off.".to_string();
+        return;
+    }
+    if app.history.is_empty() {
+        app.message = "The heatmap needs moves to play through.".to_string();
+        return;
+    }
+    app.heatmap = Some(Heatmap {
+        playing: true,
+        stepped: session_log::now(),
+    });
+    app.set_view(0);
+    app.message = format!(
+        "Heatmap: blue squares were white's over the last {HEAT_WINDOW} positions, red black's, \
+         brighter for more control. The arrow keys take over, :heatmap ends it."
+    );
+}
+
+// The next position of a playing heatmap once it is due, true if it moved
+fn advance_heatmap(app: &mut App) -> bool {
+    let ply = app.shown_ply() + 1;
+    let last = app.history.len();
+    let Some(heatmap) = app.heatmap.as_mut().filter(|heatmap| heatmap.playing) else {
+        return false;
+    };
+    if session_log::elapsed(heatmap.stepped) < HEAT_STEP {
+        return false;
+    }
+    heatmap.stepped = session_log::now();
+    heatmap.playing = ply < last;
+    step_view(app, ply);
+    true
+}
+
+// ----------------------------------------------
+// Game review in the TUI: engines like the analysis engine evaluate every
+// move in the background, the flagged moves are then a key away
+// ----------------------------------------------
+
+// Search time per position of a review
+const REVIEW_MOVETIME: Duration = Duration::from_millis(500);
+// The review pane shows that many rows at most, borders included
+const MAX_REVIEW_HEIGHT: usize = 12;
+
+fn start_review(app: &mut App) {
+    let Some(analyser) = &app.analyser else {
+        app.message = "A review needs an engine, start tess with --analysis-engine.".to_string();
+        return;
+    };
+    if app.reviewing.is_some() {
+        app.message = "The review is still running.".to_string();
+        return;
+    }
+    if app.history.is_empty() {
+        app.message = "There are no moves to review.".to_string();
+        return;
+    }
+    let jobs = std::thread::available_parallelism().map_or(1, usize::from);
+    app.reviewing = Some(review::Background::start(
+        app.start.clone(),
+        app.history.clone(),
+        analyser.path.clone(),
+        analyser.settings.clone(),
+        REVIEW_MOVETIME,
+        jobs,
+    ));
+    app.message = format!(
+        "Reviewing {} moves with {}, the board stays free meanwhile.",
+        app.history.len(),
+        analyser.name
+    );
+}
+
+// "White 1 blunder, Black 2 inaccuracies", or that there were none
+fn describe_errors(review: &review::Review) -> String {
+    let counts = review.error_counts();
+    let sides: Vec<String> = [ChessColor::White, ChessColor::Black]
+        .into_iter()
+        .filter_map(|color| {
+            let errors: Vec<String> = [
+                review::Judgement::Blunder,
+                review::Judgement::Mistake,
+                review::Judgement::Inaccuracy,
+            ]
+            .into_iter()
+            .filter(|judgement| counts.get(color)[*judgement as usize] > 0)
+            .map(|judgement| judgement.count(counts.get(color)[judgement as usize]))
+            .collect();
+            (!errors.is_empty())
+                .then(|| format!("{} {}", color.fold_wb("White", "Black"), errors.join(", ")))
+        })
+        .collect();
+    if sides.is_empty() {
+        "no inaccuracies, mistakes or blunders".to_string()
+    } else {
+        sides.join("; ")
+    }
+}
+
+// ']' and '[' show the move the review flagged after or before the one on screen
+fn step_flagged(app: &mut App, forward: bool) {
+    let Some(review) = app.current_review() else {
+        app.message = match app.reviewing {
+            Some(_) => "The review is still running.".to_string(),
+            None => "This game has no review, :review starts one.".to_string(),
+        };
+        return;
+    };
+    let judgements = review.judgements();
+    // the position after a flagged move, in plies from the start
+    let mut flagged = (1..=judgements.len()).filter(|ply| judgements[ply - 1].is_some());
+    let shown = app.shown_ply();
+    let target = if forward {
+        flagged.find(|&ply| ply > shown)
+    } else {
+        flagged.rfind(|&ply| ply < shown)
+    };
+    let Some(ply) = target else {
+        app.message = format!(
+            "No flagged move {} this one.",
+            if forward { "after" } else { "before" }
+        );
+        return;
+    };
+    let (before, after) = (&review.positions[ply - 1], &review.positions[ply]);
+    let judgement = judgements[ply - 1].expect("flagged");
+    let score = |index: usize, pos: &Chess| {
+        review.evaluations[index]
+            .as_ref()
+            .map_or("?".to_string(), |evaluation| {
+                evaluation.score.display(pos.turn())
+            })
+    };
+    let best = review.evaluations[ply - 1]
+        .as_ref()
+        .and_then(|evaluation| evaluation.pv.first())
+        .and_then(|best| uci::parse(before, best))
+        .map(|best| SanPlus::from_move(before.clone(), &best).to_string());
+    let swing = format!(
+        "a {} ({} to {})",
+        judgement.name(),
+        score(ply - 1, before),
+        score(ply, after)
+    );
+    step_view(app, ply);
+    app.message = match best {
+        Some(best) => format!("{} {swing}, best was {best}.", app.message),
+        None => format!("{} {swing}.", app.message),
+    };
+}
+
+// Play the keys of the finished puzzle attempt again, from the puzzle's start
+fn start_replay(app: &mut App) {
+    let AppMode::Puzzle {
+        solution, lichess, ..
+    } = app.mode.clone()
+    else {
+        app.message = "Only a puzzle attempt can be replayed.".to_string();
+        return;
+    };
+    if !app.puzzle_scored {
+        app.message = "Finish the puzzle first, then :replay plays the attempt back.".to_string();
+        return;
+    }
+    if app.attempt.is_empty() {
+        app.message = "Nothing was typed at this puzzle.".to_string();
+        return;
+    }
+    app.board = app.start.clone();
+    app.history.clear();
+    app.comments.clear();
+    app.times.clear();
+    app.evals.clear();
+    app.view = None;
+    app.moves_scroll = None;
+    app.highlights.clear();
+    app.mode = AppMode::Puzzle {
+        solution,
+        solution_index: 0,
+        completed: false,
+        lichess,
+    };
+    app.replay = Some(replay::Replay::new(std::mem::take(&mut app.attempt)));
+    app.message = "Replaying the attempt, Esc skips to the end.".to_string();
+}
+
+// Feed the replay's keys that are due, or all of them; true if any were
+fn advance_replay(app: &mut App, skip: bool) -> anyhow::Result<bool> {
+    // taken out so the keys reach the board rather than the replay
+    let Some(mut replay) = app.replay.take() else {
+        return Ok(false);
+    };
+    let keys = replay.due(skip);
+    for key in &keys {
+        handle_key_event(app, *key)?;
+    }
+    if !replay.is_done() {
+        app.replay = Some(replay);
+        return Ok(!keys.is_empty());
+    }
+    // the puzzle is back where the attempt ended
+    app.attempt = replay.into_recording();
+    app.message.push_str(&format!(
+        " Replay over: {} keys in {}.",
+        app.attempt.len(),
+        clock::format_duration(app.attempt.length())
+    ));
+    Ok(true)
+}
+
+// Write the game as a plain-text move list with ASCII diagrams
+fn export_diagrams(app: &mut App, path: &str, every: usize) {
+    let text = export::diagram_sequence(&app.start, &app.history, every);
+    app.message = match std::fs::write(path, text) {
+        Ok(()) => format!("Diagrams written to {path}."),
+        Err(err) => format!("Could not write {path}: {err}"),
+    };
+}
+
+// :pause stops the clock and the engines' search, again goes on with the game
+fn toggle_pause(app: &mut App) {
+    if app.mode != AppMode::StandardGame || app.game_over.is_some() || app.analysing {
+        app.message = "Only a game in play can be paused.".to_string();
+        return;
+    }
+    if app.online.is_some() {
+        app.message = "An online game goes on, lichess keeps its clock running.".to_string();
+        return;
+    }
+    app.paused = !app.paused;
+    if !app.paused {
+        if let Some(clock) = &mut app.clock {
+            clock.resume(app.board.turn());
+        }
+        app.move_started = session_log::now();
+        app.message = "Game on.".to_string();
+        app.engine_turn();
+        return;
+    }
+    if let Some(clock) = &mut app.clock {
+        clock.pause();
+    }
+    // the search starts over on resuming, its move would come in the pause
+    let mut message = "Game paused, :pause goes on with it.".to_string();
+    for engine in &mut app.engines {
+        if let Err(err) = engine.cancel() {
+            message = format!("{err}.");
+        }
+    }
+    app.message = message;
+}
+
+// :adjourn [file] pauses the game and writes it for `tess resume`
+fn adjourn_game(app: &mut App, path: Option<std::path::PathBuf>) {
+    if app.engines.is_empty() {
+        app.message =
+            "Only a game against an engine is adjourned, :save keeps this one.".to_string();
+        return;
+    }
+    if !app.paused {
+        toggle_pause(app);
+        if !app.paused {
+            return;
+        }
+    }
+    let Some(path) = path.or_else(adjourn::default_path) else {
+        app.message = "No state directory, give :adjourn a file.".to_string();
+        return;
+    };
+    app.message = match adjourn::save(app, &path) {
+        Ok(()) if path == adjourn::default_path().unwrap_or_default() => {
+            "Game adjourned, 'q' quits and `tess resume` plays on.".to_string()
+        }
+        Ok(()) => format!(
+            "Game adjourned to {}, 'q' quits and `tess resume {}` plays on.",
+            path.display(),
+            path.display()
+        ),
+        Err(err) => format!("Could not adjourn the game: {err}"),
+    };
+}
+
+// The side to move gives up the live game
+fn resign(app: &mut App) {
+    if app.analysing || app.mode != AppMode::StandardGame {
+        app.message = "Only a game in play can be resigned.".to_string();
+    } else if app.game_over.is_some() {
+        app.show_game_over = true;
+    } else if let Some(game) = &app.online {
+        // the game ends with the state lichess sends back
+        if let Err(err) = game.resign() {
+            app.message = format!("lichess did not take the resignation: {err}");
+        }
+    } else {
+        let loser = app.board.turn();
+        app.end_game(GameOver {
+            outcome: Outcome::Decisive { winner: !loser },
+            ending: Ending::Resignation,
+        });
+    }
+}
+
+// :takeback proposes one in an online game or accepts the opponent's,
+// :takeback no declines it or withdraws mine
+fn answer_takeback(app: &mut App, accept: bool) {
+    let Some(game) = &app.online else {
+        app.message = "Takebacks are asked for in online games.".to_string();
+        return;
+    };
+    if !game.in_play() {
+        app.message = "The game on lichess is over.".to_string();
+        return;
+    }
+    let opponent = game.opponent();
+    let message = match (game.takeback, accept) {
+        (Some(side), true) if side == game.color => {
+            app.message = format!("Your takeback waits for {opponent}.");
+            return;
+        }
+        (None, false) => {
+            app.message = "No takeback is proposed.".to_string();
+            return;
+        }
+        (Some(_), true) => "Takeback accepted.".to_string(),
+        (None, true) => format!("Takeback proposed to {opponent}."),
+        (Some(side), false) if side == game.color => "Takeback withdrawn.".to_string(),
+        (Some(_), false) => "Takeback declined.".to_string(),
+    };
+    app.message = match game.answer_takeback(accept) {
+        Ok(()) => message,
+        Err(err) => format!("lichess did not take the answer: {err}"),
+    };
+}
+
+// Berserk in an arena game: half my time for a point more with a win,
+// only before my first move
+fn go_berserk(app: &mut App) {
+    let my_moves = app.history.len();
+    let Some(game) = app.online.as_mut().filter(|game| game.tournament.is_some()) else {
+        app.message = "Berserk is for arena games on lichess.".to_string();
+        return;
+    };
+    app.message = if game.berserk {
+        "You went berserk already.".to_string()
+    } else if !game.in_play() || my_moves > game.color.fold_wb(0, 1) {
+        "Berserk only goes before your first move.".to_string()
+    } else {
+        match game.go_berserk() {
+            Ok(()) => "Berserk: half your time, a point more if you win.".to_string(),
+            Err(err) => format!("lichess did not take the berserk: {err}"),
+        }
+    };
+}
+
+// Search time of the opponent engine for a hint
+const HINT_MOVETIME: Duration = Duration::from_millis(300);
+
+// 'H' or :hint in a game: the piece the engine would move, or the whole move when so
+// configured or asked a second time. The analysis engine's line serves if it
+// has one, else the opponent engine searches briefly.
+fn show_game_hint(app: &mut App) {
+    if app.mode != AppMode::StandardGame || app.game_over.is_some() || app.analysing {
+        app.message = "Hints are only available in a game in play.".to_string();
+        return;
+    }
+    if app.online.is_some() {
+        app.message = "No hints in an online game.".to_string();
+        return;
+    }
+    if app.view.is_some() {
+        app.message = "Viewing an earlier position, press End to return to the game.".to_string();
+        return;
+    }
+    let pos = app.board.clone();
+    if let Some(engine) = app.engines.iter().find(|e| e.color == pos.turn()) {
+        app.message = format!("It is {}'s move.", engine.name);
+        return;
+    }
+    let best = match (&app.analyser, app.engines.first_mut()) {
+        (Some(analyser), _) => {
+            let Some(evaluation) = analyser.evaluation_of(&pos) else {
+                app.message = format!(
+                    "{} is still thinking, try again in a moment.",
+                    analyser.name
+                );
+                return;
+            };
+            evaluation.pv.first().cloned()
+        }
+        (None, Some(engine)) => match engine.evaluate(&pos, HINT_MOVETIME) {
+            Ok(evaluation) => evaluation.and_then(|evaluation| evaluation.pv.first().cloned()),
+            Err(err) => {
+                app.message = format!("No hint: {err}.");
+                return;
+            }
+        },
+        (None, None) => {
+            app.message = "A hint needs an engine: play one, or start tess with --analysis-engine."
+                .to_string();
+            return;
+        }
+    };
+    let Some(mv) = best.and_then(|best| uci::parse(&pos, &best)) else {
+        app.message = "The engine has no move to suggest.".to_string();
+        return;
+    };
+    let (Some(from), to) = (mv.from(), mv.to()) else {
+        return;
+    };
+    let ply = app.history.len();
+    let whole = app.game_hint == config::GameHint::Move || app.piece_hinted == Some(ply);
+    app.highlights.push((from, Color::Green));
+    app.message = if whole {
+        app.highlights.push((to, Color::Green));
+        format!("Hint: {}.", SanPlus::from_move(pos, &mv))
+    } else {
+        app.piece_hinted = Some(ply);
+        let role = format!("{:?}", mv.role()).to_lowercase();
+        let key = if notation::is_piece_letter('H', app.input_language) {
+            ":hint"
+        } else {
+            "'H'"
+        };
+        format!("Hint: move the {role} on {from}. {key} again shows the whole move.")
+    };
+}
+
+// Show which piece moves next in the puzzle, or the whole move, at a cost
+fn show_hint(app: &mut App, hint: Hint) {
+    let AppMode::Puzzle {
+        solution,
+        solution_index,
+        completed: false,
+        ..
+    } = &app.mode
+    else {
+        app.message = "Hints are only available in an unsolved puzzle.".to_string();
+        return;
+    };
+    let expected = solution[*solution_index].clone();
+    let Some(from) = expected.from() else {
+        return;
+    };
+    app.hint = app.hint.max(Some(hint));
+    app.highlights.push((from, Color::Green));
+    let cost = app.hint_cost().unwrap_or(0.0);
+    app.message = match hint {
+        Hint::Piece => {
+            let role = app
+                .board
+                .board()
+                .role_at(from)
+                .map_or("piece".to_string(), |role| {
+                    format!("{role:?}").to_lowercase()
+                });
+            format!(
+                "Move the {role} on {from}. Hints so far cost {cost:.1} of this puzzle's score."
+            )
+        }
+        Hint::Move => {
+            app.highlights.push((expected.to(), Color::Green));
+            format!(
+                "Play {}. Hints so far cost {cost:.1} of this puzzle's score.",
+                uci::format(&expected)
+            )
+        }
+    };
+}
+
+// List and highlight the pieces attacking and defending a square
+fn show_attackers(app: &mut App, sq: shakmaty::Square) {
+    let board = app.shown_board().board();
+    let white = analysis::attackers_of(board, sq, ChessColor::White);
+    let black = analysis::attackers_of(board, sq, ChessColor::Black);
+
+    let describe = |pieces: &[(shakmaty::Square, shakmaty::Piece)]| {
+        if pieces.is_empty() {
+            "nothing".to_string()
+        } else {
+            pieces
+                .iter()
+                .map(|(from, piece)| match piece.role {
+                    Role::Pawn => from.to_string(),
+                    role => format!("{}{from}", role.upper_char()),
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        }
+    };
+
+    app.message = match board.piece_at(sq) {
+        Some(piece) => {
+            let (defenders, attackers) = if piece.color == ChessColor::White {
+                (&white, &black)
+            } else {
+                (&black, &white)
+            };
+            format!(
+                "{sq} ({} {}): attacked by {}, defended by {}.",
+                piece.color,
+                format!("{:?}", piece.role).to_lowercase(),
+                describe(attackers),
+                describe(defenders)
+            )
+        }
+        None => format!(
+            "{sq}: white {}, black {}.",
+            describe(&white),
+            describe(&black)
+        ),
+    };
+
+    app.highlights.push((sq, Color::Cyan));
+    app.highlights
+        .extend(white.iter().map(|(from, _)| (*from, Color::Blue)));
+    app.highlights
+        .extend(black.iter().map(|(from, _)| (*from, Color::Red)));
+}
+
+// List and highlight the legal moves from a square, or by one kind of piece
+fn show_moves(app: &mut App, pattern: &str) {
+    let pos = app.shown_board().clone();
+    let role = || {
+        let letter = notation::localize_input(&pattern.to_ascii_uppercase(), app.input_language);
+        match letter.chars().collect::<Vec<_>>()[..] {
+            [ch] => Role::from_char(ch.to_ascii_lowercase()),
+            _ => None,
+        }
+    };
+    let (what, moves): (String, Vec<Move>) = if let Ok(sq) = pattern.parse::<shakmaty::Square>() {
+        let moves = pos
+            .legal_moves()
+            .into_iter()
+            .filter(|mv| mv.from() == Some(sq));
+        (format!("moves from {sq}"), moves.collect())
+    } else if let Some(role) = role() {
+        let moves = pos.legal_moves().into_iter().filter(|mv| mv.role() == role);
+        (format!("{role:?} moves").to_lowercase(), moves.collect())
+    } else {
+        app.message = format!("Not a square or a piece: {pattern}");
+        return;
+    };
+    if moves.is_empty() {
+        app.message = format!("No legal {what}.");
+        return;
+    }
+    let color = pos.turn();
+    let sans: Vec<String> = moves
+        .iter()
+        .map(|mv| {
+            let san = SanPlus::from_move(pos.clone(), mv).to_string();
+            notation::format_san(&san, color, app.notation)
+        })
+        .collect();
+    app.message = format!("{} legal {what}: {}", moves.len(), sans.join(" "));
+    for mv in &moves {
+        if let Some(from) = mv.from() {
+            app.highlights.push((from, Color::Cyan));
+        }
+        app.highlights.push((mv.to(), Color::Green));
+    }
+}
+
+// Highlight hanging pieces and one-move forks/skewers for the side to move
+fn show_tactics(app: &mut App) {
+    app.highlights.clear();
+    let hanging = analysis::hanging_pieces(app.shown_board().board());
+    let tactics = analysis::find_tactics(app.shown_board());
+
+    let mut parts = Vec::new();
+    if !hanging.is_empty() {
+        let list: Vec<String> = hanging
+            .iter()
+            .map(|(sq, piece)| format!("{} {sq}", piece.color))
+            .collect();
+        parts.push(format!("hanging: {}", list.join(", ")));
+    }
+    if !tactics.is_empty() {
+        let list: Vec<String> = tactics
+            .iter()
+            .map(|t| {
+                let kind = match t.kind {
+                    analysis::TacticKind::Fork => "fork",
+                    analysis::TacticKind::Skewer => "skewer",
+                };
+                format!("{} ({kind})", t.san)
+            })
+            .collect();
+        parts.push(format!(
+            "{} can play {}",
+            app.shown_board().turn(),
+            list.join(", ")
+        ));
+    }
+
+    app.message = if parts.is_empty() {
+        "No hanging pieces or simple tactics found.".to_string()
+    } else {
+        format!("Tactics: {}.", parts.join("; "))
+    };
+
+    app.highlights
+        .extend(hanging.iter().map(|(sq, _)| (*sq, Color::LightRed)));
+    for tactic in &tactics {
+        app.highlights.push((tactic.to, Color::Green));
+        app.highlights
+            .extend(tactic.targets.iter().map(|sq| (*sq, Color::LightGreen)));
+    }
+}
+
+// Handle moves for standard game mode
+fn handle_standard_move(app: &mut App, mv: &Move, input: &str) -> anyhow::Result<()> {
+    // the clock only belongs to the live game
+    let clock = if app.analysing {
+        None
+    } else {
+        app.clock.as_mut()
+    };
+    if clock.is_some_and(|c| c.flagged().is_some()) {
+        app.message = "The game was lost on time. Press 'n' for a new game.".to_string();
+        return Ok(());
+    }
+    if let Some(over) = app.game_over.as_ref().filter(|_| !app.analysing) {
+        app.message = format!(
+            "The game is over: {}. Press 'n' for a new game.",
+            over.describe()
+        );
+        return Ok(());
+    }
+    if app.paused && !app.analysing {
+        app.message = "The game is paused, :pause goes on with it.".to_string();
+        return Ok(());
+    }
+    let turn = app.board.turn();
+    if let Some(engine) = app
+        .engines
+        .iter()
+        .find(|e| e.color == turn && !app.analysing)
+    {
+        app.message = format!("It is {}'s move.", engine.name);
+        return Ok(());
+    }
+    // an online move counts once lichess has it
+    if let Some(game) = app.online.as_ref().filter(|_| !app.analysing) {
+        if !game.in_play() {
+            app.message = "The game on lichess is over.".to_string();
+            return Ok(());
+        }
+        if turn != game.color {
+            app.message = format!("It is {}'s move.", game.opponent());
+            return Ok(());
+        }
+        if let Err(err) = game.play(&uci::format(mv)) {
+            app.message = format!("lichess did not take the move: {err}");
+            return Ok(());
+        }
+    }
+
+    play_game_move(app, mv, format!("Move {input} played."))?;
+    app.moved = !app.analysing;
+    app.engine_turn();
+    Ok(())
+}
+
+// A move of either player in a standard game: the clock, the end of the
+// game and the hotseat handover
+fn play_game_move(app: &mut App, mv: &Move, played: String) -> anyhow::Result<()> {
+    let mover = app.board.turn();
+    app.play(mv)?;
+    if !app.analysing {
+        if let Some(time) = app.times.last_mut() {
+            *time = Some(session_log::elapsed(app.move_started));
+        }
+        app.move_started = session_log::now();
+    }
+    if let Some(clock) = app.clock.as_mut().filter(|_| !app.analysing) {
+        clock.press(mover);
+    }
+    let turn = app.board.turn();
+    app.message = format!("{played} {turn} to move.");
+    // engines playing each other take the draws they could claim
+    let over = GameOver::of_position(&app.board).or_else(|| {
+        (app.engines.len() > 1)
+            .then(|| GameOver::claimable(&app.start, &app.history, &app.board))
+            .flatten()
+    });
+    match over {
+        Some(over) if !app.analysing => app.end_game(over),
+        Some(over) => app.message = format!("{}.", over.describe()),
+        None if app.rotate && !app.analysing => {
+            app.flipped = turn == ChessColor::Black;
+            app.handover = true;
+        }
+        None => {}
+    }
+    Ok(())
+}
+
+// Any legal move on the analysis board, which only reports how a finished line ended
+fn handle_board_move(app: &mut App, mv: &Move, input: &str) -> anyhow::Result<()> {
+    app.play(mv)?;
+    app.message = match GameOver::of_position(&app.board) {
+        Some(over) => format!("Move {input} played. {}.", over.describe()),
+        None => format!("Move {input} played. {} to move.", app.board.turn()),
+    };
+    Ok(())
+}
+
+// A move typed at an earlier position of the analysis board replaces the rest of the line
+fn branch_move(app: &mut App, input: &str) -> anyhow::Result<()> {
+    let pos = app.shown_board().clone();
+    let legal = parse_move(&pos, input, app.input_language).is_some()
+        || promotion_without_piece(&pos, input, app.input_language).is_some();
+    if !legal {
+        app.message = format!("Illegal or unrecognized move: {}", input);
+        return Ok(());
+    }
+    let ply = app.shown_ply();
+    let dropped = app.history.len() - ply;
+    app.history.truncate(ply);
+    app.comments.truncate(ply);
+    app.times.truncate(ply);
+    app.evals.truncate(ply);
+    app.board = pos;
+    app.view = None;
+    submit_move(app, input)?;
+    app.message
+        .push_str(&format!(" New line, {dropped} later moves dropped."));
+    Ok(())
+}
+
+// Undo the last move of the analysis board
+fn take_back(app: &mut App) {
+    if app.history.pop().is_none() {
+        app.message = "No move to take back.".to_string();
+        return;
+    }
+    app.comments.pop();
+    app.times.pop();
+    app.evals.pop();
+    let mut pos = app.start.clone();
+    for mv in &app.history {
+        pos.play_unchecked(mv);
+    }
+    app.board = pos;
+    app.view = None;
+    app.moves_scroll = None;
+    app.highlights.clear();
+    app.message = format!("Move taken back, {} to move.", app.board.turn());
+}
+
+// ----------------------------------------------
+// Repertoire training
+// ----------------------------------------------
+
+// A new line from the start, the opponent's first move played if it is theirs
+fn next_line(app: &mut App) -> anyhow::Result<()> {
+    let Some(repertoire) = app.repertoire.as_mut() else {
+        return Ok(());
+    };
+    let start = repertoire.start();
+    app.reset_board(start);
+    app.message = "New line.".to_string();
+    repertoire_reply(app)
+}
+
+// The opponent's move from the file when it is their turn, and whether the
+// line goes on
+fn repertoire_reply(app: &mut App) -> anyhow::Result<()> {
+    let Some(repertoire) = app.repertoire.as_mut() else {
+        return Ok(());
+    };
+    let color = repertoire.color;
+    if app.board.turn() != color {
+        if let Some(reply) = repertoire.reply(&app.board) {
+            let san = SanPlus::from_move(app.board.clone(), &reply);
+            app.play(&reply)?;
+            app.message
+                .push_str(&format!(" The opponent played {san}."));
+        }
+    }
+    let Some(repertoire) = app.repertoire.as_ref() else {
+        return Ok(());
+    };
+    if repertoire.line_ends(&app.board) {
+        app.message
+            .push_str(" That is the end of the line, 'n' for the next one.");
+    } else {
+        app.message.push_str(&format!(" Your move as {color}."));
+    }
+    Ok(())
+}
+
+// My move in a repertoire line: played if it is prepared, held back otherwise
+fn handle_repertoire_move(app: &mut App, mv: &Move, input: &str) -> anyhow::Result<()> {
+    let Some(repertoire) = app.repertoire.as_mut() else {
+        return Ok(());
+    };
+    if app.board.turn() != repertoire.color || repertoire.line_ends(&app.board) {
+        app.message = "The line is over, 'n' for the next one.".to_string();
+        return Ok(());
+    }
+    match repertoire.answer(&app.start, &app.history, mv)? {
+        repertoire::Answer::Prepared => {
+            app.play(mv)?;
+            app.message = format!("{input} as prepared.");
+            repertoire_reply(app)?;
+        }
+        repertoire::Answer::Deviation(prepared) => {
+            let prepared: Vec<String> = prepared.iter().map(ToString::to_string).collect();
+            app.message = format!(
+                "{input} is not in the repertoire, {} is prepared. Play it to go on.",
+                prepared.join(" or ")
+            );
+        }
+    }
+    Ok(())
+}
+
+// ----------------------------------------------
+// Guess the move
+// ----------------------------------------------
+
+// The game from the start again, played out up to my first guess
+fn start_move_guess(app: &mut App) -> anyhow::Result<()> {
+    let Some(training) = app.move_guess.as_mut() else {
+        return Ok(());
+    };
+    training.restart();
+    let start = training.start.clone();
+    let message = format!(
+        "Guess the moves of {} in {}.",
+        training.color, training.game
+    );
+    app.reset_board(start);
+    app.message = message;
+    move_guess_replies(app)
+}
+
+// The game's moves up to my next guess, and the score once it is over
+fn move_guess_replies(app: &mut App) -> anyhow::Result<()> {
+    let Some(training) = app.move_guess.as_ref() else {
+        return Ok(());
+    };
+    let moves = training.until_my_turn(app.history.len()).to_vec();
+    for mv in &moves {
+        app.play(mv)?;
+    }
+    let Some(training) = app.move_guess.as_ref() else {
+        return Ok(());
+    };
+    if training.over(app.history.len()) {
+        app.message.push_str(&format!(
+            " The game is over: {}. 'n' goes through it again.",
+            training.score()
+        ));
+    } else {
+        app.message
+            .push_str(&format!(" Your move as {}.", training.color));
+    }
+    Ok(())
+}
+
+// My guess, scored, then the move that was played in its place
+fn handle_move_guess(app: &mut App, mv: &Move) -> anyhow::Result<()> {
+    let ply = app.history.len();
+    let board = app.board.clone();
+    let Some(training) = app.move_guess.as_mut() else {
+        return Ok(());
+    };
+    let Some(played) = training.played(ply).cloned() else {
+        app.message = "The game is over, 'n' goes through it again.".to_string();
+        return Ok(());
+    };
+    app.message = training.guess(&board, ply, mv)?;
+    app.play(&played)?;
+    move_guess_replies(app)
+}
+
+// ----------------------------------------------
+// Endgame drills
+// ----------------------------------------------
+
+// The drill from its start again
+fn start_endgame(app: &mut App) {
+    let Some(trainer) = app.endgame.as_mut() else {
+        return;
+    };
+    trainer.restart();
+    let start = trainer.ending.position();
+    let message = format!("{} Your move.", trainer.task());
+    app.reset_board(start);
+    app.message = message;
+}
+
+// My move, judged, then the best defence to it
+fn handle_endgame_move(app: &mut App, mv: &Move) -> anyhow::Result<()> {
+    let board = app.board.clone();
+    let Some(trainer) = app.endgame.as_mut() else {
+        return Ok(());
+    };
+    if trainer.over() {
+        app.message = "The drill is over, 'n' tries again.".to_string();
+        return Ok(());
+    }
+    let answer = trainer.answer(&board, mv)?;
+    app.play(mv)?;
+    app.message = answer.verdict;
+    let Some(reply) = answer.reply else {
+        return Ok(());
+    };
+    let san = SanPlus::from_move(app.board.clone(), &reply);
+    app.play(&reply)?;
+    app.message.push_str(&format!(" {san} in reply."));
+    if let Some(trainer) = app.endgame.as_mut() {
+        if let Some(verdict) = trainer.replied(&app.board) {
+            app.message.push_str(&format!(" {verdict}"));
+        }
+    }
+    Ok(())
+}
+
+// ----------------------------------------------
+// Puzzle sets
+// ----------------------------------------------
+
+// Rate the puzzle on the board once, and score it in a set, saving the
+// results once the set is done
+fn score_puzzle(app: &mut App, solved: bool) {
+    let AppMode::Puzzle { lichess, .. } = &app.mode else {
+        return;
+    };
+    if app.puzzle_scored {
+        return;
+    }
+    app.puzzle_scored = true;
+    let hint_cost = app.hint_cost();
+    let score = if solved {
+        (1.0 - hint_cost.unwrap_or(0.0)).max(0.0)
+    } else {
+        0.0
+    };
+    let attempt = history::Attempt {
+        at: stats::now(),
+        id: lichess.puzzle.id.clone(),
+        rating: lichess.puzzle.rating,
+        fen: Fen::from_position(app.start.clone(), EnPassantMode::Legal).to_string(),
+        solution: lichess.puzzle.solution.clone(),
+        moves: app.history.iter().map(uci::format).collect(),
+        solved,
+        score,
+    };
+    // best effort, like the rating below
+    let _ = history::keep_puzzle(&attempt);
+    // study chapters and exercises from a game have no rating to play against
+    let unrated = lichess.puzzle.rating == 0;
+    if unrated && app.puzzle_set.is_none() {
+        app.message
+            .push_str(&format!(" Score: {:.0}%.", score * 100.0));
+    } else if !unrated {
+        match stats::rate_puzzle(lichess.puzzle.rating, score) {
+            Ok((before, rating)) => {
+                app.message.push_str(&format!(
+                    " Your puzzle rating: {:.0} -> {:.0} ({:+.0}).",
+                    before,
+                    rating.rating,
+                    rating.rating - before
+                ));
+                app.puzzle_rating = rating;
+            }
+            Err(err) => app
+                .message
+                .push_str(&format!(" Your rating was not saved: {err}.")),
+        }
+    }
+    let Some(set) = &mut app.puzzle_set else {
+        return;
+    };
+    if set.record(solved, hint_cost) {
+        finish_set(app);
+    } else if !solved {
+        app.message.push_str(" Press 'n' for the next puzzle.");
+    }
+}
+
+// Save a finished set to the stats
+fn finish_set(app: &mut App) {
+    let Some(set) = &app.puzzle_set else {
+        return;
+    };
+    let what = set.label();
+    app.message = match stats::append(&set.record_for_stats()) {
+        Ok(()) => format!("{what} finished. Press 'q' to quit."),
+        Err(err) => format!("{what} finished, but the result was not saved: {err}"),
+    };
+}
+
+// 'n' in a set: skipping counts as a failed puzzle
+fn next_set_puzzle(app: &mut App) -> anyhow::Result<()> {
+    score_puzzle(app, false);
+    let Some(set) = &app.puzzle_set else {
+        return Ok(());
+    };
+    if set.is_finished() {
+        return Ok(());
+    }
+    let (board, solution, puzzle) = load_set_puzzle(set)?;
+    let rating = puzzle.puzzle.rating;
+    app.reset_board(board);
+    app.mode = AppMode::Puzzle {
+        solution,
+        solution_index: 0,
+        completed: false,
+        lichess: puzzle,
+    };
+    if let Some(set) = &mut app.puzzle_set {
+        set.begin(rating);
+        app.message = format!("{}. {}", set.progress(), app.start_message());
+    }
+    Ok(())
+}
+
+// ----------------------------------------------
+// Guessing the evaluation
+// ----------------------------------------------
+
+// Put the quiz's next position on the board
+fn next_guess(app: &mut App) -> anyhow::Result<()> {
+    let Some(quiz) = app.quiz.as_mut() else {
+        return Ok(());
+    };
+    let pos = quiz.next()?.clone();
+    let tolerance = f64::from(quiz.tolerance) / 100.0;
+    let turn = pos.turn();
+    app.reset_board(pos);
+    app.message = format!(
+        "Guess the evaluation from white's side in pawns (e.g. +1.5 or -0.3), then Enter; within {tolerance:.2} counts. {turn} to move."
+    );
+    Ok(())
+}
+
+// Check a typed guess against the engine
+fn answer_guess(app: &mut App, text: &str) -> anyhow::Result<()> {
+    let Some(cp) = eval_quiz::parse_guess(text) else {
+        app.message = format!("{text:?} is not an evaluation, type pawns like +1.5 or -0.3.");
+        return Ok(());
+    };
+    let Some(quiz) = app.quiz.as_mut() else {
+        return Ok(());
+    };
+    let guess = quiz.answer(cp)?;
+    let pawns = |cp: i32| f64::from(cp) / 100.0;
+    let verdict = if guess.within() {
+        "within the band"
+    } else {
+        "outside the band"
+    };
+    let mut message = format!(
+        "{}: {:+.2}, you said {:+.2}, {verdict}.",
+        quiz.engine_name(),
+        pawns(guess.engine),
+        pawns(guess.guess)
+    );
+    if let Some(calibration) = eval_quiz::calibration(quiz.session()) {
+        message.push_str(&format!(" Session: {}.", calibration.describe()));
+    }
+    message.push_str(" Press 'n' for the next position.");
+    app.message = message;
+    Ok(())
+}
+
+// Session, recent and all-time calibration of a `guess` run
+fn make_quiz_lines(quiz: &eval_quiz::Quiz) -> Vec<Line<'static>> {
+    [
+        ("Session", quiz.session()),
+        ("Recent", eval_quiz::recent(&quiz.history)),
+        ("All time", quiz.history.as_slice()),
+    ]
+    .into_iter()
+    .map(|(label, guesses)| {
+        Line::raw(match eval_quiz::calibration(guesses) {
+            Some(calibration) => format!("{label}: {}", calibration.describe()),
+            None => format!("{label}: no guesses yet"),
+        })
+    })
+    .collect()
+}
+
+// ----------------------------------------------
+// Exercises from a game under review: the position on screen as an
+// unrated puzzle, solved by the continuation played or the engine's
+// ----------------------------------------------
+
+// The trainee's move, the reply, and the trainee's next move
+const EXERCISE_PLIES: usize = 3;
+
+// A puzzle for the side to move in the position on screen, `best` asks for
+// the analysis engine's line rather than the game's
+fn exercise_from(app: &App, best: bool) -> anyhow::Result<App> {
+    if let AppMode::Puzzle { .. } = app.mode {
+        anyhow::bail!("only a game can be trained from, not a puzzle");
+    }
+    let pos = app.shown_board().clone();
+    let (line, what) = if best {
+        let Some(analyser) = &app.analyser else {
+            anyhow::bail!("the engine's best move needs --analysis-engine");
+        };
+        let Some(evaluation) = analyser.evaluation_of(&pos) else {
+            anyhow::bail!("{} has not analysed this position yet", analyser.name);
+        };
+        let mut after = pos.clone();
+        let line: Vec<Move> = evaluation
+            .pv
+            .iter()
+            .map_while(|text| {
+                let mv = uci::parse(&after, text)?;
+                after.play_unchecked(&mv);
+                Some(mv)
+            })
+            .collect();
+        (line, format!("{}'s best move", analyser.name))
+    } else {
+        let line = app.history[app.shown_ply()..].to_vec();
+        (line, "the move played in the game".to_string())
+    };
+    if line.is_empty() {
+        anyhow::bail!("there is no move to find from this position");
+    }
+    // the exercise ends on a move of the trainee
+    let plies = line.len().min(EXERCISE_PLIES);
+    let solution = line[..plies - (1 - plies % 2)].to_vec();
+
+    let turn = pos.turn();
+    let number = match turn {
+        ChessColor::White => format!("{}.", pos.fullmoves()),
+        ChessColor::Black => format!("{}...", pos.fullmoves()),
+    };
+    let fen = Fen::from_position(pos.clone(), EnPassantMode::Legal);
+    let puzzle = LichessNextPuzzle {
+        puzzle: Puzzle {
+            id: format!("from move {number}"),
+            rating: 0,
+            solution: solution.iter().map(uci::format).collect(),
+            initial_ply: 0,
+        },
+        game: Game {
+            pgn: format!("[FEN \"{fen}\"]\n\n*"),
+        },
+    };
+    let mut exercise = App::new_puzzle(pos, solution, app.display, puzzle);
+    exercise.flipped = turn == ChessColor::Black;
+    exercise.message = format!("Find {what} at move {number}, {turn} to move.");
+    Ok(exercise)
+}
+
+// A game from the end of a solved puzzle against a fresh copy of the analysis
+// engine, to convert the position won
+fn play_on_from(app: &App) -> anyhow::Result<App> {
+    let AppMode::Puzzle {
+        completed: true, ..
+    } = app.mode
+    else {
+        anyhow::bail!("only a solved puzzle can be played on");
+    };
+    if app.board.is_game_over() {
+        anyhow::bail!("the puzzle ends the game");
+    }
+    let Some(analyser) = &app.analyser else {
+        anyhow::bail!("playing on needs an engine, start tess with --analysis-engine");
+    };
+    // the puzzle ends on my move, the engine takes the other side
+    let color = app.board.turn();
+    let engine = engine::Engine::start(&analyser.path)?
+        .with_options(&analyser.settings)?
+        .playing(color, engine::Strength::default())?;
+    let mut game = App::new_standard(app.start.clone(), app.display);
+    for mv in &app.history {
+        game.play(mv)?;
+    }
+    game.flipped = app.flipped;
+    game.message = format!(
+        "Playing on against {}, {} to move. Convert the position!",
+        engine.name, color
+    );
+    game.engines.push(engine);
+    game.engine_turn();
+    Ok(game)
+}
+
+// ----------------------------------------------
+// Typed moves, in UCI or SAN in every mode
+// ----------------------------------------------
+fn submit_move(app: &mut App, input: &str) -> anyhow::Result<()> {
+    if app.puzzle_set.as_ref().is_some_and(|set| set.is_finished()) {
+        app.message = "This puzzle session is over. Press 'q' to quit.".to_string();
+        return Ok(());
+    }
+    if let AppMode::Puzzle {
+        completed: true, ..
+    } = app.mode
+    {
+        app.message = "The puzzle is solved, press 'n' for the next one.".to_string();
+        return Ok(());
+    }
+    if let Some(mv) = parse_move(&app.board, input, app.input_language) {
+        return play_input_move(app, mv, input);
+    }
+    if let Some(mv) = promotion_without_piece(&app.board, input, app.input_language) {
+        app.message = format!(
+            "Promote on {} to: q(ueen), r(ook), b(ishop) or n(ight)? Any other key cancels.",
+            mv.to()
+        );
+        app.pending_promotion = Some(mv);
+        return Ok(());
+    }
+    let candidates = ambiguous_candidates(&app.board, input, app.input_language);
+    if !candidates.is_empty() {
+        app.message = format!(
+            "{input} is ambiguous: Up/Down and Enter choose the piece, or press its file. Esc cancels."
+        );
+        app.highlights.extend(
+            candidates
+                .iter()
+                .filter_map(|mv| mv.from())
+                .map(|sq| (sq, Color::Cyan)),
+        );
+        app.pending_choice = Some((candidates, 0));
+        return Ok(());
+    }
+    app.message = format!("Illegal or unrecognized move: {}", input);
+    Ok(())
+}
+
+// The legal moves a SAN matching more than one piece could mean, empty if it is not ambiguous
+fn ambiguous_candidates(board: &Chess, input: &str, language: notation::Language) -> Vec<Move> {
+    let english = notation::localize_input(input, language);
+    let Ok(san) = SanPlus::from_ascii(english.as_bytes()).map(|san| san.san) else {
+        return Vec::new();
+    };
+    match san.to_move(board) {
+        Err(shakmaty::san::SanError::AmbiguousSan) => board
+            .legal_moves()
+            .into_iter()
+            .filter(|mv| san.matches(mv))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+// Keys of the ambiguous move chooser
+fn handle_choice_key(
+    app: &mut App,
+    candidates: Vec<Move>,
+    selected: usize,
+    key: KeyEvent,
+) -> anyhow::Result<()> {
+    let chosen = match key.code {
+        KeyCode::Enter => candidates.get(selected).cloned(),
+        // the file of the piece, when no other candidate stands on it
+        KeyCode::Char(file) => {
+            let mut on_file = candidates
+                .iter()
+                .filter(|mv| mv.from().is_some_and(|sq| sq.file().char() == file));
+            match (on_file.next(), on_file.next()) {
+                (Some(mv), None) => Some(mv.clone()),
+                _ => None,
+            }
+        }
+        KeyCode::Esc => {
+            app.highlights.clear();
+            app.message = "Move cancelled.".to_string();
+            return Ok(());
+        }
+        _ => None,
+    };
+    let Some(mv) = chosen else {
+        let selected = match key.code {
+            KeyCode::Up => selected.checked_sub(1).unwrap_or(candidates.len() - 1),
+            KeyCode::Down => (selected + 1) % candidates.len(),
+            _ => selected,
+        };
+        app.pending_choice = Some((candidates, selected));
+        return Ok(());
+    };
+    app.highlights.clear();
+    let input = SanPlus::from_move(app.board.clone(), &mv).to_string();
+    play_input_move(app, mv, &input)
+}
+
+// Play a legal move in the current mode, `input` is how the user wrote it
+fn play_input_move(app: &mut App, mv: Move, input: &str) -> anyhow::Result<()> {
+    match app.mode.clone() {
+        AppMode::StandardGame => handle_standard_move(app, &mv, input)?,
+        AppMode::Board if app.repertoire.is_some() => handle_repertoire_move(app, &mv, input)?,
+        AppMode::Board if app.move_guess.is_some() => handle_move_guess(app, &mv)?,
+        AppMode::Board if app.endgame.is_some() => handle_endgame_move(app, &mv)?,
+        AppMode::Board => handle_board_move(app, &mv, input)?,
+        AppMode::Puzzle {
+            solution,
+            solution_index,
+            lichess,
+            ..
+        } => {
+            let (new_index, completed) =
+                handle_puzzle_move(app, &mv, input, &solution, &solution_index)?;
+            app.mode = AppMode::Puzzle {
+                solution,
+                solution_index: new_index,
+                completed,
+                lichess,
+            };
+            // the first wrong move fails the puzzle for good
+            if completed || new_index == solution_index {
+                score_puzzle(app, completed);
+            }
+        }
+    }
+    Ok(())
+}
+
+// A legal move written in UCI or SAN, SAN with localized piece letters
+fn parse_move(board: &Chess, input: &str, language: notation::Language) -> Option<Move> {
+    uci::parse(board, input).or_else(|| {
+        let english = notation::localize_input(input, language);
+        SanPlus::from_ascii(english.as_bytes())
+            .ok()?
+            .san
+            .to_move(board)
+            .ok()
+    })
+}
+
+// A promotion typed without the piece (e7e8, e8), as a queen promotion
+fn promotion_without_piece(
+    board: &Chess,
+    input: &str,
+    language: notation::Language,
+) -> Option<Move> {
+    parse_move(board, &format!("{input}q"), language)
+        .or_else(|| parse_move(board, &format!("{input}=Q"), notation::Language::En))
+        .filter(|mv| mv.is_promotion())
+}
+
+// Legal moves the partial input can still become, None if no move is being typed
+fn matching_moves(app: &App) -> Option<usize> {
+    let input = app.input_buffer.trim();
+    if input.is_empty() || input.starts_with([':', '/']) {
+        return None;
+    }
+    if parse_move(&app.board, input, app.input_language).is_some() {
+        return Some(1);
+    }
+    let san_prefix = notation::localize_input(input, app.input_language).replace('0', "O");
+    let uci_prefix = input.to_ascii_lowercase();
+    let count = app
+        .board
+        .legal_moves()
+        .iter()
+        .filter(|mv| {
+            let san = SanPlus::from_move(app.board.clone(), mv).to_string();
+            // captures may be typed without the 'x'
+            san.starts_with(&san_prefix)
+                || san.replace('x', "").starts_with(&san_prefix)
+                || uci::format(mv).starts_with(&uci_prefix)
+        })
+        .count();
+    Some(count)
+}
+
+// Handle puzzle logic
+fn handle_puzzle_move(
+    app: &mut App,
+    user_move: &Move,
+    input: &str,
+    solution: &[Move],
+    solution_index: &usize,
+) -> anyhow::Result<(usize, bool)> {
+    let expected_move = &solution[*solution_index];
+    let mut new_index = *solution_index;
+
+    if user_move != expected_move {
+        app.message = format!(
+            "Incorrect move. Expected UCI: {}. Puzzle failed. Press 'n' for a new puzzle.",
+            uci::format(expected_move)
+        );
+        return Ok((new_index, false));
+    }
+
+    // correct
+    app.play(user_move)?;
+    new_index += 1;
+
+    // Check if puzzle finished
+    if new_index >= solution.len() {
+        app.message = format!(
+            "Puzzle solved! Congratulations. Press 'n' for a new puzzle{}.",
+            if app.analyser.is_some() && !app.board.is_game_over() {
+                ", 'c' to play on against the engine"
+            } else {
+                ""
+            }
+        );
+        return Ok((new_index, true));
+    }
+
+    // next move from the puzzle belongs to the "opponent", auto-play it
+    let next: &Move = &solution[new_index];
+    app.play(next)?;
+    new_index += 1;
+    app.message = format!(
+        "Move {} was correct! Opponent played: {}",
+        input,
+        uci::format(next)
+    );
+    Ok((new_index, false))
+}
+
 struct LastPosition {
     pos: Chess,
     moves: usize,
-    max_ply: Option<usize>,
+    // mainline moves to play at most
+    plies: Option<usize>,
+    start: Chess,
+    played: Vec<Move>,
+    comments: Vec<Option<String>>,
+    // the [%clk] left after each move, and the TimeControl tag
+    clocks: Vec<Option<Duration>>,
+    time_control: Option<(Duration, Duration)>,
+    // the [%eval] after each move
+    evals: Vec<Option<i32>>,
+    white: String,
+    black: String,
+    opening: String,
+    result: String,
+}
+
+/// The mainline of a PGN game
+struct PgnGame {
+    start: Chess,
+    moves: Vec<Move>,
+    // the White, Black, Opening and Result tags, empty if missing
+    white: String,
+    black: String,
+    opening: String,
+    result: String,
+    // comment following each move, if any
+    comments: Vec<Option<String>>,
+    // time taken for each move, and left after it, where the clocks in the
+    // comments tell
+    times: Vec<Option<Duration>>,
+    clocks: Vec<Option<Duration>>,
+    // evaluation after each move, where the comments give one
+    evals: Vec<Option<i32>>,
+    position: Chess,
 }
 
 impl LastPosition {
+    // With lichess's initial ply: the position after `max_ply` + 1 plies
     fn new(max_ply: Option<usize>) -> LastPosition {
+        LastPosition::after(max_ply.map(|max| max + 1))
+    }
+
+    // The position after `plies` moves of the mainline, after all of them if None
+    fn after(plies: Option<usize>) -> LastPosition {
         LastPosition {
             pos: Chess::default(),
             moves: 0,
-            max_ply,
+            plies,
+            start: Chess::default(),
+            played: Vec::new(),
+            comments: Vec::new(),
+            clocks: Vec::new(),
+            time_control: None,
+            evals: Vec::new(),
+            white: String::new(),
+            black: String::new(),
+            opening: String::new(),
+            result: String::new(),
         }
     }
+
+    fn within_plies(&self) -> bool {
+        self.plies.is_none_or(|plies| self.moves < plies)
+    }
 }
 
 impl Visitor for LastPosition {
-    type Result = Chess;
+    type Result = PgnGame;
 
     fn header(&mut self, key: &[u8], value: RawHeader<'_>) {
+        match key {
+            b"White" => self.white = value.decode_utf8_lossy().into_owned(),
+            b"Black" => self.black = value.decode_utf8_lossy().into_owned(),
+            b"Opening" => self.opening = value.decode_utf8_lossy().into_owned(),
+            b"Result" => self.result = value.decode_utf8_lossy().into_owned(),
+            b"TimeControl" => {
+                self.time_control = annotation::parse_time_control(&value.decode_utf8_lossy())
+            }
+            _ => {}
+        }
         // Support games from a non-standard starting position.
         if key == b"FEN" {
-            let pos = Fen::from_ascii(value.as_bytes())
+            let pos: Option<Chess> = Fen::from_ascii(value.as_bytes())
                 .ok()
                 .and_then(|f| f.into_position(CastlingMode::Standard).ok());
 
             if let Some(pos) = pos {
-                self.pos = pos;
+                self.pos = pos.clone();
+                self.start = pos;
             }
         }
     }
@@ -652,50 +7289,166 @@ impl Visitor for LastPosition {
     }
 
     fn san(&mut self, san_plus: SanPlus) {
-        match self.max_ply {
-            Some(max) if self.moves < max + 1 => {
-                if let Ok(m) = san_plus.san.to_move(&self.pos) {
-                    self.pos.play_unchecked(&m);
-                    self.moves += 1
+        if self.within_plies() {
+            if let Ok(m) = san_plus.san.to_move(&self.pos) {
+                self.pos.play_unchecked(&m);
+                self.moves += 1;
+                self.played.push(m);
+                self.comments.push(None);
+                self.clocks.push(None);
+                self.evals.push(None);
+            }
+        }
+    }
+
+    fn comment(&mut self, comment: RawComment<'_>) {
+        let mut text = String::from_utf8_lossy(comment.as_bytes()).into_owned();
+        let clock =
+            annotation::take(&mut text, "clk").and_then(|clk| annotation::parse_clock(&clk));
+        if let (Some(clock), Some(last)) = (clock, self.clocks.last_mut()) {
+            *last = Some(clock);
+        }
+        let eval =
+            annotation::take(&mut text, "eval").and_then(|eval| annotation::parse_eval(&eval));
+        if let (Some(eval), Some(last)) = (eval, self.evals.last_mut()) {
+            *last = Some(eval);
+        }
+        // a diagram mark, shown apart from the comment
+        let text = text.replace("[#]", "").trim().to_string();
+        if text.is_empty() {
+            return;
+        }
+        if let Some(last) = self.comments.last_mut() {
+            match last {
+                Some(existing) => {
+                    existing.push(' ');
+                    existing.push_str(&text);
                 }
+                None => *last = Some(text),
             }
-            _ => {}
         }
     }
 
     fn end_game(&mut self) -> Self::Result {
-        ::std::mem::replace(&mut self.pos, Chess::default())
+        let clocks = ::std::mem::take(&mut self.clocks);
+        PgnGame {
+            start: ::std::mem::take(&mut self.start),
+            moves: ::std::mem::take(&mut self.played),
+            comments: ::std::mem::take(&mut self.comments),
+            times: annotation::think_times(&clocks, self.time_control.take()),
+            clocks,
+            evals: ::std::mem::take(&mut self.evals),
+            white: ::std::mem::take(&mut self.white),
+            black: ::std::mem::take(&mut self.black),
+            opening: ::std::mem::take(&mut self.opening),
+            result: ::std::mem::take(&mut self.result),
+            position: ::std::mem::take(&mut self.pos),
+        }
     }
 }
 
 // ----------------------------------------------
 // Load random puzzle from lichess
 // ----------------------------------------------
+
+// Puzzles fetched for one of a phase before giving up
+const PHASE_TRIES: usize = 10;
+
 fn load_puzzle(id: Option<String>) -> anyhow::Result<(Chess, Vec<Move>, LichessNextPuzzle)> {
     let url = format!(
         "https://lichess.org/api/puzzle/{}",
         id.unwrap_or("next".to_string())
     );
+    fetch_puzzle(&url)
+}
+
+// The next puzzle of a set: a drill's next position, or one from lichess
+fn load_set_puzzle(
+    set: &puzzle_set::PuzzleSet,
+) -> anyhow::Result<(Chess, Vec<Move>, LichessNextPuzzle)> {
+    if let Some(chapters) = &set.study {
+        let puzzle = chapters
+            .get(set.results.len())
+            .ok_or_else(|| anyhow::anyhow!("the study has no more chapters"))?;
+        let (board, solution) = puzzle_from_lichess(puzzle)?;
+        return Ok((board, solution, puzzle.clone()));
+    }
+    let Some(drill) = set.drill else {
+        return load_next_puzzle(set.theme.as_deref(), set.phase);
+    };
+    let puzzle = drill
+        .puzzle(set.results.len())
+        .ok_or_else(|| anyhow::anyhow!("the {} drill has no more puzzles", drill.name()))?;
+    let (board, solution) = puzzle_from_lichess(&puzzle)?;
+    Ok((board, solution, puzzle))
+}
+
+// A random puzzle, restricted to a lichess theme and to a phase if given
+fn load_next_puzzle(
+    theme: Option<&str>,
+    phase: Option<phase::Phase>,
+) -> anyhow::Result<(Chess, Vec<Move>, LichessNextPuzzle)> {
+    // lichess has the phases as themes too, though it draws the lines elsewhere
+    let angle = theme.or(phase.map(phase::Phase::name));
+    for _ in 0..PHASE_TRIES {
+        let found = match angle {
+            Some(angle) => fetch_puzzle(&format!(
+                "https://lichess.org/api/puzzle/next?angle={angle}"
+            ))?,
+            None => load_puzzle(None)?,
+        };
+        if phase.is_none_or(|phase| phase::Phase::of(&found.0) == phase) {
+            return Ok(found);
+        }
+    }
+    anyhow::bail!(
+        "no {} puzzle among {PHASE_TRIES} from lichess",
+        phase.map_or("", phase::Phase::name)
+    )
+}
+
+// The puzzles of `phase`, all of them without one
+fn in_phase(
+    puzzles: Vec<LichessNextPuzzle>,
+    phase: Option<phase::Phase>,
+) -> anyhow::Result<Vec<LichessNextPuzzle>> {
+    let Some(phase) = phase else {
+        return Ok(puzzles);
+    };
+    let kept: Vec<LichessNextPuzzle> = puzzles
+        .into_iter()
+        .filter(|puzzle| {
+            puzzle_from_lichess(puzzle).is_ok_and(|(board, _)| phase::Phase::of(&board) == phase)
+        })
+        .collect();
+    if kept.is_empty() {
+        anyhow::bail!("none of the puzzles is in the {}", phase.name());
+    }
+    Ok(kept)
+}
 
-    let lichess_puzzle: LichessNextPuzzle = reqwest::blocking::get(url)?.json()?;
+fn fetch_puzzle(url: &str) -> anyhow::Result<(Chess, Vec<Move>, LichessNextPuzzle)> {
+    let text = session_log::fetch(url, || Ok(reqwest::blocking::get(url)?.text()?))?;
+    let lichess_puzzle: LichessNextPuzzle = serde_json::from_str(&text)?;
+    let (puzzle_game, solution_moves) = puzzle_from_lichess(&lichess_puzzle)?;
+    Ok((puzzle_game, solution_moves, lichess_puzzle))
+}
 
+// Set up the puzzle position and its solution moves from the lichess data
+fn puzzle_from_lichess(lichess_puzzle: &LichessNextPuzzle) -> anyhow::Result<(Chess, Vec<Move>)> {
     // Parse puzzle solution as UCI moves
     let puzzle_solution_uci = lichess_puzzle.puzzle.solution.clone();
     // Parse the PGN
     let pgn = lichess_puzzle.game.pgn.to_string();
     let initial_ply = lichess_puzzle.puzzle.initial_ply as usize;
-    let puzzle_game = parse_game(&pgn, Some(initial_ply))?;
+    let puzzle_game = parse_game(&pgn, Some(initial_ply))?.position;
     let mut solution_game = puzzle_game.clone();
 
     // Now parse puzzle_solution_uci
     let mut solution_moves = Vec::new();
 
     for uci_str in puzzle_solution_uci.clone().into_iter() {
-        let all_legals = solution_game.clone().legal_moves();
-        let found = all_legals
-            .into_iter()
-            .find(|m| move_to_uci(&m) == uci_str.to_string());
-        if let Some(mv) = found {
+        if let Some(mv) = uci::parse(&solution_game, &uci_str) {
             solution_moves.push(mv.to_owned());
             solution_game = solution_game.clone().play(&mv)?;
         } else {
@@ -705,22 +7458,65 @@ fn load_puzzle(id: Option<String>) -> anyhow::Result<(Chess, Vec<Move>, LichessN
         }
     }
 
-    Ok((puzzle_game, solution_moves.clone(), lichess_puzzle))
+    Ok((puzzle_game, solution_moves))
+}
+
+// Start positions for a match: an EPD file, one position per line, or the
+// position at the end of every game of a PGN file
+fn load_openings(path: &std::path::Path) -> anyhow::Result<Vec<Chess>> {
+    let text = std::fs::read_to_string(path)?;
+    let mut openings = Vec::new();
+    if path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("pgn"))
+    {
+        let mut reader = pgn_reader::BufferedReader::new(text.as_bytes());
+        while let Some(game) = reader.read_game(&mut LastPosition::new(None))? {
+            openings.push(game.position);
+        }
+    } else {
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            // the four fields of the position, without the operations after them
+            let fields: Vec<&str> = line.split_whitespace().take(4).collect();
+            let fen = Fen::from_ascii(fields.join(" ").as_bytes())?;
+            openings.push(fen.into_position(CastlingMode::Standard)?);
+        }
+    }
+    if openings.is_empty() {
+        anyhow::bail!("{} has no positions", path.display());
+    }
+    Ok(openings)
 }
 
 // ----------------------------------------------
-// Load a PGN from file and return final position
+// Load a PGN from file with its mainline moves
 // ----------------------------------------------
-fn load_pgn_position(path: &str) -> anyhow::Result<Chess> {
+fn load_pgn_game(path: &str, index: usize) -> anyhow::Result<PgnGame> {
+    read_pgn_game(path, index, LastPosition::new(None))
+}
+
+// Game `index` of a PGN file, as far as `visitor` reads it
+fn read_pgn_game(path: &str, index: usize, mut visitor: LastPosition) -> anyhow::Result<PgnGame> {
     let text = std::fs::read_to_string(path)?;
-    parse_game(&text, None)
+    let mut reader = pgn_reader::BufferedReader::new(text.as_bytes());
+    for _ in 0..index {
+        if !reader.skip_game::<LastPosition>()? {
+            anyhow::bail!("{path} has only {index} games");
+        }
+    }
+    reader
+        .read_game(&mut visitor)?
+        .ok_or(anyhow::anyhow!("unable to read game"))
 }
 
-fn parse_game(pgn: &str, inital_ply: Option<usize>) -> anyhow::Result<Chess> {
+fn parse_game(pgn: &str, inital_ply: Option<usize>) -> anyhow::Result<PgnGame> {
     let mut game_pos = LastPosition::new(inital_ply);
     // Use pgn-reader to parse the PGN
     let mut reader = pgn_reader::BufferedReader::new(pgn.as_bytes());
-    let parsed_game: Chess = reader
+    let parsed_game = reader
         .read_game(&mut game_pos)?
         .ok_or(anyhow::anyhow!("unable to read game"))?;
     Ok(parsed_game)
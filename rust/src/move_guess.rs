@@ -0,0 +1,245 @@
+use serde::{Deserialize, Serialize};
+use shakmaty::fen::Fen;
+use shakmaty::san::SanPlus;
+use shakmaty::{Chess, Color, EnPassantMode, Move, Position};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::engine::{Engine, Score};
+use crate::review::CP_CEILING;
+use crate::{session, stats, PgnGame};
+
+// ----------------------------------------------
+// Guess the move: a game of a strong player replayed from one side, the
+// continuation hidden. A point for each move guessed as played, half of
+// one for another move the engine finds about as good, if there is an
+// engine. Every guess is kept in $XDG_STATE_HOME/tess/move_guesses.jsonl
+// ----------------------------------------------
+
+// Credit for a move the engine finds about as good as the one played
+const PARTIAL: f64 = 0.5;
+
+/// One guess, the moves in SAN
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Guess {
+    /// seconds since the Unix epoch
+    pub at: u64,
+    /// "Carlsen - Nepomniachtchi"
+    pub game: String,
+    pub fen: String,
+    pub played: String,
+    pub guess: String,
+    /// 1 for the move played, PARTIAL for one as good, else 0
+    pub credit: f64,
+}
+
+fn guesses_path() -> Option<PathBuf> {
+    session::state_dir().map(|dir| dir.join("move_guesses.jsonl"))
+}
+
+fn append(guess: &Guess) -> anyhow::Result<()> {
+    let Some(path) = guesses_path() else {
+        return Ok(());
+    };
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    writeln!(file, "{}", serde_json::to_string(guess)?)?;
+    Ok(())
+}
+
+// Centipawns for the side to move, mates at the ceiling
+fn centipawns(score: Score) -> i32 {
+    match score {
+        Score::Centipawns(cp) => cp.clamp(-CP_CEILING, CP_CEILING),
+        Score::Mate(moves) if moves > 0 => CP_CEILING,
+        Score::Mate(_) => -CP_CEILING,
+    }
+}
+
+pub struct Training {
+    /// "Carlsen - Nepomniachtchi"
+    pub game: String,
+    /// the side whose moves I guess
+    pub color: Color,
+    pub start: Chess,
+    moves: Vec<Move>,
+    // the plies before this one are played out without guessing
+    first_ply: usize,
+    engine: Option<Engine>,
+    movetime: Duration,
+    /// centipawns a move may lose against the one played for the partial credit
+    tolerance: i32,
+    // this run
+    guesses: Vec<Guess>,
+    // what the last guess came to
+    last: Option<String>,
+}
+
+impl Training {
+    /// Guess the moves of `color` in `game` from move number `from` on, with
+    /// `engine` searching `movetime` for the partial credit
+    pub fn new(
+        game: &PgnGame,
+        color: Color,
+        from: u32,
+        engine: Option<Engine>,
+        movetime: Duration,
+        tolerance: i32,
+    ) -> anyhow::Result<Self> {
+        // the ply of move `from` for white
+        let first_ply = (2 * from.saturating_sub(game.start.fullmoves().get()) as usize)
+            .saturating_sub(usize::from(game.start.turn() == Color::Black));
+        let training = Self {
+            game: if game.white.is_empty() && game.black.is_empty() {
+                "the game".to_string()
+            } else {
+                format!("{} - {}", game.white, game.black)
+            },
+            color,
+            start: game.start.clone(),
+            moves: game.moves.clone(),
+            first_ply,
+            engine,
+            movetime,
+            tolerance,
+            guesses: Vec::new(),
+            last: None,
+        };
+        if training.next_guess(0) >= training.moves.len() {
+            anyhow::bail!("{} has no moves of {color} to guess", training.game);
+        }
+        Ok(training)
+    }
+
+    // The next ply from `ply` on where I guess, the end of the game if none
+    fn next_guess(&self, ply: usize) -> usize {
+        let turn = |ply: usize| {
+            if ply % 2 == 1 {
+                self.start.turn().other()
+            } else {
+                self.start.turn()
+            }
+        };
+        (ply..self.moves.len())
+            .find(|ply| *ply >= self.first_ply && turn(*ply) == self.color)
+            .unwrap_or(self.moves.len())
+    }
+
+    /// The moves of the game from `ply` on that are played out up to my next guess
+    pub fn until_my_turn(&self, ply: usize) -> &[Move] {
+        &self.moves[ply.min(self.moves.len())..self.next_guess(ply)]
+    }
+
+    /// The move played at `ply`, None past the end of the game
+    pub fn played(&self, ply: usize) -> Option<&Move> {
+        self.moves.get(ply)
+    }
+
+    pub fn over(&self, ply: usize) -> bool {
+        ply >= self.moves.len()
+    }
+
+    /// My guess `mv` in `pos` at `ply`, scored and kept; what it came to
+    pub fn guess(&mut self, pos: &Chess, ply: usize, mv: &Move) -> anyhow::Result<String> {
+        let Some(played) = self.moves.get(ply).cloned() else {
+            anyhow::bail!("the game is over");
+        };
+        let san = |mv: &Move| SanPlus::from_move(pos.clone(), mv).to_string();
+        let number = format!("{}{}", pos.fullmoves(), pos.turn().fold_wb(".", "..."));
+        let (credit, verdict) = if *mv == played {
+            (1.0, format!("{number}{} as played, a point.", san(mv)))
+        } else if let Some(engine) = self.engine.as_mut() {
+            let mut score = |mv: &Move| -> anyhow::Result<i32> {
+                let evaluation = engine
+                    .evaluate_moves(pos, self.movetime, std::slice::from_ref(mv))?
+                    .ok_or_else(|| anyhow::anyhow!("{} gave no evaluation", engine.name))?;
+                Ok(centipawns(evaluation.score))
+            };
+            let (mine, theirs) = (score(mv)?, score(&played)?);
+            let pawns = |cp: i32| f64::from(cp * pos.turn().fold_wb(1, -1)) / 100.0;
+            let credit = if theirs - mine <= self.tolerance {
+                PARTIAL
+            } else {
+                0.0
+            };
+            (
+                credit,
+                format!(
+                    "{number}{} was played, not {} ({:+.2} against {:+.2}), {}.",
+                    san(&played),
+                    san(mv),
+                    pawns(theirs),
+                    pawns(mine),
+                    if credit > 0.0 {
+                        "about as good, half a point"
+                    } else {
+                        "no point"
+                    }
+                ),
+            )
+        } else {
+            (
+                0.0,
+                format!("{number}{} was played, not {}.", san(&played), san(mv)),
+            )
+        };
+        let guess = Guess {
+            at: stats::now(),
+            game: self.game.clone(),
+            fen: Fen::from_position(pos.clone(), EnPassantMode::Legal).to_string(),
+            played: san(&played),
+            guess: san(mv),
+            credit,
+        };
+        // best effort, the run still counts it
+        let _ = append(&guess);
+        self.guesses.push(guess);
+        self.last = Some(verdict.clone());
+        Ok(verdict)
+    }
+
+    /// A new run through the game
+    pub fn restart(&mut self) {
+        self.guesses.clear();
+        self.last = None;
+    }
+
+    /// "7.5 points of 12 (63%), 6 moves as played"
+    pub fn score(&self) -> String {
+        let points = self
+            .guesses
+            .iter()
+            .fold(0.0, |sum, guess| sum + guess.credit);
+        let exact = self
+            .guesses
+            .iter()
+            .filter(|guess| guess.credit >= 1.0)
+            .count();
+        format!(
+            "{points} point{} of {} ({:.0}%), {exact} move{} as played",
+            if points == 1.0 { "" } else { "s" },
+            self.guesses.len(),
+            100.0 * points / self.guesses.len().max(1) as f64,
+            if exact == 1 { "" } else { "s" }
+        )
+    }
+
+    /// The pane, one line each
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = vec![
+            format!("{}, guessing {}'s moves", self.game, self.color),
+            self.score(),
+        ];
+        if self.engine.is_none() {
+            lines.push("Without an engine only the moves played count.".to_string());
+        }
+        lines.extend(self.last.clone());
+        lines
+    }
+}
@@ -0,0 +1,131 @@
+use serde::{Deserialize, Serialize};
+use shakmaty::Color as ChessColor;
+
+// ----------------------------------------------
+// How moves are written in the move list
+// ----------------------------------------------
+
+#[derive(Debug, clap::ValueEnum, Clone, Copy, PartialEq)]
+pub enum Notation {
+    /// Nf3
+    Letters,
+    /// ♘f3, falls back to letters if the terminal is not UTF-8
+    Figurine,
+}
+
+impl Notation {
+    /// Figurines need a UTF-8 locale, otherwise stick to letters
+    pub fn effective(self) -> Notation {
+        match self {
+            Notation::Figurine if !utf8_locale() => Notation::Letters,
+            notation => notation,
+        }
+    }
+}
+
+fn utf8_locale() -> bool {
+    ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .map(|value| {
+            let value = value.to_lowercase();
+            value.contains("utf-8") || value.contains("utf8")
+        })
+        .unwrap_or(false)
+}
+
+fn figurine(letter: char, color: ChessColor) -> Option<char> {
+    let (white, black) = match letter {
+        'K' => ('♔', '♚'),
+        'Q' => ('♕', '♛'),
+        'R' => ('♖', '♜'),
+        'B' => ('♗', '♝'),
+        'N' => ('♘', '♞'),
+        _ => return None,
+    };
+    Some(color.fold_wb(white, black))
+}
+
+/// Render a SAN string played by `color` in the given notation
+pub fn format_san(san: &str, color: ChessColor, notation: Notation) -> String {
+    if notation == Notation::Letters {
+        return san.to_string();
+    }
+    let mut out = String::with_capacity(san.len() + 4);
+    let mut after_promotion = false;
+    for (i, ch) in san.chars().enumerate() {
+        // piece letters only appear first or after '=' (castling uses 'O')
+        match figurine(ch, color) {
+            Some(glyph) if i == 0 || after_promotion => out.push(glyph),
+            _ => out.push(ch),
+        }
+        after_promotion = ch == '=';
+    }
+    out
+}
+
+// ----------------------------------------------
+// Localized piece letters on input
+// ----------------------------------------------
+
+/// Languages whose piece letters are accepted in SAN input
+#[derive(Debug, clap::ValueEnum, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    En,
+    De,
+    Fr,
+    Es,
+    It,
+    Nl,
+    Pt,
+    Sv,
+    Pl,
+    Cs,
+}
+
+impl Language {
+    // Letters for king, queen, rook, bishop and knight
+    fn piece_letters(self) -> [char; 5] {
+        match self {
+            Language::En => ['K', 'Q', 'R', 'B', 'N'],
+            Language::De => ['K', 'D', 'T', 'L', 'S'],
+            Language::Fr => ['R', 'D', 'T', 'F', 'C'],
+            Language::Es | Language::It => ['R', 'D', 'T', 'A', 'C'],
+            Language::Nl => ['K', 'D', 'T', 'L', 'P'],
+            Language::Pt => ['R', 'D', 'T', 'B', 'C'],
+            Language::Sv => ['K', 'D', 'T', 'L', 'S'],
+            Language::Pl => ['K', 'H', 'W', 'G', 'S'],
+            Language::Cs => ['K', 'D', 'V', 'S', 'J'],
+        }
+    }
+}
+
+/// Whether `ch` is one of the piece letters of `language`, so a move can start with it
+pub fn is_piece_letter(ch: char, language: Language) -> bool {
+    language.piece_letters().contains(&ch)
+}
+
+/// Translate the piece letters of a SAN move typed in `language` to English
+pub fn localize_input(input: &str, language: Language) -> String {
+    let local = language.piece_letters();
+    let english = Language::En.piece_letters();
+    let translate = |ch: char| {
+        local
+            .iter()
+            .position(|&l| l == ch)
+            .map_or(ch, |i| english[i])
+    };
+    let mut out = String::with_capacity(input.len());
+    let mut after_promotion = false;
+    for (i, ch) in input.chars().enumerate() {
+        if i == 0 || after_promotion {
+            out.push(translate(ch));
+        } else {
+            out.push(ch);
+        }
+        after_promotion = ch == '=';
+    }
+    out
+}
@@ -0,0 +1,481 @@
+use serde::Deserialize;
+use serde_json::Value;
+use shakmaty::Color as ChessColor;
+use std::io::{BufRead, BufReader};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::{Duration, Instant};
+
+use crate::{clock, session_log};
+
+// ----------------------------------------------
+// Online games through the lichess Board API, with the `lichess_token`
+// of the config file (scope `board:play`). A thread reads the game's
+// stream and hands its events over. The server's clock times come with
+// every move and count down here until the next, so the clocks tick
+// instead of jumping from one update to the next. Moves, the answers
+// to takeback proposals and berserk are posted as they are made. An
+// arena game also follows the standing of its arena, fetched every
+// little while.
+// ----------------------------------------------
+
+const API: &str = "https://lichess.org/api";
+
+// How often the standing of an arena is fetched again
+const ARENA_REFRESH: Duration = Duration::from_secs(20);
+
+#[derive(Debug, Deserialize)]
+struct Account {
+    id: String,
+    username: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Player {
+    id: Option<String>,
+    name: Option<String>,
+    title: Option<String>,
+    rating: Option<u16>,
+    #[serde(rename = "aiLevel")]
+    ai_level: Option<u8>,
+}
+
+impl Player {
+    // "GM Name (2700)", as lichess shows the player
+    fn describe(&self) -> String {
+        match (&self.name, self.ai_level) {
+            (Some(name), _) => {
+                let mut text = match &self.title {
+                    Some(title) => format!("{title} {name}"),
+                    None => name.clone(),
+                };
+                if let Some(rating) = self.rating {
+                    text.push_str(&format!(" ({rating})"));
+                }
+                text
+            }
+            (None, Some(level)) => format!("Stockfish level {level}"),
+            (None, None) => "Anonymous".to_string(),
+        }
+    }
+}
+
+// milliseconds, as lichess gives them
+#[derive(Debug, Clone, Copy, Deserialize)]
+struct ClockSetting {
+    initial: u64,
+    increment: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GameFull {
+    white: Player,
+    black: Player,
+    // absent for correspondence and unlimited games
+    clock: Option<ClockSetting>,
+    initial_fen: String,
+    tournament_id: Option<String>,
+    state: State,
+}
+
+/// The game as the server sends it after every move and change
+#[derive(Debug, Clone, Deserialize)]
+pub struct State {
+    // the moves from the start, in UCI, separated by spaces
+    moves: String,
+    // milliseconds left
+    #[serde(default)]
+    wtime: u64,
+    #[serde(default)]
+    btime: u64,
+    /// "started" while the game is on, else how it ended: "mate", "resign", ...
+    pub status: String,
+    winner: Option<String>,
+    // a takeback proposed by the side
+    #[serde(default)]
+    wtakeback: bool,
+    #[serde(default)]
+    btakeback: bool,
+}
+
+impl State {
+    /// The moves from the start, in UCI
+    pub fn moves(&self) -> impl Iterator<Item = &str> {
+        self.moves.split_whitespace()
+    }
+
+    /// The time `color` had left when the server sent the state
+    pub fn remaining(&self, color: ChessColor) -> Duration {
+        Duration::from_millis(color.fold_wb(self.wtime, self.btime))
+    }
+
+    /// The side proposing a takeback, if one does
+    pub fn takeback(&self) -> Option<ChessColor> {
+        match (self.wtakeback, self.btakeback) {
+            (true, _) => Some(ChessColor::White),
+            (_, true) => Some(ChessColor::Black),
+            _ => None,
+        }
+    }
+
+    pub fn winner(&self) -> Option<ChessColor> {
+        self.winner.as_deref().and_then(|side| match side {
+            "white" => Some(ChessColor::White),
+            "black" => Some(ChessColor::Black),
+            _ => None,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Tournament {
+    full_name: String,
+    #[serde(default)]
+    seconds_to_finish: u64,
+    #[serde(default)]
+    pairings_closed: bool,
+    // with the token, my place in it
+    me: Option<Me>,
+    standing: Option<Standing>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Me {
+    rank: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Standing {
+    players: Vec<StandingPlayer>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StandingPlayer {
+    name: String,
+    score: Option<u32>,
+    sheet: Option<Sheet>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Sheet {
+    // a digit of points for each game, as lichess lists them
+    scores: Option<Value>,
+    #[serde(default)]
+    fire: bool,
+}
+
+/// Where I stand in the arena of an online game
+#[derive(Debug, Clone)]
+pub struct Arena {
+    pub name: String,
+    pub rank: Option<u32>,
+    pub score: Option<u32>,
+    /// the points of my games: 2 for a win, 4 for a win on a streak
+    pub sheet: Option<String>,
+    /// on a streak of two wins, the next win counts double
+    pub fire: bool,
+    /// near the end no new games are paired
+    pub pairings_closed: bool,
+    ends: Instant,
+}
+
+impl Arena {
+    /// The time until the arena ends, counted down between fetches
+    pub fn ends_in(&self) -> Duration {
+        self.ends.saturating_duration_since(session_log::now())
+    }
+}
+
+// The arena `id` with my place in it; its standing comes ten players a
+// page, so the one with my rank is fetched as well
+fn arena(token: &str, id: &str, username: &str) -> anyhow::Result<Arena> {
+    let fetch = |page: u32| -> anyhow::Result<Tournament> {
+        let path = format!("tournament/{id}?page={page}");
+        Ok(get(token, &path, Some(Duration::from_secs(5)))?.json()?)
+    };
+    let mut tournament = fetch(1)?;
+    let rank = tournament.me.as_ref().and_then(|me| me.rank);
+    let page = rank.map_or(1, |rank| rank.saturating_sub(1) / 10 + 1);
+    let standing = if page == 1 {
+        tournament.standing.take()
+    } else {
+        fetch(page)?.standing
+    };
+    let mine = standing.and_then(|standing| {
+        standing
+            .players
+            .into_iter()
+            .find(|player| player.name.eq_ignore_ascii_case(username))
+    });
+    let sheet = mine.as_ref().and_then(|player| player.sheet.as_ref());
+    Ok(Arena {
+        name: tournament.full_name,
+        rank,
+        score: mine.as_ref().and_then(|player| player.score),
+        sheet: sheet
+            .and_then(|sheet| sheet.scores.as_ref())
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        fire: sheet.is_some_and(|sheet| sheet.fire),
+        pairings_closed: tournament.pairings_closed,
+        ends: session_log::now() + Duration::from_secs(tournament.seconds_to_finish),
+    })
+}
+
+pub enum Event {
+    State(State),
+    /// the opponent left the game, or came back
+    OpponentGone(bool),
+    Arena(Arena),
+    /// the stream ended, and why
+    Closed(String),
+}
+
+pub struct Game {
+    pub id: String,
+    /// the side I play
+    pub color: ChessColor,
+    /// "Name (rating)" of White and Black
+    pub players: [String; 2],
+    /// the position the game started from, None for the usual one
+    pub initial_fen: Option<String>,
+    pub control: Option<clock::TimeControl>,
+    /// as of the last state: "started" while the game is on
+    pub status: String,
+    pub opponent_gone: bool,
+    /// the side whose takeback proposal waits for an answer
+    pub takeback: Option<ChessColor>,
+    /// the arena the game is played in and where I stand in it, once fetched
+    pub tournament: Option<String>,
+    pub arena: Option<Arena>,
+    /// I went berserk: half my time for a point more with a win
+    pub berserk: bool,
+    token: String,
+    events: Receiver<Event>,
+}
+
+fn client(timeout: Option<Duration>) -> reqwest::Result<reqwest::blocking::Client> {
+    reqwest::blocking::Client::builder()
+        .timeout(timeout)
+        .build()
+}
+
+// A GET of the API, its response kept open for a stream
+fn get(
+    token: &str,
+    path: &str,
+    timeout: Option<Duration>,
+) -> anyhow::Result<reqwest::blocking::Response> {
+    let response = client(timeout)?
+        .get(format!("{API}/{path}"))
+        .bearer_auth(token)
+        .send()?;
+    if !response.status().is_success() {
+        anyhow::bail!("lichess: {}", error_of(response));
+    }
+    Ok(response)
+}
+
+// What lichess said went wrong, {"error": "..."} or the status
+fn error_of(response: reqwest::blocking::Response) -> String {
+    let status = response.status();
+    response
+        .json::<Value>()
+        .ok()
+        .and_then(|body| body["error"].as_str().map(str::to_string))
+        .unwrap_or_else(|| status.to_string())
+}
+
+// The lines of a stream of NDJSON, the keep-alive blank lines left out
+fn lines(response: reqwest::blocking::Response) -> impl Iterator<Item = std::io::Result<String>> {
+    BufReader::new(response)
+        .lines()
+        .filter(|line| !line.as_ref().is_ok_and(|line| line.trim().is_empty()))
+}
+
+// The id of the next game of the account to start, from its event stream
+fn next_game(token: &str) -> anyhow::Result<String> {
+    for line in lines(get(token, "stream/event", None)?) {
+        let event: Value = serde_json::from_str(&line?)?;
+        if event["type"] == "gameStart" {
+            if let Some(id) = event["game"]["gameId"].as_str() {
+                return Ok(id.to_string());
+            }
+        }
+    }
+    anyhow::bail!("the event stream of lichess ended before a game started")
+}
+
+// One event of the game stream, None for those not followed here like the chat
+fn parse_event(line: &str) -> anyhow::Result<Option<Event>> {
+    let value: Value = serde_json::from_str(line)?;
+    Ok(match value["type"].as_str() {
+        Some("gameState") => Some(Event::State(serde_json::from_value(value)?)),
+        Some("gameFull") => Some(Event::State(serde_json::from_value(
+            value["state"].clone(),
+        )?)),
+        Some("opponentGone") => Some(Event::OpponentGone(value["gone"].as_bool() == Some(true))),
+        _ => None,
+    })
+}
+
+// Hand the events of the stream over until it ends or the game is dropped
+fn follow(lines: impl Iterator<Item = std::io::Result<String>>, sender: Sender<Event>) {
+    for line in lines {
+        let event = match line
+            .map_err(anyhow::Error::from)
+            .and_then(|line| parse_event(&line))
+        {
+            Ok(Some(event)) => event,
+            Ok(None) => continue,
+            Err(err) => Event::Closed(err.to_string()),
+        };
+        let closed = matches!(event, Event::Closed(_));
+        if sender.send(event).is_err() || closed {
+            return;
+        }
+    }
+    let _ = sender.send(Event::Closed("lichess ended the game stream".to_string()));
+}
+
+impl Game {
+    /// Join the game `id` of the account, or wait for its next game to
+    /// start if there is no id. The game's state comes as the first event.
+    pub fn join(token: &str, id: Option<&str>) -> anyhow::Result<Game> {
+        let me: Account = get(token, "account", Some(Duration::from_secs(5)))?.json()?;
+        let id = match id {
+            Some(id) => id.to_string(),
+            None => next_game(token)?,
+        };
+        let mut stream = lines(get(token, &format!("board/game/stream/{id}"), None)?);
+        let Some(first) = stream.next() else {
+            anyhow::bail!("lichess sent nothing about game {id}");
+        };
+        let full: GameFull = serde_json::from_str(&first?)?;
+        let color = if full.white.id.as_ref() == Some(&me.id) {
+            ChessColor::White
+        } else if full.black.id.as_ref() == Some(&me.id) {
+            ChessColor::Black
+        } else {
+            anyhow::bail!("game {id} is not one of {}'s", me.id);
+        };
+        let (sender, events) = mpsc::channel();
+        let status = full.state.status.clone();
+        let _ = sender.send(Event::State(full.state));
+        if let Some(tournament) = full.tournament_id.clone() {
+            let (sender, token) = (sender.clone(), token.to_string());
+            std::thread::spawn(move || loop {
+                // a fetch that failed leaves the last standing on screen
+                if let Ok(arena) = arena(&token, &tournament, &me.username) {
+                    if sender.send(Event::Arena(arena)).is_err() {
+                        return;
+                    }
+                }
+                std::thread::sleep(ARENA_REFRESH);
+            });
+        }
+        std::thread::spawn(move || follow(stream, sender));
+        Ok(Game {
+            id,
+            color,
+            players: [full.white.describe(), full.black.describe()],
+            initial_fen: Some(full.initial_fen).filter(|fen| fen != "startpos"),
+            control: full.clock.map(|clock| clock::TimeControl {
+                base: Duration::from_millis(clock.initial),
+                increment: Duration::from_millis(clock.increment),
+            }),
+            status,
+            opponent_gone: false,
+            takeback: None,
+            tournament: full.tournament_id,
+            arena: None,
+            berserk: false,
+            token: token.to_string(),
+            events,
+        })
+    }
+
+    /// The next event of the stream, if one came, kept track of here as well
+    pub fn poll(&mut self) -> Option<Event> {
+        let event = self.events.try_recv().ok()?;
+        match &event {
+            Event::State(state) => self.status = state.status.clone(),
+            Event::OpponentGone(gone) => self.opponent_gone = *gone,
+            Event::Arena(arena) => self.arena = Some(arena.clone()),
+            Event::Closed(_) => {}
+        }
+        Some(event)
+    }
+
+    /// The game is still being played: neither over nor aborted
+    pub fn in_play(&self) -> bool {
+        matches!(self.status.as_str(), "created" | "started")
+    }
+
+    /// The name of the player against me
+    pub fn opponent(&self) -> &str {
+        &self.players[(!self.color).fold_wb(0, 1)]
+    }
+
+    // Post an action of the game, e.g. "move/e2e4"
+    fn post(&self, action: &str) -> anyhow::Result<()> {
+        let response = client(Some(Duration::from_secs(5)))?
+            .post(format!("{API}/board/game/{}/{action}", self.id))
+            .bearer_auth(&self.token)
+            .send()?;
+        if !response.status().is_success() {
+            anyhow::bail!("{}", error_of(response));
+        }
+        Ok(())
+    }
+
+    /// Play my move, in UCI
+    pub fn play(&self, uci: &str) -> anyhow::Result<()> {
+        self.post(&format!("move/{uci}"))
+    }
+
+    /// Propose a takeback or accept the opponent's, or with `false` decline
+    /// it or withdraw mine
+    pub fn answer_takeback(&self, accept: bool) -> anyhow::Result<()> {
+        self.post(if accept {
+            "takeback/yes"
+        } else {
+            "takeback/no"
+        })
+    }
+
+    /// Halve my clock in an arena game, for a point more with a win
+    pub fn go_berserk(&mut self) -> anyhow::Result<()> {
+        self.post("berserk")?;
+        self.berserk = true;
+        Ok(())
+    }
+
+    pub fn resign(&self) -> anyhow::Result<()> {
+        self.post("resign")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_state_of_the_stream() {
+        let line = r#"{"type":"gameState","moves":"e2e4 c7c5","wtime":295000,"btime":298500,"winc":0,"binc":0,"status":"started"}"#;
+        let Ok(Some(Event::State(state))) = parse_event(line) else {
+            panic!("no state in {line}");
+        };
+        assert_eq!(state.moves().collect::<Vec<_>>(), ["e2e4", "c7c5"]);
+        assert_eq!(
+            state.remaining(ChessColor::Black),
+            Duration::from_millis(298500)
+        );
+        assert_eq!(state.winner(), None);
+        assert!(matches!(
+            parse_event(r#"{"type":"chatLine","username":"x","text":"hi","room":"player"}"#),
+            Ok(None)
+        ));
+    }
+}
@@ -0,0 +1,299 @@
+use pgn_reader::{BufferedReader, RawHeader, SanPlus, Skip, Visitor};
+use serde::{Deserialize, Serialize};
+use shakmaty::fen::Epd;
+use shakmaty::{Chess, Color, EnPassantMode, Move, Outcome, Position};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use crate::{session, uci};
+
+// ----------------------------------------------
+// Opening tree: the moves of my repertoire files, the moves I actually
+// played in my games and the moves of a PGN database with their results,
+// by position, kept in $XDG_STATE_HOME/tess/opening_tree.json
+// ----------------------------------------------
+
+/// Plies of each game that count as the opening
+const MAX_PLIES: usize = 30;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct OpeningTree {
+    // by the position's EPD, so move counters don't matter
+    positions: BTreeMap<String, Node>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Node {
+    /// my prepared moves, in UCI
+    repertoire: BTreeSet<String>,
+    /// my moves in imported games, in UCI
+    played: BTreeMap<String, Played>,
+    /// the moves of both sides in database games, in UCI
+    #[serde(default)]
+    database: BTreeMap<String, Results>,
+}
+
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+struct Played {
+    games: u32,
+    /// my points from those games
+    points: f64,
+}
+
+/// How the database games with a move went
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub struct Results {
+    pub games: u32,
+    pub white: u32,
+    pub draws: u32,
+    pub black: u32,
+}
+
+/// What I know about one position, for the info pane
+pub struct Summary {
+    /// SAN, games and score of the moves I played here, most played first
+    pub played: Vec<(String, u32, f64)>,
+    pub repertoire: Vec<String>,
+    /// games in which I left the repertoire here
+    pub deviations: u32,
+}
+
+fn tree_path() -> Option<PathBuf> {
+    session::state_dir().map(|dir| dir.join("opening_tree.json"))
+}
+
+fn key(pos: &Chess) -> String {
+    Epd::from_position(pos.clone(), EnPassantMode::Legal).to_string()
+}
+
+impl OpeningTree {
+    /// The saved tree, empty if there is none yet
+    pub fn load() -> Self {
+        tree_path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let path = tree_path().ok_or_else(|| anyhow::anyhow!("no state directory (HOME unset)"))?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_vec(self)?)?;
+        Ok(())
+    }
+
+    /// Add the moves of `color` in every line of a repertoire file, variations included.
+    /// Returns the number of moves added.
+    pub fn import_repertoire(&mut self, path: &Path, color: Color) -> anyhow::Result<usize> {
+        let mut added = 0;
+        for game in read_games(path, true)? {
+            for (pos, mv) in game.moves.iter().filter(|(pos, _)| pos.turn() == color) {
+                let node = self.positions.entry(key(pos)).or_default();
+                added += usize::from(node.repertoire.insert(uci::format(mv)));
+            }
+        }
+        Ok(added)
+    }
+
+    /// Add the opening moves `player` made in the games of a PGN file.
+    /// Returns the number of games that were theirs.
+    pub fn import_games(&mut self, path: &Path, player: &str) -> anyhow::Result<usize> {
+        let mut imported = 0;
+        for game in read_games(path, true)? {
+            let color = if game.white.eq_ignore_ascii_case(player) {
+                Color::White
+            } else if game.black.eq_ignore_ascii_case(player) {
+                Color::Black
+            } else {
+                continue;
+            };
+            let points = match game.outcome {
+                Some(Outcome::Decisive { winner }) if winner == color => 1.0,
+                Some(Outcome::Draw) => 0.5,
+                _ => 0.0,
+            };
+            for (pos, mv) in game.moves.iter().take(MAX_PLIES) {
+                if pos.turn() != color {
+                    continue;
+                }
+                let node = self.positions.entry(key(pos)).or_default();
+                let played = node.played.entry(uci::format(mv)).or_default();
+                played.games += 1;
+                played.points += points;
+            }
+            imported += 1;
+        }
+        Ok(imported)
+    }
+
+    /// Add the opening moves of both sides in every game of a PGN file, the
+    /// main lines only. Returns the number of games.
+    pub fn import_database(&mut self, path: &Path) -> anyhow::Result<usize> {
+        let games = read_games(path, false)?;
+        for game in &games {
+            for (pos, mv) in game.moves.iter().take(MAX_PLIES) {
+                let node = self.positions.entry(key(pos)).or_default();
+                let results = node.database.entry(uci::format(mv)).or_default();
+                results.games += 1;
+                match game.outcome {
+                    Some(Outcome::Decisive {
+                        winner: Color::White,
+                    }) => results.white += 1,
+                    Some(Outcome::Decisive {
+                        winner: Color::Black,
+                    }) => results.black += 1,
+                    Some(Outcome::Draw) => results.draws += 1,
+                    None => {}
+                }
+            }
+        }
+        Ok(games.len())
+    }
+
+    /// The database moves in `pos` with their results, most played first
+    pub fn database_moves(&self, pos: &Chess) -> Vec<(Move, Results)> {
+        let Some(node) = self.positions.get(&key(pos)) else {
+            return Vec::new();
+        };
+        let mut moves: Vec<(Move, Results)> = node
+            .database
+            .iter()
+            .filter_map(|(mv, results)| Some((uci::parse(pos, mv)?, *results)))
+            .collect();
+        moves.sort_by_key(|(_, results)| std::cmp::Reverse(results.games));
+        moves
+    }
+
+    /// What the tree holds of my own for `pos`, None if nothing
+    pub fn summary(&self, pos: &Chess) -> Option<Summary> {
+        let node = self
+            .positions
+            .get(&key(pos))
+            .filter(|node| !node.played.is_empty() || !node.repertoire.is_empty())?;
+        let san = |text: &str| {
+            uci::parse(pos, text).map_or(text.to_string(), |mv| {
+                SanPlus::from_move(pos.clone(), &mv).to_string()
+            })
+        };
+        let mut played: Vec<(String, u32, f64)> = node
+            .played
+            .iter()
+            .map(|(mv, stats)| (san(mv), stats.games, stats.points / stats.games as f64))
+            .collect();
+        played.sort_by_key(|(_, games, _)| std::cmp::Reverse(*games));
+        let deviations = if node.repertoire.is_empty() {
+            0
+        } else {
+            node.played
+                .iter()
+                .filter(|(mv, _)| !node.repertoire.contains(*mv))
+                .map(|(_, stats)| stats.games)
+                .sum()
+        };
+        Some(Summary {
+            played,
+            repertoire: node.repertoire.iter().map(|mv| san(mv)).collect(),
+            deviations,
+        })
+    }
+}
+
+// ----------------------------------------------
+// Reading every line of a PGN file
+// ----------------------------------------------
+
+#[derive(Default)]
+struct TreeGame {
+    white: String,
+    black: String,
+    outcome: Option<Outcome>,
+    // each move with the position it was played in, variations included
+    moves: Vec<(Chess, Move)>,
+}
+
+#[derive(Default)]
+struct Lines {
+    game: TreeGame,
+    pos: Chess,
+    // position before the last move, where a variation starts
+    before: Chess,
+    stack: Vec<(Chess, Chess)>,
+    // false skips the variations
+    variations: bool,
+}
+
+impl Visitor for Lines {
+    type Result = TreeGame;
+
+    fn begin_game(&mut self) {
+        *self = Lines {
+            variations: self.variations,
+            ..Lines::default()
+        };
+    }
+
+    fn header(&mut self, key: &[u8], value: RawHeader<'_>) {
+        match key {
+            b"White" => self.game.white = value.decode_utf8_lossy().into_owned(),
+            b"Black" => self.game.black = value.decode_utf8_lossy().into_owned(),
+            b"FEN" => {
+                let pos = shakmaty::fen::Fen::from_ascii(value.as_bytes())
+                    .ok()
+                    .and_then(|fen| fen.into_position(shakmaty::CastlingMode::Standard).ok());
+                if let Some(pos) = pos {
+                    self.pos = pos;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn san(&mut self, san_plus: SanPlus) {
+        if let Ok(mv) = san_plus.san.to_move(&self.pos) {
+            self.before = self.pos.clone();
+            self.game.moves.push((self.pos.clone(), mv.clone()));
+            self.pos.play_unchecked(&mv);
+        }
+    }
+
+    fn begin_variation(&mut self) -> Skip {
+        if !self.variations {
+            return Skip(true);
+        }
+        self.stack.push((self.pos.clone(), self.before.clone()));
+        self.pos = self.before.clone();
+        Skip(false)
+    }
+
+    fn end_variation(&mut self) {
+        if let Some((pos, before)) = self.stack.pop() {
+            self.pos = pos;
+            self.before = before;
+        }
+    }
+
+    fn outcome(&mut self, outcome: Option<Outcome>) {
+        self.game.outcome = outcome;
+    }
+
+    fn end_game(&mut self) -> Self::Result {
+        std::mem::take(&mut self.game)
+    }
+}
+
+fn read_games(path: &Path, variations: bool) -> anyhow::Result<Vec<TreeGame>> {
+    let file =
+        std::fs::File::open(path).map_err(|err| anyhow::anyhow!("{}: {err}", path.display()))?;
+    let mut reader = BufferedReader::new(file);
+    let mut lines = Lines {
+        variations,
+        ..Lines::default()
+    };
+    let mut games = Vec::new();
+    while let Some(game) = reader.read_game(&mut lines)? {
+        games.push(game);
+    }
+    Ok(games)
+}
@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+use shakmaty::Color as ChessColor;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::engine::{Engine, Strength};
+
+// ----------------------------------------------
+// Opponent personalities: named engine setups from `opponents` in the
+// config file, e.g. lc0 with a Maia network to play like a 1500 player
+// ----------------------------------------------
+
+/// One opponent, everything but `engine` optional
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    /// the UCI engine binary, e.g. lc0 or stockfish
+    pub engine: PathBuf,
+    /// a network file, given to the engine as its WeightsFile option
+    pub weights: Option<PathBuf>,
+    /// play at about this Elo (UCI_Elo)
+    pub elo: Option<u32>,
+    /// the engine's Skill Level, 0 to 20
+    pub skill: Option<u8>,
+    /// longest time per move in milliseconds
+    pub movetime: Option<u64>,
+    /// nodes to search per move instead of a time, 1 for a Maia network
+    pub nodes: Option<u64>,
+    /// UCI options over those for every engine, e.g. {"Threads": "1"}
+    pub options: BTreeMap<String, String>,
+}
+
+/// The opponents of the config file by name
+pub struct Registry<'a> {
+    profiles: &'a BTreeMap<String, Profile>,
+}
+
+impl<'a> Registry<'a> {
+    pub fn new(profiles: &'a BTreeMap<String, Profile>) -> Self {
+        Self { profiles }
+    }
+
+    /// The profile called `name` with its name as configured, names are not
+    /// case sensitive
+    pub fn get(&self, name: &str) -> anyhow::Result<(&'a str, &'a Profile)> {
+        if let Some((known, profile)) = self
+            .profiles
+            .iter()
+            .find(|(known, _)| known.eq_ignore_ascii_case(name))
+        {
+            return Ok((known, profile));
+        }
+        if self.profiles.is_empty() {
+            anyhow::bail!("no opponent {name:?}, the config file has no `opponents`");
+        }
+        let names: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+        anyhow::bail!(
+            "no opponent {name:?}, the config file has {}",
+            names.join(", ")
+        )
+    }
+
+    /// Start the opponent `name` playing `color`; `engine_options` come first, the
+    /// profile's own options and weights after them. `movetime` overrides the profile's.
+    pub fn start(
+        &self,
+        name: &str,
+        color: ChessColor,
+        engine_options: &[(String, String)],
+        movetime: Option<Duration>,
+    ) -> anyhow::Result<Engine> {
+        let (name, profile) = self.get(name)?;
+        let mut options = engine_options.to_vec();
+        options.extend(profile.options.clone());
+        if let Some(weights) = &profile.weights {
+            options.push(("WeightsFile".to_string(), weights.display().to_string()));
+        }
+        let strength = Strength {
+            elo: profile.elo,
+            skill: profile.skill,
+            movetime: movetime.or(profile.movetime.map(|ms| Duration::from_millis(ms.max(1)))),
+            nodes: profile.nodes,
+        };
+        let mut engine = Engine::start(&profile.engine)?
+            .with_options(&options)?
+            .playing(color, strength)?;
+        // the personality, rather than the engine behind it
+        engine.name = name.to_string();
+        Ok(engine)
+    }
+}
@@ -0,0 +1,341 @@
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Color as ScreenColor, Modifier, Style};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::Terminal;
+use shakmaty::fen::Epd;
+use shakmaty::san::SanPlus;
+use shakmaty::{ByColor, Color, EnPassantMode, Position};
+use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
+
+use crate::eco::Eco;
+use crate::review::{self, Judgement};
+use crate::scouting::{self, Analysis};
+use crate::{LastPosition, PgnGame};
+
+// ----------------------------------------------
+// My results by opening: from PGN files of my games, each game put under
+// its ECO opening, the score as white and as black and the errors I make
+// in those games, the ones I keep making first; shown on a screen of its
+// own or printed
+// ----------------------------------------------
+
+// Errors listed for each opening
+const MISTAKES_SHOWN: usize = 3;
+
+#[derive(Default)]
+struct Mistake {
+    // "8...Qb6?"
+    text: String,
+    judgement: Option<Judgement>,
+    count: usize,
+}
+
+#[derive(Default)]
+struct Opening {
+    // "B90 Sicilian Defense: Najdorf Variation"
+    name: String,
+    // by my color: won, drawn, lost
+    results: ByColor<[usize; 3]>,
+    // games with evaluations, my moves judged in them, and my errors by judgement
+    analysed: usize,
+    judged: usize,
+    errors: [usize; 3],
+    // my errors by the position and the move
+    mistakes: HashMap<(String, String), Mistake>,
+}
+
+impl Opening {
+    fn games(&self) -> usize {
+        self.results.iter().flatten().sum()
+    }
+
+    // "12 games, 58%", "-" without any
+    fn score(results: &[usize; 3]) -> String {
+        let [won, drawn, lost] = *results;
+        let games = won + drawn + lost;
+        if games == 0 {
+            return "-".to_string();
+        }
+        let points = won as f64 + drawn as f64 / 2.0;
+        format!(
+            "{games} game{}, {:.0}%",
+            if games == 1 { "" } else { "s" },
+            100.0 * points / games as f64
+        )
+    }
+
+    // most often made first, then the worst
+    fn worst_mistakes(&self) -> Vec<&Mistake> {
+        let mut mistakes: Vec<&Mistake> = self.mistakes.values().collect();
+        mistakes.sort_by_key(|mistake| {
+            (
+                std::cmp::Reverse(mistake.count),
+                std::cmp::Reverse(mistake.judgement.map(|j| j as usize)),
+                mistake.text.clone(),
+            )
+        });
+        mistakes.truncate(MISTAKES_SHOWN);
+        mistakes
+    }
+
+    // The details, one line each
+    fn lines(&self) -> Vec<String> {
+        let mut lines = vec![self.name.clone()];
+        for color in [Color::White, Color::Black] {
+            let [won, drawn, lost] = *self.results.get(color);
+            if won + drawn + lost == 0 {
+                continue;
+            }
+            lines.push(format!(
+                "As {}: {}, {won} won, {drawn} drawn, {lost} lost",
+                color.fold_wb("white", "black"),
+                Opening::score(self.results.get(color)),
+            ));
+        }
+        if self.analysed == 0 {
+            lines.push("No game has evaluations, errors are not known.".to_string());
+            return lines;
+        }
+        let counts: Vec<String> = [
+            Judgement::Blunder,
+            Judgement::Mistake,
+            Judgement::Inaccuracy,
+        ]
+        .into_iter()
+        .map(|judgement| judgement.count(self.errors[judgement as usize]))
+        .collect();
+        lines.push(format!(
+            "Errors in {} analysed games: {} in {} moves",
+            self.analysed,
+            counts.join(", "),
+            self.judged
+        ));
+        for mistake in self.worst_mistakes() {
+            let times = match mistake.count {
+                1 => "once".to_string(),
+                2 => "twice".to_string(),
+                count => format!("{count} times"),
+            };
+            lines.push(format!("  {} {times}", mistake.text));
+        }
+        lines
+    }
+}
+
+pub struct Report {
+    pub name: String,
+    pub games: usize,
+    // most played first
+    openings: Vec<Opening>,
+}
+
+/// The games of `name` in the PGN files by opening; without [%eval]
+/// comments they are reviewed with `analysis` if given. `progress` is told
+/// of every review.
+pub fn report(
+    name: &str,
+    paths: &[PathBuf],
+    eco: &Eco,
+    analysis: Option<&Analysis>,
+    progress: &(dyn Fn(usize, usize) + Sync),
+) -> anyhow::Result<Report> {
+    let mut openings: HashMap<String, Opening> = HashMap::new();
+    let mut games = 0;
+    for path in paths {
+        let text = std::fs::read_to_string(path)
+            .map_err(|err| anyhow::anyhow!("{}: {err}", path.display()))?;
+        let mut reader = pgn_reader::BufferedReader::new(text.as_bytes());
+        while let Some(game) = reader.read_game(&mut LastPosition::new(None))? {
+            let color = if game.white.eq_ignore_ascii_case(name) {
+                Color::White
+            } else if game.black.eq_ignore_ascii_case(name) {
+                Color::Black
+            } else {
+                continue;
+            };
+            games += 1;
+            let opening_name = match eco.classify(&game.start, &game.moves) {
+                Some(opening) => opening.describe(),
+                None if !game.opening.is_empty() => game.opening.clone(),
+                None => "Unclassified".to_string(),
+            };
+            let opening = openings
+                .entry(opening_name.clone())
+                .or_insert_with(|| Opening {
+                    name: opening_name,
+                    ..Opening::default()
+                });
+            let result = match (game.result.as_str(), color) {
+                ("1-0", Color::White) | ("0-1", Color::Black) => Some(0),
+                ("1/2-1/2", _) => Some(1),
+                ("1-0", Color::Black) | ("0-1", Color::White) => Some(2),
+                _ => None,
+            };
+            if let Some(result) = result {
+                opening.results.get_mut(color)[result] += 1;
+            }
+            add_errors(opening, &game, color, analysis, progress)?;
+        }
+    }
+    let mut openings: Vec<Opening> = openings.into_values().collect();
+    openings.sort_by(|a, b| b.games().cmp(&a.games()).then(a.name.cmp(&b.name)));
+    Ok(Report {
+        name: name.to_string(),
+        games,
+        openings,
+    })
+}
+
+// The errors of `color` in the game
+fn add_errors(
+    opening: &mut Opening,
+    game: &PgnGame,
+    color: Color,
+    analysis: Option<&Analysis>,
+    progress: &(dyn Fn(usize, usize) + Sync),
+) -> anyhow::Result<()> {
+    let Some(cps) = scouting::evaluations(game, analysis, progress)? else {
+        return Ok(());
+    };
+    opening.analysed += 1;
+    let mut pos = game.start.clone();
+    for (ply, mv) in game.moves.iter().enumerate() {
+        let before = pos.clone();
+        let san = SanPlus::from_move_and_play_unchecked(&mut pos, mv);
+        if before.turn() != color {
+            continue;
+        }
+        let cp = |index: usize| cps.get(index).copied().flatten();
+        let (Some(cp_before), Some(cp_after)) = (cp(ply), cp(ply + 1)) else {
+            continue;
+        };
+        opening.judged += 1;
+        let Some(judgement) = review::judge(cp_before, cp_after, color) else {
+            continue;
+        };
+        opening.errors[judgement as usize] += 1;
+        let key = (
+            Epd::from_position(before.clone(), EnPassantMode::Legal).to_string(),
+            san.to_string(),
+        );
+        let mistake = opening.mistakes.entry(key).or_default();
+        if mistake.count == 0 {
+            let number = before.fullmoves();
+            let dots = color.fold_wb(".", "...");
+            mistake.text = format!("{number}{dots}{san}{}", judgement.symbol());
+        }
+        mistake.count += 1;
+        // the same move may be judged differently in different games
+        mistake.judgement = mistake.judgement.max(Some(judgement));
+    }
+    Ok(())
+}
+
+impl Report {
+    /// The report, one line each
+    pub fn lines(&self) -> Vec<String> {
+        if self.games == 0 {
+            return vec![format!("No games of {} found.", self.name)];
+        }
+        let mut lines = vec![format!(
+            "{}: {} games in {} openings",
+            self.name,
+            self.games,
+            self.openings.len()
+        )];
+        for opening in &self.openings {
+            lines.push(String::new());
+            lines.extend(opening.lines());
+        }
+        lines
+    }
+
+    /// The report on a screen of its own: the openings on the left, the
+    /// details of the one selected on the right
+    pub fn show(&self) -> anyhow::Result<()> {
+        if self.games == 0 {
+            anyhow::bail!("no games of {} found", self.name);
+        }
+        enable_raw_mode()?;
+        crossterm::execute!(io::stdout(), EnterAlternateScreen)?;
+        let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+        let result = self.show_loop(&mut terminal);
+        disable_raw_mode()?;
+        crossterm::execute!(io::stdout(), LeaveAlternateScreen)?;
+        result
+    }
+
+    fn show_loop(
+        &self,
+        terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    ) -> anyhow::Result<()> {
+        let mut state = ListState::default().with_selected(Some(0));
+        loop {
+            terminal.draw(|f| {
+                let rows = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Min(3), Constraint::Length(1)])
+                    .split(f.area());
+                let columns = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints([Constraint::Percentage(55), Constraint::Percentage(45)])
+                    .split(rows[0]);
+                let items: Vec<ListItem> = self
+                    .openings
+                    .iter()
+                    .map(|opening| {
+                        let [white, black] = [Color::White, Color::Black]
+                            .map(|color| Opening::score(opening.results.get(color)));
+                        ListItem::new(format!(
+                            "{:<44} W {:<14} B {}",
+                            opening.name.chars().take(44).collect::<String>(),
+                            white,
+                            black
+                        ))
+                    })
+                    .collect();
+                let title = format!("Openings of {} ({} games)", self.name, self.games);
+                let list = List::new(items)
+                    .block(Block::bordered().title(title))
+                    .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+                f.render_stateful_widget(list, columns[0], &mut state);
+                let details = state
+                    .selected()
+                    .and_then(|i| self.openings.get(i))
+                    .map(Opening::lines)
+                    .unwrap_or_default();
+                f.render_widget(
+                    Paragraph::new(details.join("\n"))
+                        .wrap(Wrap { trim: false })
+                        .block(Block::bordered().title("Details")),
+                    columns[1],
+                );
+                let help = Paragraph::new("Up/Down select an opening, Esc quits.")
+                    .style(Style::default().fg(ScreenColor::Gray));
+                f.render_widget(help, rows[1]);
+            })?;
+
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Esc | KeyCode::Char('q') => return Ok(()),
+                KeyCode::Up => state.select_previous(),
+                KeyCode::Down => state.select_next(),
+                KeyCode::Home => state.select_first(),
+                KeyCode::End => state.select_last(),
+                _ => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,548 @@
+use pgn_reader::{Nag, Outcome, RawComment, RawHeader, SanPlus, Skip, Visitor};
+use shakmaty::fen::Fen;
+use shakmaty::{CastlingMode, Chess, Color as ChessColor, Move, Position};
+use std::path::Path;
+
+use crate::export;
+
+// ----------------------------------------------
+// A PGN game with everything in it, for annotated collections: every tag
+// in order, comments, any number of NAGs per move and variations nested
+// to any depth, written back as it was read
+// ----------------------------------------------
+
+// NAGs for a diagram, and from black's side, as ChessBase writes them
+const DIAGRAM: u8 = 220;
+const DIAGRAM_FROM_BLACK: u8 = 221;
+
+#[derive(Clone, Debug)]
+pub struct Node {
+    /// as written, disambiguation and all
+    pub san: SanPlus,
+    /// None if the SAN is not legal where it stands; the rest of its line is
+    /// still kept, as written
+    pub mv: Option<Move>,
+    pub nags: Vec<u8>,
+    /// comments before the move, at the start of a variation or after one
+    pub before: Vec<String>,
+    pub after: Vec<String>,
+    /// lines played instead of this move
+    pub variations: Vec<Vec<Node>>,
+}
+
+impl Node {
+    fn new(pos: &mut Chess, mv: &Move) -> Self {
+        Self {
+            san: SanPlus::from_move_and_play_unchecked(pos, mv),
+            mv: Some(mv.clone()),
+            nags: Vec::new(),
+            before: Vec::new(),
+            after: Vec::new(),
+            variations: Vec::new(),
+        }
+    }
+
+    /// The NAGs for the move list: "!", "?!" right after the move, "+=" or
+    /// "$146" after a space; empty without any
+    pub fn glyphs(&self) -> String {
+        let (moves, others): (Vec<u8>, Vec<u8>) = self
+            .nags
+            .iter()
+            .filter(|&&nag| nag != DIAGRAM && nag != DIAGRAM_FROM_BLACK)
+            .partition(|&&nag| (1..=6).contains(&nag));
+        let mut out: String = moves.into_iter().map(glyph).collect();
+        for nag in others {
+            out.push(' ');
+            out.push_str(&glyph(nag));
+        }
+        out
+    }
+
+    /// A diagram is to be shown after the move: NAG 220 or 221, or "[#]" in a
+    /// comment
+    pub fn has_diagram(&self) -> bool {
+        self.nags
+            .iter()
+            .any(|&nag| nag == DIAGRAM || nag == DIAGRAM_FROM_BLACK)
+            || self.after.iter().any(|comment| comment.contains("[#]"))
+    }
+}
+
+fn glyph(nag: u8) -> String {
+    let text = match nag {
+        1 => "!",
+        2 => "?",
+        3 => "!!",
+        4 => "??",
+        5 => "!?",
+        6 => "?!",
+        7 => "[]",
+        10 => "=",
+        13 => "~",
+        14 => "+=",
+        15 => "=+",
+        16 => "+/-",
+        17 => "-/+",
+        18 => "+-",
+        19 => "-+",
+        22 | 23 => "(.)",
+        146 => "N",
+        _ => return format!("${nag}"),
+    };
+    text.to_string()
+}
+
+#[derive(Clone, Debug)]
+pub struct Game {
+    /// every tag, in the order of the file
+    pub headers: Vec<(String, String)>,
+    pub start: Chess,
+    pub moves: Vec<Node>,
+    /// comments of a game without moves, or after the last variation of the
+    /// mainline
+    pub comments: Vec<String>,
+    /// "1-0", "0-1", "1/2-1/2" or "*"
+    pub result: String,
+}
+
+impl Game {
+    /// The nodes along `moves` from the start, into variations where they go;
+    /// shorter than `moves` where those leave the tree
+    pub fn path(&self, moves: &[Move]) -> Vec<&Node> {
+        let mut nodes = Vec::new();
+        let mut line = &self.moves;
+        let mut index = 0;
+        for mv in moves {
+            let Some(node) = line.get(index) else {
+                break;
+            };
+            if node.mv.as_ref() == Some(mv) {
+                nodes.push(node);
+                index += 1;
+                continue;
+            }
+            let Some(variation) = node
+                .variations
+                .iter()
+                .find(|variation| variation[0].mv.as_ref() == Some(mv))
+            else {
+                break;
+            };
+            nodes.push(&variation[0]);
+            line = variation;
+            index = 1;
+        }
+        nodes
+    }
+
+    /// Add `moves` from the start: along the moves already there, as a new
+    /// variation where they leave them, extending a line where it ends
+    pub fn merge(&mut self, moves: &[Move]) {
+        merge_into(&mut self.moves, self.start.clone(), moves);
+    }
+
+    /// Every legal move of the tree, mainline and variations at any depth,
+    /// with the position it is played in; a line stops at an illegal move
+    pub fn walk(&self, visit: &mut dyn FnMut(&Chess, &Move)) {
+        walk_line(&self.moves, self.start.clone(), visit);
+    }
+
+    /// Set the result, in the tag too
+    pub fn set_result(&mut self, result: &str) {
+        self.result = result.to_string();
+        match self.headers.iter_mut().find(|(key, _)| key == "Result") {
+            Some((_, value)) => *value = result.to_string(),
+            None => self
+                .headers
+                .push(("Result".to_string(), result.to_string())),
+        }
+    }
+}
+
+fn walk_line(line: &[Node], mut pos: Chess, visit: &mut dyn FnMut(&Chess, &Move)) {
+    for node in line {
+        for variation in &node.variations {
+            walk_line(variation, pos.clone(), visit);
+        }
+        let Some(mv) = &node.mv else {
+            return;
+        };
+        visit(&pos, mv);
+        pos.play_unchecked(mv);
+    }
+}
+
+fn merge_into(line: &mut Vec<Node>, mut pos: Chess, moves: &[Move]) {
+    for (index, mv) in moves.iter().enumerate() {
+        let Some(node) = line.get_mut(index) else {
+            for mv in &moves[index..] {
+                line.push(Node::new(&mut pos, mv));
+            }
+            return;
+        };
+        if node.mv.as_ref() == Some(mv) {
+            pos.play_unchecked(mv);
+            continue;
+        }
+        match node
+            .variations
+            .iter()
+            .position(|variation| variation[0].mv.as_ref() == Some(mv))
+        {
+            Some(known) => merge_into(&mut node.variations[known], pos, &moves[index..]),
+            None => {
+                let mut variation = Vec::new();
+                merge_into(&mut variation, pos, &moves[index..]);
+                node.variations.push(variation);
+            }
+        }
+        return;
+    }
+}
+
+/// Game `index` of the PGN file at `path`, counting from 0
+pub fn load(path: &Path, index: usize) -> anyhow::Result<Game> {
+    let text = std::fs::read_to_string(path)?;
+    let mut reader = pgn_reader::BufferedReader::new(text.as_bytes());
+    for _ in 0..index {
+        if !reader.skip_game::<Reader>()? {
+            anyhow::bail!("{} has only {index} games", path.display());
+        }
+    }
+    reader
+        .read_game(&mut Reader::default())?
+        .ok_or_else(|| anyhow::anyhow!("unable to read game"))
+}
+
+/// Every game of the PGN file at `path`
+pub fn load_all(path: &Path) -> anyhow::Result<Vec<Game>> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|err| anyhow::anyhow!("{}: {err}", path.display()))?;
+    let mut reader = pgn_reader::BufferedReader::new(text.as_bytes());
+    let mut games = Vec::new();
+    while let Some(game) = reader.read_game(&mut Reader::default())? {
+        games.push(game);
+    }
+    Ok(games)
+}
+
+// ----------------------------------------------
+// Reading: a line for every open variation, each knowing its position
+// ----------------------------------------------
+
+struct Frame {
+    line: Vec<Node>,
+    // after the line's last move, and before it where a variation of it starts;
+    // None past an illegal move
+    pos: Option<Chess>,
+    before_last: Option<Chess>,
+}
+
+impl Frame {
+    fn new(pos: Option<Chess>) -> Self {
+        Self {
+            line: Vec::new(),
+            pos,
+            before_last: None,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Reader {
+    headers: Vec<(String, String)>,
+    start: Chess,
+    // the mainline first, the innermost variation last
+    frames: Vec<Frame>,
+    // comments waiting for the next move
+    pending: Vec<String>,
+    result: Option<String>,
+}
+
+impl Reader {
+    fn frame(&mut self) -> &mut Frame {
+        if self.frames.is_empty() {
+            self.frames.push(Frame::new(Some(self.start.clone())));
+        }
+        self.frames.last_mut().expect("just pushed")
+    }
+
+    // Give the comments nobody took to the last move of the line
+    fn close(&mut self, line: &mut [Node]) {
+        if let Some(last) = line.last_mut() {
+            last.after.append(&mut self.pending);
+        }
+    }
+
+    // Comments left over from a closed variation go with the move the next
+    // one is an alternative to
+    fn close_pending_into_last(&mut self) {
+        let mut pending = std::mem::take(&mut self.pending);
+        if let Some(last) = self.frame().line.last_mut() {
+            last.after.append(&mut pending);
+        }
+    }
+}
+
+impl Visitor for Reader {
+    type Result = Game;
+
+    fn header(&mut self, key: &[u8], value: RawHeader<'_>) {
+        let key = String::from_utf8_lossy(key).into_owned();
+        let value = value.decode_utf8_lossy().into_owned();
+        if key == "FEN" {
+            if let Some(pos) = Fen::from_ascii(value.as_bytes())
+                .ok()
+                .and_then(|fen| fen.into_position(CastlingMode::Standard).ok())
+            {
+                self.start = pos;
+            }
+        }
+        self.headers.push((key, value));
+    }
+
+    fn san(&mut self, san: SanPlus) {
+        let before = std::mem::take(&mut self.pending);
+        let frame = self.frame();
+        let mv = frame.pos.as_ref().and_then(|pos| san.san.to_move(pos).ok());
+        frame.before_last = frame.pos.take();
+        frame.pos = match (&mv, frame.before_last.clone()) {
+            (Some(mv), Some(mut pos)) => {
+                pos.play_unchecked(mv);
+                Some(pos)
+            }
+            _ => None,
+        };
+        frame.line.push(Node {
+            san,
+            mv,
+            nags: Vec::new(),
+            before,
+            after: Vec::new(),
+            variations: Vec::new(),
+        });
+    }
+
+    fn nag(&mut self, nag: Nag) {
+        if let Some(last) = self.frame().line.last_mut() {
+            last.nags.push(nag.0);
+        }
+    }
+
+    fn comment(&mut self, comment: RawComment<'_>) {
+        let text = String::from_utf8_lossy(comment.as_bytes())
+            .trim()
+            .to_string();
+        // after a variation the comment goes with the next move
+        match self.frame().line.last_mut() {
+            Some(last) if last.variations.is_empty() => last.after.push(text),
+            _ => self.pending.push(text),
+        }
+    }
+
+    fn begin_variation(&mut self) -> Skip {
+        let frame = self.frame();
+        if frame.line.is_empty() {
+            // nothing to be an alternative to
+            return Skip(true);
+        }
+        let pos = frame.before_last.clone();
+        self.close_pending_into_last();
+        self.frames.push(Frame::new(pos));
+        Skip(false)
+    }
+
+    fn end_variation(&mut self) {
+        let Some(mut frame) = self.frames.pop() else {
+            return;
+        };
+        self.close(&mut frame.line);
+        if frame.line.is_empty() {
+            return;
+        }
+        if let Some(last) = self.frame().line.last_mut() {
+            last.variations.push(frame.line);
+        }
+    }
+
+    fn outcome(&mut self, outcome: Option<Outcome>) {
+        self.result = Some(outcome.map_or("*".to_string(), |outcome| outcome.to_string()));
+    }
+
+    fn end_game(&mut self) -> Self::Result {
+        let mut moves = self
+            .frames
+            .drain(..)
+            .next()
+            .map(|frame| frame.line)
+            .unwrap_or_default();
+        let comments = match moves.last_mut() {
+            Some(last) if last.variations.is_empty() => {
+                last.after.append(&mut self.pending);
+                Vec::new()
+            }
+            _ => std::mem::take(&mut self.pending),
+        };
+        let tag = self
+            .headers
+            .iter()
+            .find(|(key, _)| key == "Result")
+            .map(|(_, value)| value.clone());
+        Game {
+            headers: std::mem::take(&mut self.headers),
+            start: std::mem::take(&mut self.start),
+            moves,
+            comments,
+            result: self.result.take().or(tag).unwrap_or("*".to_string()),
+        }
+    }
+}
+
+// ----------------------------------------------
+// Writing, in the PGN export format: NAGs as $n, a move number for black
+// after a comment or a variation
+// ----------------------------------------------
+
+pub fn write(game: &Game) -> String {
+    let mut out = String::new();
+    for (key, value) in &game.headers {
+        let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+        out.push_str(&format!("[{key} \"{value}\"]\n"));
+    }
+    let mut words = Vec::new();
+    write_line(
+        &game.moves,
+        (game.start.turn(), game.start.fullmoves().get()),
+        &mut words,
+    );
+    push_comments(&game.comments, &mut words);
+    words.push(game.result.clone());
+    format!("{out}\n{}\n", export::wrap(&words, 80))
+}
+
+fn write_line(line: &[Node], (mut turn, mut number): (ChessColor, u32), words: &mut Vec<String>) {
+    // black's moves carry their number at the start of a line and after an interruption
+    let mut numbered = true;
+    for node in line {
+        push_comments(&node.before, words);
+        numbered |= !node.before.is_empty();
+        match turn {
+            ChessColor::White => words.push(format!("{number}.")),
+            ChessColor::Black if numbered => words.push(format!("{number}...")),
+            ChessColor::Black => {}
+        }
+        words.push(node.san.to_string());
+        words.extend(node.nags.iter().map(|nag| format!("${nag}")));
+        push_comments(&node.after, words);
+        for variation in &node.variations {
+            let mut inner = Vec::new();
+            write_line(variation, (turn, number), &mut inner);
+            if let (Some(first), Some(last)) = (inner.first().cloned(), inner.last_mut()) {
+                last.push(')');
+                inner[0] = format!("({first}");
+            }
+            words.extend(inner);
+        }
+        numbered = !node.after.is_empty() || !node.variations.is_empty();
+        if turn == ChessColor::Black {
+            number += 1;
+        }
+        turn = !turn;
+    }
+}
+
+fn push_comments(comments: &[String], words: &mut Vec<String>) {
+    for comment in comments {
+        let mut inner: Vec<String> = comment.split_whitespace().map(str::to_string).collect();
+        match (inner.len(), inner.first_mut()) {
+            (1, Some(only)) => *only = format!("{{{only}}}"),
+            (_, Some(first)) => {
+                *first = format!("{{{first}");
+                if let Some(last) = inner.last_mut() {
+                    last.push('}');
+                }
+            }
+            (_, None) => inner.push("{}".to_string()),
+        }
+        words.extend(inner);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ANNOTATED: &str = r#"[Event "Informant 1"]
+[Site "?"]
+[White "A"]
+[Black "B \"the annotator\""]
+[Result "1-0"]
+[Annotator "X"]
+
+{Opening remark} 1. e4 $1 $14 {best by test} e5 (1... c5 {Sicilian} 2. Nf3 (2. c3
+d5) 2... d6) (1... e6 $6) 2. Nf3 Nc6 3. Bb5 {[#] the Spanish} 3... a6 $220 1-0
+"#;
+
+    fn read(text: &str) -> Game {
+        pgn_reader::BufferedReader::new(text.as_bytes())
+            .read_game(&mut Reader::default())
+            .unwrap()
+            .unwrap()
+    }
+
+    #[test]
+    fn keeps_variations_nags_and_comments() {
+        let game = read(ANNOTATED);
+        assert_eq!(game.headers.len(), 6);
+        assert_eq!(game.headers[3].1, "B \"the annotator\"");
+        assert_eq!(game.moves.len(), 6);
+        assert_eq!(game.moves[0].before, ["Opening remark"]);
+        assert_eq!(game.moves[0].nags, [1, 14]);
+        assert_eq!(game.moves[0].glyphs(), "! +=");
+        let sicilian = &game.moves[1].variations[0];
+        assert_eq!(sicilian.len(), 3);
+        assert_eq!(sicilian[1].variations[0].len(), 2);
+        assert_eq!(game.moves[1].variations[1][0].nags, [6]);
+        assert!(game.moves[4].has_diagram() && game.moves[5].has_diagram());
+        assert_eq!(game.result, "1-0");
+    }
+
+    #[test]
+    fn writes_back_what_it_read() {
+        let written = write(&read(ANNOTATED));
+        assert_eq!(write(&read(&written)), written);
+        assert!(written.contains("1. e4 $1 $14 {best by test} 1... e5 (1... c5 {Sicilian} 2. Nf3"));
+        assert!(written.contains("(2. c3 d5) 2... d6) (1... e6 $6) 2. Nf3"));
+    }
+
+    #[test]
+    fn walks_every_line() {
+        let mut moves = Vec::new();
+        read(ANNOTATED).walk(&mut |pos, mv| {
+            moves.push(SanPlus::from_move(pos.clone(), mv).to_string());
+        });
+        assert_eq!(moves.len(), 12);
+        for san in ["c5", "c3", "d5", "d6", "e6", "a6"] {
+            assert!(moves.contains(&san.to_string()), "{san}");
+        }
+    }
+
+    #[test]
+    fn merges_new_moves_as_variations() {
+        let mut game = read(ANNOTATED);
+        let mut pos = Chess::default();
+        let moves: Vec<Move> = ["e4", "c5", "Nf3", "Nc6"]
+            .iter()
+            .map(|san| {
+                let mv = san.parse::<SanPlus>().unwrap().san.to_move(&pos).unwrap();
+                pos.play_unchecked(&mv);
+                mv
+            })
+            .collect();
+        game.merge(&moves);
+        let sicilian = &game.moves[1].variations[0];
+        assert_eq!(sicilian[2].san.to_string(), "d6");
+        assert_eq!(sicilian[2].variations[0][0].san.to_string(), "Nc6");
+        assert_eq!(game.path(&moves).len(), 4);
+    }
+}
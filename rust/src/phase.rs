@@ -0,0 +1,45 @@
+use shakmaty::{Chess, Position};
+
+// ----------------------------------------------
+// Game phases: the opening, the middlegame and the endgame of a
+// position, by the move number and the material left, for scouting
+// reports, reviews and training one phase at a time
+// ----------------------------------------------
+
+// Pieces left besides kings and pawns once the endgame starts
+const ENDGAME_PIECES: usize = 6;
+// The last move of the opening
+const OPENING_MOVES: u32 = 12;
+
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+pub enum Phase {
+    Opening,
+    Middlegame,
+    Endgame,
+}
+
+impl Phase {
+    pub const ALL: [Phase; 3] = [Phase::Opening, Phase::Middlegame, Phase::Endgame];
+
+    /// The first twelve moves, then the endgame once six pieces or fewer are
+    /// left besides kings and pawns, whatever the move
+    pub fn of(pos: &Chess) -> Phase {
+        let board = pos.board();
+        let pieces = (board.occupied() & !board.kings() & !board.pawns()).count();
+        if pieces <= ENDGAME_PIECES {
+            Phase::Endgame
+        } else if u32::from(pos.fullmoves()) <= OPENING_MOVES {
+            Phase::Opening
+        } else {
+            Phase::Middlegame
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Phase::Opening => "opening",
+            Phase::Middlegame => "middlegame",
+            Phase::Endgame => "endgame",
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use serde::{Deserialize, Serialize};
+use shakmaty::fen::Fen;
+use shakmaty::{CastlingMode, Chess, EnPassantMode};
+use std::path::PathBuf;
+
+use crate::{session, stats};
+
+// ----------------------------------------------
+// My library of named positions ("Lucena", "my d4 tabiya #3"), saved
+// from any board with tags to find them by and opened again to play,
+// drill or analyse; kept in $XDG_STATE_HOME/tess/positions.json
+// ----------------------------------------------
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Saved {
+    pub name: String,
+    pub fen: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// when it was last played as a drill, unix seconds, 0 for never
+    #[serde(default)]
+    pub drilled: u64,
+}
+
+impl Saved {
+    pub fn position(&self) -> anyhow::Result<Chess> {
+        Ok(Fen::from_ascii(self.fen.as_bytes())?.into_position(CastlingMode::Standard)?)
+    }
+
+    /// Whether `query` is in the name or a tag, ignoring case
+    pub fn matches(&self, query: &str) -> bool {
+        let query = query.to_lowercase();
+        self.name.to_lowercase().contains(&query)
+            || self
+                .tags
+                .iter()
+                .any(|tag| tag.to_lowercase().contains(&query))
+    }
+
+    /// "Lucena  [rook, endgame]"
+    pub fn describe(&self) -> String {
+        if self.tags.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{}  [{}]", self.name, self.tags.join(", "))
+        }
+    }
+}
+
+fn library_path() -> anyhow::Result<PathBuf> {
+    session::state_dir()
+        .map(|dir| dir.join("positions.json"))
+        .ok_or_else(|| anyhow::anyhow!("no state directory (HOME unset)"))
+}
+
+/// Every saved position, in the order saved
+pub fn load() -> anyhow::Result<Vec<Saved>> {
+    let path = library_path()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+}
+
+fn store(positions: &[Saved]) -> anyhow::Result<()> {
+    let path = library_path()?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, serde_json::to_vec_pretty(positions)?)?;
+    Ok(())
+}
+
+/// Save `pos` as `name`, in place of a position of that name if there is one.
+/// True if one was replaced.
+pub fn save(name: &str, pos: &Chess, tags: Vec<String>) -> anyhow::Result<bool> {
+    let name = name.trim();
+    if name.is_empty() {
+        anyhow::bail!("a saved position needs a name");
+    }
+    let saved = Saved {
+        name: name.to_string(),
+        fen: Fen::from_position(pos.clone(), EnPassantMode::Legal).to_string(),
+        tags,
+        drilled: 0,
+    };
+    let mut positions = load()?;
+    let replaced = match positions
+        .iter_mut()
+        .find(|old| old.name.eq_ignore_ascii_case(name))
+    {
+        Some(old) => {
+            *old = saved;
+            true
+        }
+        None => {
+            positions.push(saved);
+            false
+        }
+    };
+    store(&positions)?;
+    Ok(replaced)
+}
+
+/// Forget the position named `name`, false if there is none
+pub fn remove(name: &str) -> anyhow::Result<bool> {
+    let mut positions = load()?;
+    let count = positions.len();
+    positions.retain(|saved| !saved.name.eq_ignore_ascii_case(name.trim()));
+    if positions.len() == count {
+        return Ok(false);
+    }
+    store(&positions)?;
+    Ok(true)
+}
+
+/// The position named `query`, else those it finds as a search
+pub fn find(positions: &[Saved], query: &str) -> Vec<Saved> {
+    let query = query.trim();
+    match positions
+        .iter()
+        .find(|saved| saved.name.eq_ignore_ascii_case(query))
+    {
+        Some(saved) => vec![saved.clone()],
+        None => positions
+            .iter()
+            .filter(|saved| saved.matches(query))
+            .cloned()
+            .collect(),
+    }
+}
+
+/// The position named `query`, else the first it finds, to open
+pub fn pick(query: &str) -> anyhow::Result<Saved> {
+    find(&load()?, query)
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("no saved position matches '{query}'"))
+}
+
+/// Of the positions `query` finds, the one drilled longest ago, marked as drilled
+/// now; with how many it found
+pub fn next_drill(query: &str) -> anyhow::Result<(Saved, usize)> {
+    let mut positions = load()?;
+    let found = find(&positions, query);
+    let Some(next) = found.iter().min_by_key(|saved| saved.drilled) else {
+        anyhow::bail!("no saved position matches '{query}'");
+    };
+    if let Some(saved) = positions.iter_mut().find(|saved| saved.name == next.name) {
+        saved.drilled = stats::now();
+    }
+    store(&positions)?;
+    Ok((next.clone(), found.len()))
+}
@@ -0,0 +1,275 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Paragraph, Wrap};
+use shakmaty::{CastlingMode, Color as ChessColor, Position};
+use std::time::{Duration, Instant};
+
+use tess_rs::board::{BoardWidget, DisplayMode};
+
+use crate::{session_log, step_view, App};
+
+// ----------------------------------------------
+// Presentation mode for teaching over a shared screen: a prepared PGN game
+// on a board as large as the terminal takes, the last move beside it in
+// huge letters, played through by hand or on a timer. Only the keys that
+// move through the game work, nothing can be typed or played
+// ----------------------------------------------
+
+// Rows of a glyph of the huge letters, the last one for descenders
+const GLYPH_ROWS: usize = 6;
+// The board takes at most that share of the width, in percent
+const BOARD_SHARE: usize = 60;
+// Times a glyph is drawn larger at most
+const MAX_SCALE: usize = 3;
+
+pub struct Presentation {
+    // "Carlsen - Nepomniachtchi, 1-0"
+    title: String,
+    // time each position stays on screen, None without auto-advance
+    step: Option<Duration>,
+    pub playing: bool,
+    stepped: Instant,
+}
+
+impl Presentation {
+    pub fn new(title: String, step: Option<Duration>) -> Self {
+        Self {
+            title,
+            step,
+            playing: step.is_some(),
+            stepped: session_log::now(),
+        }
+    }
+
+    /// Time until the next position is due, None unless playing
+    pub fn due_in(&self) -> Option<Duration> {
+        let step = self.step.filter(|_| self.playing)?;
+        Some(step.saturating_sub(session_log::elapsed(self.stepped)))
+    }
+}
+
+/// Handle a key of a presentation, false to quit. The arrows, Home and End
+/// move through the game and stop the auto-advance, space pauses and resumes
+/// it; every other key is ignored.
+pub fn handle_key(app: &mut App, key: KeyEvent) -> bool {
+    let ply = app.shown_ply();
+    let last = app.history.len();
+    let Some(presentation) = app.presentation.as_mut() else {
+        return true;
+    };
+    let target = match key.code {
+        KeyCode::Esc | KeyCode::Char('q') => return false,
+        KeyCode::Char(' ') if presentation.step.is_some() => {
+            presentation.playing = !presentation.playing && ply < last;
+            presentation.stepped = session_log::now();
+            return true;
+        }
+        KeyCode::Left | KeyCode::Up => ply.saturating_sub(1),
+        KeyCode::Right | KeyCode::Down => (ply + 1).min(last),
+        KeyCode::Home => 0,
+        KeyCode::End => last,
+        _ => return true,
+    };
+    presentation.playing = false;
+    step_view(app, target);
+    true
+}
+
+/// The next position of an auto-advancing presentation once it is due, true if it moved
+pub fn advance(app: &mut App) -> bool {
+    let ply = app.shown_ply() + 1;
+    let last = app.history.len();
+    let Some(presentation) = app.presentation.as_mut() else {
+        return false;
+    };
+    if presentation.due_in() != Some(Duration::ZERO) {
+        return false;
+    }
+    presentation.stepped = session_log::now();
+    presentation.playing = ply < last;
+    step_view(app, ply);
+    true
+}
+
+/// The whole screen of a presentation: the board on the left, the last move
+/// and what the PGN says of it on the right, the keys at the bottom
+pub fn draw(f: &mut ratatui::Frame, app: &App, presentation: &Presentation, area: Rect) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(3), Constraint::Length(1)])
+        .split(area);
+
+    // square size: as tall as the rows allow, twice as wide as tall like the
+    // terminal's cells, the big pieces once they fit
+    let height = (rows[0].height.saturating_sub(2) / 8) as usize;
+    let max_width = (usize::from(area.width) * BOARD_SHARE / 100).saturating_sub(2) / 8;
+    let cell_width = (2 * height).min(max_width);
+    let cell_height = height.min(cell_width.div_ceil(2));
+    let (min_width, min_height) = DisplayMode::Big.default_cell_dimensions();
+    let display = if cell_width >= min_width && cell_height >= min_height {
+        DisplayMode::Big
+    } else {
+        DisplayMode::Simple
+    };
+
+    let shown = app.shown_ply();
+    let last_move = shown.checked_sub(1).map(|ply| &app.history[ply]);
+    let mut board = BoardWidget::new(app.shown_board())
+        .flipped(app.flipped)
+        .theme(&app.palette)
+        .display(display)
+        .pieces(app.pieces)
+        .wide_symbols(app.wide_symbols)
+        .cell_size(cell_width, cell_height);
+    if let Some(mv) = last_move {
+        if let shakmaty::uci::UciMove::Normal { from, to, .. } = mv.to_uci(CastlingMode::Standard) {
+            board = board.arrow(from, to);
+        }
+    }
+    let (board_width, _) = board.size();
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Length(board_width + 2), Constraint::Min(10)])
+        .split(rows[0]);
+    f.render_widget(board, columns[0]);
+
+    // the move: its number above it, both in huge letters if there is room
+    let side = columns[1];
+    let mut lines = vec![
+        Line::styled(
+            presentation.title.clone(),
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+        Line::raw(""),
+    ];
+    match last_move {
+        None => lines.push(Line::raw("Start position")),
+        Some(_) => {
+            let (color, san) = app.san_history().swap_remove(shown - 1);
+            let number = app.start.fullmoves().get() as usize
+                + (shown - 1 + usize::from(app.start.turn() == ChessColor::Black)) / 2;
+            let dots = color.fold_wb(".", "...");
+            let label = Style::default().fg(app.palette.label);
+            let callout = Style::default().fg(Color::Yellow);
+            let width = usize::from(side.width);
+            lines.extend(huge(
+                &format!("{number}{dots}"),
+                (width, GLYPH_ROWS),
+                1,
+                label,
+            ));
+            lines.push(Line::raw(""));
+            // rows left under it, the opening and the comment kept in view
+            let height = usize::from(side.height).saturating_sub(lines.len() + 3);
+            lines.extend(huge(&san, (width, height), MAX_SCALE, callout));
+        }
+    }
+    lines.push(Line::raw(""));
+    if let Some(opening) = app.opening() {
+        lines.push(Line::styled(
+            opening.describe(),
+            Style::default().fg(Color::Cyan),
+        ));
+    }
+    if let Some(comment) = shown
+        .checked_sub(1)
+        .and_then(|ply| app.comments.get(ply))
+        .and_then(Option::as_ref)
+    {
+        lines.push(Line::raw(comment.clone()));
+    }
+    f.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false }), side);
+
+    let mut help = format!("Ply {shown} of {}", app.history.len());
+    if presentation.step.is_some() {
+        help.push_str(if presentation.playing {
+            ", playing: space pauses"
+        } else {
+            ", paused: space plays on"
+        });
+    }
+    help.push_str(". Arrows step, Home/End jump, q quits.");
+    f.render_widget(
+        Paragraph::new(help).style(Style::default().fg(app.palette.label)),
+        rows[1],
+    );
+}
+
+// `text` in huge letters, drawn up to `max_scale` times larger while it fits
+// `width` columns and `height` rows; as it is when it does not fit at all
+fn huge(
+    text: &str,
+    (width, height): (usize, usize),
+    max_scale: usize,
+    style: Style,
+) -> Vec<Line<'static>> {
+    let glyphs: Vec<[&str; GLYPH_ROWS]> = text.chars().map(glyph).collect();
+    // a column between the glyphs
+    let columns: usize = glyphs.iter().map(|glyph| glyph[0].len() + 1).sum();
+    let Some(scale) = (1..=max_scale)
+        .rev()
+        .find(|scale| columns * scale <= width && GLYPH_ROWS * scale <= height)
+    else {
+        return vec![Line::styled(text.to_string(), style)];
+    };
+    let mut lines = Vec::new();
+    for row in 0..GLYPH_ROWS {
+        let mut line = String::new();
+        for glyph in &glyphs {
+            for ch in glyph[row].chars().chain([' ']) {
+                let ch = if ch == '#' { '█' } else { ' ' };
+                line.extend(std::iter::repeat_n(ch, scale));
+            }
+        }
+        // the descender row stays out where no glyph needs it
+        if row == GLYPH_ROWS - 1 && line.trim().is_empty() {
+            break;
+        }
+        for _ in 0..scale {
+            lines.push(Line::styled(line.trim_end().to_string(), style));
+        }
+    }
+    lines
+}
+
+// The huge letter of `ch`, '#' for the filled cells; blank for characters
+// that do not come up in a move
+fn glyph(ch: char) -> [&'static str; GLYPH_ROWS] {
+    match ch {
+        'K' => ["#   #", "#  # ", "###  ", "#  # ", "#   #", "     "],
+        'Q' => [" ### ", "#   #", "# # #", "#  # ", " ## #", "     "],
+        'R' => ["#### ", "#   #", "#### ", "#  # ", "#   #", "     "],
+        'B' => ["#### ", "#   #", "#### ", "#   #", "#### ", "     "],
+        'N' => ["#   #", "##  #", "# # #", "#  ##", "#   #", "     "],
+        'O' => [" ### ", "#   #", "#   #", "#   #", " ### ", "     "],
+        'a' => ["    ", "    ", " ###", "#  #", " ###", "    "],
+        'b' => ["#   ", "#   ", "### ", "#  #", "### ", "    "],
+        'c' => ["    ", "    ", " ###", "#   ", " ###", "    "],
+        'd' => ["   #", "   #", " ###", "#  #", " ###", "    "],
+        'e' => ["    ", " ## ", "####", "#   ", " ###", "    "],
+        'f' => ["  ##", " #  ", "### ", " #  ", " #  ", "    "],
+        'g' => ["    ", " ###", "#  #", " ###", "   #", " ## "],
+        'h' => ["#   ", "#   ", "### ", "#  #", "#  #", "    "],
+        '0' => [" ## ", "#  #", "#  #", "#  #", " ## ", "    "],
+        '1' => [" #  ", "##  ", " #  ", " #  ", "### ", "    "],
+        '2' => ["### ", "   #", " ## ", "#   ", "####", "    "],
+        '3' => ["### ", "   #", " ## ", "   #", "### ", "    "],
+        '4' => ["#  #", "#  #", "####", "   #", "   #", "    "],
+        '5' => ["####", "#   ", "### ", "   #", "### ", "    "],
+        '6' => [" ## ", "#   ", "### ", "#  #", " ## ", "    "],
+        '7' => ["####", "   #", "  # ", " #  ", " #  ", "    "],
+        '8' => [" ## ", "#  #", " ## ", "#  #", " ## ", "    "],
+        '9' => [" ## ", "#  #", " ###", "   #", " ## ", "    "],
+        'x' => ["   ", "   ", "# #", " # ", "# #", "   "],
+        '+' => ["   ", " # ", "###", " # ", "   ", "   "],
+        '#' => [" # # ", "#####", " # # ", "#####", " # # ", "     "],
+        '=' => ["   ", "###", "   ", "###", "   ", "   "],
+        '-' => ["   ", "   ", "###", "   ", "   ", "   "],
+        '.' => [" ", " ", " ", " ", "#", " "],
+        '!' => ["#", "#", "#", " ", "#", " "],
+        '?' => ["### ", "   #", " ## ", "    ", " #  ", "    "],
+        _ => ["  "; GLYPH_ROWS],
+    }
+}
@@ -0,0 +1,215 @@
+use std::time::{Duration, Instant};
+
+use crate::drills::Drill;
+use crate::phase::Phase;
+use crate::{session_log, stats, LichessNextPuzzle};
+
+// ----------------------------------------------
+// Puzzle sets: exactly `count` puzzles, as many as fit in a
+// sprint's time limit or all of a drill or study, then a score screen
+// ----------------------------------------------
+
+pub struct PuzzleResult {
+    pub rating: u16,
+    pub solved: bool,
+    /// 1 for a solve without hints, less each hint's penalty
+    pub score: f64,
+    pub hinted: bool,
+    pub time: Duration,
+}
+
+pub struct PuzzleSet {
+    pub count: usize,
+    pub theme: Option<String>,
+    // lichess puzzles of this phase only
+    pub phase: Option<Phase>,
+    // sprints end when this runs out, whatever the count
+    pub limit: Option<Duration>,
+    // built-in puzzles instead of lichess ones
+    pub drill: Option<Drill>,
+    // the chapters of a study, unrated
+    pub study: Option<Vec<LichessNextPuzzle>>,
+    pub results: Vec<PuzzleResult>,
+    started: Instant,
+    // rating of the puzzle on the board and when it was shown, None once it is scored
+    current: Option<(u16, Instant)>,
+    finished_after: Option<Duration>,
+}
+
+impl PuzzleSet {
+    pub fn new(count: usize, theme: Option<String>) -> Self {
+        Self {
+            count,
+            theme,
+            phase: None,
+            limit: None,
+            drill: None,
+            study: None,
+            results: Vec::new(),
+            started: session_log::now(),
+            current: None,
+            finished_after: None,
+        }
+    }
+
+    /// As many puzzles as can be solved within `limit`
+    pub fn sprint(limit: Duration, theme: Option<String>) -> Self {
+        Self {
+            limit: Some(limit),
+            ..Self::new(usize::MAX, theme)
+        }
+    }
+
+    /// Every puzzle of a built-in drill, in order
+    pub fn drill(drill: Drill) -> Self {
+        Self {
+            drill: Some(drill),
+            ..Self::new(drill.count(), Some(drill.name().to_string()))
+        }
+    }
+
+    /// Every chapter of a study, in order
+    pub fn study(name: String, chapters: Vec<LichessNextPuzzle>) -> Self {
+        Self {
+            count: chapters.len(),
+            study: Some(chapters),
+            ..Self::new(0, Some(name))
+        }
+    }
+
+    /// Only lichess puzzles of `phase`, if given
+    pub fn of_phase(mut self, phase: Option<Phase>) -> Self {
+        self.phase = phase;
+        self
+    }
+
+    pub fn is_sprint(&self) -> bool {
+        self.limit.is_some()
+    }
+
+    /// "Set", "Sprint", "Drill" or "Study"
+    pub fn label(&self) -> &'static str {
+        match (self.is_sprint(), self.drill, &self.study) {
+            (true, _, _) => "Sprint",
+            (false, Some(_), _) => "Drill",
+            (false, None, Some(_)) => "Study",
+            (false, None, None) => "Set",
+        }
+    }
+
+    /// A new puzzle of the set is on the board
+    pub fn begin(&mut self, rating: u16) {
+        self.current = Some((rating, session_log::now()));
+    }
+
+    /// Score the current puzzle, later calls for the same puzzle are ignored.
+    /// `hint_cost` is the penalty of the hints taken, None without hints.
+    /// True if this finished the set.
+    pub fn record(&mut self, solved: bool, hint_cost: Option<f64>) -> bool {
+        if self.expire() {
+            return true;
+        }
+        let Some((rating, shown)) = self.current.take() else {
+            return false;
+        };
+        self.results.push(PuzzleResult {
+            rating,
+            solved,
+            score: if solved {
+                (1.0 - hint_cost.unwrap_or(0.0)).max(0.0)
+            } else {
+                0.0
+            },
+            hinted: hint_cost.is_some(),
+            time: session_log::elapsed(shown),
+        });
+        if self.is_finished() {
+            self.finished_after = Some(session_log::elapsed(self.started));
+        }
+        self.is_finished()
+    }
+
+    /// End a sprint whose time is up, true the first time it happens.
+    /// The puzzle on the board at that moment is not scored.
+    pub fn expire(&mut self) -> bool {
+        match self.limit {
+            Some(limit)
+                if self.finished_after.is_none() && session_log::elapsed(self.started) >= limit =>
+            {
+                self.finished_after = Some(limit);
+                self.current = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished_after.is_some() || self.results.len() >= self.count
+    }
+
+    pub fn solved(&self) -> usize {
+        self.results.iter().filter(|r| r.solved).count()
+    }
+
+    pub fn score(&self) -> f64 {
+        self.results.iter().map(|r| r.score).sum()
+    }
+
+    pub fn hinted(&self) -> usize {
+        self.results.iter().filter(|r| r.hinted).count()
+    }
+
+    /// Time spent on the whole set, frozen once it is finished
+    pub fn elapsed(&self) -> Duration {
+        self.finished_after
+            .unwrap_or_else(|| session_log::elapsed(self.started))
+    }
+
+    /// Time left in a sprint
+    pub fn remaining(&self) -> Option<Duration> {
+        self.limit.map(|limit| limit.saturating_sub(self.elapsed()))
+    }
+
+    pub fn rating_range(&self) -> Option<(u16, u16)> {
+        if self.study.is_some() {
+            return None;
+        }
+        let ratings = self.results.iter().map(|r| r.rating);
+        Some((ratings.clone().min()?, ratings.max()?))
+    }
+
+    /// "Puzzle 3 of 20, 2 solved", or "Puzzle 3, 2 solved" in a sprint
+    pub fn progress(&self) -> String {
+        let number = (self.results.len() + 1).min(self.count);
+        if self.is_sprint() {
+            format!("Puzzle {number}, {} solved", self.solved())
+        } else {
+            format!(
+                "Puzzle {number} of {}, {} solved",
+                self.count,
+                self.solved()
+            )
+        }
+    }
+
+    pub fn record_for_stats(&self) -> stats::SessionRecord {
+        let range = self.rating_range();
+        stats::SessionRecord {
+            kind: self.label().to_lowercase(),
+            theme: self
+                .theme
+                .clone()
+                .or(self.phase.map(|phase| phase.name().to_string())),
+            finished_at: stats::now(),
+            puzzles: self.results.len(),
+            solved: self.solved(),
+            seconds: self.elapsed().as_secs_f64(),
+            rating_min: range.map(|(min, _)| min),
+            rating_max: range.map(|(_, max)| max),
+            puzzle_seconds: self.results.iter().map(|r| r.time.as_secs_f64()).collect(),
+            score: self.score(),
+            hinted: self.hinted(),
+        }
+    }
+}
@@ -0,0 +1,249 @@
+use serde::{Deserialize, Serialize};
+use shakmaty::fen::Epd;
+use shakmaty::san::{San, SanPlus};
+use shakmaty::{Chess, Color, EnPassantMode, Move, Position};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::eval_quiz::random;
+use crate::{pgn_tree, session, session_log};
+
+// ----------------------------------------------
+// Repertoire training: the moves of a PGN file with variations, all games
+// of it together, as my preparation for one color. A line is played from
+// the start: the opponent's moves from the file, mine typed as prepared.
+// How each of my positions went is kept in
+// $XDG_STATE_HOME/tess/repertoire.json, the shakiest lines come up first
+// ----------------------------------------------
+
+// Deviations listed in the pane
+const DEVIATIONS_SHOWN: usize = 3;
+
+/// How often a position of mine was answered as prepared, and not
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+struct Record {
+    right: u32,
+    wrong: u32,
+}
+
+impl Record {
+    // lower for the positions that need it more
+    fn practice(&self) -> i64 {
+        i64::from(self.right) - 2 * i64::from(self.wrong)
+    }
+}
+
+pub enum Answer {
+    Prepared,
+    /// not in the repertoire, with the moves that are
+    Deviation(Vec<San>),
+}
+
+pub struct Repertoire {
+    pub name: String,
+    pub color: Color,
+    starts: Vec<Chess>,
+    // the moves of the file in every position of it, by EPD, in the file's order
+    moves: HashMap<String, Vec<Move>>,
+    records: HashMap<String, Record>,
+    seed: u64,
+    // this session
+    right: usize,
+    wrong: usize,
+    lines: usize,
+    // "1. e4 c5 2. Nc3 (prepared Nf3)"
+    deviations: Vec<String>,
+}
+
+fn key(pos: &Chess) -> String {
+    Epd::from_position(pos.clone(), EnPassantMode::Legal).to_string()
+}
+
+fn records_path() -> Option<PathBuf> {
+    session::state_dir().map(|dir| dir.join("repertoire.json"))
+}
+
+fn load_records() -> HashMap<String, Record> {
+    records_path()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+// "1. e4 c5 2. Nf3", from "1..." when black starts
+fn numbered(start: &Chess, moves: &[Move]) -> String {
+    let mut pos = start.clone();
+    let mut words = Vec::new();
+    for mv in moves {
+        match pos.turn() {
+            Color::White => words.push(format!("{}.", pos.fullmoves())),
+            Color::Black if words.is_empty() => words.push(format!("{}...", pos.fullmoves())),
+            Color::Black => {}
+        }
+        words.push(SanPlus::from_move_and_play_unchecked(&mut pos, mv).to_string());
+    }
+    words.join(" ")
+}
+
+// "1 line", "3 lines"
+fn count(n: usize, thing: &str) -> String {
+    format!("{n} {thing}{}", if n == 1 { "" } else { "s" })
+}
+
+impl Repertoire {
+    /// The repertoire for `color` in the PGN file at `path`
+    pub fn load(path: &Path, color: Color) -> anyhow::Result<Self> {
+        let games = pgn_tree::load_all(path)?;
+        let mut starts: Vec<Chess> = Vec::new();
+        let mut moves: HashMap<String, Vec<Move>> = HashMap::new();
+        for game in &games {
+            if !starts.iter().any(|start| key(start) == key(&game.start)) {
+                starts.push(game.start.clone());
+            }
+            game.walk(&mut |pos, mv| {
+                let known = moves.entry(key(pos)).or_default();
+                if !known.contains(mv) {
+                    known.push(mv.clone());
+                }
+            });
+        }
+        let mut mine = moves
+            .keys()
+            .filter(|epd| epd.split(' ').nth(1) == Some(color.fold_wb("w", "b")));
+        if mine.next().is_none() {
+            anyhow::bail!("{} has no moves for {color} to prepare", path.display());
+        }
+        let nanos = session_log::system_time()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.subsec_nanos());
+        Ok(Self {
+            name: path
+                .file_stem()
+                .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned()),
+            color,
+            starts,
+            moves,
+            records: load_records(),
+            seed: u64::from(nanos) | 1,
+            right: 0,
+            wrong: 0,
+            lines: 0,
+            deviations: Vec::new(),
+        })
+    }
+
+    fn known(&self, pos: &Chess) -> &[Move] {
+        self.moves.get(&key(pos)).map_or(&[], Vec::as_slice)
+    }
+
+    /// Where the next line starts
+    pub fn start(&mut self) -> Chess {
+        self.lines += 1;
+        let index = random(&mut self.seed, self.starts.len());
+        self.starts[index].clone()
+    }
+
+    /// Whether the line is over: no move prepared for the side to move
+    pub fn line_ends(&self, pos: &Chess) -> bool {
+        self.known(pos).is_empty()
+    }
+
+    /// The opponent's move in `pos`: of those in the file, the one leading
+    /// to my least practised position, a random one among equals
+    pub fn reply(&mut self, pos: &Chess) -> Option<Move> {
+        let practice = |mv: &Move| {
+            let mut after = pos.clone();
+            after.play_unchecked(mv);
+            self.records
+                .get(&key(&after))
+                .copied()
+                .unwrap_or_default()
+                .practice()
+        };
+        let least = self.known(pos).iter().map(practice).min()?;
+        let candidates: Vec<Move> = self
+            .known(pos)
+            .iter()
+            .filter(|mv| practice(mv) == least)
+            .cloned()
+            .collect();
+        let index = random(&mut self.seed, candidates.len());
+        Some(candidates[index].clone())
+    }
+
+    /// Judge my move `mv` after `history` from `start`, and keep how it went
+    pub fn answer(&mut self, start: &Chess, history: &[Move], mv: &Move) -> anyhow::Result<Answer> {
+        let mut pos = start.clone();
+        for played in history {
+            pos.play_unchecked(played);
+        }
+        let pos = &pos;
+        let known = self.known(pos);
+        let answer = if known.contains(mv) {
+            Answer::Prepared
+        } else {
+            Answer::Deviation(known.iter().map(|mv| San::from_move(pos, mv)).collect())
+        };
+        let record = self.records.entry(key(pos)).or_default();
+        match &answer {
+            Answer::Prepared => {
+                record.right += 1;
+                self.right += 1;
+            }
+            Answer::Deviation(prepared) => {
+                record.wrong += 1;
+                self.wrong += 1;
+                let prepared: Vec<String> = prepared.iter().map(San::to_string).collect();
+                let mut line = history.to_vec();
+                line.push(mv.clone());
+                self.deviations.push(format!(
+                    "{} (prepared {})",
+                    numbered(start, &line),
+                    prepared.join(" or ")
+                ));
+            }
+        }
+        self.save()?;
+        Ok(answer)
+    }
+
+    fn save(&self) -> anyhow::Result<()> {
+        let Some(path) = records_path() else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_vec(&self.records)?)?;
+        Ok(())
+    }
+
+    /// The repertoire pane, one line each
+    pub fn lines(&self) -> Vec<String> {
+        let mine = self
+            .moves
+            .keys()
+            .filter(|epd| epd.split(' ').nth(1) == Some(self.color.fold_wb("w", "b")));
+        let (mut positions, mut shaky) = (0, 0);
+        for epd in mine {
+            positions += 1;
+            let record = self.records.get(epd).copied().unwrap_or_default();
+            shaky += usize::from(record.wrong > record.right);
+        }
+        let mut lines = vec![
+            format!(
+                "{}, {}: {positions} positions to know, {shaky} shaky",
+                self.name, self.color
+            ),
+            format!(
+                "This session: {}, {} as prepared, {}",
+                count(self.lines, "line"),
+                count(self.right, "move"),
+                count(self.wrong, "deviation")
+            ),
+        ];
+        let shown = self.deviations.len().saturating_sub(DEVIATIONS_SHOWN);
+        lines.extend(self.deviations[shown..].iter().rev().cloned());
+        lines
+    }
+}
@@ -0,0 +1,81 @@
+use crossterm::event::KeyEvent;
+use std::time::{Duration, Instant};
+
+use crate::session_log;
+
+// ----------------------------------------------
+// Puzzle attempts key by key: the keys typed while solving, with their
+// timing, played back afterwards on the puzzle's start position
+// ----------------------------------------------
+
+/// The keys of one attempt, each with the time since the puzzle appeared
+pub struct Recording {
+    started: Instant,
+    keys: Vec<(Duration, KeyEvent)>,
+}
+
+impl Default for Recording {
+    fn default() -> Self {
+        Self {
+            started: session_log::now(),
+            keys: Vec::new(),
+        }
+    }
+}
+
+impl Recording {
+    pub fn record(&mut self, key: KeyEvent) {
+        self.keys.push((session_log::elapsed(self.started), key));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// From the puzzle appearing to the last key
+    pub fn length(&self) -> Duration {
+        self.keys.last().map_or(Duration::ZERO, |(at, _)| *at)
+    }
+}
+
+/// A recording being played back in its own time
+pub struct Replay {
+    recording: Recording,
+    started: Instant,
+    next: usize,
+}
+
+impl Replay {
+    pub fn new(recording: Recording) -> Self {
+        Self {
+            recording,
+            started: session_log::now(),
+            next: 0,
+        }
+    }
+
+    /// The keys that are due by now, or all that are left when skipping
+    pub fn due(&mut self, skip: bool) -> Vec<KeyEvent> {
+        let elapsed = session_log::elapsed(self.started);
+        let keys: Vec<KeyEvent> = self.recording.keys[self.next..]
+            .iter()
+            .take_while(|(at, _)| skip || *at <= elapsed)
+            .map(|(_, key)| *key)
+            .collect();
+        self.next += keys.len();
+        keys
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.next >= self.recording.keys.len()
+    }
+
+    /// The recording back, to be replayed again
+    pub fn into_recording(self) -> Recording {
+        self.recording
+    }
+}
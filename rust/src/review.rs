@@ -0,0 +1,427 @@
+use serde::{Deserialize, Serialize};
+use shakmaty::{san::SanPlus, ByColor, Chess, Move, Position};
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::engine::{Engine, Evaluation, Score};
+use crate::session;
+
+// ----------------------------------------------
+// Game review: every position of a game evaluated by a UCI engine,
+// the positions shared out among several engine processes
+// ----------------------------------------------
+
+pub struct Review {
+    /// the start position and the position after every move
+    pub positions: Vec<Chess>,
+    pub moves: Vec<Move>,
+    /// by position, None where the game is over
+    pub evaluations: Vec<Option<Evaluation>>,
+    /// engine processes that took part
+    pub engines: usize,
+}
+
+/// Evaluate the start and every position after `moves` with up to `jobs` engines,
+/// each set up with `options`, telling `progress` the positions done of all
+pub fn analyse(
+    start: &Chess,
+    moves: &[Move],
+    engine: &Path,
+    options: &[(String, String)],
+    movetime: Duration,
+    jobs: usize,
+    progress: &(dyn Fn(usize, usize) + Sync),
+) -> anyhow::Result<Review> {
+    let mut positions = vec![start.clone()];
+    for mv in moves {
+        let mut pos = positions[positions.len() - 1].clone();
+        pos.play_unchecked(mv);
+        positions.push(pos);
+    }
+    let jobs = jobs.clamp(1, positions.len());
+    // all engines start before the work does, so a bad path fails at once
+    let engines = (0..jobs)
+        .map(|_| Engine::start(engine)?.with_options(options))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let next = AtomicUsize::new(0);
+    let done = AtomicUsize::new(0);
+    let evaluations = Mutex::new(vec![None; positions.len()]);
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = engines
+            .into_iter()
+            .map(|mut engine| {
+                let (positions, next, done, evaluations) = (&positions, &next, &done, &evaluations);
+                scope.spawn(move || -> anyhow::Result<()> {
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(pos) = positions.get(index) else {
+                            return Ok(());
+                        };
+                        if pos.outcome().is_none() {
+                            let evaluation = engine.evaluate(pos, movetime)?;
+                            evaluations.lock().unwrap()[index] = evaluation;
+                        }
+                        done.fetch_add(1, Ordering::Relaxed);
+                    }
+                })
+            })
+            .collect();
+        let mut reported = None;
+        loop {
+            let finished = workers.iter().all(|worker| worker.is_finished());
+            let count = done.load(Ordering::Relaxed);
+            if reported != Some(count) {
+                progress(count, positions.len());
+                reported = Some(count);
+            }
+            if finished {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        workers
+            .into_iter()
+            .try_for_each(|worker| worker.join().expect("analysis thread panicked"))
+    })?;
+    Ok(Review {
+        positions,
+        moves: moves.to_vec(),
+        evaluations: evaluations.into_inner().unwrap(),
+        engines: jobs,
+    })
+}
+
+/// "[##########          ] 31/62 positions", redrawn in place when stderr is a terminal
+pub fn show_progress(done: usize, total: usize) {
+    const WIDTH: usize = 30;
+    let mut stderr = std::io::stderr();
+    if !stderr.is_terminal() {
+        return;
+    }
+    let filled = WIDTH * done / total.max(1);
+    let _ = write!(
+        stderr,
+        "\r[{}{}] {done}/{total} positions",
+        "#".repeat(filled),
+        " ".repeat(WIDTH - filled)
+    );
+    if done == total {
+        let _ = writeln!(stderr);
+    }
+    let _ = stderr.flush();
+}
+
+/// A review on threads of its own, so the board stays usable meanwhile
+pub struct Background {
+    done: Arc<AtomicUsize>,
+    // positions done when last asked
+    shown: usize,
+    pub total: usize,
+    result: Receiver<anyhow::Result<Review>>,
+}
+
+impl Background {
+    pub fn start(
+        start: Chess,
+        moves: Vec<Move>,
+        engine: PathBuf,
+        options: Vec<(String, String)>,
+        movetime: Duration,
+        jobs: usize,
+    ) -> Self {
+        let done = Arc::new(AtomicUsize::new(0));
+        let (sender, result) = mpsc::channel();
+        let counter = Arc::clone(&done);
+        let total = moves.len() + 1;
+        std::thread::spawn(move || {
+            let progress = |count, _| counter.store(count, Ordering::Relaxed);
+            let review = analyse(&start, &moves, &engine, &options, movetime, jobs, &progress);
+            let _ = sender.send(review);
+        });
+        Self {
+            done,
+            shown: 0,
+            total,
+            result,
+        }
+    }
+
+    /// Positions evaluated so far
+    pub fn done(&self) -> usize {
+        self.done.load(Ordering::Relaxed)
+    }
+
+    /// True if more positions are done than when last asked
+    pub fn advanced(&mut self) -> bool {
+        let done = self.done();
+        let advanced = done != self.shown;
+        self.shown = done;
+        advanced
+    }
+
+    /// The review once it is over, None while it runs
+    pub fn finished(&self) -> Option<anyhow::Result<Review>> {
+        match self.result.try_recv() {
+            Ok(review) => Some(review),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(anyhow::anyhow!("the review stopped"))),
+        }
+    }
+}
+
+impl Review {
+    /// One line per move: its SAN and the evaluation after it, from white's side
+    pub fn report(&self) -> String {
+        let judgements = self.judgements();
+        let mut out = String::new();
+        if let Some(evaluation) = &self.evaluations[0] {
+            out.push_str(&format!(
+                "start      {}\n",
+                evaluation.score.display(self.positions[0].turn())
+            ));
+        }
+        for (ply, mv) in self.moves.iter().enumerate() {
+            let before = &self.positions[ply];
+            let after = &self.positions[ply + 1];
+            let number = match before.turn() {
+                shakmaty::Color::White => format!("{}.", before.fullmoves()),
+                shakmaty::Color::Black => format!("{}...", before.fullmoves()),
+            };
+            let san = SanPlus::from_move(before.clone(), mv).to_string();
+            let san = match judgements[ply] {
+                Some(judgement) => format!("{san}{}", judgement.symbol()),
+                None => san,
+            };
+            let score = match &self.evaluations[ply + 1] {
+                Some(evaluation) => evaluation.score.display(after.turn()),
+                None if after.is_checkmate() => "mate".to_string(),
+                None if after.outcome().is_some() => "draw".to_string(),
+                None => "?".to_string(),
+            };
+            out.push_str(&format!("{number:<6} {san:<8} {score}\n"));
+        }
+        out
+    }
+}
+
+// ----------------------------------------------
+// Accuracy and average centipawn loss, as lichess computes them:
+// winning chances from the evaluations, move accuracy from the chances
+// lost, and the game accuracy as the mean of a volatility-weighted mean
+// and the harmonic mean of the move accuracies
+// ----------------------------------------------
+
+/// Evaluations beyond this count as won, mates included
+pub const CP_CEILING: i32 = 1000;
+
+/// How well one side played
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Scores {
+    /// 0 to 100
+    pub accuracy: f64,
+    /// average centipawn loss per move
+    pub acpl: f64,
+}
+
+/// A reviewed game, one JSON line in $XDG_STATE_HOME/tess/reviews.jsonl
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReviewRecord {
+    /// seconds since the Unix epoch
+    pub analysed_at: u64,
+    pub file: String,
+    /// the game's number in the file, from 1
+    pub game: usize,
+    pub white: String,
+    pub black: String,
+    pub white_scores: Option<Scores>,
+    pub black_scores: Option<Scores>,
+}
+
+/// 0 to 100 for the side the centipawns are from
+pub fn winning_chances(cp: i32) -> f64 {
+    50.0 + 50.0 * (2.0 / (1.0 + (-0.00368208 * f64::from(cp)).exp()) - 1.0)
+}
+
+fn move_accuracy(win_before: f64, win_after: f64) -> f64 {
+    let lost = (win_before - win_after).max(0.0);
+    (103.1668 * (-0.04354 * lost).exp() - 3.1669 + 1.0).clamp(0.0, 100.0)
+}
+
+fn standard_deviation(values: &[f64]) -> f64 {
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / values.len() as f64;
+    variance.sqrt()
+}
+
+impl Review {
+    /// The evaluation of a position in centipawns from white's side, capped; a game
+    /// that is over counts as won or drawn
+    pub fn white_cp(&self, index: usize) -> Option<i32> {
+        let pos = &self.positions[index];
+        let sign = pos.turn().fold_wb(1, -1);
+        let cp = match &self.evaluations[index] {
+            Some(evaluation) => match evaluation.score {
+                Score::Centipawns(cp) => cp,
+                Score::Mate(moves) if moves > 0 => CP_CEILING,
+                Score::Mate(_) => -CP_CEILING,
+            },
+            None if pos.is_checkmate() => -CP_CEILING,
+            None if pos.outcome().is_some() => 0,
+            None => return None,
+        };
+        Some((cp * sign).clamp(-CP_CEILING, CP_CEILING))
+    }
+
+    /// Accuracy and centipawn loss of each side, None for a side without an evaluated move
+    pub fn scores(&self) -> ByColor<Option<Scores>> {
+        let cps: Vec<Option<i32>> = (0..self.positions.len())
+            .map(|index| self.white_cp(index))
+            .collect();
+        // positions without an evaluation take the one before, for the weights only
+        let mut last = 0;
+        let chances: Vec<f64> = cps
+            .iter()
+            .map(|cp| {
+                last = cp.unwrap_or(last);
+                winning_chances(last)
+            })
+            .collect();
+        let window = (self.moves.len() / 10).clamp(2, 8).min(chances.len());
+        let mut windows: Vec<&[f64]> = vec![&chances[..window]; window.saturating_sub(2)];
+        windows.extend(chances.windows(window));
+        let weights: Vec<f64> = windows
+            .iter()
+            .map(|values| standard_deviation(values).clamp(0.5, 12.0))
+            .collect();
+
+        // per side: (accuracy, weight, centipawns lost) of every evaluated move
+        let mut moves: ByColor<Vec<(f64, f64, f64)>> = ByColor::default();
+        for ply in 0..self.moves.len() {
+            let (Some(before), Some(after)) = (cps[ply], cps[ply + 1]) else {
+                continue;
+            };
+            let mover = self.positions[ply].turn();
+            let sign = mover.fold_wb(1, -1);
+            let (before, after) = (before * sign, after * sign);
+            let accuracy = move_accuracy(winning_chances(before), winning_chances(after));
+            let weight = weights.get(ply).copied().unwrap_or(0.5);
+            let lost = f64::from((before - after).max(0));
+            moves.get_mut(mover).push((accuracy, weight, lost));
+        }
+        moves.map(|moves| {
+            if moves.is_empty() {
+                return None;
+            }
+            let weighted = moves.iter().map(|(a, w, _)| a * w).sum::<f64>()
+                / moves.iter().map(|(_, w, _)| w).sum::<f64>();
+            let harmonic =
+                moves.len() as f64 / moves.iter().map(|(a, _, _)| 1.0 / a.max(1.0)).sum::<f64>();
+            Some(Scores {
+                accuracy: (weighted + harmonic) / 2.0,
+                acpl: moves.iter().map(|(_, _, lost)| lost).sum::<f64>() / moves.len() as f64,
+            })
+        })
+    }
+}
+
+// ----------------------------------------------
+// Inaccuracies, mistakes and blunders, by the winning chances a move
+// gives away, with lichess's thresholds
+// ----------------------------------------------
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Judgement {
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+impl Judgement {
+    /// "?!", "?" or "??", as annotated after the move
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Judgement::Inaccuracy => "?!",
+            Judgement::Mistake => "?",
+            Judgement::Blunder => "??",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Judgement::Inaccuracy => "inaccuracy",
+            Judgement::Mistake => "mistake",
+            Judgement::Blunder => "blunder",
+        }
+    }
+
+    /// "1 blunder", "2 inaccuracies"
+    pub fn count(self, n: usize) -> String {
+        let (one, many) = match self {
+            Judgement::Inaccuracy => ("inaccuracy", "inaccuracies"),
+            Judgement::Mistake => ("mistake", "mistakes"),
+            Judgement::Blunder => ("blunder", "blunders"),
+        };
+        format!("{n} {}", if n == 1 { one } else { many })
+    }
+}
+
+/// The judgement of a move by `mover` from the evaluations before and after it,
+/// centipawns from white's side; None for a sound move
+pub fn judge(before: i32, after: i32, mover: shakmaty::Color) -> Option<Judgement> {
+    let sign = mover.fold_wb(1, -1);
+    let lost = winning_chances(before * sign) - winning_chances(after * sign);
+    match lost {
+        lost if lost >= 15.0 => Some(Judgement::Blunder),
+        lost if lost >= 10.0 => Some(Judgement::Mistake),
+        lost if lost >= 5.0 => Some(Judgement::Inaccuracy),
+        _ => None,
+    }
+}
+
+impl Review {
+    /// The judgement of every move, None for a sound one or one without evaluations
+    pub fn judgements(&self) -> Vec<Option<Judgement>> {
+        (0..self.moves.len())
+            .map(|ply| {
+                let (before, after) = (self.white_cp(ply)?, self.white_cp(ply + 1)?);
+                judge(before, after, self.positions[ply].turn())
+            })
+            .collect()
+    }
+
+    /// How often each side made each kind of error, in the order of `Judgement`
+    pub fn error_counts(&self) -> ByColor<[usize; 3]> {
+        let mut counts: ByColor<[usize; 3]> = ByColor::default();
+        for (ply, judgement) in self.judgements().into_iter().enumerate() {
+            if let Some(judgement) = judgement {
+                counts.get_mut(self.positions[ply].turn())[judgement as usize] += 1;
+            }
+        }
+        counts
+    }
+}
+
+fn reviews_path() -> Option<PathBuf> {
+    session::state_dir().map(|dir| dir.join("reviews.jsonl"))
+}
+
+/// Keep the review's result, so the scores can be followed over time
+pub fn append(record: &ReviewRecord) -> anyhow::Result<()> {
+    let Some(path) = reviews_path() else {
+        return Ok(());
+    };
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    writeln!(file, "{}", serde_json::to_string(record)?)?;
+    Ok(())
+}
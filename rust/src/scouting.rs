@@ -0,0 +1,277 @@
+use shakmaty::san::SanPlus;
+use shakmaty::{ByColor, Chess, Color, Move, Position};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::phase::Phase;
+use crate::review::{self, Judgement};
+use crate::{LastPosition, PgnGame};
+
+// ----------------------------------------------
+// Scouting an opponent, an engine persona or a lichess user: from PGN
+// files of games against them, the openings they choose, the time they
+// take per move and the errors they make, as a briefing before the
+// next game
+// ----------------------------------------------
+
+// Plies naming an opening that has no Opening tag
+const OPENING_PLIES: usize = 6;
+// Openings listed for each color
+const OPENINGS_SHOWN: usize = 3;
+// A move played with less than that left on the clock is played in time trouble
+const TIME_TROUBLE: Duration = Duration::from_secs(30);
+
+/// How an engine reviews the games without evaluations of their own
+pub struct Analysis<'a> {
+    pub engine: &'a Path,
+    pub options: &'a [(String, String)],
+    pub movetime: Duration,
+    pub jobs: usize,
+}
+
+#[derive(Default)]
+pub struct Report {
+    pub name: String,
+    pub games: usize,
+    // the opponent's wins, draws and losses
+    results: [usize; 3],
+    // by the opponent's color, the openings with how often they came up
+    openings: ByColor<Vec<(String, usize)>>,
+    // time taken by the opponent's timed moves, and how many there were
+    thought: Duration,
+    timed: usize,
+    // games with evaluations, and the opponent's moves in them judged
+    analysed: usize,
+    judged: usize,
+    // errors by phase and judgement, and those made in time trouble
+    errors: [[usize; 3]; 3],
+    in_time_trouble: usize,
+}
+
+/// The games of `name` in the PGN files, as white or as black; without
+/// [%eval] comments they are reviewed with `analysis` if given. `progress` is
+/// told of every review.
+pub fn scout(
+    name: &str,
+    paths: &[PathBuf],
+    analysis: Option<&Analysis>,
+    progress: &(dyn Fn(usize, usize) + Sync),
+) -> anyhow::Result<Report> {
+    let mut report = Report {
+        name: name.to_string(),
+        ..Report::default()
+    };
+    for path in paths {
+        let text = std::fs::read_to_string(path)
+            .map_err(|err| anyhow::anyhow!("{}: {err}", path.display()))?;
+        let mut reader = pgn_reader::BufferedReader::new(text.as_bytes());
+        while let Some(game) = reader.read_game(&mut LastPosition::new(None))? {
+            let color = if game.white.eq_ignore_ascii_case(name) {
+                Color::White
+            } else if game.black.eq_ignore_ascii_case(name) {
+                Color::Black
+            } else {
+                continue;
+            };
+            report.games += 1;
+            let result = match (game.result.as_str(), color) {
+                ("1-0", Color::White) | ("0-1", Color::Black) => Some(0),
+                ("1/2-1/2", _) => Some(1),
+                ("1-0", Color::Black) | ("0-1", Color::White) => Some(2),
+                _ => None,
+            };
+            if let Some(result) = result {
+                report.results[result] += 1;
+            }
+            let opening = if game.opening.is_empty() {
+                first_moves(&game.start, &game.moves)
+            } else {
+                game.opening.clone()
+            };
+            let openings = report.openings.get_mut(color);
+            match openings.iter_mut().find(|(known, _)| *known == opening) {
+                Some((_, count)) => *count += 1,
+                None => openings.push((opening, 1)),
+            }
+
+            let cps = evaluations(&game, analysis, progress)?;
+            report.analysed += usize::from(cps.is_some());
+
+            let mut pos = game.start.clone();
+            for (ply, mv) in game.moves.iter().enumerate() {
+                if pos.turn() == color {
+                    if let Some(time) = game.times.get(ply).copied().flatten() {
+                        report.thought += time;
+                        report.timed += 1;
+                    }
+                    let cp = |index: usize| cps.as_ref()?.get(index).copied().flatten();
+                    if let (Some(before), Some(after)) = (cp(ply), cp(ply + 1)) {
+                        report.judged += 1;
+                        if let Some(judgement) = review::judge(before, after, color) {
+                            report.errors[Phase::of(&pos) as usize][judgement as usize] += 1;
+                            let left = game.clocks.get(ply).copied().flatten();
+                            if left.is_some_and(|left| left < TIME_TROUBLE) {
+                                report.in_time_trouble += 1;
+                            }
+                        }
+                    }
+                }
+                pos.play_unchecked(mv);
+            }
+        }
+    }
+    for openings in report.openings.iter_mut() {
+        openings.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    }
+    Ok(report)
+}
+
+/// Centipawns from white's side of the start and every position after it:
+/// from the game's [%eval] comments, else from a review with `analysis`;
+/// None with neither
+pub fn evaluations(
+    game: &PgnGame,
+    analysis: Option<&Analysis>,
+    progress: &(dyn Fn(usize, usize) + Sync),
+) -> anyhow::Result<Option<Vec<Option<i32>>>> {
+    if game.evals.iter().any(Option::is_some) {
+        return Ok(Some(
+            std::iter::once(None)
+                .chain(game.evals.iter().copied())
+                .collect(),
+        ));
+    }
+    let Some(analysis) = analysis else {
+        return Ok(None);
+    };
+    let review = review::analyse(
+        &game.start,
+        &game.moves,
+        analysis.engine,
+        analysis.options,
+        analysis.movetime,
+        analysis.jobs,
+        progress,
+    )?;
+    Ok(Some(
+        (0..review.positions.len())
+            .map(|i| review.white_cp(i))
+            .collect(),
+    ))
+}
+
+// "1. e4 c5 2. Nf3 d6 3. d4 cxd4"
+fn first_moves(start: &Chess, moves: &[Move]) -> String {
+    let mut pos = start.clone();
+    let mut words = Vec::new();
+    for mv in moves.iter().take(OPENING_PLIES) {
+        match pos.turn() {
+            Color::White => words.push(format!("{}.", pos.fullmoves())),
+            Color::Black if words.is_empty() => words.push(format!("{}...", pos.fullmoves())),
+            Color::Black => {}
+        }
+        words.push(SanPlus::from_move_and_play_unchecked(&mut pos, mv).to_string());
+    }
+    if words.is_empty() {
+        "no moves".to_string()
+    } else {
+        words.join(" ")
+    }
+}
+
+impl Report {
+    /// The briefing, one line each
+    pub fn lines(&self) -> Vec<String> {
+        if self.games == 0 {
+            return vec![format!("No games of {} found.", self.name)];
+        }
+        let [wins, draws, losses] = self.results;
+        let mut lines = vec![format!(
+            "{}: {} games, {wins} won, {draws} drawn, {losses} lost",
+            self.name, self.games
+        )];
+        for color in [Color::White, Color::Black] {
+            let openings = self.openings.get(color);
+            if openings.is_empty() {
+                continue;
+            }
+            let shown: Vec<String> = openings
+                .iter()
+                .take(OPENINGS_SHOWN)
+                .map(|(opening, count)| format!("{opening} ({count})"))
+                .collect();
+            lines.push(format!(
+                "With {}: {}",
+                color.fold_wb("white", "black"),
+                shown.join(", ")
+            ));
+        }
+        lines.push(match self.timed {
+            0 => "Time per move: the games have no clock times.".to_string(),
+            timed => format!(
+                "Time per move: {:.1}s on average over {timed} moves",
+                self.thought.as_secs_f64() / timed as f64
+            ),
+        });
+        if self.analysed == 0 {
+            lines.push(
+                "Errors: no game has evaluations, analyse them on lichess or give --engine."
+                    .to_string(),
+            );
+            return lines;
+        }
+        let by_judgement: Vec<usize> = (0..3)
+            .map(|judgement| self.errors.iter().map(|phase| phase[judgement]).sum())
+            .collect();
+        let total: usize = by_judgement.iter().sum();
+        let counts: Vec<String> = [
+            Judgement::Blunder,
+            Judgement::Mistake,
+            Judgement::Inaccuracy,
+        ]
+        .into_iter()
+        .map(|judgement| judgement.count(by_judgement[judgement as usize]))
+        .collect();
+        lines.push(format!(
+            "Errors in {} analysed game{}: {} in {} moves",
+            self.analysed,
+            if self.analysed == 1 { "" } else { "s" },
+            counts.join(", "),
+            self.judged
+        ));
+        if total == 0 {
+            return lines;
+        }
+        let phases: Vec<String> = Phase::ALL
+            .into_iter()
+            .map(|phase| {
+                let errors: usize = self.errors[phase as usize].iter().sum();
+                format!("{} {errors}", phase.name())
+            })
+            .collect();
+        lines.push(format!("  by phase: {}", phases.join(", ")));
+        let worst = Phase::ALL
+            .into_iter()
+            .max_by_key(|phase| {
+                let errors = &self.errors[*phase as usize];
+                // blunders weigh most, as they decide games
+                errors[Judgement::Blunder as usize] * 4
+                    + errors[Judgement::Mistake as usize] * 2
+                    + errors[Judgement::Inaccuracy as usize]
+            })
+            .unwrap_or(Phase::Middlegame);
+        lines.push(format!(
+            "  one every {:.0} moves, the costliest in the {}",
+            self.judged as f64 / total as f64,
+            worst.name()
+        ));
+        if self.in_time_trouble > 0 {
+            lines.push(format!(
+                "  {} of them with under {}s on the clock",
+                self.in_time_trouble,
+                TIME_TROUBLE.as_secs()
+            ));
+        }
+        lines
+    }
+}
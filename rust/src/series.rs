@@ -0,0 +1,310 @@
+use shakmaty::{Chess, Color, Move, Outcome};
+
+use crate::export;
+
+// ----------------------------------------------
+// A series of rematches between the same two players, who swap
+// colors after every game. The player at the bottom of the board
+// keeps that place, the board flips with the colors.
+// A match is a series with a set length, optionally played from a
+// list of openings. Against a single engine the match can be adaptive,
+// the engine's Elo going up after each of my wins and down after each
+// loss.
+// ----------------------------------------------
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MatchLength {
+    Games(usize),
+    /// the first player to reach this many points wins
+    FirstTo(f64),
+}
+
+#[derive(Clone, Debug)]
+struct PlayedGame {
+    start: Chess,
+    moves: Vec<Move>,
+    outcome: Outcome,
+    bottom: Color,
+}
+
+/// How far an adaptive match moves the engine's Elo after a decisive game
+const ELO_STEP: u32 = 100;
+/// The UCI_Elo range of Stockfish, which most engines follow
+const ELO_RANGE: (u32, u32) = (1320, 3190);
+
+/// One player's line of the results table
+#[derive(Clone, Debug)]
+pub struct Standing {
+    pub name: String,
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+    pub points: f64,
+}
+
+#[derive(Clone, Debug)]
+pub struct Series {
+    // color of the bottom player in the current game
+    bottom: Color,
+    // points of the bottom and the top player
+    points: [f64; 2],
+    // names of the bottom and the top player
+    names: [String; 2],
+    pub length: Option<MatchLength>,
+    // start positions, each played once with either color
+    openings: Vec<Chess>,
+    played: Vec<PlayedGame>,
+    // the engine's Elo for the next game of an adaptive match
+    engine_elo: Option<u32>,
+}
+
+impl Series {
+    pub fn new(bottom: Color) -> Self {
+        Self {
+            bottom,
+            points: [0.0; 2],
+            names: ["Bottom player".to_string(), "Top player".to_string()],
+            length: None,
+            openings: Vec::new(),
+            played: Vec::new(),
+            engine_elo: None,
+        }
+    }
+
+    /// A match that ends after `length`
+    pub fn with_length(bottom: Color, length: MatchLength) -> Self {
+        Self {
+            length: Some(length),
+            ..Self::new(bottom)
+        }
+    }
+
+    /// Name the players for the PGN and the results table
+    pub fn with_names(mut self, bottom: String, top: String) -> Self {
+        self.names = [bottom, top];
+        self
+    }
+
+    /// Play the games from `openings` in turn, each twice with the colors swapped
+    pub fn with_openings(mut self, openings: Vec<Chess>) -> Self {
+        self.openings = openings;
+        self
+    }
+
+    /// Adapt the top player, an engine, to the bottom player's results,
+    /// starting from `elo`
+    pub fn with_adaptive(mut self, elo: u32) -> Self {
+        self.engine_elo = Some(elo.clamp(ELO_RANGE.0, ELO_RANGE.1));
+        self
+    }
+
+    /// The engine's Elo for the next game, None unless the match is adaptive
+    pub fn engine_elo(&self) -> Option<u32> {
+        self.engine_elo
+    }
+
+    /// The start position of the next game, None without openings
+    pub fn opening(&self) -> Option<Chess> {
+        if self.openings.is_empty() {
+            return None;
+        }
+        Some(self.openings[self.games() / 2 % self.openings.len()].clone())
+    }
+
+    /// True once a match has reached its length, never for open-ended series
+    pub fn is_over(&self) -> bool {
+        match self.length {
+            Some(MatchLength::Games(games)) => self.games() >= games,
+            Some(MatchLength::FirstTo(points)) => self.points.iter().any(|&p| p >= points),
+            None => false,
+        }
+    }
+
+    pub fn record(&mut self, start: &Chess, moves: &[Move], outcome: Outcome) {
+        self.played.push(PlayedGame {
+            start: start.clone(),
+            moves: moves.to_vec(),
+            outcome,
+            bottom: self.bottom,
+        });
+        match outcome {
+            Outcome::Decisive { winner } if winner == self.bottom => {
+                self.points[0] += 1.0;
+                self.engine_elo = self.engine_elo.map(|elo| (elo + ELO_STEP).min(ELO_RANGE.1));
+            }
+            Outcome::Decisive { .. } => {
+                self.points[1] += 1.0;
+                self.engine_elo = self
+                    .engine_elo
+                    .map(|elo| elo.saturating_sub(ELO_STEP).max(ELO_RANGE.0));
+            }
+            Outcome::Draw => {
+                self.points[0] += 0.5;
+                self.points[1] += 0.5;
+            }
+        }
+    }
+
+    pub fn games(&self) -> usize {
+        self.played.len()
+    }
+
+    pub fn swap_colors(&mut self) {
+        self.bottom = !self.bottom;
+    }
+
+    /// Wins, draws, losses and points of the bottom and the top player
+    pub fn standings(&self) -> [Standing; 2] {
+        let mut standings = self.names.clone().map(|name| Standing {
+            name,
+            wins: 0,
+            draws: 0,
+            losses: 0,
+            points: 0.0,
+        });
+        let [bottom, top] = &mut standings;
+        for game in &self.played {
+            match game.outcome {
+                Outcome::Decisive { winner } if winner == game.bottom => {
+                    bottom.wins += 1;
+                    top.losses += 1;
+                }
+                Outcome::Decisive { .. } => {
+                    top.wins += 1;
+                    bottom.losses += 1;
+                }
+                Outcome::Draw => {
+                    bottom.draws += 1;
+                    top.draws += 1;
+                }
+            }
+        }
+        bottom.points = self.points[0];
+        top.points = self.points[1];
+        standings
+    }
+
+    /// The bottom player's Elo difference to the top player, from the points
+    /// per game, with the error of its 95% confidence interval; None before
+    /// the first game and while one player has every point
+    pub fn elo_difference(&self) -> Option<(f64, f64)> {
+        let [bottom, _] = self.standings();
+        let games = self.games() as f64;
+        let score = bottom.points / games;
+        if !(score > 0.0 && score < 1.0) {
+            return None;
+        }
+        let share = |count: usize| count as f64 / games;
+        let variance = share(bottom.wins) * (1.0 - score).powi(2)
+            + share(bottom.draws) * (0.5 - score).powi(2)
+            + share(bottom.losses) * score.powi(2);
+        let margin = 1.96 * (variance / games).sqrt();
+        // an interval reaching 0 or 1 has no finite Elo at that end
+        let error = (elo(score + margin) - elo(score - margin)) / 2.0;
+        Some((
+            elo(score),
+            if error.is_nan() { f64::INFINITY } else { error },
+        ))
+    }
+
+    /// The names of the players of the current game, white first
+    pub fn white_and_black(&self) -> [&str; 2] {
+        self.names_with(self.bottom)
+    }
+
+    fn names_with(&self, bottom: Color) -> [&str; 2] {
+        let [bottom_name, top_name] = self.names.each_ref().map(String::as_str);
+        match bottom {
+            Color::White => [bottom_name, top_name],
+            Color::Black => [top_name, bottom_name],
+        }
+    }
+
+    /// Every game played as one PGN, the players named as the series names them
+    pub fn report_pgn(&self) -> String {
+        let mut out = String::new();
+        for (i, game) in self.played.iter().enumerate() {
+            let [white, black] = self.names_with(game.bottom);
+            let tags = export::Tags {
+                event: "tess match",
+                date: None,
+                round: (i + 1).to_string(),
+                white,
+                black,
+                result: game.outcome.to_string(),
+                termination: None,
+            };
+            if i > 0 {
+                out.push('\n');
+            }
+            out.push_str(&export::pgn_with_tags(&game.start, &game.moves, &tags));
+        }
+        out
+    }
+
+    /// "2.5-1.5", the bottom player's points first
+    pub fn score(&self) -> String {
+        format!("{}-{}", points(self.points[0]), points(self.points[1]))
+    }
+}
+
+// The Elo difference at which a player is expected to score `score` per game
+fn elo(score: f64) -> f64 {
+    400.0 * (score / (1.0 - score)).log10()
+}
+
+/// Whole points without a decimal
+pub fn points(value: f64) -> String {
+    if value.fract() == 0.0 {
+        format!("{value:.0}")
+    } else {
+        format!("{value:.1}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adaptive_engine_follows_my_results() {
+        let mut series = Series::new(Color::White).with_adaptive(1400);
+        let start = Chess::default();
+        let [won, lost] = [Color::White, Color::Black].map(|winner| Outcome::Decisive { winner });
+        series.record(&start, &[], won);
+        assert_eq!(series.engine_elo(), Some(1500));
+        series.record(&start, &[], Outcome::Draw);
+        assert_eq!(series.engine_elo(), Some(1500));
+        series.record(&start, &[], lost);
+        assert_eq!(series.engine_elo(), Some(1400));
+        series.record(&start, &[], lost);
+        assert_eq!(series.engine_elo(), Some(ELO_RANGE.0));
+    }
+
+    #[test]
+    fn elo_difference_of_the_results() {
+        let mut series = Series::new(Color::White);
+        let start = Chess::default();
+        assert!(series.elo_difference().is_none());
+        let [won, lost] = [Color::White, Color::Black].map(|winner| Outcome::Decisive { winner });
+        series.record(&start, &[], won);
+        assert!(series.elo_difference().is_none());
+        // 6 wins, 2 draws and 2 losses: 70%
+        for outcome in [
+            won,
+            won,
+            won,
+            won,
+            won,
+            Outcome::Draw,
+            Outcome::Draw,
+            lost,
+            lost,
+        ] {
+            series.record(&start, &[], outcome);
+        }
+        let (elo, error) = series.elo_difference().unwrap();
+        assert!((elo - 147.2).abs() < 0.1, "{elo}");
+        assert!(error > 100.0 && error.is_finite(), "{error}");
+    }
+}
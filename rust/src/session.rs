@@ -0,0 +1,159 @@
+use serde::{Deserialize, Serialize};
+use shakmaty::fen::Fen;
+use shakmaty::uci::UciMove;
+use shakmaty::{CastlingMode, Chess, EnPassantMode, Move, Position};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::{App, AppMode, LichessNextPuzzle};
+
+// ----------------------------------------------
+// Crash recovery: the running session is written to a recovery file
+// and removed again on a clean exit
+// ----------------------------------------------
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum SavedMode {
+    Standard,
+    Board,
+    Puzzle {
+        lichess: LichessNextPuzzle,
+        solution_index: usize,
+        completed: bool,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedSession {
+    pub mode: SavedMode,
+    pub start_fen: String,
+    /// Moves played from `start_fen`, in UCI
+    pub moves: Vec<String>,
+}
+
+impl SavedSession {
+    pub fn from_app(app: &App) -> Self {
+        let mode = match &app.mode {
+            AppMode::StandardGame => SavedMode::Standard,
+            AppMode::Board => SavedMode::Board,
+            AppMode::Puzzle {
+                solution_index,
+                completed,
+                lichess,
+                ..
+            } => SavedMode::Puzzle {
+                lichess: lichess.clone(),
+                solution_index: *solution_index,
+                completed: *completed,
+            },
+        };
+        SavedSession {
+            mode,
+            start_fen: Fen::from_position(app.start.clone(), EnPassantMode::Legal).to_string(),
+            moves: app
+                .history
+                .iter()
+                .map(|mv| mv.to_uci(CastlingMode::Standard).to_string())
+                .collect(),
+        }
+    }
+
+    /// Replay the saved moves, returning the start position and the history
+    pub fn replay(&self) -> anyhow::Result<(Chess, Vec<Move>)> {
+        let start: Chess =
+            Fen::from_ascii(self.start_fen.as_bytes())?.into_position(CastlingMode::Standard)?;
+        let mut pos = start.clone();
+        let mut history = Vec::new();
+        for uci in &self.moves {
+            let mv = UciMove::from_ascii(uci.as_bytes())?.to_move(&pos)?;
+            pos.play_unchecked(&mv);
+            history.push(mv);
+        }
+        Ok((start, history))
+    }
+
+    pub fn summary(&self) -> String {
+        let mode = match &self.mode {
+            SavedMode::Standard => "standard game".to_string(),
+            SavedMode::Board => "analysis board".to_string(),
+            SavedMode::Puzzle { lichess, .. } => format!("puzzle {}", lichess.puzzle.id),
+        };
+        format!("{mode}, {} moves played", self.moves.len())
+    }
+}
+
+pub fn state_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state")))
+        .map(|dir| dir.join("tess"))
+}
+
+fn recovery_path() -> Option<PathBuf> {
+    state_dir().map(|dir| dir.join("recovery.json"))
+}
+
+pub fn save(app: &App) -> anyhow::Result<()> {
+    let Some(path) = recovery_path() else {
+        return Ok(());
+    };
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    // Write next to the target and rename, so a crash mid-write keeps the old file
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_vec(&SavedSession::from_app(app))?)?;
+    std::fs::rename(tmp, path)?;
+    Ok(())
+}
+
+/// The session left behind by a run that did not exit cleanly, if any
+pub fn load_recovery() -> Option<SavedSession> {
+    let text = std::fs::read_to_string(recovery_path()?).ok()?;
+    serde_json::from_str(&text).ok()
+}
+
+pub fn clear_recovery() {
+    if let Some(path) = recovery_path() {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+/// Decides when the session is written to the recovery file
+pub struct Autosave {
+    every_moves: usize,
+    every: Duration,
+    last_save: Instant,
+    // plies played and start position at the last save
+    saved: Option<(usize, String)>,
+}
+
+impl Autosave {
+    pub fn new(every_moves: usize, every_seconds: u64) -> Self {
+        Self {
+            every_moves: every_moves.max(1),
+            every: Duration::from_secs(every_seconds),
+            last_save: Instant::now(),
+            saved: None,
+        }
+    }
+
+    pub fn tick(&mut self, app: &App) {
+        let plies = app.history.len();
+        let start = Fen::from_position(app.start.clone(), EnPassantMode::Legal).to_string();
+        let due = match &self.saved {
+            None => true,
+            Some((_, saved_start)) if *saved_start != start => true,
+            Some((saved_plies, _)) if *saved_plies == plies => false,
+            Some((saved_plies, _)) => {
+                plies.abs_diff(*saved_plies) >= self.every_moves
+                    || self.last_save.elapsed() >= self.every
+            }
+        };
+        // Recovery is best effort, a failing disk must not end the game
+        if due && save(app).is_ok() {
+            self.saved = Some((plies, start));
+            self.last_save = Instant::now();
+        }
+    }
+}
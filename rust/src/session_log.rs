@@ -0,0 +1,752 @@
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent,
+    MouseEventKind,
+};
+use ratatui::buffer::Buffer;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::config::Config;
+use crate::session;
+
+// ----------------------------------------------
+// Session logs: `--record session.log` writes every key, mouse event and
+// resize of a session with its time, the answers of the network and a
+// hash of the screen after each input; `tess replay session.log` plays it
+// back on the recorded command line, config and state files, checking
+// that every screen comes out the same.
+//
+// The clock of the app is read here (`now`, `system_time`) so that timers
+// and clocks see the recorded times, and the network is asked through
+// `fetch`. Both are reached from threads and from modules far from the
+// event loop, which is why the log is one global and not passed along.
+// Engines are not recorded: they run again and may answer differently.
+// ----------------------------------------------
+
+const VERSION: u32 = 1;
+// Environment variables that change how the screen looks
+const RECORDED_ENV: [&str; 4] = ["COLORFGBG", "LANG", "LC_ALL", "LC_CTYPE"];
+
+static LOG: Mutex<Option<Log>> = Mutex::new(None);
+
+// A response body, or what went wrong getting it
+type Body = Result<String, String>;
+
+#[derive(Serialize, Deserialize)]
+struct Header {
+    version: u32,
+    // the command line without the program
+    args: Vec<String>,
+    config: Config,
+    columns: u16,
+    rows: u16,
+    // wall clock at the start, unix milliseconds
+    unix_ms: u64,
+    env: BTreeMap<String, String>,
+    // the files in the state directory, by name
+    state: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "entry", rename_all = "snake_case")]
+enum Entry {
+    Start(Box<Header>),
+    Input { at_ms: u64, event: Input },
+    // the screen first drawn after input `after`, 0 for the first screen
+    Screen { after: usize, hash: String },
+    Response { url: String, at_ms: u64, body: Body },
+    Answer { yes: bool },
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Input {
+    Key {
+        key: String,
+        modifiers: u8,
+        kind: String,
+    },
+    Mouse {
+        kind: String,
+        column: u16,
+        row: u16,
+        modifiers: u8,
+    },
+    Resize {
+        columns: u16,
+        rows: u16,
+    },
+}
+
+// Keys by name, those not in here are a character or F1 to F24
+const KEY_NAMES: [(&str, KeyCode); 15] = [
+    ("Backspace", KeyCode::Backspace),
+    ("Enter", KeyCode::Enter),
+    ("Left", KeyCode::Left),
+    ("Right", KeyCode::Right),
+    ("Up", KeyCode::Up),
+    ("Down", KeyCode::Down),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+    ("PageUp", KeyCode::PageUp),
+    ("PageDown", KeyCode::PageDown),
+    ("Tab", KeyCode::Tab),
+    ("BackTab", KeyCode::BackTab),
+    ("Delete", KeyCode::Delete),
+    ("Insert", KeyCode::Insert),
+    ("Esc", KeyCode::Esc),
+];
+const KEY_KINDS: [(&str, KeyEventKind); 3] = [
+    ("press", KeyEventKind::Press),
+    ("repeat", KeyEventKind::Repeat),
+    ("release", KeyEventKind::Release),
+];
+const BUTTONS: [(&str, MouseButton); 3] = [
+    ("left", MouseButton::Left),
+    ("right", MouseButton::Right),
+    ("middle", MouseButton::Middle),
+];
+
+fn key_name(code: KeyCode) -> Option<String> {
+    match code {
+        KeyCode::Char(c) => Some(c.to_string()),
+        KeyCode::F(n) => Some(format!("F{n}")),
+        code => KEY_NAMES
+            .iter()
+            .find(|(_, named)| *named == code)
+            .map(|(name, _)| name.to_string()),
+    }
+}
+
+fn key_code(name: &str) -> Option<KeyCode> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(KeyCode::Char(c));
+    }
+    if let Some(n) = name.strip_prefix('F').and_then(|n| n.parse().ok()) {
+        return Some(KeyCode::F(n));
+    }
+    KEY_NAMES
+        .iter()
+        .find(|(named, _)| *named == name)
+        .map(|(_, code)| *code)
+}
+
+// "down left", "scroll up", "moved"
+fn mouse_name(kind: MouseEventKind) -> String {
+    let button = |button| {
+        BUTTONS
+            .iter()
+            .find(|(_, named)| *named == button)
+            .map_or("left", |(name, _)| name)
+    };
+    match kind {
+        MouseEventKind::Down(b) => format!("down {}", button(b)),
+        MouseEventKind::Up(b) => format!("up {}", button(b)),
+        MouseEventKind::Drag(b) => format!("drag {}", button(b)),
+        MouseEventKind::Moved => "moved".to_string(),
+        MouseEventKind::ScrollDown => "scroll down".to_string(),
+        MouseEventKind::ScrollUp => "scroll up".to_string(),
+        MouseEventKind::ScrollLeft => "scroll left".to_string(),
+        MouseEventKind::ScrollRight => "scroll right".to_string(),
+    }
+}
+
+fn mouse_kind(name: &str) -> Option<MouseEventKind> {
+    let button = |name| {
+        BUTTONS
+            .iter()
+            .find(|(named, _)| *named == name)
+            .map(|(_, button)| *button)
+    };
+    Some(match name.split_once(' ') {
+        Some(("down", b)) => MouseEventKind::Down(button(b)?),
+        Some(("up", b)) => MouseEventKind::Up(button(b)?),
+        Some(("drag", b)) => MouseEventKind::Drag(button(b)?),
+        Some(("scroll", "down")) => MouseEventKind::ScrollDown,
+        Some(("scroll", "up")) => MouseEventKind::ScrollUp,
+        Some(("scroll", "left")) => MouseEventKind::ScrollLeft,
+        Some(("scroll", "right")) => MouseEventKind::ScrollRight,
+        None if name == "moved" => MouseEventKind::Moved,
+        _ => return None,
+    })
+}
+
+impl Input {
+    // None for the events the app does not look at (focus, paste)
+    fn from_event(event: &Event) -> Option<Self> {
+        Some(match *event {
+            Event::Key(key) => Input::Key {
+                key: key_name(key.code)?,
+                modifiers: key.modifiers.bits(),
+                kind: KEY_KINDS
+                    .iter()
+                    .find(|(_, kind)| *kind == key.kind)?
+                    .0
+                    .to_string(),
+            },
+            Event::Mouse(mouse) => Input::Mouse {
+                kind: mouse_name(mouse.kind),
+                column: mouse.column,
+                row: mouse.row,
+                modifiers: mouse.modifiers.bits(),
+            },
+            Event::Resize(columns, rows) => Input::Resize { columns, rows },
+            _ => return None,
+        })
+    }
+
+    fn to_event(&self) -> Option<Event> {
+        Some(match self {
+            Input::Key {
+                key,
+                modifiers,
+                kind,
+            } => Event::Key(KeyEvent::new_with_kind(
+                key_code(key)?,
+                KeyModifiers::from_bits_truncate(*modifiers),
+                KEY_KINDS.iter().find(|(name, _)| name == kind)?.1,
+            )),
+            Input::Mouse {
+                kind,
+                column,
+                row,
+                modifiers,
+            } => Event::Mouse(MouseEvent {
+                kind: mouse_kind(kind)?,
+                column: *column,
+                row: *row,
+                modifiers: KeyModifiers::from_bits_truncate(*modifiers),
+            }),
+            Input::Resize { columns, rows } => Event::Resize(*columns, *rows),
+        })
+    }
+}
+
+// FNV-1a, the same on every build, unlike the std hasher
+struct Fnv(u64);
+
+impl Hasher for Fnv {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ u64::from(*byte)).wrapping_mul(0x100_0000_01b3);
+        }
+    }
+}
+
+fn screen_hash(buffer: &Buffer) -> String {
+    let mut hasher = Fnv(0xcbf2_9ce4_8422_2325);
+    buffer.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+struct Log {
+    mode: Mode,
+    // the start of the session, and the wall clock then
+    start: Instant,
+    unix_ms: u64,
+    // the time of the input being handled, until the next poll
+    pinned: Option<Instant>,
+    // how far a fast replay is ahead of the real clock
+    ahead: Duration,
+    inputs: usize,
+    screen_due: bool,
+}
+
+enum Mode {
+    Recording {
+        file: File,
+        path: PathBuf,
+    },
+    Replaying(Box<Player>),
+    // the replay is over, what is typed now is live
+    Replayed {
+        summary: String,
+        shown: bool,
+        state_dir: PathBuf,
+    },
+}
+
+struct Player {
+    fast: bool,
+    inputs: VecDeque<(u64, Input)>,
+    total: usize,
+    screens: HashMap<usize, String>,
+    responses: HashMap<String, VecDeque<(u64, Body)>>,
+    answers: VecDeque<bool>,
+    // inputs whose screens came out different
+    differing: Vec<usize>,
+    // requests the recording has no answer for
+    unanswered: usize,
+    state_dir: PathBuf,
+}
+
+impl Player {
+    fn summary(&self, stopped: bool) -> String {
+        let done = self.total - self.inputs.len();
+        let mut summary = if stopped {
+            format!("Replay stopped after input {done} of {}", self.total)
+        } else {
+            format!("Replay done: {} inputs", self.total)
+        };
+        match self.differing.first() {
+            None => summary.push_str(", every screen as recorded"),
+            Some(first) => summary.push_str(&format!(
+                ", {} of the screens differ from the recording, the first after input {first}",
+                self.differing.len()
+            )),
+        }
+        if self.unanswered > 0 {
+            summary.push_str(&format!(
+                ", {} requests not in the recording were sent",
+                self.unanswered
+            ));
+        }
+        summary.push('.');
+        summary
+    }
+}
+
+fn lock() -> std::sync::MutexGuard<'static, Option<Log>> {
+    // a panic elsewhere does not make the log unreadable
+    LOG.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn millis(duration: Duration) -> u64 {
+    duration.as_millis().try_into().unwrap_or(u64::MAX)
+}
+
+impl Log {
+    fn new(mode: Mode, start: Instant, unix_ms: u64) -> Self {
+        Self {
+            mode,
+            start,
+            unix_ms,
+            // the start up happens at the start, however long it takes
+            pinned: Some(start),
+            ahead: Duration::ZERO,
+            inputs: 0,
+            screen_due: true,
+        }
+    }
+
+    fn now(&self) -> Instant {
+        self.pinned.unwrap_or_else(|| Instant::now() + self.ahead)
+    }
+
+    fn at_ms(&self) -> u64 {
+        millis(self.now().saturating_duration_since(self.start))
+    }
+
+    // The input at `at_ms` is handled from now until the next poll
+    fn pin(&mut self, at_ms: u64) {
+        let at = self.start + Duration::from_millis(at_ms);
+        self.ahead = self.ahead.max(at.saturating_duration_since(Instant::now()));
+        self.pinned = Some(at);
+        self.inputs += 1;
+        self.screen_due = true;
+    }
+
+    fn write(&mut self, entry: &Entry) {
+        if let Mode::Recording { file, .. } = &mut self.mode {
+            if let Ok(mut line) = serde_json::to_string(entry) {
+                line.push('\n');
+                // a full disk ends the log, not the session
+                let _ = file.write_all(line.as_bytes());
+            }
+        }
+    }
+
+    fn end_replay(&mut self, stopped: bool) {
+        if let Mode::Replaying(player) = &self.mode {
+            self.mode = Mode::Replayed {
+                summary: player.summary(stopped),
+                shown: false,
+                state_dir: player.state_dir.clone(),
+            };
+        }
+    }
+}
+
+/// Record the session to `path`: `args` run with `config` on a terminal of
+/// this size, starting from the state directory as it is now
+pub fn record(path: &Path, args: Vec<String>, config: &Config) -> anyhow::Result<()> {
+    let mut config = config.clone();
+    // the log is meant to be handed around, the account stays out of it
+    config.lichess_token = config.lichess_token.map(|_| "recorded".to_string());
+    let (columns, rows) = crossterm::terminal::size().unwrap_or((80, 24));
+    let unix_ms = millis(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default(),
+    );
+    let header = Header {
+        version: VERSION,
+        args,
+        config,
+        columns,
+        rows,
+        unix_ms,
+        env: RECORDED_ENV
+            .iter()
+            .filter_map(|name| Some((name.to_string(), std::env::var(name).ok()?)))
+            .collect(),
+        state: read_state(),
+    };
+    let file = File::create(path)
+        .map_err(|err| anyhow::anyhow!("cannot record to {}: {err}", path.display()))?;
+    let mode = Mode::Recording {
+        file,
+        path: path.to_path_buf(),
+    };
+    let mut log = Log::new(mode, Instant::now(), unix_ms);
+    log.write(&Entry::Start(Box::new(header)));
+    *lock() = Some(log);
+    Ok(())
+}
+
+// The files of the state directory that read as text
+fn read_state() -> BTreeMap<String, String> {
+    let Some(entries) = session::state_dir().and_then(|dir| std::fs::read_dir(dir).ok()) else {
+        return BTreeMap::new();
+    };
+    entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let name = path.file_name()?.to_str()?.to_string();
+            Some((name, std::fs::read_to_string(&path).ok()?))
+        })
+        .collect()
+}
+
+/// What a replay runs: the recorded command line (without the program),
+/// config and terminal size
+pub struct Recorded {
+    pub args: Vec<String>,
+    pub config: Config,
+    pub size: (u16, u16),
+}
+
+/// Play back the log at `path`, as fast as it goes with `fast`. The recorded
+/// state files are put in a directory of their own, which becomes the state
+/// directory, and the recorded environment is set.
+pub fn replay(path: &Path, fast: bool) -> anyhow::Result<Recorded> {
+    let file =
+        File::open(path).map_err(|err| anyhow::anyhow!("cannot read {}: {err}", path.display()))?;
+    let mut lines = io::BufReader::new(file).lines();
+    let first = lines.next().transpose()?.unwrap_or_default();
+    let Ok(Entry::Start(header)) = serde_json::from_str(&first) else {
+        anyhow::bail!("{} is not a session log", path.display());
+    };
+    if header.version != VERSION {
+        anyhow::bail!(
+            "{} is a version {} session log, this tess replays version {VERSION}",
+            path.display(),
+            header.version
+        );
+    }
+    let state_dir = std::env::temp_dir().join(format!("tess-replay-{}", std::process::id()));
+    let tess_dir = state_dir.join("tess");
+    std::fs::create_dir_all(&tess_dir)?;
+    for (name, text) in &header.state {
+        std::fs::write(tess_dir.join(name), text)?;
+    }
+    std::env::set_var("XDG_STATE_HOME", &state_dir);
+    for name in RECORDED_ENV {
+        match header.env.get(name) {
+            Some(value) => std::env::set_var(name, value),
+            None => std::env::remove_var(name),
+        }
+    }
+
+    let mut player = Player {
+        fast,
+        inputs: VecDeque::new(),
+        total: 0,
+        screens: HashMap::new(),
+        responses: HashMap::new(),
+        answers: VecDeque::new(),
+        differing: Vec::new(),
+        unanswered: 0,
+        state_dir,
+    };
+    for (number, line) in lines.enumerate() {
+        let line = line?;
+        // the last line of a session that crashed may be cut short
+        let entry = match serde_json::from_str(&line) {
+            Ok(entry) => entry,
+            Err(_) if line.trim().is_empty() => continue,
+            Err(err) => anyhow::bail!("{}:{}: {err}", path.display(), number + 2),
+        };
+        match entry {
+            Entry::Start(_) => anyhow::bail!("{}:{}: a second start", path.display(), number + 2),
+            Entry::Input { at_ms, event } => player.inputs.push_back((at_ms, event)),
+            Entry::Screen { after, hash } => {
+                player.screens.insert(after, hash);
+            }
+            Entry::Response { url, at_ms, body } => player
+                .responses
+                .entry(url)
+                .or_default()
+                .push_back((at_ms, body)),
+            Entry::Answer { yes } => player.answers.push_back(yes),
+        }
+    }
+    player.total = player.inputs.len();
+    let recorded = Recorded {
+        args: header.args,
+        config: header.config,
+        size: (header.columns, header.rows),
+    };
+    let log = Log::new(
+        Mode::Replaying(Box::new(player)),
+        Instant::now(),
+        header.unix_ms,
+    );
+    *lock() = Some(log);
+    Ok(recorded)
+}
+
+/// Whether a log is being played back right now
+pub fn replaying() -> bool {
+    matches!(
+        lock().as_ref().map(|log| &log.mode),
+        Some(Mode::Replaying(_))
+    )
+}
+
+/// The clock of the app: the recorded time of the input being handled
+/// during recordings and replays, the real clock otherwise
+pub fn now() -> Instant {
+    lock().as_ref().map_or_else(Instant::now, Log::now)
+}
+
+/// Time since `earlier`, on the clock of `now`
+pub fn elapsed(earlier: Instant) -> Duration {
+    now().saturating_duration_since(earlier)
+}
+
+/// The wall clock, following `now`
+pub fn system_time() -> SystemTime {
+    match lock().as_ref() {
+        Some(log) => {
+            UNIX_EPOCH
+                + Duration::from_millis(log.unix_ms)
+                + log.now().saturating_duration_since(log.start)
+        }
+        None => SystemTime::now(),
+    }
+}
+
+/// The body `request` gets from `url`; recorded, or the recorded one in a
+/// replay, on time
+pub fn fetch(
+    url: &str,
+    request: impl FnOnce() -> anyhow::Result<String>,
+) -> anyhow::Result<String> {
+    let mut guard = lock();
+    let Some(log) = guard.as_mut() else {
+        drop(guard);
+        return request();
+    };
+    match &mut log.mode {
+        Mode::Replaying(player) => {
+            let Some((at_ms, body)) = player.responses.get_mut(url).and_then(VecDeque::pop_front)
+            else {
+                player.unanswered += 1;
+                drop(guard);
+                return request();
+            };
+            let due = log.start + Duration::from_millis(at_ms);
+            let fast = player.fast;
+            drop(guard);
+            if !fast {
+                std::thread::sleep(due.saturating_duration_since(Instant::now()));
+            }
+            body.map_err(|err| anyhow::anyhow!(err))
+        }
+        Mode::Recording { .. } => {
+            drop(guard);
+            let body = request().map_err(|err| err.to_string());
+            let mut guard = lock();
+            if let Some(log) = guard.as_mut() {
+                let entry = Entry::Response {
+                    url: url.to_string(),
+                    at_ms: log.at_ms(),
+                    body: body.clone(),
+                };
+                log.write(&entry);
+            }
+            body.map_err(|err| anyhow::anyhow!(err))
+        }
+        Mode::Replayed { .. } => {
+            drop(guard);
+            request()
+        }
+    }
+}
+
+/// A yes or no `ask` gives, taken from the recording in a replay
+pub fn answer(ask: impl FnOnce() -> anyhow::Result<bool>) -> anyhow::Result<bool> {
+    if let Some(Log {
+        mode: Mode::Replaying(player),
+        ..
+    }) = lock().as_mut()
+    {
+        if let Some(yes) = player.answers.pop_front() {
+            return Ok(yes);
+        }
+    }
+    let yes = ask()?;
+    if let Some(log) = lock().as_mut() {
+        log.write(&Entry::Answer { yes });
+    }
+    Ok(yes)
+}
+
+/// Whether an input is there within `timeout`, like `event::poll`; in a
+/// replay, whether the next recorded input is due. Esc typed during a
+/// replay stops it.
+pub fn poll(timeout: Duration) -> io::Result<bool> {
+    let wait = {
+        let mut guard = lock();
+        let Some(log) = guard.as_mut() else {
+            drop(guard);
+            return event::poll(timeout);
+        };
+        log.pinned = None;
+        log.screen_due = false;
+        let Mode::Replaying(player) = &log.mode else {
+            drop(guard);
+            return event::poll(timeout);
+        };
+        let Some((at_ms, _)) = player.inputs.front() else {
+            log.end_replay(false);
+            return Ok(false);
+        };
+        while event::poll(Duration::ZERO)? {
+            if let Event::Key(KeyEvent {
+                code: KeyCode::Esc, ..
+            }) = event::read()?
+            {
+                log.end_replay(true);
+                return Ok(false);
+            }
+        }
+        let due = log.start + Duration::from_millis(*at_ms);
+        if player.fast {
+            Duration::ZERO
+        } else {
+            due.saturating_duration_since(Instant::now() + log.ahead)
+        }
+    };
+    if wait > timeout {
+        std::thread::sleep(timeout);
+        return Ok(false);
+    }
+    std::thread::sleep(wait);
+    Ok(true)
+}
+
+/// The input `poll` found, recorded; in a replay the next recorded one
+pub fn read() -> io::Result<Event> {
+    let mut guard = lock();
+    let Some(log) = guard.as_mut() else {
+        drop(guard);
+        return event::read();
+    };
+    match &mut log.mode {
+        Mode::Replaying(player) => {
+            let (at_ms, input) = player
+                .inputs
+                .pop_front()
+                .ok_or_else(|| io::Error::other("no recorded input left"))?;
+            log.pin(at_ms);
+            input
+                .to_event()
+                .ok_or_else(|| io::Error::other(format!("input {} does not read", log.inputs)))
+        }
+        Mode::Recording { .. } => {
+            drop(guard);
+            let event = event::read()?;
+            if let (Some(input), Some(log)) = (Input::from_event(&event), lock().as_mut()) {
+                let at_ms = millis(Instant::now().saturating_duration_since(log.start));
+                log.pin(at_ms);
+                log.write(&Entry::Input {
+                    at_ms,
+                    event: input,
+                });
+            }
+            Ok(event)
+        }
+        Mode::Replayed { .. } => {
+            drop(guard);
+            event::read()
+        }
+    }
+}
+
+/// The screen as drawn: the first one after an input is recorded, or
+/// compared with the recording
+pub fn screen(buffer: &Buffer) {
+    let mut guard = lock();
+    let Some(log) = guard.as_mut().filter(|log| log.screen_due) else {
+        return;
+    };
+    log.screen_due = false;
+    let hash = screen_hash(buffer);
+    let after = log.inputs;
+    match &mut log.mode {
+        Mode::Recording { .. } => log.write(&Entry::Screen { after, hash }),
+        Mode::Replaying(player) => {
+            if player
+                .screens
+                .get(&after)
+                .is_some_and(|recorded| *recorded != hash)
+            {
+                player.differing.push(after);
+            }
+        }
+        Mode::Replayed { .. } => {}
+    }
+}
+
+/// How the replay went, once, when it is over
+pub fn replay_summary() -> Option<String> {
+    match lock().as_mut().map(|log| &mut log.mode) {
+        Some(Mode::Replayed { summary, shown, .. }) if !*shown => {
+            *shown = true;
+            Some(summary.clone())
+        }
+        _ => None,
+    }
+}
+
+/// At the end of the session: where it was recorded, or how the replay went;
+/// the state directory of a replay is removed
+pub fn finish() -> Option<String> {
+    let log = lock().take()?;
+    match log.mode {
+        Mode::Recording { path, .. } => Some(format!("Session recorded to {}.", path.display())),
+        Mode::Replaying(player) => {
+            let _ = std::fs::remove_dir_all(&player.state_dir);
+            Some(player.summary(!player.inputs.is_empty()))
+        }
+        Mode::Replayed {
+            summary, state_dir, ..
+        } => {
+            let _ = std::fs::remove_dir_all(&state_dir);
+            Some(summary)
+        }
+    }
+}
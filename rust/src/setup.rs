@@ -0,0 +1,479 @@
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Paragraph, Wrap};
+use ratatui::{Frame, Terminal};
+use shakmaty::fen::Fen;
+use shakmaty::{CastlingMode, Chess};
+use std::io;
+use std::path::PathBuf;
+
+use crate::config::{self, Config};
+use crate::engine::Engine;
+use tess_rs::board::{BoardWidget, DisplayMode, PieceSet};
+use tess_rs::theme::{Background, Palette};
+
+// ----------------------------------------------
+// The setup: on the first run, before there is a config file, a few
+// questions about the terminal, each answered on a live board. What
+// the terminal tells about itself picks the first answers; the last
+// step writes the config file. `tess setup` asks again.
+// ----------------------------------------------
+
+// Engines looked for on PATH and in /usr/games, where Debian puts them
+const ENGINES: &[&str] = &[
+    "stockfish",
+    "lc0",
+    "berserk",
+    "ethereal",
+    "koivisto",
+    "rubichess",
+    "komodo",
+];
+// Every kind of piece of both colors on the preview
+const PREVIEW_FEN: &str = "r1bqk2r/pppp1ppp/2n2n2/2b1p3/2B1P3/3P1N2/PPP2PPP/RNBQK2R w KQkq - 1 5";
+// A terminal this size has room for the big display next to the panes
+const BIG_COLUMNS: u16 = 100;
+const BIG_ROWS: u16 = 32;
+
+const DISPLAYS: [DisplayMode; 2] = [DisplayMode::Simple, DisplayMode::Big];
+const PIECE_SETS: [PieceSet; 2] = [PieceSet::Symbols, PieceSet::Letters];
+const BACKGROUNDS: [Background; 3] = [Background::Dark, Background::Light, Background::Auto];
+
+// What the terminal tells about itself
+struct Capabilities {
+    columns: u16,
+    rows: u16,
+    colors: &'static str,
+    unicode: bool,
+    // the background $COLORFGBG gives, if it is set
+    colorfgbg: Option<Background>,
+}
+
+impl Capabilities {
+    fn detect() -> Self {
+        let (columns, rows) = crossterm::terminal::size().unwrap_or((80, 24));
+        let var = |name: &str| std::env::var(name).unwrap_or_default();
+        let colors = if matches!(var("COLORTERM").as_str(), "truecolor" | "24bit") {
+            "24-bit color"
+        } else if var("TERM").contains("256") {
+            "256 colors"
+        } else {
+            "16 colors"
+        };
+        // the first locale variable set decides, as for every program
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+            .into_iter()
+            .map(var)
+            .find(|value| !value.is_empty())
+            .unwrap_or_default()
+            .to_uppercase();
+        Self {
+            columns,
+            rows,
+            colors,
+            unicode: locale.contains("UTF-8") || locale.contains("UTF8"),
+            colorfgbg: std::env::var_os("COLORFGBG").map(|_| Background::Auto.effective()),
+        }
+    }
+
+    // "120x40, 256 colors, UTF-8, light background ($COLORFGBG)"
+    fn describe(&self) -> String {
+        let background = match self.colorfgbg {
+            Some(Background::Light) => "light background ($COLORFGBG)",
+            Some(_) => "dark background ($COLORFGBG)",
+            None => "background unknown",
+        };
+        format!(
+            "{}x{}, {}, {}, {background}",
+            self.columns,
+            self.rows,
+            self.colors,
+            if self.unicode { "UTF-8" } else { "no UTF-8" }
+        )
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Step {
+    Display,
+    Pieces,
+    Background,
+    Engine,
+    Save,
+}
+
+const STEPS: [Step; 5] = [
+    Step::Display,
+    Step::Pieces,
+    Step::Background,
+    Step::Engine,
+    Step::Save,
+];
+
+impl Step {
+    fn title(self) -> &'static str {
+        match self {
+            Step::Display => "Board size",
+            Step::Pieces => "Pieces",
+            Step::Background => "Colors",
+            Step::Engine => "Analysis engine",
+            Step::Save => "Done",
+        }
+    }
+}
+
+struct Wizard {
+    config: Config,
+    terminal: Capabilities,
+    preview: Chess,
+    step: usize,
+    display: DisplayMode,
+    pieces: PieceSet,
+    background: Background,
+    // engines found, then one typed in; the choice 0 is none
+    found: Vec<PathBuf>,
+    typed: String,
+    engine: usize,
+    message: String,
+}
+
+impl Wizard {
+    // The choices of the config given, else those the terminal suggests
+    fn new(config: Config) -> Self {
+        let terminal = Capabilities::detect();
+        let found = find_engines();
+        let display = config.display.unwrap_or(
+            if terminal.columns >= BIG_COLUMNS && terminal.rows >= BIG_ROWS {
+                DisplayMode::Big
+            } else {
+                DisplayMode::Simple
+            },
+        );
+        let pieces = config.pieces.unwrap_or(if terminal.unicode {
+            PieceSet::Symbols
+        } else {
+            PieceSet::Letters
+        });
+        let background = config.background.unwrap_or(match terminal.colorfgbg {
+            Some(_) => Background::Auto,
+            None => Background::Dark,
+        });
+        let (engine, typed) = match &config.analysis_engine {
+            None => (0, String::new()),
+            Some(path) => match found.iter().position(|known| known == path) {
+                Some(index) => (index + 1, String::new()),
+                None => (found.len() + 1, path.display().to_string()),
+            },
+        };
+        let preview = Fen::from_ascii(PREVIEW_FEN.as_bytes())
+            .ok()
+            .and_then(|fen| fen.into_position(CastlingMode::Standard).ok())
+            .unwrap_or_default();
+        Self {
+            config,
+            terminal,
+            preview,
+            step: 0,
+            display,
+            pieces,
+            background,
+            found,
+            typed,
+            engine,
+            message: String::new(),
+        }
+    }
+
+    fn current(&self) -> Step {
+        STEPS[self.step]
+    }
+
+    // The choices of the step with a line on each
+    fn choices(&self) -> Vec<(String, String)> {
+        let pair = |name: &str, about: &str| (name.to_string(), about.to_string());
+        match self.current() {
+            Step::Display => vec![
+                pair("simple", "one character per piece, fits any terminal"),
+                pair("big", "pieces in three lines, for large terminals"),
+            ],
+            Step::Pieces => vec![
+                pair("symbols", "chess symbols, if the font has them"),
+                pair("letters", "K Q R B N P for white, lowercase for black"),
+            ],
+            Step::Background => vec![
+                pair("dark", "colors for a dark terminal background"),
+                pair(
+                    "light",
+                    if self.terminal.colors == "16 colors" {
+                        "for a light background, needs 256 colors"
+                    } else {
+                        "colors for a light terminal background"
+                    },
+                ),
+                pair("auto", "follow $COLORFGBG, dark where it is not set"),
+            ],
+            Step::Engine => std::iter::once(pair("none", "no engine, or --analysis-engine"))
+                .chain(self.found.iter().map(|path| {
+                    pair(
+                        &path.display().to_string(),
+                        "evaluates the position on screen",
+                    )
+                }))
+                .chain(std::iter::once(pair(
+                    &format!("{}_", self.typed),
+                    "type a path or a command",
+                )))
+                .collect(),
+            Step::Save => Vec::new(),
+        }
+    }
+
+    fn choice(&self) -> usize {
+        match self.current() {
+            Step::Display => DISPLAYS
+                .iter()
+                .position(|d| *d == self.display)
+                .unwrap_or(0),
+            Step::Pieces => PIECE_SETS
+                .iter()
+                .position(|p| *p == self.pieces)
+                .unwrap_or(0),
+            Step::Background => BACKGROUNDS
+                .iter()
+                .position(|b| *b == self.background)
+                .unwrap_or(0),
+            Step::Engine => self.engine,
+            Step::Save => 0,
+        }
+    }
+
+    fn choose(&mut self, choice: usize) {
+        match self.current() {
+            Step::Display => self.display = DISPLAYS[choice.min(DISPLAYS.len() - 1)],
+            Step::Pieces => self.pieces = PIECE_SETS[choice.min(PIECE_SETS.len() - 1)],
+            Step::Background => self.background = BACKGROUNDS[choice.min(BACKGROUNDS.len() - 1)],
+            Step::Engine => self.engine = choice.min(self.found.len() + 1),
+            Step::Save => {}
+        }
+    }
+
+    // The engine chosen, if any
+    fn engine_path(&self) -> Option<PathBuf> {
+        match self.engine {
+            0 => None,
+            index if index <= self.found.len() => Some(self.found[index - 1].clone()),
+            _ if self.typed.trim().is_empty() => None,
+            _ => Some(PathBuf::from(self.typed.trim())),
+        }
+    }
+
+    // On to the next step; an engine has to start and answer first
+    fn next(&mut self) {
+        self.message.clear();
+        if self.current() == Step::Engine {
+            if let Some(path) = self.engine_path() {
+                match Engine::start(&path) {
+                    Ok(engine) => self.message = format!("{} answered.", engine.name),
+                    Err(err) => {
+                        self.message = format!("The engine did not start: {err}");
+                        return;
+                    }
+                }
+            }
+        }
+        self.step = (self.step + 1).min(STEPS.len() - 1);
+    }
+
+    fn finish(self) -> Config {
+        let analysis_engine = self.engine_path();
+        Config {
+            display: Some(self.display),
+            pieces: Some(self.pieces),
+            background: Some(self.background),
+            analysis_engine,
+            ..self.config
+        }
+    }
+
+    // Some(true) when done, Some(false) when given up, None to go on
+    fn handle_key(&mut self, key: KeyEvent) -> Option<bool> {
+        let typing = self.current() == Step::Engine;
+        match key.code {
+            KeyCode::Esc => return Some(false),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return Some(false)
+            }
+            KeyCode::Enter if self.current() == Step::Save => return Some(true),
+            KeyCode::Enter | KeyCode::Right | KeyCode::Tab => self.next(),
+            KeyCode::Up => self.choose(self.choice().saturating_sub(1)),
+            KeyCode::Down => self.choose(self.choice() + 1),
+            KeyCode::Backspace if typing && !self.typed.is_empty() => {
+                self.typed.pop();
+                self.engine = self.found.len() + 1;
+            }
+            KeyCode::Left | KeyCode::Backspace | KeyCode::BackTab => {
+                self.message.clear();
+                self.step = self.step.saturating_sub(1);
+            }
+            KeyCode::Char(c) if typing => {
+                self.typed.push(c);
+                self.engine = self.found.len() + 1;
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+// Engines on PATH by the names in ENGINES, each once
+fn find_engines() -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = std::env::var_os("PATH")
+        .map(|path| std::env::split_paths(&path).collect())
+        .unwrap_or_default();
+    dirs.push(PathBuf::from("/usr/games"));
+    ENGINES
+        .iter()
+        .filter_map(|name| {
+            dirs.iter()
+                .map(|dir| dir.join(name))
+                .find(|path| path.is_file())
+        })
+        .collect()
+}
+
+fn draw(frame: &mut Frame, wizard: &Wizard) {
+    let step = wizard.current();
+    let outer = Block::bordered().title(format!(
+        "tess setup, {} of {}: {}",
+        wizard.step + 1,
+        STEPS.len(),
+        step.title()
+    ));
+    let area = outer.inner(frame.area());
+    frame.render_widget(outer, frame.area());
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(2),
+            Constraint::Min(0),
+            Constraint::Length(2),
+        ])
+        .split(area);
+    frame.render_widget(
+        Paragraph::new(format!("Terminal: {}", wizard.terminal.describe()))
+            .style(Style::default().fg(Color::DarkGray)),
+        rows[0],
+    );
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Length(50), Constraint::Min(0)])
+        .split(rows[1]);
+
+    let mut lines: Vec<Line> = Vec::new();
+    if step == Step::Save {
+        let engine = wizard
+            .engine_path()
+            .map_or("none".to_string(), |path| path.display().to_string());
+        lines.push(Line::raw(
+            format!("display:         {:?}", wizard.display).to_lowercase(),
+        ));
+        lines.push(Line::raw(
+            format!("pieces:          {:?}", wizard.pieces).to_lowercase(),
+        ));
+        lines.push(Line::raw(
+            format!("background:      {:?}", wizard.background).to_lowercase(),
+        ));
+        lines.push(Line::raw(format!("analysis engine: {engine}")));
+        lines.push(Line::raw(""));
+        let dir = config::config_dir().map_or("~/.config/tess".to_string(), |dir| {
+            dir.display().to_string()
+        });
+        lines.push(Line::raw(format!(
+            "Enter writes them to {dir}/config.json, where the other settings are."
+        )));
+    } else {
+        let chosen = wizard.choice();
+        for (index, (name, about)) in wizard.choices().into_iter().enumerate() {
+            let style = if index == chosen {
+                Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED)
+            } else {
+                Style::default()
+            };
+            lines.push(Line::from(Span::styled(format!(" {name} "), style)));
+            lines.push(Line::styled(
+                format!("   {about}"),
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+    }
+    if !wizard.message.is_empty() {
+        lines.push(Line::raw(""));
+        lines.push(Line::raw(wizard.message.clone()));
+    }
+    frame.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false }), columns[0]);
+
+    let palette = Palette::for_background(wizard.background);
+    let board = BoardWidget::new(&wizard.preview)
+        .theme(&palette)
+        .display(wizard.display)
+        .pieces(wizard.pieces)
+        .wide_symbols(wizard.config.wide_symbols);
+    let (width, height) = board.size();
+    let preview = columns[1];
+    if preview.width >= width && preview.height >= height {
+        frame.render_widget(board, preview);
+    } else {
+        frame.render_widget(
+            Paragraph::new(format!(
+                "This board needs {width}x{height}, the preview has {}x{}.",
+                preview.width, preview.height
+            ))
+            .wrap(Wrap { trim: false }),
+            preview,
+        );
+    }
+
+    let keys = match step {
+        Step::Save => "Enter: write the config   Left: back   Esc: leave without it",
+        Step::Engine => "Up/Down: choose   type: a path   Enter: next   Left: back   Esc: skip",
+        _ => "Up/Down: choose   Enter: next   Left: back   Esc: skip the setup",
+    };
+    frame.render_widget(
+        Paragraph::new(keys).style(Style::default().fg(Color::DarkGray)),
+        rows[2],
+    );
+}
+
+/// Ask the setup questions on the whole screen, starting from `config`; the
+/// config with the answers, or None if the setup was left with Esc
+pub fn run(config: Config) -> anyhow::Result<Option<Config>> {
+    let mut wizard = Wizard::new(config);
+    enable_raw_mode()?;
+    crossterm::execute!(io::stdout(), EnterAlternateScreen)?;
+    let result = (|| -> anyhow::Result<bool> {
+        let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+        loop {
+            terminal.draw(|frame| draw(frame, &wizard))?;
+            match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => {
+                    if let Some(done) = wizard.handle_key(key) {
+                        return Ok(done);
+                    }
+                }
+                Event::Resize(columns, rows) => {
+                    (wizard.terminal.columns, wizard.terminal.rows) = (columns, rows);
+                }
+                _ => {}
+            }
+        }
+    })();
+    disable_raw_mode()?;
+    crossterm::execute!(io::stdout(), LeaveAlternateScreen)?;
+    Ok(result?.then(|| wizard.finish()))
+}
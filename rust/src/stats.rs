@@ -0,0 +1,146 @@
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::UNIX_EPOCH;
+
+use crate::{session, session_log};
+
+// ----------------------------------------------
+// Training statistics, one JSON line per finished session in
+// $XDG_STATE_HOME/tess/stats.jsonl so results can be compared over time
+// ----------------------------------------------
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SessionRecord {
+    /// "set", "sprint" or "drill"
+    pub kind: String,
+    pub theme: Option<String>,
+    /// seconds since the Unix epoch
+    pub finished_at: u64,
+    pub puzzles: usize,
+    pub solved: usize,
+    pub seconds: f64,
+    pub rating_min: Option<u16>,
+    pub rating_max: Option<u16>,
+    /// seconds spent on each puzzle, in order
+    #[serde(default)]
+    pub puzzle_seconds: Vec<f64>,
+    /// solved puzzles less the hint penalties
+    #[serde(default)]
+    pub score: f64,
+    /// puzzles where a hint was taken
+    #[serde(default)]
+    pub hinted: usize,
+}
+
+fn stats_path() -> Option<PathBuf> {
+    session::state_dir().map(|dir| dir.join("stats.jsonl"))
+}
+
+pub fn now() -> u64 {
+    session_log::system_time()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+pub fn append(record: &SessionRecord) -> anyhow::Result<()> {
+    let Some(path) = stats_path() else {
+        return Ok(());
+    };
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    writeln!(file, "{}", serde_json::to_string(record)?)?;
+    Ok(())
+}
+
+// ----------------------------------------------
+// Local puzzle rating, an Elo rating against the puzzles' own ratings
+// kept in $XDG_STATE_HOME/tess/rating.json
+// ----------------------------------------------
+
+const START_RATING: f64 = 1500.0;
+const K_FACTOR: f64 = 32.0;
+const RECENT: usize = 30;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PuzzleRating {
+    pub rating: f64,
+    pub puzzles: usize,
+    /// the rating after each of the last attempts, oldest first
+    #[serde(default)]
+    pub recent: Vec<f64>,
+}
+
+impl Default for PuzzleRating {
+    fn default() -> Self {
+        Self {
+            rating: START_RATING,
+            puzzles: 0,
+            recent: Vec::new(),
+        }
+    }
+}
+
+impl PuzzleRating {
+    /// Change of the last attempt
+    pub fn last_change(&self) -> Option<f64> {
+        match self.recent[..] {
+            [.., before, after] => Some(after - before),
+            [after] => Some(after - START_RATING),
+            [] => None,
+        }
+    }
+
+    /// The recent ratings as a line of block characters, low to high
+    pub fn sparkline(&self) -> String {
+        const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+        let low = self.recent.iter().copied().fold(f64::INFINITY, f64::min);
+        let high = self
+            .recent
+            .iter()
+            .copied()
+            .fold(f64::NEG_INFINITY, f64::max);
+        let span = (high - low).max(1.0);
+        self.recent
+            .iter()
+            .map(|r| BARS[(((r - low) / span) * 7.0).round() as usize])
+            .collect()
+    }
+}
+
+fn rating_path() -> Option<PathBuf> {
+    session::state_dir().map(|dir| dir.join("rating.json"))
+}
+
+pub fn puzzle_rating() -> PuzzleRating {
+    rating_path()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+/// Rate one puzzle, `score` is 1 for a clean solve down to 0 for a failure.
+/// Returns the rating before and the updated one.
+pub fn rate_puzzle(puzzle_rating: u16, score: f64) -> anyhow::Result<(f64, PuzzleRating)> {
+    let mut current = self::puzzle_rating();
+    let before = current.rating;
+    let expected = 1.0 / (1.0 + 10f64.powf((f64::from(puzzle_rating) - before) / 400.0));
+    current.rating += K_FACTOR * (score.clamp(0.0, 1.0) - expected);
+    current.puzzles += 1;
+    if current.recent.len() >= RECENT {
+        current.recent.remove(0);
+    }
+    current.recent.push(current.rating);
+    if let Some(path) = rating_path() {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_vec(&current)?)?;
+    }
+    Ok((before, current))
+}
@@ -0,0 +1,99 @@
+use crossterm::terminal::SetTitle;
+use shakmaty::san::San;
+use shakmaty::{Color as ChessColor, Position};
+use std::io::{self, Write};
+use std::process::Command;
+
+use crate::{App, AppMode};
+
+// ----------------------------------------------
+// Game state in the terminal title and, optionally, a tmux user
+// option (`@tess_status`) that can be used in `status-right`
+// ----------------------------------------------
+
+// xterm window operations to save and restore the previous title
+const PUSH_TITLE: &str = "\x1b[22;0t";
+const POP_TITLE: &str = "\x1b[23;0t";
+
+pub struct StatusReporter {
+    tmux: bool,
+    last: String,
+}
+
+impl StatusReporter {
+    pub fn new(tmux: bool) -> Self {
+        let _ = write!(io::stdout(), "{PUSH_TITLE}");
+        Self {
+            // only meaningful when actually running inside tmux
+            tmux: tmux && std::env::var_os("TMUX").is_some(),
+            last: String::new(),
+        }
+    }
+
+    pub fn update(&mut self, app: &App) {
+        let status = status_text(app);
+        if status == self.last {
+            return;
+        }
+        let _ = crossterm::execute!(io::stdout(), SetTitle(format!("tess: {status}")));
+        if self.tmux {
+            let _ = Command::new("tmux")
+                .args(["set-option", "-q", "@tess_status", &status])
+                .status();
+        }
+        self.last = status;
+    }
+}
+
+impl Drop for StatusReporter {
+    fn drop(&mut self) {
+        let _ = write!(io::stdout(), "{POP_TITLE}");
+        let _ = io::stdout().flush();
+        if self.tmux {
+            let _ = Command::new("tmux")
+                .args(["set-option", "-q", "-u", "@tess_status"])
+                .status();
+        }
+    }
+}
+
+/// Short description like "puzzle 9xKcG, black to move - 12...Nf6"
+pub fn status_text(app: &App) -> String {
+    let state = if app.board.is_game_over() {
+        "game over".to_string()
+    } else {
+        format!("{} to move", app.board.turn())
+    };
+    let mode = match &app.mode {
+        AppMode::StandardGame => state,
+        AppMode::Board => format!("board, {state}"),
+        AppMode::Puzzle {
+            lichess, completed, ..
+        } if *completed => format!("puzzle {} solved", lichess.puzzle.id),
+        AppMode::Puzzle { lichess, .. } => format!("puzzle {}, {state}", lichess.puzzle.id),
+    };
+    let mode = if app.analysing {
+        format!("analysis, {mode}")
+    } else {
+        mode
+    };
+    match last_move_text(app) {
+        Some(last) => format!("{mode} \u{2014} {last}"),
+        None => mode,
+    }
+}
+
+// The last move with its move number, e.g. "12.e4" or "12…Nf6"
+fn last_move_text(app: &App) -> Option<String> {
+    let (last, earlier) = app.history.split_last()?;
+    let mut before = app.start.clone();
+    for mv in earlier {
+        before.play_unchecked(mv);
+    }
+    let san = San::from_move(&before, last);
+    let number = before.fullmoves();
+    Some(match before.turn() {
+        ChessColor::White => format!("{number}.{san}"),
+        ChessColor::Black => format!("{number}\u{2026}{san}"),
+    })
+}
@@ -0,0 +1,127 @@
+use pgn_reader::{BufferedReader, RawHeader, SanPlus, Skip, Visitor};
+use shakmaty::fen::Fen;
+use shakmaty::{CastlingMode, Chess, Color, EnPassantMode, Position};
+
+use crate::{session_log, uci, Game, LichessNextPuzzle, Puzzle};
+
+// ----------------------------------------------
+// Lichess studies as training sets: every chapter becomes a puzzle
+// whose solution is the chapter's mainline, played from the side the
+// chapter is oriented to
+// ----------------------------------------------
+
+#[derive(Default)]
+struct Chapter {
+    name: String,
+    fen: Option<String>,
+    orientation: Option<Color>,
+    moves: Vec<SanPlus>,
+}
+
+impl Visitor for Chapter {
+    type Result = Chapter;
+
+    fn begin_game(&mut self) {
+        *self = Chapter::default();
+    }
+
+    fn header(&mut self, key: &[u8], value: RawHeader<'_>) {
+        let value = value.decode_utf8_lossy().into_owned();
+        match key {
+            // lichess writes "Study name: Chapter name"
+            b"Event" => {
+                self.name = value
+                    .rsplit_once(": ")
+                    .map_or(value.clone(), |(_, chapter)| chapter.to_string())
+            }
+            b"FEN" => self.fen = Some(value),
+            b"Orientation" => {
+                self.orientation = Color::from_char(value.chars().next().unwrap_or('w'))
+            }
+            _ => {}
+        }
+    }
+
+    fn begin_variation(&mut self) -> Skip {
+        Skip(true) // the mainline is the expected answer
+    }
+
+    fn san(&mut self, san_plus: SanPlus) {
+        self.moves.push(san_plus);
+    }
+
+    fn end_game(&mut self) -> Self::Result {
+        std::mem::take(self)
+    }
+}
+
+/// The chapters of a study, from a PGN file or fetched from lichess by study id
+pub fn load(source: &str) -> anyhow::Result<Vec<LichessNextPuzzle>> {
+    let text = if std::path::Path::new(source).exists() {
+        std::fs::read_to_string(source)?
+    } else {
+        let url = format!("https://lichess.org/api/study/{source}.pgn");
+        session_log::fetch(&url, || {
+            Ok(reqwest::blocking::get(&url)?.error_for_status()?.text()?)
+        })?
+    };
+    let mut reader = BufferedReader::new(text.as_bytes());
+    let mut chapter = Chapter::default();
+    let mut puzzles = Vec::new();
+    let mut number = 0;
+    while let Some(chapter) = reader.read_game(&mut chapter)? {
+        number += 1;
+        if let Some(puzzle) = chapter_puzzle(chapter, number)? {
+            puzzles.push(puzzle);
+        }
+    }
+    if puzzles.is_empty() {
+        anyhow::bail!("{source} has no chapter with moves to play");
+    }
+    Ok(puzzles)
+}
+
+// The chapter's position from the trainee's side, None if they have no move in it.
+// Studies carry no rating, the puzzles get 0.
+fn chapter_puzzle(chapter: Chapter, number: usize) -> anyhow::Result<Option<LichessNextPuzzle>> {
+    let mut pos: Chess = match &chapter.fen {
+        Some(fen) => Fen::from_ascii(fen.as_bytes())?.into_position(CastlingMode::Standard)?,
+        None => Chess::default(),
+    };
+    let mut moves = chapter.moves.iter();
+    // a chapter oriented to the side not on move opens with the other side's move
+    if chapter.orientation.is_some_and(|side| side != pos.turn()) {
+        let Some(first) = moves.next() else {
+            return Ok(None);
+        };
+        let mv = first.san.to_move(&pos)?;
+        pos.play_unchecked(&mv);
+    }
+    let mut solution = Vec::new();
+    let mut line = pos.clone();
+    for san in moves {
+        let mv = san.san.to_move(&line)?;
+        solution.push(uci::format(&mv));
+        line.play_unchecked(&mv);
+    }
+    if solution.is_empty() {
+        return Ok(None);
+    }
+    let fen = Fen::from_position(pos, EnPassantMode::Legal);
+    let name = if chapter.name.is_empty() {
+        format!("chapter {number}")
+    } else {
+        chapter.name
+    };
+    Ok(Some(LichessNextPuzzle {
+        puzzle: Puzzle {
+            id: name,
+            rating: 0,
+            solution,
+            initial_ply: 0,
+        },
+        game: Game {
+            pgn: format!("[FEN \"{fen}\"]\n\n*"),
+        },
+    }))
+}
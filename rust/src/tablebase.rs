@@ -0,0 +1,234 @@
+use serde::Deserialize;
+use shakmaty::fen::{Epd, Fen};
+use shakmaty::{Chess, Color, EnPassantMode, Outcome, Position};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::time::Duration;
+
+use crate::engine::{Engine, Evaluation, Score};
+use crate::session_log;
+
+// ----------------------------------------------
+// Syzygy tablebases: the engines read them through their SyzygyPath
+// option and play the endgames in them perfectly; here the directories
+// are checked for tables and the engine's score of a position in them
+// is read as the tables' verdict
+// ----------------------------------------------
+
+// Stockfish reports a tablebase win as "cp 20000" less the plies to the
+// position it was found in, far above any real evaluation
+const TB_WIN_CP: i32 = 19000;
+
+pub struct Tablebase {
+    /// the directories as given, ':' separated like SyzygyPath
+    pub path: String,
+    /// pieces, kings included, of the largest tables found
+    pub pieces: usize,
+}
+
+impl Tablebase {
+    /// Look for the WDL tables (KQvK.rtbw) in the directories of `path`
+    pub fn open(path: &str) -> anyhow::Result<Self> {
+        let mut pieces = 0;
+        for dir in std::env::split_paths(path) {
+            let entries = std::fs::read_dir(&dir)
+                .map_err(|err| anyhow::anyhow!("Syzygy path {}: {err}", dir.display()))?;
+            for entry in entries {
+                let name = PathBuf::from(entry?.file_name());
+                if name.extension().is_some_and(|ext| ext == "rtbw") {
+                    let stem = name.file_stem().unwrap_or_default().to_string_lossy();
+                    pieces = pieces.max(stem.chars().filter(char::is_ascii_uppercase).count());
+                }
+            }
+        }
+        if pieces == 0 {
+            anyhow::bail!("no Syzygy tables (*.rtbw) in {path}");
+        }
+        Ok(Self {
+            path: path.to_string(),
+            pieces,
+        })
+    }
+
+    /// Whether `pos` is in the tables: few enough pieces and no castling rights
+    pub fn covers(&self, pos: &Chess) -> bool {
+        within(pos, self.pieces)
+    }
+
+    /// Point `engine` at the tables, false if it has no SyzygyPath option
+    pub fn setup(&self, engine: &mut Engine) -> anyhow::Result<bool> {
+        if !engine.has_option("SyzygyPath") {
+            return Ok(false);
+        }
+        engine.set_option("SyzygyPath", &self.path)?;
+        Ok(true)
+    }
+
+    /// The result of `pos` with best play, from the engine's `evaluation` of it
+    /// once the engine took it from the tables; tess reads no tables itself,
+    /// so this is only ever an engine-reported result
+    pub fn verdict(&self, pos: &Chess, evaluation: &Evaluation) -> Option<Outcome> {
+        if !self.covers(pos) || evaluation.tbhits == 0 {
+            return None;
+        }
+        let winner = match evaluation.score {
+            Score::Mate(moves) if moves > 0 => pos.turn(),
+            Score::Mate(_) => !pos.turn(),
+            Score::Centipawns(cp) if cp >= TB_WIN_CP => pos.turn(),
+            Score::Centipawns(cp) if cp <= -TB_WIN_CP => !pos.turn(),
+            Score::Centipawns(_) => return Some(Outcome::Draw),
+        };
+        Some(Outcome::Decisive { winner })
+    }
+}
+
+/// "White wins" or "a draw"
+pub fn describe(outcome: Outcome) -> &'static str {
+    match outcome {
+        Outcome::Decisive {
+            winner: Color::White,
+        } => "White wins",
+        Outcome::Decisive {
+            winner: Color::Black,
+        } => "Black wins",
+        Outcome::Draw => "a draw",
+    }
+}
+
+fn within(pos: &Chess, pieces: usize) -> bool {
+    pos.board().occupied().count() <= pieces && pos.castles().is_empty()
+}
+
+// ----------------------------------------------
+// Without tables of my own, the lichess tablebase server: the best moves
+// and their DTZ for endgames of up to seven pieces, asked in the
+// background and kept for the session
+// ----------------------------------------------
+
+const LICHESS_URL: &str = "https://tablebase.lichess.ovh/standard";
+const LICHESS_PIECES: usize = 7;
+
+/// The server's answer for a position
+#[derive(Debug, Deserialize)]
+pub struct Lookup {
+    /// "win", "draw", "loss", "cursed-win", ... for the side to move
+    pub category: String,
+    /// distance to zeroing the 50-move counter, in plies
+    pub dtz: Option<i32>,
+    /// distance to mate, in plies, where it is known
+    pub dtm: Option<i32>,
+    /// every legal move, best first
+    #[serde(default)]
+    pub moves: Vec<LookupMove>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LookupMove {
+    pub san: String,
+    /// the category after the move, for the opponent
+    pub category: String,
+    pub dtz: Option<i32>,
+}
+
+/// "White wins", "a draw by the 50-move rule", ... for a category of the side `turn`
+pub fn describe_category(category: &str, turn: Color) -> String {
+    let (side, other) = (
+        turn.fold_wb("White", "Black"),
+        turn.fold_wb("Black", "White"),
+    );
+    match category {
+        "win" | "syzygy-win" => format!("{side} wins"),
+        "maybe-win" => format!("{side} wins, unless the 50-move rule saves {other}"),
+        "cursed-win" => format!("a draw by the 50-move rule, else {side} would win"),
+        "draw" => "a draw".to_string(),
+        "blessed-loss" => format!("a draw by the 50-move rule, else {other} would win"),
+        "maybe-loss" => format!("{other} wins, unless the 50-move rule saves {side}"),
+        "loss" | "syzygy-loss" => format!("{other} wins"),
+        _ => "unknown".to_string(),
+    }
+}
+
+impl LookupMove {
+    /// The move's result for the side playing it: "win", "draw", "loss"
+    pub fn result(&self) -> &'static str {
+        match self.category.as_str() {
+            "loss" | "syzygy-loss" | "maybe-loss" => "win",
+            "win" | "syzygy-win" | "maybe-win" => "loss",
+            "unknown" => "unknown",
+            _ => "draw",
+        }
+    }
+}
+
+fn fetch(fen: &str) -> anyhow::Result<Lookup> {
+    let lookup = || -> reqwest::Result<String> {
+        reqwest::blocking::Client::builder()
+            .timeout(Duration::from_secs(5))
+            .build()?
+            .get(LICHESS_URL)
+            .query(&[("fen", fen)])
+            .send()?
+            .error_for_status()?
+            .text()
+    };
+    // without the URL, which only repeats the FEN and overflows the pane
+    let text = session_log::fetch(&format!("{LICHESS_URL}?fen={fen}"), || {
+        lookup().map_err(|err| anyhow::anyhow!("{}", err.without_url()))
+    })?;
+    Ok(serde_json::from_str(&text)?)
+}
+
+/// The server's answer for `pos`, waited for
+pub fn lookup(pos: &Chess) -> anyhow::Result<Lookup> {
+    fetch(&Fen::from_position(pos.clone(), EnPassantMode::Legal).to_string())
+}
+
+#[derive(Default)]
+pub struct Online {
+    // answers by EPD, or what went wrong asking
+    cache: HashMap<String, Result<Lookup, String>>,
+    // the position asked for and its answer on the way
+    pending: Option<(String, Receiver<anyhow::Result<Lookup>>)>,
+}
+
+impl Online {
+    /// Ask for `pos` unless it is known, asked already or not in the tables.
+    /// One question at a time, the server limits how often it is asked.
+    pub fn request(&mut self, pos: &Chess) {
+        if self.pending.is_some() || !within(pos, LICHESS_PIECES) {
+            return;
+        }
+        let epd = Epd::from_position(pos.clone(), EnPassantMode::Legal).to_string();
+        if self.cache.contains_key(&epd) {
+            return;
+        }
+        let fen = Fen::from_position(pos.clone(), EnPassantMode::Legal).to_string();
+        let (sender, answer) = mpsc::channel();
+        std::thread::spawn(move || {
+            let _ = sender.send(fetch(&fen));
+        });
+        self.pending = Some((epd, answer));
+    }
+
+    /// Take in an answer that arrived, true if there is one
+    pub fn poll(&mut self) -> bool {
+        let Some((epd, answer)) = &self.pending else {
+            return false;
+        };
+        let result = match answer.try_recv() {
+            Ok(result) => result.map_err(|err| err.to_string()),
+            Err(TryRecvError::Empty) => return false,
+            Err(TryRecvError::Disconnected) => Err("the lookup failed".to_string()),
+        };
+        self.cache.insert(epd.clone(), result);
+        self.pending = None;
+        true
+    }
+
+    /// The answer for `pos`, once it has arrived
+    pub fn get(&self, pos: &Chess) -> Option<&Result<Lookup, String>> {
+        let epd = Epd::from_position(pos.clone(), EnPassantMode::Legal).to_string();
+        self.cache.get(&epd)
+    }
+}
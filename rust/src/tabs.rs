@@ -0,0 +1,266 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Modifier, Style};
+use ratatui::widgets::Tabs as TabBar;
+use shakmaty::{Chess, Position};
+
+use crate::{
+    app_from_pgn, beginner, exercise_from, load_puzzle, play_on_from, position_app, positions, App,
+    AppMode,
+};
+
+// ----------------------------------------------
+// Tabbed workspaces: independent games, puzzles and PGN reviews,
+// switched with the number keys while the input line is empty.
+// In a simul every tab is a board against an engine, and once I move
+// on a board it gives way to the next one where it is my move.
+// ----------------------------------------------
+
+struct Tab {
+    name: String,
+    app: App,
+}
+
+pub struct Tabs {
+    tabs: Vec<Tab>,
+    active: usize,
+    simul: bool,
+}
+
+impl Tabs {
+    pub fn new(app: App) -> Self {
+        let name = default_name(&app);
+        Self {
+            tabs: vec![Tab { name, app }],
+            active: 0,
+            simul: false,
+        }
+    }
+
+    /// Play a simul: the active tab is the first board, `boards` the others
+    pub fn start_simul(&mut self, boards: Vec<App>) {
+        self.simul = true;
+        self.tabs[0].name = board_name(1, &self.tabs[0].app);
+        for mut app in boards {
+            let name = board_name(self.tabs.len() + 1, &app);
+            // the boards' own messages, not the start message of a game
+            app.message = format!("{name}: your move.");
+            self.open(name, app);
+        }
+        self.active = 0;
+    }
+
+    /// In a simul, move on from the board I just moved on to the next one
+    /// where it is my move; a board picked with the number keys stays on
+    /// screen. True if the board on screen changed
+    pub fn follow_simul(&mut self) -> bool {
+        if !std::mem::take(&mut self.active_mut().moved) || !self.simul {
+            return false;
+        }
+        let count = self.tabs.len();
+        let next = (1..count)
+            .map(|step| (self.active + step) % count)
+            .find(|&index| my_move(&self.tabs[index].app));
+        match next {
+            Some(index) => {
+                self.active = index;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn active(&self) -> &App {
+        &self.tabs[self.active].app
+    }
+
+    pub fn active_mut(&mut self) -> &mut App {
+        &mut self.tabs[self.active].app
+    }
+
+    pub fn apps_mut(&mut self) -> impl Iterator<Item = &mut App> {
+        self.tabs.iter_mut().map(|tab| &mut tab.app)
+    }
+
+    /// Tab switching and the `:tab` commands; false if the key is for the active tab
+    pub fn handle_key(&mut self, key: KeyEvent) -> anyhow::Result<bool> {
+        let app = self.active();
+        // the options editor and the piece prompt keep the keys, digits included
+        if app.options_editor.is_some() || app.pending_choice.is_some() {
+            return Ok(false);
+        }
+        match key.code {
+            // not while a guess of the evaluation is typed
+            KeyCode::Char(ch @ '1'..='9') if app.input_buffer.is_empty() && !app.guessing() => {
+                let index = ch as usize - '1' as usize;
+                if index < self.tabs.len() {
+                    self.active = index;
+                } else {
+                    self.active_mut().message = format!("There is no tab {ch}.");
+                }
+                Ok(true)
+            }
+            // the game goes on in a tab of its own, next to the puzzles
+            KeyCode::Char('c')
+                if app.input_buffer.is_empty()
+                    && !app.analysing
+                    && matches!(
+                        app.mode,
+                        AppMode::Puzzle {
+                            completed: true,
+                            ..
+                        }
+                    ) =>
+            {
+                match play_on_from(app) {
+                    Ok(game) => self.open("play on".to_string(), game),
+                    Err(err) => self.active_mut().message = format!("Cannot play on: {err}."),
+                }
+                Ok(true)
+            }
+            KeyCode::Enter if is_tab_command(&app.input_buffer) => {
+                let input = std::mem::take(&mut self.active_mut().input_buffer);
+                let mut args = input[1..].split_whitespace();
+                let command = args.next().unwrap_or_default();
+                let args: Vec<&str> = args.collect();
+                if let Err(err) = self.run_command(command, &args) {
+                    self.active_mut().message = format!("Could not open tab: {err}");
+                }
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    fn run_command(&mut self, command: &str, args: &[&str]) -> anyhow::Result<()> {
+        let display = self.active().display;
+        match (command, args) {
+            ("tab", ["standard"] | []) => {
+                let app = App::new_standard(Chess::default(), display);
+                self.open("game".to_string(), app);
+            }
+            ("tab", ["board"]) => self.open("board".to_string(), App::new_board(display)),
+            ("tab", ["puzzle", id @ ..]) => {
+                let (board, solution, puzzle) = load_puzzle(id.first().map(|id| id.to_string()))?;
+                let app = App::new_puzzle(board, solution, display, puzzle);
+                let name = default_name(&app);
+                self.open(name, app);
+            }
+            ("tab", ["load", path]) => {
+                let app = app_from_pgn(path, 0, display)?;
+                let name = std::path::Path::new(path)
+                    .file_name()
+                    .map_or(path.to_string(), |name| name.to_string_lossy().into_owned());
+                self.open(name, app);
+            }
+            ("tab", ["position", search @ ..]) if !search.is_empty() => {
+                let saved = positions::pick(&search.join(" "))?;
+                let app = position_app(&saved, display)?;
+                self.open(saved.name.clone(), app);
+            }
+            ("train", [] | ["best"]) => {
+                let app = exercise_from(self.active(), !args.is_empty())?;
+                self.open("train".to_string(), app);
+            }
+            ("tabclose", []) if self.tabs.len() > 1 => {
+                self.tabs.remove(self.active);
+                self.active = self.active.min(self.tabs.len() - 1);
+            }
+            ("tabclose", []) => {
+                self.active_mut().message = "The last tab cannot be closed.".to_string()
+            }
+            _ => {
+                self.active_mut().message =
+                    "Usage: :tab [standard | board | puzzle [id] | load <file> | position <name>], :train [best], :tabclose"
+                        .to_string()
+            }
+        }
+        Ok(())
+    }
+
+    // Add a tab with the active tab's display settings and switch to it
+    fn open(&mut self, name: String, mut app: App) {
+        let current = self.active();
+        app.low_bandwidth = current.low_bandwidth;
+        app.notation = current.notation;
+        app.palette = current.palette;
+        app.pieces = current.pieces;
+        app.wide_symbols = current.wide_symbols;
+        if current.beginner.is_some() {
+            app.beginner = Some(beginner::Assist::default());
+        }
+        app.input_language = current.input_language;
+        app.hint_penalties = current.hint_penalties;
+        app.opening_tree = current.opening_tree.clone();
+        app.eco = current.eco.clone();
+        app.set_layout(current.layout);
+        if app.message.is_empty() {
+            app.message = app.start_message();
+        }
+        self.tabs.push(Tab { name, app });
+        self.active = self.tabs.len() - 1;
+    }
+
+    /// Draw the tab bar when there is more than one tab, returns the area left for the tab
+    pub fn draw_bar(&self, f: &mut ratatui::Frame) -> Rect {
+        if self.tabs.len() < 2 {
+            return f.area();
+        }
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(0)])
+            .split(f.area());
+        let titles = self
+            .tabs
+            .iter()
+            .enumerate()
+            .map(|(i, tab)| format!("{} {}", i + 1, tab.name));
+        let bar = TabBar::new(titles)
+            .select(self.active)
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        f.render_widget(bar, rows[0]);
+        rows[1]
+    }
+}
+
+fn is_tab_command(input: &str) -> bool {
+    let command = input
+        .strip_prefix(':')
+        .and_then(|c| c.split_whitespace().next());
+    matches!(command, Some("tab" | "tabclose" | "train"))
+}
+
+// "board 2: Stockfish 17"
+fn board_name(number: usize, app: &App) -> String {
+    match app.engines.first() {
+        Some(engine) => format!("board {number}: {}", engine.name),
+        None => format!("board {number}"),
+    }
+}
+
+// A simul board where I have nothing to do: the engine is to move, or the
+// game is over and its overlay closed. Not while it is being analysed.
+fn waiting(app: &App) -> bool {
+    if app.analysing || !app.input_buffer.is_empty() {
+        return false;
+    }
+    match &app.game_over {
+        Some(_) => !app.show_game_over,
+        None => app
+            .engines
+            .iter()
+            .any(|engine| engine.color == app.board.turn()),
+    }
+}
+
+fn my_move(app: &App) -> bool {
+    app.game_over.is_none() && !waiting(app) && !app.analysing
+}
+
+fn default_name(app: &App) -> String {
+    match &app.mode {
+        AppMode::StandardGame => "game".to_string(),
+        AppMode::Board => "board".to_string(),
+        AppMode::Puzzle { lichess, .. } => format!("puzzle {}", lichess.puzzle.id),
+    }
+}
@@ -0,0 +1,65 @@
+use ratatui::style::Color;
+use serde::{Deserialize, Serialize};
+
+// ----------------------------------------------
+// Board colors for dark and light terminal backgrounds
+// ----------------------------------------------
+
+#[derive(Debug, clap::ValueEnum, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Background {
+    /// guess from $COLORFGBG, dark if it is not set
+    Auto,
+    Dark,
+    Light,
+}
+
+impl Background {
+    /// Resolve `Auto` to dark or light
+    pub fn effective(self) -> Background {
+        match self {
+            Background::Auto if colorfgbg_is_light() => Background::Light,
+            Background::Auto => Background::Dark,
+            background => background,
+        }
+    }
+}
+
+// rxvt-style "fg;bg" (sometimes "fg;default;bg"), light backgrounds are 7 and 9-15
+fn colorfgbg_is_light() -> bool {
+    std::env::var("COLORFGBG")
+        .ok()
+        .and_then(|value| value.rsplit(';').next()?.parse::<u8>().ok())
+        .is_some_and(|bg| bg == 7 || (9..=15).contains(&bg))
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Palette {
+    pub light_square: Color,
+    pub dark_square: Color,
+    pub white_piece: Color,
+    pub black_piece: Color,
+    pub label: Color,
+}
+
+impl Palette {
+    pub fn for_background(background: Background) -> Palette {
+        match background.effective() {
+            Background::Light => Palette {
+                // fixed 256-color entries, light themes remap the basic sixteen
+                light_square: Color::Indexed(180),
+                dark_square: Color::Indexed(132),
+                white_piece: Color::Indexed(231),
+                black_piece: Color::Indexed(16),
+                label: Color::Black,
+            },
+            _ => Palette {
+                light_square: Color::Yellow,
+                dark_square: Color::Magenta,
+                white_piece: Color::White,
+                black_piece: Color::Black,
+                label: Color::White,
+            },
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use shakmaty::uci::UciMove;
+use shakmaty::{CastlingMode, Chess, Move};
+
+// ----------------------------------------------
+// UCI move notation (e2e4, e7e8q, e1g1), used by lichess puzzle
+// solutions and accepted as move input
+// ----------------------------------------------
+
+/// Standard UCI: the promotion piece in lowercase, castling as the king's two-square step
+pub fn format(mv: &Move) -> String {
+    mv.to_uci(CastlingMode::Standard).to_string()
+}
+
+/// The legal move `text` stands for in `pos`, in any case.
+/// Castling may also be written as the king taking its rook (e1h1).
+pub fn parse(pos: &Chess, text: &str) -> Option<Move> {
+    let uci = UciMove::from_ascii(text.trim().to_ascii_lowercase().as_bytes()).ok()?;
+    uci.to_move(pos).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shakmaty::fen::Fen;
+    use shakmaty::{Position, Role};
+
+    fn position(fen: &str) -> Chess {
+        Fen::from_ascii(fen.as_bytes())
+            .unwrap()
+            .into_position(CastlingMode::Standard)
+            .unwrap()
+    }
+
+    const CASTLING: &str = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+    const PROMOTION: &str = "1n5k/P7/8/8/8/8/8/K7 w - - 0 1";
+
+    #[test]
+    fn formats_promotions_with_the_piece() {
+        let pos = position(PROMOTION);
+        let mut promotions: Vec<String> = pos
+            .legal_moves()
+            .iter()
+            .filter(|mv| mv.is_promotion())
+            .map(format)
+            .collect();
+        promotions.sort();
+        assert_eq!(
+            promotions,
+            ["a7a8b", "a7a8n", "a7a8q", "a7a8r", "a7b8b", "a7b8n", "a7b8q", "a7b8r"]
+        );
+    }
+
+    #[test]
+    fn formats_castling_as_the_king_step() {
+        let pos = position(CASTLING);
+        let mut castles: Vec<String> = pos
+            .legal_moves()
+            .iter()
+            .filter(|mv| mv.is_castle())
+            .map(format)
+            .collect();
+        castles.sort();
+        assert_eq!(castles, ["e1c1", "e1g1"]);
+
+        let black = position("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1");
+        let mut castles: Vec<String> = black
+            .legal_moves()
+            .iter()
+            .filter(|mv| mv.is_castle())
+            .map(format)
+            .collect();
+        castles.sort();
+        assert_eq!(castles, ["e8c8", "e8g8"]);
+    }
+
+    #[test]
+    fn parses_both_castling_forms() {
+        let pos = position(CASTLING);
+        for text in ["e1g1", "e1h1"] {
+            let mv = parse(&pos, text).unwrap();
+            assert!(mv.is_castle(), "{text}");
+            assert_eq!(mv.castling_side().unwrap().king_to_file().char(), 'g');
+        }
+        for text in ["e1c1", "e1a1"] {
+            let mv = parse(&pos, text).unwrap();
+            assert!(mv.is_castle(), "{text}");
+            assert_eq!(mv.castling_side().unwrap().king_to_file().char(), 'c');
+        }
+    }
+
+    #[test]
+    fn parses_promotions_in_any_case() {
+        let pos = position(PROMOTION);
+        for (text, role) in [
+            ("a7a8n", Role::Knight),
+            ("A7B8Q", Role::Queen),
+            ("a7a8R", Role::Rook),
+        ] {
+            assert_eq!(parse(&pos, text).unwrap().promotion(), Some(role), "{text}");
+        }
+        // a pawn reaching the last rank must say what it becomes
+        assert_eq!(parse(&pos, "a7a8"), None);
+    }
+
+    #[test]
+    fn rejects_illegal_and_malformed_moves() {
+        let pos = Chess::default();
+        for text in ["e2e5", "e1g1", "e7e5", "0000", "", "e2", "Nf3", "e2e4e"] {
+            assert_eq!(parse(&pos, text), None, "{text}");
+        }
+    }
+
+    #[test]
+    fn every_legal_move_round_trips() {
+        let kiwipete = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        for fen in [kiwipete, CASTLING, PROMOTION] {
+            let pos = position(fen);
+            for mv in pos.legal_moves() {
+                assert_eq!(parse(&pos, &format(&mv)), Some(mv.clone()), "{fen} {mv:?}");
+            }
+        }
+    }
+}